@@ -0,0 +1,61 @@
+//! Benchmarks of decoding big frames, read in chunks as from a socket.
+//!
+//! Run with `cargo bench --bench codec`.
+
+use bytes::BytesMut;
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use tokio_util::codec::Decoder;
+
+use redis_clone::RespCodec;
+
+/// Size of the data of one read from the socket.
+const READ_SIZE: usize = 64 * 1024;
+
+/// Decode a frame of `data` buffered in chunks of `READ_SIZE` bytes.
+fn decode_in_chunks(data: &[u8]) {
+    let mut codec = RespCodec::default();
+    let mut buffer = BytesMut::with_capacity(READ_SIZE);
+    let mut frames = 0;
+    for chunk in data.chunks(READ_SIZE) {
+        buffer.extend_from_slice(chunk);
+        while let Some(frame) = codec.decode(&mut buffer).unwrap() {
+            criterion::black_box(frame);
+            frames += 1;
+        }
+    }
+    assert_eq!(frames, 1);
+}
+
+/// `SET key value` with a value of 512MB, the maximum size of a bulk string.
+fn set_512mb(c: &mut Criterion) {
+    let value_len = 512 * 1024 * 1024;
+    let mut data = b"*3\r\n$3\r\nSET\r\n$3\r\nkey\r\n".to_vec();
+    data.extend_from_slice(format!("${}\r\n", value_len).as_bytes());
+    data.resize(data.len() + value_len, b'x');
+    data.extend_from_slice(b"\r\n");
+
+    let mut group = c.benchmark_group("decode");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(data.len() as u64));
+    group.bench_function("set_512mb", |b| b.iter(|| decode_in_chunks(&data)));
+    group.finish();
+}
+
+/// `RPUSH list` with 1M elements.
+fn rpush_1m_elements(c: &mut Criterion) {
+    let elements = 1_000_000;
+    let mut data = format!("*{}\r\n$5\r\nRPUSH\r\n$4\r\nlist\r\n", elements + 2).into_bytes();
+    for i in 0..elements {
+        let element = i.to_string();
+        data.extend_from_slice(format!("${}\r\n{}\r\n", element.len(), element).as_bytes());
+    }
+
+    let mut group = c.benchmark_group("decode");
+    group.sample_size(10);
+    group.throughput(Throughput::Elements(elements as u64));
+    group.bench_function("rpush_1m_elements", |b| b.iter(|| decode_in_chunks(&data)));
+    group.finish();
+}
+
+criterion_group!(benches, set_512mb, rpush_1m_elements);
+criterion_main!(benches);
@@ -0,0 +1,25 @@
+use anyhow::{bail, Context};
+use redis_clone::Snapshot;
+
+/// Convert between Redis RDB files and snapshots of this server.
+/// The format of the input is detected, the output has the other format.
+///
+/// $ cargo run --bin rdb_convert -- dump.rdb dump.snap
+/// $ cargo run --bin rdb_convert -- dump.snap dump.rdb
+#[cfg(not(tarpaulin_include))]
+fn main() -> anyhow::Result<()> {
+    env_logger::init();
+
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let [input, output] = args.as_slice() else {
+        bail!("usage: rdb_convert <input> <output>");
+    };
+    let data = std::fs::read(input).with_context(|| format!("cannot read {}", input))?;
+    let encoded = if data.starts_with(b"REDIS") {
+        Snapshot::decode_rdb(&data)?.encode()
+    } else {
+        Snapshot::decode(&data)?.encode_rdb()
+    };
+    std::fs::write(output, encoded).with_context(|| format!("cannot write {}", output))?;
+    Ok(())
+}
@@ -1,4 +1,4 @@
-use redis_clone::RedisServer;
+use redis_clone::{Config, RedisServer};
 
 /// Connect via `redis-cli -h <hostname> -p <port>`
 /// (Stop `redis-server` first `sudo systemctl stop redis-server` or choose a custom port.)
@@ -8,13 +8,30 @@ use redis_clone::RedisServer;
 /// `echo -ne '*2\r\n$3\r\nGET\r\n$4\r\ntest\r\n' | nc 127.0.0.1 6379`
 ///
 /// $ RUST_LOG=debug cargo run --bin server
+///
+/// Persistence options follow `redis.conf`, e.g.,
+/// `cargo run --bin server -- --dir /tmp --dbfilename dump.snap --save "900 1 300 10"`
+///
+/// The port is set by `--port`, e.g., for nodes of a local cluster.
 #[tokio::main]
 #[cfg(not(tarpaulin_include))]
 async fn main() -> anyhow::Result<()> {
     // Initialize the logger.
     env_logger::init();
 
-    let mut server = RedisServer::new("127.0.0.1", 6379).await?;
+    let mut args: Vec<String> = std::env::args().skip(1).collect();
+    let mut port = 6379;
+    if let Some(index) = args.iter().position(|arg| arg == "--port") {
+        let value = args
+            .get(index + 1)
+            .ok_or_else(|| anyhow::anyhow!("missing value of option: --port"))?;
+        port = value
+            .parse()
+            .map_err(|_| anyhow::anyhow!("invalid port: {}", value))?;
+        args.drain(index..index + 2);
+    }
+    let config = Config::from_args(args)?;
+    let mut server = RedisServer::with_config("127.0.0.1", port, config).await?;
     server.run().await?;
 
     Ok(())
@@ -1,17 +1,445 @@
+use std::collections::HashMap;
 use std::time::Duration;
 
-use anyhow::bail;
+use anyhow::{anyhow, bail};
 use bytes::Bytes;
+use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::{net::TcpStream, time::timeout};
 
-use crate::bulk;
-use crate::cmd::Command;
+use crate::cluster::{ClusterNode, SlotRange, SlotState};
+use crate::cmd::{parse_stream_id, Command};
 use crate::connection::Connection;
 use crate::constants::CLIENT_CONNECTION_TIMEOUT;
-use crate::frame::Frame;
+use crate::db::{
+    AutoClaim, ClaimOptions, ConsumerInfo, GroupInfo, InsertPosition, ListEnd, PendingEntry,
+    PendingRange, PendingSummary, ScoreBound, SetOperation, StreamEntry, StreamId, StreamInfo,
+    StreamTrim, XAddId, ZAddOptions, ZRangeBy,
+};
+use crate::err::RedisClientError;
+use crate::frame::{Frame, Protocol};
+use crate::replication::{LinkState, ReplicaInfo, Role};
+
+mod cluster;
+mod pipeline;
+mod subscriber;
+
+pub use cluster::ClusterClient;
+pub use pipeline::{Pipeline, Responses};
+pub use subscriber::Subscriber;
+
+/// Conversion of a response frame into a typed value.
+///
+/// Error frames are converted to `RedisClientError::ServerError`.
+pub trait FromFrame: Sized {
+    fn from_frame(frame: Frame) -> anyhow::Result<Self>;
+}
+
+/// Returns an error for error frames and frames of unexpected type.
+fn unexpected(frame: Frame) -> anyhow::Error {
+    match frame {
+        Frame::Error(msg) => anyhow!(RedisClientError::ServerError(msg)),
+        Frame::BlobError(msg) => anyhow!(RedisClientError::ServerError(
+            String::from_utf8_lossy(&msg).to_string()
+        )),
+        frame => anyhow!(RedisClientError::UnexpectedResponse(format!("{:?}", frame))),
+    }
+}
+
+impl FromFrame for Frame {
+    fn from_frame(frame: Frame) -> anyhow::Result<Self> {
+        match frame {
+            Frame::Error(_) | Frame::BlobError(_) => Err(unexpected(frame)),
+            frame => Ok(frame),
+        }
+    }
+}
+
+impl FromFrame for () {
+    fn from_frame(frame: Frame) -> anyhow::Result<Self> {
+        match frame {
+            Frame::Simple(_) => Ok(()),
+            frame => Err(unexpected(frame)),
+        }
+    }
+}
+
+impl FromFrame for i64 {
+    fn from_frame(frame: Frame) -> anyhow::Result<Self> {
+        match frame {
+            Frame::Integer(value) => Ok(value),
+            frame => Err(unexpected(frame)),
+        }
+    }
+}
+
+impl FromFrame for bool {
+    fn from_frame(frame: Frame) -> anyhow::Result<Self> {
+        match frame {
+            Frame::Integer(value) => Ok(value != 0),
+            Frame::Boolean(value) => Ok(value),
+            frame => Err(unexpected(frame)),
+        }
+    }
+}
+
+impl FromFrame for Bytes {
+    fn from_frame(frame: Frame) -> anyhow::Result<Self> {
+        match frame {
+            Frame::Bulk(value) | Frame::Verbatim(_, value) => Ok(value),
+            Frame::Simple(value) => Ok(Bytes::from(value)),
+            frame => Err(unexpected(frame)),
+        }
+    }
+}
+
+impl FromFrame for String {
+    fn from_frame(frame: Frame) -> anyhow::Result<Self> {
+        match frame {
+            Frame::Bulk(ref value) | Frame::Verbatim(_, ref value) => std::str::from_utf8(value)
+                .map(str::to_string)
+                .map_err(|_| unexpected(frame)),
+            Frame::Simple(value) => Ok(value),
+            frame => Err(unexpected(frame)),
+        }
+    }
+}
+
+impl FromFrame for u64 {
+    fn from_frame(frame: Frame) -> anyhow::Result<Self> {
+        match frame {
+            Frame::Integer(value) if value >= 0 => Ok(value as u64),
+            // Counts are sometimes sent as bulk strings, e.g., by `XPENDING`
+            Frame::Bulk(ref value) => std::str::from_utf8(value)
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .ok_or_else(|| unexpected(frame)),
+            frame => Err(unexpected(frame)),
+        }
+    }
+}
+
+impl FromFrame for usize {
+    fn from_frame(frame: Frame) -> anyhow::Result<Self> {
+        u64::from_frame(frame).map(|value| value as usize)
+    }
+}
+
+/// Scores of sorted set members are sent as bulk strings, e.g., `1.5` or `inf`,
+/// or as doubles with RESP3.
+impl FromFrame for f64 {
+    fn from_frame(frame: Frame) -> anyhow::Result<Self> {
+        match frame {
+            Frame::Double(value) => Ok(value),
+            Frame::Bulk(ref value) => std::str::from_utf8(value)
+                .ok()
+                .and_then(|value| value.parse().ok())
+                .ok_or_else(|| unexpected(frame)),
+            frame => Err(unexpected(frame)),
+        }
+    }
+}
+
+impl FromFrame for StreamId {
+    fn from_frame(frame: Frame) -> anyhow::Result<Self> {
+        match frame {
+            Frame::Bulk(ref value) => std::str::from_utf8(value)
+                .ok()
+                .and_then(|value| parse_stream_id(value, 0).ok())
+                .ok_or_else(|| unexpected(frame)),
+            frame => Err(unexpected(frame)),
+        }
+    }
+}
+
+/// Stream entries are sent as `[id, [field, value, ...]]` arrays.
+impl FromFrame for StreamEntry {
+    fn from_frame(frame: Frame) -> anyhow::Result<Self> {
+        let [id, fields] = fixed_array(frame)?;
+        Ok(StreamEntry {
+            id: StreamId::from_frame(id)?,
+            fields: Vec::from_frame(fields)?,
+        })
+    }
+}
+
+/// Split an array of a fixed length into its elements.
+fn fixed_array<const N: usize>(frame: Frame) -> anyhow::Result<[Frame; N]> {
+    match frame {
+        Frame::Array(frames) if frames.len() == N => Ok(frames.try_into().unwrap()),
+        frame => Err(unexpected(frame)),
+    }
+}
+
+/// Convert a map or a flat array of name-value pairs, e.g., returned by `XINFO`, to a map.
+fn flat_map(frame: Frame) -> anyhow::Result<HashMap<String, Frame>> {
+    Ok(Vec::<(String, Frame)>::from_frame(frame)?
+        .into_iter()
+        .collect())
+}
+
+/// Take and convert the value of `name` from a map created by `flat_map`.
+fn take_field<T: FromFrame>(map: &mut HashMap<String, Frame>, name: &str) -> anyhow::Result<T> {
+    match map.remove(name) {
+        Some(frame) => T::from_frame(frame),
+        None => bail!(RedisClientError::UnexpectedResponse(format!(
+            "Missing field: {}",
+            name
+        ))),
+    }
+}
+
+impl FromFrame for PendingSummary {
+    fn from_frame(frame: Frame) -> anyhow::Result<Self> {
+        let [count, min_id, max_id, consumers] = fixed_array(frame)?;
+        Ok(PendingSummary {
+            count: usize::from_frame(count)?,
+            min_id: Option::from_frame(min_id)?,
+            max_id: Option::from_frame(max_id)?,
+            consumers: Option::<Vec<Frame>>::from_frame(consumers)?
+                .unwrap_or_default()
+                .into_iter()
+                .map(|consumer| {
+                    let [name, count] = fixed_array(consumer)?;
+                    Ok((String::from_frame(name)?, usize::from_frame(count)?))
+                })
+                .collect::<anyhow::Result<_>>()?,
+        })
+    }
+}
+
+impl FromFrame for PendingEntry {
+    fn from_frame(frame: Frame) -> anyhow::Result<Self> {
+        let [id, consumer, idle, delivery_count] = fixed_array(frame)?;
+        Ok(PendingEntry {
+            id: StreamId::from_frame(id)?,
+            consumer: String::from_frame(consumer)?,
+            idle: u64::from_frame(idle)?,
+            delivery_count: u64::from_frame(delivery_count)?,
+        })
+    }
+}
+
+impl FromFrame for AutoClaim {
+    fn from_frame(frame: Frame) -> anyhow::Result<Self> {
+        let [next_id, entries, deleted] = fixed_array(frame)?;
+        Ok(AutoClaim {
+            next_id: StreamId::from_frame(next_id)?,
+            entries: Vec::from_frame(entries)?,
+            deleted: Vec::from_frame(deleted)?,
+        })
+    }
+}
+
+impl FromFrame for StreamInfo {
+    fn from_frame(frame: Frame) -> anyhow::Result<Self> {
+        let mut map = flat_map(frame)?;
+        Ok(StreamInfo {
+            length: take_field(&mut map, "length")?,
+            last_generated_id: take_field(&mut map, "last-generated-id")?,
+            groups: take_field(&mut map, "groups")?,
+            first_entry: take_field(&mut map, "first-entry")?,
+            last_entry: take_field(&mut map, "last-entry")?,
+        })
+    }
+}
+
+impl FromFrame for GroupInfo {
+    fn from_frame(frame: Frame) -> anyhow::Result<Self> {
+        let mut map = flat_map(frame)?;
+        Ok(GroupInfo {
+            name: take_field(&mut map, "name")?,
+            consumers: take_field(&mut map, "consumers")?,
+            pending: take_field(&mut map, "pending")?,
+            last_delivered_id: take_field(&mut map, "last-delivered-id")?,
+        })
+    }
+}
+
+impl FromFrame for ConsumerInfo {
+    fn from_frame(frame: Frame) -> anyhow::Result<Self> {
+        let mut map = flat_map(frame)?;
+        Ok(ConsumerInfo {
+            name: take_field(&mut map, "name")?,
+            pending: take_field(&mut map, "pending")?,
+            idle: take_field(&mut map, "idle")?,
+        })
+    }
+}
+
+/// Replicas are sent as `[ip, port, offset]` arrays with bulk strings.
+impl FromFrame for ReplicaInfo {
+    fn from_frame(frame: Frame) -> anyhow::Result<Self> {
+        let [ip, port, offset] = fixed_array(frame)?;
+        Ok(ReplicaInfo {
+            ip: String::from_frame(ip)?,
+            port: u64::from_frame(port)?
+                .try_into()
+                .map_err(|_| anyhow!("Invalid port"))?,
+            offset: u64::from_frame(offset)?,
+        })
+    }
+}
+
+impl FromFrame for LinkState {
+    fn from_frame(frame: Frame) -> anyhow::Result<Self> {
+        let state = String::from_frame(frame)?;
+        LinkState::parse(&state).ok_or_else(|| {
+            anyhow!(RedisClientError::UnexpectedResponse(format!(
+                "Invalid link state: {}",
+                state
+            )))
+        })
+    }
+}
+
+impl FromFrame for Role {
+    fn from_frame(frame: Frame) -> anyhow::Result<Self> {
+        let Frame::Array(mut frames) = frame else {
+            return Err(unexpected(frame));
+        };
+        match frames.first().cloned().map(String::from_frame) {
+            Some(Ok(role)) if role == "master" && frames.len() == 3 => {
+                let replicas = frames.pop().unwrap();
+                Ok(Role::Primary {
+                    offset: u64::from_frame(frames.pop().unwrap())?,
+                    replicas: Vec::from_frame(replicas)?,
+                })
+            }
+            Some(Ok(role)) if role == "slave" && frames.len() == 5 => {
+                let [_, host, port, state, offset] = fixed_array(Frame::Array(frames))?;
+                Ok(Role::Replica {
+                    host: String::from_frame(host)?,
+                    port: u64::from_frame(port)?
+                        .try_into()
+                        .map_err(|_| anyhow!("Invalid port"))?,
+                    state: LinkState::from_frame(state)?,
+                    offset: u64::from_frame(offset)?,
+                })
+            }
+            _ => Err(unexpected(Frame::Array(frames))),
+        }
+    }
+}
+
+/// Slot ranges are sent as `[start, end, [host, port, id], ...]` arrays.
+impl FromFrame for SlotRange {
+    fn from_frame(frame: Frame) -> anyhow::Result<Self> {
+        let Frame::Array(mut frames) = frame else {
+            return Err(unexpected(frame));
+        };
+        if frames.len() < 3 {
+            return Err(unexpected(Frame::Array(frames)));
+        }
+        // Replicas of the range follow the primary
+        frames.truncate(3);
+        let [start, end, node] = fixed_array(Frame::Array(frames))?;
+        let [host, port, id] = fixed_array(node)?;
+        Ok(SlotRange {
+            start: slot_from_frame(start)?,
+            end: slot_from_frame(end)?,
+            host: String::from_frame(host)?,
+            port: u16::try_from(u64::from_frame(port)?).map_err(|_| anyhow!("Invalid port"))?,
+            id: String::from_frame(id)?,
+        })
+    }
+}
+
+fn slot_from_frame(frame: Frame) -> anyhow::Result<u16> {
+    u16::try_from(u64::from_frame(frame)?).map_err(|_| anyhow!("Invalid slot"))
+}
+
+/// Shards are sent as maps with the slots and the nodes of the shard, its primary is converted.
+impl FromFrame for ClusterNode {
+    fn from_frame(frame: Frame) -> anyhow::Result<Self> {
+        let mut shard = flat_map(frame)?;
+        let slots: Vec<Frame> = take_field(&mut shard, "slots")?;
+        let nodes: Vec<Frame> = take_field(&mut shard, "nodes")?;
+        let Some(node) = nodes.into_iter().next() else {
+            bail!(RedisClientError::UnexpectedResponse(
+                "Shard without nodes".to_string()
+            ));
+        };
+        let mut node = flat_map(node)?;
+        let mut slots = slots.into_iter();
+        let mut ranges = Vec::with_capacity(slots.len() / 2);
+        while let (Some(start), Some(end)) = (slots.next(), slots.next()) {
+            ranges.push((slot_from_frame(start)?, slot_from_frame(end)?));
+        }
+        Ok(ClusterNode {
+            id: take_field(&mut node, "id")?,
+            host: take_field(&mut node, "ip")?,
+            port: u16::try_from(take_field::<u64>(&mut node, "port")?)
+                .map_err(|_| anyhow!("Invalid port"))?,
+            slots: ranges,
+        })
+    }
+}
+
+/// Convert a reply of `XREAD` or `XREADGROUP`, i.e., an array of `[key, entries]` arrays.
+fn streams_from_frame(frame: Frame) -> anyhow::Result<Vec<(String, Vec<StreamEntry>)>> {
+    Option::<Vec<Frame>>::from_frame(frame)?
+        .unwrap_or_default()
+        .into_iter()
+        .map(|stream| {
+            let [key, entries] = fixed_array(stream)?;
+            Ok((String::from_frame(key)?, Vec::from_frame(entries)?))
+        })
+        .collect()
+}
+
+impl<T: FromFrame> FromFrame for Option<T> {
+    fn from_frame(frame: Frame) -> anyhow::Result<Self> {
+        match frame {
+            Frame::Null => Ok(None),
+            frame => T::from_frame(frame).map(Some),
+        }
+    }
+}
+
+impl<T: FromFrame> FromFrame for Vec<T> {
+    fn from_frame(frame: Frame) -> anyhow::Result<Self> {
+        match frame {
+            Frame::Array(frames) | Frame::Set(frames) | Frame::Push(frames) => {
+                frames.into_iter().map(T::from_frame).collect()
+            }
+            frame => Err(unexpected(frame)),
+        }
+    }
+}
+
+/// Map or flat array of pairs, e.g., field-value pairs returned by `HGETALL`.
+impl<A: FromFrame, B: FromFrame> FromFrame for Vec<(A, B)> {
+    fn from_frame(frame: Frame) -> anyhow::Result<Self> {
+        match frame {
+            Frame::Map(pairs) => pairs
+                .into_iter()
+                .map(|(a, b)| Ok((A::from_frame(a)?, B::from_frame(b)?)))
+                .collect(),
+            Frame::Array(frames) => {
+                if frames.len() % 2 != 0 {
+                    bail!(RedisClientError::UnexpectedResponse(
+                        "Expected an even number of elements".to_string()
+                    ));
+                }
+                let mut frames = frames.into_iter();
+                let mut pairs = Vec::with_capacity(frames.len() / 2);
+                while let (Some(a), Some(b)) = (frames.next(), frames.next()) {
+                    pairs.push((A::from_frame(a)?, B::from_frame(b)?));
+                }
+                Ok(pairs)
+            }
+            frame => Err(unexpected(frame)),
+        }
+    }
+}
+
+/// Stream of a client connection, e.g., a TCP socket or an in-memory duplex.
+trait ClientStream: AsyncRead + AsyncWrite + Unpin + Send + Sync {}
+
+impl<S: AsyncRead + AsyncWrite + Unpin + Send + Sync> ClientStream for S {}
 
 pub struct RedisClient {
-    conn: Connection,
+    conn: Connection<Box<dyn ClientStream>>,
 }
 
 impl RedisClient {
@@ -22,7 +450,7 @@ impl RedisClient {
             TcpStream::connect((address, port)),
         )
         .await??;
-        let mut conn = Connection::new(stream);
+        let mut conn = Connection::new(Box::new(stream) as Box<dyn ClientStream>);
 
         // TODO: Dirty workaround to check if the server is not full
         // (i.e., reached max client limit).
@@ -39,50 +467,20 @@ impl RedisClient {
         Ok(RedisClient { conn })
     }
 
+    /// Create a client of a connection that is already open, e.g., a Unix socket
+    /// or an in-memory duplex.
+    pub fn from_stream<S>(stream: S) -> Self
+    where
+        S: AsyncRead + AsyncWrite + Unpin + Send + Sync + 'static,
+    {
+        RedisClient {
+            conn: Connection::new(Box::new(stream)),
+        }
+    }
+
     /// Send a command and receive a response
     async fn execute(&mut self, command: Command) -> anyhow::Result<Option<Frame>> {
-        // Convert command to frame
-        let frame = match command {
-            Command::Get { key } => Frame::Array(vec![bulk!("GET"), bulk!(key)]),
-            Command::Set {
-                key,
-                val,
-                expiration,
-            } => {
-                let mut cmd = Frame::Array(vec![bulk!("SET"), bulk!(key), bulk!(val)]);
-                if expiration.is_some() {
-                    cmd.append(bulk!("PX"))?;
-                    cmd.append(bulk!(expiration.unwrap().as_millis().to_string()))?;
-                }
-                cmd
-            }
-            Command::Ping { msg } => match msg {
-                Some(message) => Frame::Array(vec![bulk!("PING"), bulk!(message)]),
-                None => Frame::Array(vec![bulk!("PING")]),
-            },
-            Command::Del { keys } => {
-                let mut frames = vec![bulk!("DEL")];
-                frames.extend(keys.into_iter().map(|key| bulk!(key)));
-                Frame::Array(frames)
-            }
-            Command::Exists { keys } => {
-                let mut frames = vec![bulk!("EXISTS")];
-                frames.extend(keys.into_iter().map(|key| bulk!(key)));
-                Frame::Array(frames)
-            }
-            Command::Increment { key } => Frame::Array(vec![bulk!("INCR"), bulk!(key)]),
-            Command::FlushDB => Frame::Array(vec![bulk!("FLUSHDB")]),
-            Command::DBSize => Frame::Array(vec![bulk!("DBSIZE")]),
-            Command::Keys { pattern } => Frame::Array(vec![bulk!("KEYS"), bulk!(pattern)]),
-            Command::Unknown(cmd) => Frame::Array(vec![bulk!(cmd)]),
-            Command::Lolwut(frames) => Frame::Array(vec![bulk!("LOLWUT"), Frame::Array(frames)]),
-            Command::Expire { key, seconds } => Frame::Array(vec![
-                bulk!("EXPIRE"),
-                bulk!(key),
-                bulk!(seconds.to_string()),
-            ]),
-            Command::TTL { key } => Frame::Array(vec![bulk!("TTL"), bulk!(key)]),
-        };
+        let frame = command.into_frame()?;
 
         // Write the frame to the connection
         self.conn.write_frame(&frame).await?;
@@ -93,12 +491,51 @@ impl RedisClient {
         Ok(response)
     }
 
+    /// Send frames in one batch and receive a response to each of them
+    async fn execute_batch(&mut self, frames: &[Frame]) -> anyhow::Result<Vec<Frame>> {
+        for frame in frames {
+            self.conn.feed_frame(frame).await?;
+        }
+        self.conn.flush().await?;
+
+        let mut responses = Vec::with_capacity(frames.len());
+        for _ in frames {
+            match self.conn.read_frame().await? {
+                Some(response) => responses.push(response),
+                None => bail!(RedisClientError::ConnectionClosed),
+            }
+        }
+        Ok(responses)
+    }
+
+    /// Send a command and convert the response to a typed value.
+    ///
+    /// Error responses are returned as `RedisClientError::ServerError`.
+    async fn execute_as<T: FromFrame>(&mut self, command: Command) -> anyhow::Result<T> {
+        match self.execute(command).await? {
+            // Attributes are auxiliary data, only the value is converted
+            Some(Frame::Attribute(_, frame)) => T::from_frame(*frame),
+            Some(frame) => T::from_frame(frame),
+            None => bail!(RedisClientError::ConnectionClosed),
+        }
+    }
+
     /// Ping the Redis server
     pub async fn ping(&mut self, message: Option<String>) -> anyhow::Result<Option<Frame>> {
         let command = Command::Ping { msg: message };
         self.execute(command).await
     }
 
+    /// Switch the protocol of the connection to RESP2 or RESP3 (`2` or `3`),
+    /// returns the properties of the server, e.g., `proto` or `role`.
+    pub async fn hello(&mut self, protocol: Option<u64>) -> anyhow::Result<HashMap<String, Frame>> {
+        let reply = self.execute_as(Command::Hello { protocol }).await?;
+        if let Some(protocol) = protocol.and_then(Protocol::from_version) {
+            self.conn.set_protocol(protocol);
+        }
+        flat_map(reply)
+    }
+
     /// Get a value by key
     pub async fn get(&mut self, key: String) -> anyhow::Result<Option<Frame>> {
         let command = Command::Get { key };
@@ -150,6 +587,132 @@ impl RedisClient {
         self.execute(command).await
     }
 
+    /// Save a snapshot of the database and wait until it is written
+    pub async fn save(&mut self) -> anyhow::Result<()> {
+        self.execute_as(Command::Save).await
+    }
+
+    /// Save a snapshot of the database in the background
+    pub async fn bgsave(&mut self) -> anyhow::Result<()> {
+        self.execute_as(Command::BgSave).await
+    }
+
+    /// Rewrite the append-only file in the background
+    pub async fn bgrewriteaof(&mut self) -> anyhow::Result<()> {
+        self.execute_as(Command::BgRewriteAof).await
+    }
+
+    /// Make the server a read-only replica of the primary at `host:port`
+    pub async fn replicaof(&mut self, host: &str, port: u16) -> anyhow::Result<()> {
+        self.execute_as(Command::ReplicaOf {
+            primary: Some((host.to_string(), port)),
+        })
+        .await
+    }
+
+    /// Stop the replication, the replica becomes a primary and keeps its data
+    pub async fn replicaof_no_one(&mut self) -> anyhow::Result<()> {
+        self.execute_as(Command::ReplicaOf { primary: None }).await
+    }
+
+    /// Wait until `numreplicas` replicas acknowledged the previous writes, `None` waits
+    /// indefinitely. Returns the number of replicas that acknowledged them before the timeout.
+    pub async fn wait(
+        &mut self,
+        numreplicas: u64,
+        timeout: Option<Duration>,
+    ) -> anyhow::Result<u64> {
+        self.execute_as(Command::Wait {
+            numreplicas,
+            timeout,
+        })
+        .await
+    }
+
+    /// Wait until the previous writes are synced to the local append-only file
+    /// and to those of `numreplicas` replicas. Returns the number of synced local files
+    /// (`0` or `1`) and replicas.
+    pub async fn waitaof(
+        &mut self,
+        numlocal: u64,
+        numreplicas: u64,
+        timeout: Option<Duration>,
+    ) -> anyhow::Result<(u64, u64)> {
+        let frame = self
+            .execute_as(Command::WaitAof {
+                numlocal,
+                numreplicas,
+                timeout,
+            })
+            .await?;
+        let [local, replicas] = fixed_array(frame)?;
+        Ok((u64::from_frame(local)?, u64::from_frame(replicas)?))
+    }
+
+    /// Get the replication role of the server
+    pub async fn role(&mut self) -> anyhow::Result<Role> {
+        self.execute_as(Command::Role).await
+    }
+
+    /// Get the ranges of hash slots with the nodes serving them
+    pub async fn cluster_slots(&mut self) -> anyhow::Result<Vec<SlotRange>> {
+        self.execute_as(Command::ClusterSlots).await
+    }
+
+    /// Get the shards of the cluster, i.e., the primary nodes with their slots
+    pub async fn cluster_shards(&mut self) -> anyhow::Result<Vec<ClusterNode>> {
+        self.execute_as(Command::ClusterShards).await
+    }
+
+    /// Get the nodes of the cluster in the format of the nodes file
+    pub async fn cluster_nodes(&mut self) -> anyhow::Result<String> {
+        self.execute_as(Command::ClusterNodes).await
+    }
+
+    /// Get the ID of the cluster node
+    pub async fn cluster_myid(&mut self) -> anyhow::Result<String> {
+        self.execute_as(Command::ClusterMyId).await
+    }
+
+    /// Get the hash slot of a key
+    pub async fn cluster_keyslot(&mut self, key: String) -> anyhow::Result<u16> {
+        let slot: u64 = self.execute_as(Command::ClusterKeySlot { key }).await?;
+        u16::try_from(slot).map_err(|_| anyhow!("Invalid slot: {}", slot))
+    }
+
+    /// Count the keys of a hash slot
+    pub async fn cluster_countkeysinslot(&mut self, slot: u16) -> anyhow::Result<u64> {
+        self.execute_as(Command::ClusterCountKeysInSlot { slot })
+            .await
+    }
+
+    /// Get at most `count` keys of a hash slot
+    pub async fn cluster_getkeysinslot(
+        &mut self,
+        slot: u16,
+        count: u64,
+    ) -> anyhow::Result<Vec<String>> {
+        self.execute_as(Command::ClusterGetKeysInSlot { slot, count })
+            .await
+    }
+
+    /// Change the state of a hash slot, e.g., to migrate it to another node
+    pub async fn cluster_setslot(&mut self, slot: u16, state: SlotState) -> anyhow::Result<()> {
+        self.execute_as(Command::ClusterSetSlot { slot, state })
+            .await
+    }
+
+    /// Serve the next command even if its slot is still being imported from another node,
+    /// i.e., after an `ASK` redirection
+    pub async fn asking(&mut self) -> anyhow::Result<()> {
+        self.execute_as(Command::Asking).await
+    }
+
+    /// Get the Unix time of the last successful snapshot in seconds
+    pub async fn lastsave(&mut self) -> anyhow::Result<i64> {
+        self.execute_as(Command::LastSave).await
+    }
+
     /// Get all keys matching a pattern
     pub async fn keys(&mut self, pattern: String) -> anyhow::Result<Option<Frame>> {
         let command = Command::Keys { pattern };
@@ -160,7 +723,10 @@ impl RedisClient {
     ///
     /// Returns 1 if the timeout was set, 0 if the timeout was not set.
     pub async fn expire(&mut self, key: String, seconds: u64) -> anyhow::Result<Option<Frame>> {
-        let command = Command::Expire { key, seconds };
+        let command = Command::Expire {
+            key,
+            duration: Duration::from_secs(seconds),
+        };
         self.execute(command).await
     }
 
@@ -175,4 +741,1082 @@ impl RedisClient {
         let command = Command::TTL { key };
         self.execute(command).await
     }
+
+    /// Serialize the value of a key, `None` if the key does not exist
+    pub async fn dump(&mut self, key: String) -> anyhow::Result<Option<Bytes>> {
+        self.execute_as(Command::Dump { key }).await
+    }
+
+    /// Create a key from a payload of `dump`, expiring after `ttl`.
+    /// An existing key is only replaced with `replace`.
+    pub async fn restore(
+        &mut self,
+        key: String,
+        ttl: Option<Duration>,
+        payload: Bytes,
+        replace: bool,
+    ) -> anyhow::Result<()> {
+        self.execute_as(Command::Restore {
+            key,
+            ttl: ttl.map_or(0, |ttl| (ttl.as_millis() as u64).max(1)),
+            payload,
+            replace,
+            absttl: false,
+        })
+        .await
+    }
+
+    /// Move keys to the server at `host:port`, or copy them with `copy`.
+    ///
+    /// Returns `false` if none of the keys exist.
+    pub async fn migrate(
+        &mut self,
+        host: &str,
+        port: u16,
+        keys: Vec<String>,
+        timeout: Duration,
+        copy: bool,
+        replace: bool,
+    ) -> anyhow::Result<bool> {
+        let reply: String = self
+            .execute_as(Command::Migrate {
+                host: host.to_string(),
+                port,
+                keys,
+                timeout,
+                copy,
+                replace,
+            })
+            .await?;
+        Ok(reply == "OK")
+    }
+
+    /// Prepend values to a list
+    ///
+    /// Returns the length of the list after the push.
+    pub async fn lpush(
+        &mut self,
+        key: String,
+        values: Vec<Bytes>,
+    ) -> anyhow::Result<Option<Frame>> {
+        let command = Command::Push {
+            key,
+            values,
+            end: ListEnd::Left,
+        };
+        self.execute(command).await
+    }
+
+    /// Append values to a list
+    ///
+    /// Returns the length of the list after the push.
+    pub async fn rpush(
+        &mut self,
+        key: String,
+        values: Vec<Bytes>,
+    ) -> anyhow::Result<Option<Frame>> {
+        let command = Command::Push {
+            key,
+            values,
+            end: ListEnd::Right,
+        };
+        self.execute(command).await
+    }
+
+    /// Remove and get the first element(s) of a list
+    ///
+    /// Returns a bulk string without `count`, an array with `count`.
+    pub async fn lpop(&mut self, key: String, count: Option<u64>) -> anyhow::Result<Option<Frame>> {
+        let command = Command::Pop {
+            key,
+            end: ListEnd::Left,
+            count,
+        };
+        self.execute(command).await
+    }
+
+    /// Remove and get the last element(s) of a list
+    ///
+    /// Returns a bulk string without `count`, an array with `count`.
+    pub async fn rpop(&mut self, key: String, count: Option<u64>) -> anyhow::Result<Option<Frame>> {
+        let command = Command::Pop {
+            key,
+            end: ListEnd::Right,
+            count,
+        };
+        self.execute(command).await
+    }
+
+    /// Get a range of elements from a list (negative indices count from the end)
+    pub async fn lrange(
+        &mut self,
+        key: String,
+        start: i64,
+        stop: i64,
+    ) -> anyhow::Result<Option<Frame>> {
+        let command = Command::LRange { key, start, stop };
+        self.execute(command).await
+    }
+
+    /// Get the length of a list
+    pub async fn llen(&mut self, key: String) -> anyhow::Result<Option<Frame>> {
+        let command = Command::LLen { key };
+        self.execute(command).await
+    }
+
+    /// Get an element from a list by its index
+    pub async fn lindex(&mut self, key: String, index: i64) -> anyhow::Result<Option<Frame>> {
+        let command = Command::LIndex { key, index };
+        self.execute(command).await
+    }
+
+    /// Set the value of an element in a list by its index
+    pub async fn lset(
+        &mut self,
+        key: String,
+        index: i64,
+        value: Bytes,
+    ) -> anyhow::Result<Option<Frame>> {
+        let command = Command::LSet { key, index, value };
+        self.execute(command).await
+    }
+
+    /// Trim a list to the specified range
+    pub async fn ltrim(
+        &mut self,
+        key: String,
+        start: i64,
+        stop: i64,
+    ) -> anyhow::Result<Option<Frame>> {
+        let command = Command::LTrim { key, start, stop };
+        self.execute(command).await
+    }
+
+    /// Remove `count` occurrences of `value` from a list
+    ///
+    /// Returns the number of removed elements.
+    pub async fn lrem(
+        &mut self,
+        key: String,
+        count: i64,
+        value: Bytes,
+    ) -> anyhow::Result<Option<Frame>> {
+        let command = Command::LRem { key, count, value };
+        self.execute(command).await
+    }
+
+    /// Insert an element before or after another element in a list
+    ///
+    /// Returns the length of the list after the insert, -1 if the pivot was not found.
+    pub async fn linsert(
+        &mut self,
+        key: String,
+        position: InsertPosition,
+        pivot: Bytes,
+        value: Bytes,
+    ) -> anyhow::Result<Option<Frame>> {
+        let command = Command::LInsert {
+            key,
+            position,
+            pivot,
+            value,
+        };
+        self.execute(command).await
+    }
+
+    /// Remove and get the first element of the first non-empty list,
+    /// or block until an element is pushed to one of the lists
+    ///
+    /// Returns the key and the element, or `None` if the `timeout` elapsed.
+    /// A `timeout` of `None` blocks indefinitely.
+    pub async fn blpop(
+        &mut self,
+        keys: Vec<String>,
+        timeout: Option<Duration>,
+    ) -> anyhow::Result<Option<(String, Bytes)>> {
+        let command = Command::BPop {
+            keys,
+            end: ListEnd::Left,
+            timeout,
+        };
+        self.execute_bpop(command).await
+    }
+
+    /// Remove and get the last element of the first non-empty list,
+    /// or block until an element is pushed to one of the lists
+    ///
+    /// Returns the key and the element, or `None` if the `timeout` elapsed.
+    /// A `timeout` of `None` blocks indefinitely.
+    pub async fn brpop(
+        &mut self,
+        keys: Vec<String>,
+        timeout: Option<Duration>,
+    ) -> anyhow::Result<Option<(String, Bytes)>> {
+        let command = Command::BPop {
+            keys,
+            end: ListEnd::Right,
+            timeout,
+        };
+        self.execute_bpop(command).await
+    }
+
+    async fn execute_bpop(&mut self, command: Command) -> anyhow::Result<Option<(String, Bytes)>> {
+        match self.execute_as::<Option<Frame>>(command).await? {
+            Some(frame) => {
+                let [key, value] = fixed_array(frame)?;
+                Ok(Some((String::from_frame(key)?, Bytes::from_frame(value)?)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// Move an element from one list to another, or block until an element
+    /// is pushed to the `source` list
+    ///
+    /// Returns the moved element, or `None` if the `timeout` elapsed.
+    pub async fn blmove(
+        &mut self,
+        source: String,
+        destination: String,
+        from: ListEnd,
+        to: ListEnd,
+        timeout: Option<Duration>,
+    ) -> anyhow::Result<Option<Bytes>> {
+        let command = Command::BLMove {
+            source,
+            destination,
+            from,
+            to,
+            timeout,
+        };
+        self.execute_as(command).await
+    }
+
+    /// Move the last element of a list to the head of another list,
+    /// or block until an element is pushed to the `source` list
+    ///
+    /// Returns the moved element, or `None` if the `timeout` elapsed.
+    pub async fn brpoplpush(
+        &mut self,
+        source: String,
+        destination: String,
+        timeout: Option<Duration>,
+    ) -> anyhow::Result<Option<Bytes>> {
+        self.blmove(source, destination, ListEnd::Right, ListEnd::Left, timeout)
+            .await
+    }
+
+    /// Subscribe to channels. The client enters the subscriber mode,
+    /// so it is converted to a `Subscriber`.
+    pub async fn subscribe(self, channels: Vec<String>) -> anyhow::Result<Subscriber> {
+        let mut subscriber = Subscriber::new(self);
+        subscriber.subscribe(channels).await?;
+        Ok(subscriber)
+    }
+
+    /// Subscribe to channels matching glob patterns. The client enters the subscriber mode,
+    /// so it is converted to a `Subscriber`.
+    pub async fn psubscribe(self, patterns: Vec<String>) -> anyhow::Result<Subscriber> {
+        let mut subscriber = Subscriber::new(self);
+        subscriber.psubscribe(patterns).await?;
+        Ok(subscriber)
+    }
+
+    /// Publish a message to a channel
+    ///
+    /// Returns the number of clients that received the message.
+    pub async fn publish(&mut self, channel: String, message: Bytes) -> anyhow::Result<i64> {
+        let command = Command::Publish { channel, message };
+        self.execute_as(command).await
+    }
+
+    /// Get the channels with at least one subscriber, optionally matching a glob pattern
+    pub async fn pubsub_channels(
+        &mut self,
+        pattern: Option<String>,
+    ) -> anyhow::Result<Vec<String>> {
+        let command = Command::PubSubChannels { pattern };
+        self.execute_as(command).await
+    }
+
+    /// Get the number of subscribers of each channel
+    pub async fn pubsub_numsub(
+        &mut self,
+        channels: Vec<String>,
+    ) -> anyhow::Result<Vec<(String, i64)>> {
+        let command = Command::PubSubNumSub { channels };
+        self.execute_as(command).await
+    }
+
+    /// Get the number of patterns subscribed to
+    pub async fn pubsub_numpat(&mut self) -> anyhow::Result<i64> {
+        self.execute_as(Command::PubSubNumPat).await
+    }
+
+    /// Start a pipeline. Queued commands are sent to the server at once by `Pipeline::exec`.
+    pub fn pipeline(&mut self) -> Pipeline<'_> {
+        Pipeline::new(self)
+    }
+
+    /// Start a transaction, i.e., an atomic pipeline wrapped in `MULTI` and `EXEC`.
+    pub fn transaction(&mut self) -> Pipeline<'_> {
+        Pipeline::new(self).atomic()
+    }
+
+    /// Watch keys for the next transaction
+    ///
+    /// The transaction is aborted if any of the keys is modified before `EXEC`.
+    pub async fn watch(&mut self, keys: Vec<String>) -> anyhow::Result<()> {
+        let command = Command::Watch { keys };
+        self.execute_as(command).await
+    }
+
+    /// Forget all watched keys
+    pub async fn unwatch(&mut self) -> anyhow::Result<()> {
+        self.execute_as(Command::Unwatch).await
+    }
+
+    /// Set field-value pairs of a hash
+    ///
+    /// Returns the number of fields that were added.
+    pub async fn hset(&mut self, key: String, fields: Vec<(Bytes, Bytes)>) -> anyhow::Result<i64> {
+        let command = Command::HSet { key, fields };
+        self.execute_as(command).await
+    }
+
+    /// Set a field of a hash only if it does not exist
+    ///
+    /// Returns `true` if the field was set.
+    pub async fn hsetnx(
+        &mut self,
+        key: String,
+        field: Bytes,
+        value: Bytes,
+    ) -> anyhow::Result<bool> {
+        let command = Command::HSetNx { key, field, value };
+        self.execute_as(command).await
+    }
+
+    /// Get the value of a hash field
+    pub async fn hget(&mut self, key: String, field: Bytes) -> anyhow::Result<Option<Bytes>> {
+        let command = Command::HGet { key, field };
+        self.execute_as(command).await
+    }
+
+    /// Get the values of multiple hash fields
+    pub async fn hmget(
+        &mut self,
+        key: String,
+        fields: Vec<Bytes>,
+    ) -> anyhow::Result<Vec<Option<Bytes>>> {
+        let command = Command::HMGet { key, fields };
+        self.execute_as(command).await
+    }
+
+    /// Delete one or more hash fields
+    ///
+    /// Returns the number of removed fields.
+    pub async fn hdel(&mut self, key: String, fields: Vec<Bytes>) -> anyhow::Result<i64> {
+        let command = Command::HDel { key, fields };
+        self.execute_as(command).await
+    }
+
+    /// Get all field-value pairs of a hash
+    pub async fn hgetall(&mut self, key: String) -> anyhow::Result<Vec<(Bytes, Bytes)>> {
+        let command = Command::HGetAll { key };
+        self.execute_as(command).await
+    }
+
+    /// Increment the integer value of a hash field
+    ///
+    /// Returns the value after the increment.
+    pub async fn hincrby(
+        &mut self,
+        key: String,
+        field: Bytes,
+        increment: i64,
+    ) -> anyhow::Result<i64> {
+        let command = Command::HIncrBy {
+            key,
+            field,
+            increment,
+        };
+        self.execute_as(command).await
+    }
+
+    /// Get all field names of a hash
+    pub async fn hkeys(&mut self, key: String) -> anyhow::Result<Vec<Bytes>> {
+        let command = Command::HKeys { key };
+        self.execute_as(command).await
+    }
+
+    /// Get all values of a hash
+    pub async fn hvals(&mut self, key: String) -> anyhow::Result<Vec<Bytes>> {
+        let command = Command::HVals { key };
+        self.execute_as(command).await
+    }
+
+    /// Get the number of fields of a hash
+    pub async fn hlen(&mut self, key: String) -> anyhow::Result<i64> {
+        let command = Command::HLen { key };
+        self.execute_as(command).await
+    }
+
+    /// Check if a hash field exists
+    pub async fn hexists(&mut self, key: String, field: Bytes) -> anyhow::Result<bool> {
+        let command = Command::HExists { key, field };
+        self.execute_as(command).await
+    }
+
+    /// Add members to a set
+    ///
+    /// Returns the number of members that were added.
+    pub async fn sadd(&mut self, key: String, members: Vec<Bytes>) -> anyhow::Result<i64> {
+        let command = Command::SAdd { key, members };
+        self.execute_as(command).await
+    }
+
+    /// Remove members from a set
+    ///
+    /// Returns the number of removed members.
+    pub async fn srem(&mut self, key: String, members: Vec<Bytes>) -> anyhow::Result<i64> {
+        let command = Command::SRem { key, members };
+        self.execute_as(command).await
+    }
+
+    /// Get all members of a set
+    pub async fn smembers(&mut self, key: String) -> anyhow::Result<Vec<Bytes>> {
+        let command = Command::SMembers { key };
+        self.execute_as(command).await
+    }
+
+    /// Check if a value is a member of a set
+    pub async fn sismember(&mut self, key: String, member: Bytes) -> anyhow::Result<bool> {
+        let command = Command::SIsMember { key, member };
+        self.execute_as(command).await
+    }
+
+    /// Get the number of members of a set
+    pub async fn scard(&mut self, key: String) -> anyhow::Result<i64> {
+        let command = Command::SCard { key };
+        self.execute_as(command).await
+    }
+
+    /// Remove and return a random member of a set
+    pub async fn spop(&mut self, key: String) -> anyhow::Result<Option<Bytes>> {
+        let command = Command::SPop { key, count: None };
+        self.execute_as(command).await
+    }
+
+    /// Remove and return up to `count` random members of a set
+    pub async fn spop_count(&mut self, key: String, count: u64) -> anyhow::Result<Vec<Bytes>> {
+        let command = Command::SPop {
+            key,
+            count: Some(count),
+        };
+        self.execute_as(command).await
+    }
+
+    /// Get a random member of a set
+    pub async fn srandmember(&mut self, key: String) -> anyhow::Result<Option<Bytes>> {
+        let command = Command::SRandMember { key, count: None };
+        self.execute_as(command).await
+    }
+
+    /// Get random members of a set
+    ///
+    /// Members are distinct for a positive `count`, and may repeat for a negative `count`.
+    pub async fn srandmember_count(
+        &mut self,
+        key: String,
+        count: i64,
+    ) -> anyhow::Result<Vec<Bytes>> {
+        let command = Command::SRandMember {
+            key,
+            count: Some(count),
+        };
+        self.execute_as(command).await
+    }
+
+    /// Intersect multiple sets
+    pub async fn sinter(&mut self, keys: Vec<String>) -> anyhow::Result<Vec<Bytes>> {
+        self.set_operation(SetOperation::Inter, keys).await
+    }
+
+    /// Add multiple sets
+    pub async fn sunion(&mut self, keys: Vec<String>) -> anyhow::Result<Vec<Bytes>> {
+        self.set_operation(SetOperation::Union, keys).await
+    }
+
+    /// Subtract multiple sets from the first one
+    pub async fn sdiff(&mut self, keys: Vec<String>) -> anyhow::Result<Vec<Bytes>> {
+        self.set_operation(SetOperation::Diff, keys).await
+    }
+
+    /// Intersect multiple sets and store the result in `destination`
+    ///
+    /// Returns the number of members of the resulting set.
+    pub async fn sinterstore(
+        &mut self,
+        destination: String,
+        keys: Vec<String>,
+    ) -> anyhow::Result<i64> {
+        self.set_operation_store(SetOperation::Inter, destination, keys)
+            .await
+    }
+
+    /// Add multiple sets and store the result in `destination`
+    ///
+    /// Returns the number of members of the resulting set.
+    pub async fn sunionstore(
+        &mut self,
+        destination: String,
+        keys: Vec<String>,
+    ) -> anyhow::Result<i64> {
+        self.set_operation_store(SetOperation::Union, destination, keys)
+            .await
+    }
+
+    /// Subtract multiple sets and store the result in `destination`
+    ///
+    /// Returns the number of members of the resulting set.
+    pub async fn sdiffstore(
+        &mut self,
+        destination: String,
+        keys: Vec<String>,
+    ) -> anyhow::Result<i64> {
+        self.set_operation_store(SetOperation::Diff, destination, keys)
+            .await
+    }
+
+    async fn set_operation(
+        &mut self,
+        operation: SetOperation,
+        keys: Vec<String>,
+    ) -> anyhow::Result<Vec<Bytes>> {
+        let command = Command::SetOp { operation, keys };
+        self.execute_as(command).await
+    }
+
+    async fn set_operation_store(
+        &mut self,
+        operation: SetOperation,
+        destination: String,
+        keys: Vec<String>,
+    ) -> anyhow::Result<i64> {
+        let command = Command::SetOpStore {
+            operation,
+            destination,
+            keys,
+        };
+        self.execute_as(command).await
+    }
+
+    /// Add members with scores to a sorted set
+    ///
+    /// Returns the number of added members.
+    pub async fn zadd(&mut self, key: String, members: Vec<(f64, Bytes)>) -> anyhow::Result<i64> {
+        self.zadd_with_options(key, members, ZAddOptions::default())
+            .await
+    }
+
+    /// Add members with scores to a sorted set using `NX`, `XX`, `GT`, `LT` or `CH`
+    ///
+    /// Returns the number of added (or changed with `CH`) members.
+    pub async fn zadd_with_options(
+        &mut self,
+        key: String,
+        members: Vec<(f64, Bytes)>,
+        options: ZAddOptions,
+    ) -> anyhow::Result<i64> {
+        let options = ZAddOptions {
+            increment: false,
+            ..options
+        };
+        let command = Command::ZAdd {
+            key,
+            options,
+            members,
+        };
+        self.execute_as(command).await
+    }
+
+    /// Increment the score of a member of a sorted set like `ZADD ... INCR`
+    ///
+    /// Returns the new score, or `None` if the operation was aborted by the options.
+    pub async fn zadd_incr(
+        &mut self,
+        key: String,
+        increment: f64,
+        member: Bytes,
+        options: ZAddOptions,
+    ) -> anyhow::Result<Option<f64>> {
+        let options = ZAddOptions {
+            increment: true,
+            ..options
+        };
+        let command = Command::ZAdd {
+            key,
+            options,
+            members: vec![(increment, member)],
+        };
+        self.execute_as(command).await
+    }
+
+    /// Get a range of members of a sorted set
+    ///
+    /// For score and lex ranges, `limit` is an `(offset, count)` pair.
+    pub async fn zrange(
+        &mut self,
+        key: String,
+        by: ZRangeBy,
+        rev: bool,
+        limit: Option<(i64, i64)>,
+    ) -> anyhow::Result<Vec<Bytes>> {
+        let command = Command::ZRange {
+            key,
+            by,
+            rev,
+            limit,
+            with_scores: false,
+        };
+        self.execute_as(command).await
+    }
+
+    /// Get a range of members of a sorted set together with their scores
+    pub async fn zrange_withscores(
+        &mut self,
+        key: String,
+        by: ZRangeBy,
+        rev: bool,
+        limit: Option<(i64, i64)>,
+    ) -> anyhow::Result<Vec<(Bytes, f64)>> {
+        let command = Command::ZRange {
+            key,
+            by,
+            rev,
+            limit,
+            with_scores: true,
+        };
+        self.execute_as(command).await
+    }
+
+    /// Get the rank (0-based, ascending by score) of a member of a sorted set
+    pub async fn zrank(&mut self, key: String, member: Bytes) -> anyhow::Result<Option<i64>> {
+        let command = Command::ZRank { key, member };
+        self.execute_as(command).await
+    }
+
+    /// Get the score of a member of a sorted set
+    pub async fn zscore(&mut self, key: String, member: Bytes) -> anyhow::Result<Option<f64>> {
+        let command = Command::ZScore { key, member };
+        self.execute_as(command).await
+    }
+
+    /// Remove members from a sorted set
+    ///
+    /// Returns the number of removed members.
+    pub async fn zrem(&mut self, key: String, members: Vec<Bytes>) -> anyhow::Result<i64> {
+        let command = Command::ZRem { key, members };
+        self.execute_as(command).await
+    }
+
+    /// Increment the score of a member of a sorted set
+    ///
+    /// Returns the new score.
+    pub async fn zincrby(
+        &mut self,
+        key: String,
+        increment: f64,
+        member: Bytes,
+    ) -> anyhow::Result<f64> {
+        let command = Command::ZIncrBy {
+            key,
+            increment,
+            member,
+        };
+        self.execute_as(command).await
+    }
+
+    /// Count the members of a sorted set with a score within the given bounds
+    pub async fn zcount(
+        &mut self,
+        key: String,
+        min: ScoreBound,
+        max: ScoreBound,
+    ) -> anyhow::Result<i64> {
+        let command = Command::ZCount { key, min, max };
+        self.execute_as(command).await
+    }
+
+    /// Get the number of members of a sorted set
+    pub async fn zcard(&mut self, key: String) -> anyhow::Result<i64> {
+        let command = Command::ZCard { key };
+        self.execute_as(command).await
+    }
+
+    /// Remove and return up to `count` members with the lowest scores
+    pub async fn zpopmin(
+        &mut self,
+        key: String,
+        count: Option<u64>,
+    ) -> anyhow::Result<Vec<(Bytes, f64)>> {
+        let command = Command::ZPop {
+            key,
+            count,
+            highest: false,
+        };
+        self.execute_as(command).await
+    }
+
+    /// Remove and return up to `count` members with the highest scores
+    pub async fn zpopmax(
+        &mut self,
+        key: String,
+        count: Option<u64>,
+    ) -> anyhow::Result<Vec<(Bytes, f64)>> {
+        let command = Command::ZPop {
+            key,
+            count,
+            highest: true,
+        };
+        self.execute_as(command).await
+    }
+
+    /// Append an entry to a stream, creating the stream if it does not exist
+    ///
+    /// Returns the ID of the added entry.
+    pub async fn xadd(
+        &mut self,
+        key: String,
+        id: XAddId,
+        fields: Vec<(Bytes, Bytes)>,
+    ) -> anyhow::Result<StreamId> {
+        let command = Command::XAdd {
+            key,
+            id,
+            fields,
+            trim: None,
+            create: true,
+        };
+        self.execute_as(command).await
+    }
+
+    /// Append an entry to a stream and trim the stream
+    ///
+    /// Returns `None` if the stream does not exist and `create` is `false` (`NOMKSTREAM`).
+    pub async fn xadd_with_options(
+        &mut self,
+        key: String,
+        id: XAddId,
+        fields: Vec<(Bytes, Bytes)>,
+        trim: Option<StreamTrim>,
+        create: bool,
+    ) -> anyhow::Result<Option<StreamId>> {
+        let command = Command::XAdd {
+            key,
+            id,
+            fields,
+            trim,
+            create,
+        };
+        self.execute_as(command).await
+    }
+
+    /// Get entries of a stream with IDs within the inclusive range `start..=end`
+    pub async fn xrange(
+        &mut self,
+        key: String,
+        start: StreamId,
+        end: StreamId,
+        count: Option<u64>,
+    ) -> anyhow::Result<Vec<StreamEntry>> {
+        let command = Command::XRange {
+            key,
+            start,
+            end,
+            rev: false,
+            count,
+        };
+        self.execute_as(command).await
+    }
+
+    /// Get entries of a stream with IDs within the inclusive range `start..=end`,
+    /// from the newest to the oldest
+    pub async fn xrevrange(
+        &mut self,
+        key: String,
+        end: StreamId,
+        start: StreamId,
+        count: Option<u64>,
+    ) -> anyhow::Result<Vec<StreamEntry>> {
+        let command = Command::XRange {
+            key,
+            start,
+            end,
+            rev: true,
+            count,
+        };
+        self.execute_as(command).await
+    }
+
+    /// Get the number of entries of a stream
+    pub async fn xlen(&mut self, key: String) -> anyhow::Result<i64> {
+        let command = Command::XLen { key };
+        self.execute_as(command).await
+    }
+
+    /// Trim a stream
+    ///
+    /// Returns the number of removed entries.
+    pub async fn xtrim(&mut self, key: String, trim: StreamTrim) -> anyhow::Result<i64> {
+        let command = Command::XTrim { key, trim };
+        self.execute_as(command).await
+    }
+
+    /// Delete entries from a stream
+    ///
+    /// Returns the number of deleted entries.
+    pub async fn xdel(&mut self, key: String, ids: Vec<StreamId>) -> anyhow::Result<i64> {
+        let command = Command::XDel { key, ids };
+        self.execute_as(command).await
+    }
+
+    /// Set the last ID of a stream, new entries must have a greater ID
+    pub async fn xsetid(&mut self, key: String, last_id: StreamId) -> anyhow::Result<()> {
+        let command = Command::XSetId { key, last_id };
+        self.execute_as(command).await
+    }
+
+    /// Read entries with an ID greater than the given one from multiple streams
+    ///
+    /// The ID `None` stands for `$`, i.e., only entries added after the call.
+    /// Returns only the streams with new entries.
+    pub async fn xread(
+        &mut self,
+        streams: Vec<(String, Option<StreamId>)>,
+        count: Option<u64>,
+    ) -> anyhow::Result<Vec<(String, Vec<StreamEntry>)>> {
+        let command = Command::XRead { streams, count };
+        let frame = self.execute_as(command).await?;
+        streams_from_frame(frame)
+    }
+
+    /// Create a consumer group that starts after `id` (`None` stands for `$`)
+    ///
+    /// With `create` (`MKSTREAM`), an empty stream is created if it does not exist.
+    pub async fn xgroup_create(
+        &mut self,
+        key: String,
+        group: String,
+        id: Option<StreamId>,
+        create: bool,
+    ) -> anyhow::Result<()> {
+        let command = Command::XGroupCreate {
+            key,
+            group,
+            id,
+            create,
+        };
+        self.execute_as(command).await
+    }
+
+    /// Destroy a consumer group
+    pub async fn xgroup_destroy(&mut self, key: String, group: String) -> anyhow::Result<bool> {
+        let command = Command::XGroupDestroy { key, group };
+        self.execute_as(command).await
+    }
+
+    /// Create a consumer in a consumer group
+    pub async fn xgroup_createconsumer(
+        &mut self,
+        key: String,
+        group: String,
+        consumer: String,
+    ) -> anyhow::Result<bool> {
+        let command = Command::XGroupCreateConsumer {
+            key,
+            group,
+            consumer,
+        };
+        self.execute_as(command).await
+    }
+
+    /// Delete a consumer from a consumer group
+    ///
+    /// Returns the number of pending entries the consumer had.
+    pub async fn xgroup_delconsumer(
+        &mut self,
+        key: String,
+        group: String,
+        consumer: String,
+    ) -> anyhow::Result<i64> {
+        let command = Command::XGroupDelConsumer {
+            key,
+            group,
+            consumer,
+        };
+        self.execute_as(command).await
+    }
+
+    /// Set the last delivered ID of a consumer group (`None` stands for `$`)
+    pub async fn xgroup_setid(
+        &mut self,
+        key: String,
+        group: String,
+        id: Option<StreamId>,
+    ) -> anyhow::Result<()> {
+        let command = Command::XGroupSetId { key, group, id };
+        self.execute_as(command).await
+    }
+
+    /// Read entries from multiple streams as `consumer` of `group`
+    ///
+    /// The ID `None` (`>`) reads new entries, any other ID reads the pending entries
+    /// of the consumer.
+    pub async fn xreadgroup(
+        &mut self,
+        group: String,
+        consumer: String,
+        streams: Vec<(String, Option<StreamId>)>,
+        count: Option<u64>,
+        noack: bool,
+    ) -> anyhow::Result<Vec<(String, Vec<StreamEntry>)>> {
+        let command = Command::XReadGroup {
+            group,
+            consumer,
+            streams,
+            count,
+            noack,
+        };
+        let frame = self.execute_as(command).await?;
+        streams_from_frame(frame)
+    }
+
+    /// Acknowledge entries of a consumer group
+    ///
+    /// Returns the number of acknowledged entries.
+    pub async fn xack(
+        &mut self,
+        key: String,
+        group: String,
+        ids: Vec<StreamId>,
+    ) -> anyhow::Result<i64> {
+        let command = Command::XAck { key, group, ids };
+        self.execute_as(command).await
+    }
+
+    /// Get the summary of the pending entries of a consumer group
+    pub async fn xpending(&mut self, key: String, group: String) -> anyhow::Result<PendingSummary> {
+        let command = Command::XPending {
+            key,
+            group,
+            range: None,
+        };
+        self.execute_as(command).await
+    }
+
+    /// Get the pending entries of a consumer group within a range
+    pub async fn xpending_range(
+        &mut self,
+        key: String,
+        group: String,
+        range: PendingRange,
+    ) -> anyhow::Result<Vec<PendingEntry>> {
+        let command = Command::XPending {
+            key,
+            group,
+            range: Some(range),
+        };
+        self.execute_as(command).await
+    }
+
+    /// Claim pending entries idle for at least `min_idle` milliseconds
+    pub async fn xclaim(
+        &mut self,
+        key: String,
+        group: String,
+        consumer: String,
+        min_idle: u64,
+        ids: Vec<StreamId>,
+    ) -> anyhow::Result<Vec<StreamEntry>> {
+        let command = Command::XClaim {
+            key,
+            group,
+            consumer,
+            min_idle,
+            ids,
+            just_id: false,
+            options: ClaimOptions::default(),
+        };
+        self.execute_as(command).await
+    }
+
+    /// Claim pending entries like `xclaim` without incrementing their delivery count
+    ///
+    /// Returns only the IDs of the claimed entries.
+    pub async fn xclaim_justid(
+        &mut self,
+        key: String,
+        group: String,
+        consumer: String,
+        min_idle: u64,
+        ids: Vec<StreamId>,
+    ) -> anyhow::Result<Vec<StreamId>> {
+        let command = Command::XClaim {
+            key,
+            group,
+            consumer,
+            min_idle,
+            ids,
+            just_id: true,
+            options: ClaimOptions::default(),
+        };
+        self.execute_as(command).await
+    }
+
+    /// Scan the pending entries from `start` and claim those idle
+    /// for at least `min_idle` milliseconds
+    pub async fn xautoclaim(
+        &mut self,
+        key: String,
+        group: String,
+        consumer: String,
+        min_idle: u64,
+        start: StreamId,
+        count: Option<u64>,
+    ) -> anyhow::Result<AutoClaim> {
+        let command = Command::XAutoClaim {
+            key,
+            group,
+            consumer,
+            min_idle,
+            start,
+            count,
+            just_id: false,
+        };
+        self.execute_as(command).await
+    }
+
+    /// Get information about a stream
+    pub async fn xinfo_stream(&mut self, key: String) -> anyhow::Result<StreamInfo> {
+        let command = Command::XInfoStream { key };
+        self.execute_as(command).await
+    }
+
+    /// Get information about the consumer groups of a stream
+    pub async fn xinfo_groups(&mut self, key: String) -> anyhow::Result<Vec<GroupInfo>> {
+        let command = Command::XInfoGroups { key };
+        self.execute_as(command).await
+    }
+
+    /// Get information about the consumers of a consumer group
+    pub async fn xinfo_consumers(
+        &mut self,
+        key: String,
+        group: String,
+    ) -> anyhow::Result<Vec<ConsumerInfo>> {
+        let command = Command::XInfoConsumers { key, group };
+        self.execute_as(command).await
+    }
 }
@@ -0,0 +1,357 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use anyhow::{anyhow, bail};
+use bytes::Bytes;
+
+use super::{FromFrame, RedisClient};
+use crate::cluster::{Cluster, SLOTS};
+use crate::cmd::Command;
+use crate::constants::{CLUSTER_MAX_REDIRECTIONS, CLUSTER_RETRY_DELAY};
+use crate::db::{ListEnd, SetOperation};
+use crate::err::RedisClientError;
+use crate::frame::Frame;
+
+/// Address of a node, i.e., its host and port.
+type Addr = (String, u16);
+
+/// Redirection of a command to another node, parsed from an error reply.
+enum Redirection {
+    /// `MOVED slot host:port`, the slot is served by another node.
+    Moved(u16, Addr),
+    /// `ASK slot host:port`, the key is being migrated to another node.
+    Ask(Addr),
+    /// `TRYAGAIN`, some keys of the command are being migrated.
+    TryAgain,
+}
+
+impl Redirection {
+    fn parse(error: &str) -> Option<Self> {
+        let mut parts = error.split_whitespace();
+        let kind = parts.next()?;
+        if kind == "TRYAGAIN" {
+            return Some(Self::TryAgain);
+        }
+        let slot = parts.next()?.parse().ok()?;
+        let (host, port) = parts.next()?.rsplit_once(':')?;
+        let addr = (host.to_string(), port.parse().ok()?);
+        match kind {
+            "MOVED" => Some(Self::Moved(slot, addr)),
+            "ASK" => Some(Self::Ask(addr)),
+            _ => None,
+        }
+    }
+}
+
+/// Client of a cluster that sends each command to the node serving the slot of its keys.
+///
+/// The slots of the nodes are discovered by `CLUSTER SLOTS` and reloaded when a node
+/// redirects a command by `MOVED`. Keys that are being migrated are followed by `ASK`.
+/// Commands with keys of different slots are rejected before they are sent.
+///
+/// ## Example
+/// ```no_run
+/// # async fn example() -> anyhow::Result<()> {
+/// let mut client = redis_clone::ClusterClient::new("127.0.0.1", 7000).await?;
+/// client
+///     .set("foo".to_string(), bytes::Bytes::from("bar"), None)
+///     .await?;
+/// let value = client.get("foo".to_string()).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct ClusterClient {
+    /// Connections to the nodes, opened on first use.
+    connections: HashMap<Addr, RedisClient>,
+    /// Node serving each slot.
+    slots: Vec<Option<Addr>>,
+    /// Nodes to discover the slots from, i.e., the initial node and all nodes seen since.
+    seeds: Vec<Addr>,
+}
+
+impl ClusterClient {
+    /// Connect to a node of the cluster and discover the slots of all nodes
+    pub async fn new(address: &str, port: u16) -> anyhow::Result<Self> {
+        let mut client = Self {
+            connections: HashMap::new(),
+            slots: vec![None; SLOTS as usize],
+            seeds: vec![(address.to_string(), port)],
+        };
+        client.refresh_slots().await?;
+        Ok(client)
+    }
+
+    /// Reload the slots of the nodes from the first node that replies to `CLUSTER SLOTS`
+    pub async fn refresh_slots(&mut self) -> anyhow::Result<()> {
+        let mut last_error = None;
+        for addr in self.seeds.clone() {
+            let ranges = match self.connection(&addr).await {
+                Ok(client) => client.cluster_slots().await,
+                Err(e) => Err(e),
+            };
+            let ranges = match ranges {
+                Ok(ranges) => ranges,
+                Err(e) => {
+                    self.connections.remove(&addr);
+                    last_error = Some(e);
+                    continue;
+                }
+            };
+            self.slots.fill(None);
+            for range in ranges {
+                let addr = (range.host, range.port);
+                for slot in range.start..=range.end {
+                    self.slots[slot as usize] = Some(addr.clone());
+                }
+                if !self.seeds.contains(&addr) {
+                    self.seeds.push(addr);
+                }
+            }
+            return Ok(());
+        }
+        Err(last_error.unwrap_or_else(|| anyhow!("No nodes to discover the cluster from")))
+    }
+
+    /// Get the address of the node serving a slot, as last seen by the client
+    pub fn node_for_slot(&self, slot: u16) -> Option<(&str, u16)> {
+        self.slots
+            .get(slot as usize)?
+            .as_ref()
+            .map(|(host, port)| (host.as_str(), *port))
+    }
+
+    async fn connection(&mut self, addr: &Addr) -> anyhow::Result<&mut RedisClient> {
+        if !self.connections.contains_key(addr) {
+            let client = RedisClient::new(&addr.0, addr.1).await?;
+            self.connections.insert(addr.clone(), client);
+        }
+        Ok(self.connections.get_mut(addr).unwrap())
+    }
+
+    /// Send a command to the node serving its keys and follow the redirections.
+    /// Commands without keys are sent to the initial node.
+    async fn execute(&mut self, command: Command) -> anyhow::Result<Option<Frame>> {
+        let slot = Cluster::keys_slot(&command.keys())?;
+        let mut addr = slot
+            .and_then(|slot| self.slots[slot as usize].clone())
+            .unwrap_or_else(|| self.seeds[0].clone());
+        let mut asking = false;
+        for _ in 0..CLUSTER_MAX_REDIRECTIONS {
+            let client = self.connection(&addr).await?;
+            if asking {
+                if let Err(e) = client.execute_as::<()>(Command::Asking).await {
+                    self.connections.remove(&addr);
+                    return Err(e);
+                }
+            }
+            let response = match client.execute(command.clone()).await {
+                Ok(response) => response,
+                Err(e) => {
+                    self.connections.remove(&addr);
+                    return Err(e);
+                }
+            };
+            let redirection = match &response {
+                Some(Frame::Error(e)) => Redirection::parse(e),
+                _ => None,
+            };
+            match redirection {
+                Some(Redirection::Moved(slot, node)) => {
+                    // The slot is updated right away in case the other slots cannot be reloaded
+                    self.slots[slot as usize] = Some(node.clone());
+                    if let Err(e) = self.refresh_slots().await {
+                        log::warn!("Error reloading the slots of the cluster: {}", e);
+                    }
+                    (addr, asking) = (node, false);
+                }
+                Some(Redirection::Ask(node)) => (addr, asking) = (node, true),
+                Some(Redirection::TryAgain) => tokio::time::sleep(CLUSTER_RETRY_DELAY).await,
+                None => return Ok(response),
+            }
+        }
+        bail!(RedisClientError::TooManyRedirections)
+    }
+
+    /// Send a command and convert the response to a typed value.
+    async fn execute_as<T: FromFrame>(&mut self, command: Command) -> anyhow::Result<T> {
+        match self.execute(command).await? {
+            Some(frame) => T::from_frame(frame),
+            None => bail!(RedisClientError::ConnectionClosed),
+        }
+    }
+
+    /// Ping the initial node
+    pub async fn ping(&mut self, message: Option<String>) -> anyhow::Result<Option<Frame>> {
+        self.execute(Command::Ping { msg: message }).await
+    }
+
+    /// Get a value by key
+    pub async fn get(&mut self, key: String) -> anyhow::Result<Option<Frame>> {
+        self.execute(Command::Get { key }).await
+    }
+
+    /// Set a key-value pair with an optional expiration
+    pub async fn set(
+        &mut self,
+        key: String,
+        val: Bytes,
+        expiration: Option<Duration>,
+    ) -> anyhow::Result<Option<Frame>> {
+        self.execute(Command::Set {
+            key,
+            val,
+            expiration,
+        })
+        .await
+    }
+
+    /// Delete keys of one slot
+    pub async fn del(&mut self, keys: Vec<String>) -> anyhow::Result<Option<Frame>> {
+        self.execute(Command::Del { keys }).await
+    }
+
+    /// Count the existing keys of one slot
+    pub async fn exists(&mut self, keys: Vec<String>) -> anyhow::Result<Option<Frame>> {
+        self.execute(Command::Exists { keys }).await
+    }
+
+    /// Increment the integer value of a key by one
+    pub async fn incr(&mut self, key: String) -> anyhow::Result<Option<Frame>> {
+        self.execute(Command::Increment { key }).await
+    }
+
+    /// Set a key to expire in `seconds`
+    pub async fn expire(&mut self, key: String, seconds: u64) -> anyhow::Result<Option<Frame>> {
+        self.execute(Command::Expire {
+            key,
+            duration: Duration::from_secs(seconds),
+        })
+        .await
+    }
+
+    /// Get the time-to-live for a key
+    pub async fn ttl(&mut self, key: String) -> anyhow::Result<Option<Frame>> {
+        self.execute(Command::TTL { key }).await
+    }
+
+    /// Prepend values to a list
+    pub async fn lpush(
+        &mut self,
+        key: String,
+        values: Vec<Bytes>,
+    ) -> anyhow::Result<Option<Frame>> {
+        self.execute(Command::Push {
+            key,
+            values,
+            end: ListEnd::Left,
+        })
+        .await
+    }
+
+    /// Append values to a list
+    pub async fn rpush(
+        &mut self,
+        key: String,
+        values: Vec<Bytes>,
+    ) -> anyhow::Result<Option<Frame>> {
+        self.execute(Command::Push {
+            key,
+            values,
+            end: ListEnd::Right,
+        })
+        .await
+    }
+
+    /// Remove and get the first element(s) of a list
+    pub async fn lpop(&mut self, key: String, count: Option<u64>) -> anyhow::Result<Option<Frame>> {
+        self.execute(Command::Pop {
+            key,
+            end: ListEnd::Left,
+            count,
+        })
+        .await
+    }
+
+    /// Remove and get the last element(s) of a list
+    pub async fn rpop(&mut self, key: String, count: Option<u64>) -> anyhow::Result<Option<Frame>> {
+        self.execute(Command::Pop {
+            key,
+            end: ListEnd::Right,
+            count,
+        })
+        .await
+    }
+
+    /// Get a range of elements of a list
+    pub async fn lrange(
+        &mut self,
+        key: String,
+        start: i64,
+        stop: i64,
+    ) -> anyhow::Result<Option<Frame>> {
+        self.execute(Command::LRange { key, start, stop }).await
+    }
+
+    /// Get the length of a list
+    pub async fn llen(&mut self, key: String) -> anyhow::Result<Option<Frame>> {
+        self.execute(Command::LLen { key }).await
+    }
+
+    /// Set fields of a hash, returns the number of added fields
+    pub async fn hset(&mut self, key: String, fields: Vec<(Bytes, Bytes)>) -> anyhow::Result<i64> {
+        self.execute_as(Command::HSet { key, fields }).await
+    }
+
+    /// Get the value of a field of a hash
+    pub async fn hget(&mut self, key: String, field: Bytes) -> anyhow::Result<Option<Bytes>> {
+        self.execute_as(Command::HGet { key, field }).await
+    }
+
+    /// Delete fields of a hash, returns the number of removed fields
+    pub async fn hdel(&mut self, key: String, fields: Vec<Bytes>) -> anyhow::Result<i64> {
+        self.execute_as(Command::HDel { key, fields }).await
+    }
+
+    /// Get all field-value pairs of a hash
+    pub async fn hgetall(&mut self, key: String) -> anyhow::Result<Vec<(Bytes, Bytes)>> {
+        self.execute_as(Command::HGetAll { key }).await
+    }
+
+    /// Add members to a set, returns the number of added members
+    pub async fn sadd(&mut self, key: String, members: Vec<Bytes>) -> anyhow::Result<i64> {
+        self.execute_as(Command::SAdd { key, members }).await
+    }
+
+    /// Remove members from a set, returns the number of removed members
+    pub async fn srem(&mut self, key: String, members: Vec<Bytes>) -> anyhow::Result<i64> {
+        self.execute_as(Command::SRem { key, members }).await
+    }
+
+    /// Get all members of a set
+    pub async fn smembers(&mut self, key: String) -> anyhow::Result<Vec<Bytes>> {
+        self.execute_as(Command::SMembers { key }).await
+    }
+
+    /// Intersect sets of one slot
+    pub async fn sinter(&mut self, keys: Vec<String>) -> anyhow::Result<Vec<Bytes>> {
+        self.set_operation(SetOperation::Inter, keys).await
+    }
+
+    /// Add sets of one slot
+    pub async fn sunion(&mut self, keys: Vec<String>) -> anyhow::Result<Vec<Bytes>> {
+        self.set_operation(SetOperation::Union, keys).await
+    }
+
+    /// Subtract sets of one slot from the first one
+    pub async fn sdiff(&mut self, keys: Vec<String>) -> anyhow::Result<Vec<Bytes>> {
+        self.set_operation(SetOperation::Diff, keys).await
+    }
+
+    async fn set_operation(
+        &mut self,
+        operation: SetOperation,
+        keys: Vec<String>,
+    ) -> anyhow::Result<Vec<Bytes>> {
+        self.execute_as(Command::SetOp { operation, keys }).await
+    }
+}
@@ -0,0 +1,287 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use anyhow::bail;
+use bytes::Bytes;
+
+use super::{unexpected, FromFrame, RedisClient};
+use crate::cmd::Command;
+use crate::db::{ListEnd, ZAddOptions};
+use crate::err::RedisClientError;
+use crate::frame::Frame;
+
+/// Commands sent to the server at once, created by `RedisClient::pipeline`
+/// or `RedisClient::transaction`.
+///
+/// Commands are only sent to the server by `exec`, which writes all of them before
+/// reading the responses. With `atomic`, the commands are wrapped in `MULTI` and `EXEC`.
+///
+/// ## Example
+/// ```no_run
+/// # async fn example(client: &mut redis_clone::RedisClient) -> anyhow::Result<()> {
+/// let mut responses = client
+///     .pipeline()
+///     .incr("visits".to_string())
+///     .get("total".to_string())
+///     .exec()
+///     .await?;
+/// let visits: i64 = responses.next_as()?;
+/// let total: Option<bytes::Bytes> = responses.next_as()?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct Pipeline<'a> {
+    client: &'a mut RedisClient,
+    commands: Vec<Command>,
+    atomic: bool,
+}
+
+/// Responses of the commands of a pipeline, in the order the commands were queued.
+#[derive(Debug)]
+pub struct Responses {
+    frames: VecDeque<Frame>,
+}
+
+impl Responses {
+    /// Number of remaining responses
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+
+    /// Take the response of the next command and convert it to a typed value.
+    ///
+    /// Error responses are returned as `RedisClientError::ServerError`.
+    pub fn next_as<T: FromFrame>(&mut self) -> anyhow::Result<T> {
+        match self.frames.pop_front() {
+            // Attributes are auxiliary data, only the value is converted
+            Some(Frame::Attribute(_, frame)) => T::from_frame(*frame),
+            Some(frame) => T::from_frame(frame),
+            None => bail!(RedisClientError::UnexpectedResponse(
+                "No more responses in the pipeline".to_string()
+            )),
+        }
+    }
+
+    /// Take the remaining responses, including error frames
+    pub fn into_frames(self) -> Vec<Frame> {
+        self.frames.into()
+    }
+}
+
+impl<'a> Pipeline<'a> {
+    pub(super) fn new(client: &'a mut RedisClient) -> Self {
+        Self {
+            client,
+            commands: Vec::new(),
+            atomic: false,
+        }
+    }
+
+    fn command(mut self, command: Command) -> Self {
+        self.commands.push(command);
+        self
+    }
+
+    /// Execute the commands atomically, i.e., wrapped in `MULTI` and `EXEC`
+    pub fn atomic(mut self) -> Self {
+        self.atomic = true;
+        self
+    }
+
+    /// Queue `PING`
+    pub fn ping(self, message: Option<String>) -> Self {
+        self.command(Command::Ping { msg: message })
+    }
+
+    /// Queue `GET`
+    pub fn get(self, key: String) -> Self {
+        self.command(Command::Get { key })
+    }
+
+    /// Queue `SET`
+    pub fn set(self, key: String, val: Bytes, expiration: Option<Duration>) -> Self {
+        self.command(Command::Set {
+            key,
+            val,
+            expiration,
+        })
+    }
+
+    /// Queue `DEL`
+    pub fn del(self, keys: Vec<String>) -> Self {
+        self.command(Command::Del { keys })
+    }
+
+    /// Queue `EXISTS`
+    pub fn exists(self, keys: Vec<String>) -> Self {
+        self.command(Command::Exists { keys })
+    }
+
+    /// Queue `INCR`
+    pub fn incr(self, key: String) -> Self {
+        self.command(Command::Increment { key })
+    }
+
+    /// Queue `EXPIRE`
+    pub fn expire(self, key: String, seconds: u64) -> Self {
+        self.command(Command::Expire {
+            key,
+            duration: Duration::from_secs(seconds),
+        })
+    }
+
+    /// Queue `TTL`
+    pub fn ttl(self, key: String) -> Self {
+        self.command(Command::TTL { key })
+    }
+
+    /// Queue `LPUSH`
+    pub fn lpush(self, key: String, values: Vec<Bytes>) -> Self {
+        self.command(Command::Push {
+            key,
+            values,
+            end: ListEnd::Left,
+        })
+    }
+
+    /// Queue `RPUSH`
+    pub fn rpush(self, key: String, values: Vec<Bytes>) -> Self {
+        self.command(Command::Push {
+            key,
+            values,
+            end: ListEnd::Right,
+        })
+    }
+
+    /// Queue `LPOP`
+    pub fn lpop(self, key: String, count: Option<u64>) -> Self {
+        self.command(Command::Pop {
+            key,
+            end: ListEnd::Left,
+            count,
+        })
+    }
+
+    /// Queue `RPOP`
+    pub fn rpop(self, key: String, count: Option<u64>) -> Self {
+        self.command(Command::Pop {
+            key,
+            end: ListEnd::Right,
+            count,
+        })
+    }
+
+    /// Queue `LRANGE`
+    pub fn lrange(self, key: String, start: i64, stop: i64) -> Self {
+        self.command(Command::LRange { key, start, stop })
+    }
+
+    /// Queue `LLEN`
+    pub fn llen(self, key: String) -> Self {
+        self.command(Command::LLen { key })
+    }
+
+    /// Queue `HSET`
+    pub fn hset(self, key: String, fields: Vec<(Bytes, Bytes)>) -> Self {
+        self.command(Command::HSet { key, fields })
+    }
+
+    /// Queue `HGET`
+    pub fn hget(self, key: String, field: Bytes) -> Self {
+        self.command(Command::HGet { key, field })
+    }
+
+    /// Queue `HGETALL`
+    pub fn hgetall(self, key: String) -> Self {
+        self.command(Command::HGetAll { key })
+    }
+
+    /// Queue `HINCRBY`
+    pub fn hincrby(self, key: String, field: Bytes, increment: i64) -> Self {
+        self.command(Command::HIncrBy {
+            key,
+            field,
+            increment,
+        })
+    }
+
+    /// Queue `SADD`
+    pub fn sadd(self, key: String, members: Vec<Bytes>) -> Self {
+        self.command(Command::SAdd { key, members })
+    }
+
+    /// Queue `SREM`
+    pub fn srem(self, key: String, members: Vec<Bytes>) -> Self {
+        self.command(Command::SRem { key, members })
+    }
+
+    /// Queue `SMEMBERS`
+    pub fn smembers(self, key: String) -> Self {
+        self.command(Command::SMembers { key })
+    }
+
+    /// Queue `ZADD`
+    pub fn zadd(self, key: String, members: Vec<(f64, Bytes)>) -> Self {
+        self.command(Command::ZAdd {
+            key,
+            options: ZAddOptions::default(),
+            members,
+        })
+    }
+
+    /// Queue `ZINCRBY`
+    pub fn zincrby(self, key: String, increment: f64, member: Bytes) -> Self {
+        self.command(Command::ZIncrBy {
+            key,
+            increment,
+            member,
+        })
+    }
+
+    /// Send the queued commands in one batch and read their responses.
+    ///
+    /// Commands that fail do not stop the pipeline, their responses are error frames.
+    /// An atomic pipeline fails as a whole if a command is rejected while queuing,
+    /// or with `RedisClientError::TransactionAborted` if a watched key was modified.
+    pub async fn exec(self) -> anyhow::Result<Responses> {
+        let Pipeline {
+            client,
+            commands,
+            atomic,
+        } = self;
+        let count = commands.len();
+        let mut frames = Vec::with_capacity(count + 2);
+        if atomic {
+            frames.push(Command::Multi.into_frame()?);
+        }
+        for command in commands {
+            frames.push(command.into_frame()?);
+        }
+        if atomic {
+            frames.push(Command::Exec.into_frame()?);
+        }
+        let mut responses = client.execute_batch(&frames).await?;
+        if !atomic {
+            return Ok(Responses {
+                frames: responses.into(),
+            });
+        }
+
+        // Replies of `MULTI` and of the queued commands precede the reply of `EXEC`
+        let exec = responses.pop().unwrap();
+        for response in responses {
+            <()>::from_frame(response)?;
+        }
+        match exec {
+            Frame::Array(frames) if frames.len() == count => Ok(Responses {
+                frames: frames.into(),
+            }),
+            Frame::Null => bail!(RedisClientError::TransactionAborted),
+            frame => Err(unexpected(frame)),
+        }
+    }
+}
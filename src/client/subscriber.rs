@@ -0,0 +1,178 @@
+use std::collections::VecDeque;
+
+use anyhow::bail;
+use bytes::Bytes;
+use tokio_stream::Stream;
+
+use super::{fixed_array, unexpected, FromFrame, RedisClient};
+use crate::cmd::Command;
+use crate::err::RedisClientError;
+use crate::frame::Frame;
+use crate::pubsub::Message;
+
+/// Frame pushed by the server to a subscribed client.
+enum Push {
+    Message(Message),
+    /// Confirmation of `SUBSCRIBE`, `UNSUBSCRIBE` etc., with the channel or pattern name.
+    Confirmation(String, Option<String>),
+}
+
+impl FromFrame for Push {
+    fn from_frame(frame: Frame) -> anyhow::Result<Self> {
+        let (Frame::Array(frames) | Frame::Push(frames)) = frame else {
+            return Err(unexpected(frame));
+        };
+        let kind = match frames.first() {
+            Some(Frame::Bulk(kind)) => kind.clone(),
+            _ => return Err(unexpected(Frame::Array(frames))),
+        };
+        match kind.as_ref() {
+            b"message" => {
+                let [_, channel, payload] = fixed_array(Frame::Array(frames))?;
+                Ok(Push::Message(Message {
+                    channel: String::from_frame(channel)?,
+                    pattern: None,
+                    payload: Bytes::from_frame(payload)?,
+                }))
+            }
+            b"pmessage" => {
+                let [_, pattern, channel, payload] = fixed_array(Frame::Array(frames))?;
+                Ok(Push::Message(Message {
+                    channel: String::from_frame(channel)?,
+                    pattern: Some(String::from_frame(pattern)?),
+                    payload: Bytes::from_frame(payload)?,
+                }))
+            }
+            _ => {
+                let [kind, name, _count] = fixed_array(Frame::Array(frames))?;
+                Ok(Push::Confirmation(
+                    String::from_frame(kind)?,
+                    Option::from_frame(name)?,
+                ))
+            }
+        }
+    }
+}
+
+/// Client in the subscriber mode, created by `RedisClient::subscribe` or `RedisClient::psubscribe`.
+///
+/// Only subscription commands can be sent, messages are received with `next_message`
+/// or as a `Stream` with `into_stream`.
+pub struct Subscriber {
+    client: RedisClient,
+    channels: Vec<String>,
+    patterns: Vec<String>,
+    /// Messages received while waiting for subscription confirmations.
+    pending: VecDeque<Message>,
+}
+
+impl Subscriber {
+    pub(super) fn new(client: RedisClient) -> Self {
+        Self {
+            client,
+            channels: Vec::new(),
+            patterns: Vec::new(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// Channels the client is subscribed to.
+    pub fn channels(&self) -> &[String] {
+        &self.channels
+    }
+
+    /// Patterns the client is subscribed to.
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+
+    /// Subscribe to additional channels
+    pub async fn subscribe(&mut self, channels: Vec<String>) -> anyhow::Result<()> {
+        let expected = channels.len();
+        self.send(Command::Subscribe { channels }, expected).await
+    }
+
+    /// Unsubscribe from channels, or from all channels if `channels` is empty
+    pub async fn unsubscribe(&mut self, channels: Vec<String>) -> anyhow::Result<()> {
+        let expected = match channels.len() {
+            0 => self.channels.len().max(1),
+            len => len,
+        };
+        self.send(Command::Unsubscribe { channels }, expected).await
+    }
+
+    /// Subscribe to channels matching glob patterns
+    pub async fn psubscribe(&mut self, patterns: Vec<String>) -> anyhow::Result<()> {
+        let expected = patterns.len();
+        self.send(Command::PSubscribe { patterns }, expected).await
+    }
+
+    /// Unsubscribe from patterns, or from all patterns if `patterns` is empty
+    pub async fn punsubscribe(&mut self, patterns: Vec<String>) -> anyhow::Result<()> {
+        let expected = match patterns.len() {
+            0 => self.patterns.len().max(1),
+            len => len,
+        };
+        self.send(Command::PUnsubscribe { patterns }, expected)
+            .await
+    }
+
+    /// Receive the next message.
+    ///
+    /// Returns `None` if the server closed the connection.
+    pub async fn next_message(&mut self) -> anyhow::Result<Option<Message>> {
+        if let Some(message) = self.pending.pop_front() {
+            return Ok(Some(message));
+        }
+        match self.client.conn.read_frame().await? {
+            Some(frame) => match Push::from_frame(frame)? {
+                Push::Message(message) => Ok(Some(message)),
+                Push::Confirmation(kind, _) => bail!(RedisClientError::UnexpectedResponse(
+                    format!("Unexpected '{}' confirmation", kind)
+                )),
+            },
+            None => Ok(None),
+        }
+    }
+
+    /// Convert the subscriber into a `Stream` of messages.
+    /// The stream ends when the server closes the connection.
+    pub fn into_stream(mut self) -> impl Stream<Item = anyhow::Result<Message>> {
+        async_stream::try_stream! {
+            while let Some(message) = self.next_message().await? {
+                yield message;
+            }
+        }
+    }
+
+    /// Send a subscription command and wait for `expected` confirmations.
+    /// Messages received in the meantime are kept for `next_message`.
+    async fn send(&mut self, command: Command, expected: usize) -> anyhow::Result<()> {
+        let frame = command.into_frame()?;
+        self.client.conn.write_frame(&frame).await?;
+
+        let mut confirmed = 0;
+        while confirmed < expected {
+            let Some(frame) = self.client.conn.read_frame().await? else {
+                bail!(RedisClientError::ConnectionClosed);
+            };
+            match Push::from_frame(frame)? {
+                Push::Message(message) => self.pending.push_back(message),
+                Push::Confirmation(kind, name) => {
+                    confirmed += 1;
+                    let Some(name) = name else {
+                        continue;
+                    };
+                    match kind.as_str() {
+                        "subscribe" => self.channels.push(name),
+                        "psubscribe" => self.patterns.push(name),
+                        "unsubscribe" => self.channels.retain(|channel| *channel != name),
+                        "punsubscribe" => self.patterns.retain(|pattern| *pattern != name),
+                        _ => bail!(RedisClientError::UnexpectedResponse(kind)),
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
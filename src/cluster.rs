@@ -0,0 +1,516 @@
+use std::collections::BTreeMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex as StdMutex};
+
+use anyhow::{anyhow, bail};
+
+use crate::common::random_id;
+use crate::config::Config;
+use crate::db::DB;
+use crate::err::RedisClusterError;
+
+/// Number of hash slots of a cluster.
+pub const SLOTS: u16 = 16384;
+
+/// Offset of the cluster bus port of a node, written to the nodes file as `ip:port@cport`.
+const BUS_PORT_OFFSET: u32 = 10000;
+
+/// Lookup table of CRC16 (XMODEM), i.e., polynomial `0x1021` with zero initial value.
+const CRC16_TABLE: [u16; 256] = {
+    let mut table = [0u16; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = (i as u16) << 8;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 0x8000 != 0 {
+                (crc << 1) ^ 0x1021
+            } else {
+                crc << 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+/// CRC16 (XMODEM) used to map keys to hash slots.
+pub fn crc16(data: &[u8]) -> u16 {
+    data.iter().fold(0u16, |crc, &byte| {
+        (crc << 8) ^ CRC16_TABLE[((crc >> 8) as u8 ^ byte) as usize]
+    })
+}
+
+/// Hash slot of a key. If the key contains a non-empty hash tag, e.g., `{user1000}.following`,
+/// only the tag is hashed, so that related keys are stored on the same node.
+pub fn key_slot(key: &[u8]) -> u16 {
+    let hashed = match key.iter().position(|&b| b == b'{') {
+        Some(start) => match key[start + 1..].iter().position(|&b| b == b'}') {
+            Some(len) if len > 0 => &key[start + 1..start + 1 + len],
+            _ => key,
+        },
+        None => key,
+    };
+    crc16(hashed) % SLOTS
+}
+
+/// Primary node of a cluster with the ranges of hash slots it serves.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ClusterNode {
+    pub id: String,
+    pub host: String,
+    pub port: u16,
+    /// Inclusive ranges of slots, e.g., `(0, 5460)`.
+    pub slots: Vec<(u16, u16)>,
+}
+
+impl ClusterNode {
+    /// Address of the node in the `host:port` format of redirections.
+    pub fn addr(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+}
+
+/// Range of slots served by a node, an element of the reply of `CLUSTER SLOTS`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlotRange {
+    pub start: u16,
+    pub end: u16,
+    pub host: String,
+    pub port: u16,
+    pub id: String,
+}
+
+/// State of a hash slot set by `CLUSTER SETSLOT`, used to migrate the slot between nodes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SlotState {
+    /// The slot is imported from the node with the ID, its keys are served after `ASKING`.
+    Importing(String),
+    /// The slot is migrated to the node with the ID, missing keys are redirected by `ASK`.
+    Migrating(String),
+    /// The slot is assigned to the node with the ID, which ends the migration.
+    Node(String),
+    /// The migration of the slot is cancelled.
+    Stable,
+}
+
+/// Parse the slots of a node in the nodes file, e.g., `0-5460` or `5461`.
+fn parse_slots(value: &str) -> anyhow::Result<(u16, u16)> {
+    let (start, end) = value.split_once('-').unwrap_or((value, value));
+    let parse = |slot: &str| {
+        slot.parse::<u16>()
+            .ok()
+            .filter(|slot| *slot < SLOTS)
+            .ok_or_else(|| anyhow!("invalid slot: {}", value))
+    };
+    let (start, end) = (parse(start)?, parse(end)?);
+    if start > end {
+        bail!("invalid slot range: {}", value);
+    }
+    Ok((start, end))
+}
+
+/// Parse a slot in transit, i.e., `[slot->-id]` for a slot migrating to the node `id`
+/// or `[slot-<-id]` for a slot imported from it. Returns the slot, whether it is migrating
+/// and the ID of the other node.
+fn parse_transfer(value: &str) -> anyhow::Result<(u16, bool, &str)> {
+    let transfer = value
+        .strip_prefix('[')
+        .and_then(|value| value.strip_suffix(']'))
+        .ok_or_else(|| anyhow!("invalid slot in transit: {}", value))?;
+    let (slot, migrating, id) = match (transfer.split_once("->-"), transfer.split_once("-<-")) {
+        (Some((slot, id)), _) => (slot, true, id),
+        (_, Some((slot, id))) => (slot, false, id),
+        _ => bail!("invalid slot in transit: {}", value),
+    };
+    let (slot, _) = parse_slots(slot)?;
+    Ok((slot, migrating, id))
+}
+
+/// Parse a node of the nodes file, i.e., `id ip:port@cport flags master ping-sent pong-recv
+/// config-epoch link-state slot...`. Returns the node, whether it is flagged `myself`
+/// and its slots in transit. Replicas are skipped, they serve no slots.
+fn parse_node(line: &str) -> anyhow::Result<Option<(ClusterNode, bool, Vec<&str>)>> {
+    let fields: Vec<&str> = line.split_whitespace().collect();
+    if fields.len() < 8 {
+        bail!("invalid node: {}", line);
+    }
+    let flags: Vec<&str> = fields[2].split(',').collect();
+    if flags.contains(&"slave") || flags.contains(&"replica") {
+        log::warn!(
+            "Replicas of cluster nodes are not supported, skipping: {}",
+            line
+        );
+        return Ok(None);
+    }
+    let addr = fields[1].split(['@', ',']).next().unwrap_or_default();
+    let (host, port) = addr
+        .rsplit_once(':')
+        .ok_or_else(|| anyhow!("invalid address of node: {}", fields[1]))?;
+    let port = port
+        .parse()
+        .map_err(|_| anyhow!("invalid port of node: {}", fields[1]))?;
+    let (transfers, slots): (Vec<&str>, Vec<&str>) =
+        fields[8..].iter().partition(|slots| slots.starts_with('['));
+    let slots = slots
+        .into_iter()
+        .map(parse_slots)
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    let node = ClusterNode {
+        id: fields[0].to_string(),
+        host: host.to_string(),
+        port,
+        slots,
+    };
+    Ok(Some((node, flags.contains(&"myself"), transfers)))
+}
+
+/// Format slot ranges as in the nodes file, e.g., `0-5460 5462`.
+fn format_slots(slots: &[(u16, u16)]) -> String {
+    slots
+        .iter()
+        .map(|&(start, end)| match start == end {
+            true => start.to_string(),
+            false => format!("{}-{}", start, end),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[derive(Debug)]
+struct State {
+    nodes: Vec<ClusterNode>,
+    /// Index of this node in `nodes`.
+    myself: usize,
+    /// Index of the node serving each slot.
+    owners: Vec<Option<usize>>,
+    /// Slots of this node migrating to other nodes, with the index of the target.
+    migrating: BTreeMap<u16, usize>,
+    /// Slots imported from other nodes, with the index of the source.
+    importing: BTreeMap<u16, usize>,
+}
+
+impl State {
+    fn new(nodes: Vec<ClusterNode>, myself: usize) -> anyhow::Result<Self> {
+        let mut owners = vec![None; SLOTS as usize];
+        for (index, node) in nodes.iter().enumerate() {
+            for &(start, end) in &node.slots {
+                for slot in start..=end {
+                    if let Some(owner) = owners[slot as usize].replace(index) {
+                        bail!(
+                            "slot {} is served by both {} and {}",
+                            slot,
+                            nodes[owner].id,
+                            node.id
+                        );
+                    }
+                }
+            }
+        }
+        Ok(Self {
+            nodes,
+            myself,
+            owners,
+            migrating: BTreeMap::new(),
+            importing: BTreeMap::new(),
+        })
+    }
+
+    fn index_of(&self, id: &str) -> anyhow::Result<usize> {
+        self.nodes
+            .iter()
+            .position(|node| node.id == id)
+            .ok_or_else(|| anyhow!("I don't know about node {}", id))
+    }
+
+    /// Update the slot ranges of the nodes after a change of the owners.
+    fn update_ranges(&mut self) {
+        self.nodes.iter_mut().for_each(|node| node.slots.clear());
+        for (slot, owner) in self.owners.iter().enumerate() {
+            let Some(index) = *owner else {
+                continue;
+            };
+            let slots = &mut self.nodes[index].slots;
+            match slots.last_mut() {
+                Some((_, end)) if *end as usize + 1 == slot => *end = slot as u16,
+                _ => slots.push((slot as u16, slot as u16)),
+            }
+        }
+    }
+}
+
+/// Cluster configuration shared by all connections, i.e., the nodes and the slots they serve.
+#[derive(Debug, Clone)]
+pub(crate) struct Cluster {
+    state: Arc<StdMutex<State>>,
+    /// Path of the nodes file, rewritten when the slots change.
+    path: PathBuf,
+}
+
+impl Cluster {
+    /// Load the nodes file if the cluster mode is enabled. This node is the one listening
+    /// on `port`, or the one flagged `myself`. A missing file is created with this node
+    /// serving all slots.
+    pub(crate) fn open(config: &Config, host: &str, port: u16) -> anyhow::Result<Option<Self>> {
+        if !config.cluster_enabled {
+            return Ok(None);
+        }
+        let path = config.cluster_config_path();
+        let state = match std::fs::read_to_string(&path) {
+            Ok(text) => Self::parse(&text, port)
+                .map_err(|e| anyhow!("invalid nodes file {}: {}", path.display(), e))?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                let node = ClusterNode {
+                    id: random_id(),
+                    host: host.to_string(),
+                    port,
+                    slots: vec![(0, SLOTS - 1)],
+                };
+                let state = State::new(vec![node], 0)?;
+                Self::write(&path, &state)?;
+                state
+            }
+            Err(e) => bail!("cannot read nodes file {}: {}", path.display(), e),
+        };
+        let myself = &state.nodes[state.myself];
+        log::info!(
+            "Cluster node {} with {} of {} nodes",
+            myself.id,
+            format_slots(&myself.slots),
+            state.nodes.len()
+        );
+        Ok(Some(Self {
+            state: Arc::new(StdMutex::new(state)),
+            path,
+        }))
+    }
+
+    fn parse(text: &str, port: u16) -> anyhow::Result<State> {
+        let mut nodes = Vec::new();
+        let mut transfers = Vec::new();
+        let mut flagged = None;
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with("vars ") || line.starts_with('#') {
+                continue;
+            }
+            if let Some((node, myself, node_transfers)) = parse_node(line)? {
+                if myself {
+                    flagged = Some(nodes.len());
+                }
+                nodes.push(node);
+                transfers.push(node_transfers);
+            }
+        }
+        // A nodes file shared by all nodes of a local cluster has no `myself` flag
+        let by_port: Vec<usize> = (0..nodes.len())
+            .filter(|&index| nodes[index].port == port)
+            .collect();
+        let myself = match (by_port.as_slice(), flagged) {
+            ([index], _) => *index,
+            (_, Some(index)) => index,
+            ([], None) => bail!("no node with port {}", port),
+            (_, None) => bail!("multiple nodes with port {}, flag one as myself", port),
+        };
+        let mut state = State::new(nodes, myself)?;
+        for transfer in &transfers[myself] {
+            let (slot, migrating, id) = parse_transfer(transfer)?;
+            let index = state.index_of(id)?;
+            match migrating {
+                true => state.migrating.insert(slot, index),
+                false => state.importing.insert(slot, index),
+            };
+        }
+        Ok(state)
+    }
+
+    fn write(path: &Path, state: &State) -> anyhow::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        std::fs::write(path, Self::nodes_text(state))
+            .map_err(|e| anyhow!("cannot write nodes file {}: {}", path.display(), e))
+    }
+
+    /// Nodes in the format of the nodes file and `CLUSTER NODES`.
+    fn nodes_text(state: &State) -> String {
+        let mut text = String::new();
+        for (index, node) in state.nodes.iter().enumerate() {
+            let flags = match index == state.myself {
+                true => "myself,master",
+                false => "master",
+            };
+            let _ = write!(
+                text,
+                "{} {}:{}@{} {} - 0 0 0 connected",
+                node.id,
+                node.host,
+                node.port,
+                node.port as u32 + BUS_PORT_OFFSET,
+                flags
+            );
+            if !node.slots.is_empty() {
+                let _ = write!(text, " {}", format_slots(&node.slots));
+            }
+            if index == state.myself {
+                for (slot, target) in &state.migrating {
+                    let _ = write!(text, " [{}->-{}]", slot, state.nodes[*target].id);
+                }
+                for (slot, source) in &state.importing {
+                    let _ = write!(text, " [{}-<-{}]", slot, state.nodes[*source].id);
+                }
+            }
+            text.push('\n');
+        }
+        text
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub(crate) fn myid(&self) -> String {
+        let state = self.lock();
+        state.nodes[state.myself].id.clone()
+    }
+
+    /// Check that this node serves the keys of a command. Keys of another node
+    /// are redirected by `MOVED`, keys of different slots are rejected.
+    ///
+    /// Keys of a migrating slot that are missing here are redirected by `ASK` to the target,
+    /// which serves the slot it imports only to clients that sent `ASKING` (`asking`).
+    /// Commands with multiple keys, of which some were already migrated, have to be retried.
+    pub(crate) async fn check_keys(
+        &self,
+        keys: &[&str],
+        asking: bool,
+        db: &DB,
+    ) -> Result<(), RedisClusterError> {
+        let Some(slot) = Self::keys_slot(keys)? else {
+            return Ok(());
+        };
+        let (owner, migrating, importing) = {
+            let state = self.lock();
+            let owner = state.owners[slot as usize].map(|index| match index == state.myself {
+                true => None,
+                false => Some(state.nodes[index].addr()),
+            });
+            let migrating = state.migrating.get(&slot).map(|&i| state.nodes[i].addr());
+            (owner, migrating, state.importing.contains_key(&slot))
+        };
+        let mut missing = 0;
+        if migrating.is_some() || (importing && asking) {
+            for key in keys {
+                if !db.exists(key).await {
+                    missing += 1;
+                }
+            }
+        }
+        match owner {
+            Some(None) => match migrating {
+                Some(target) if missing == keys.len() => Err(RedisClusterError::Ask(slot, target)),
+                Some(_) if missing > 0 => Err(RedisClusterError::TryAgain),
+                _ => Ok(()),
+            },
+            _ if importing && asking => match keys.len() > 1 && missing > 0 {
+                true => Err(RedisClusterError::TryAgain),
+                false => Ok(()),
+            },
+            Some(Some(addr)) => Err(RedisClusterError::Moved(slot, addr)),
+            None => Err(RedisClusterError::SlotNotServed),
+        }
+    }
+
+    /// Common slot of the keys, `None` if there are no keys.
+    pub(crate) fn keys_slot(keys: &[&str]) -> Result<Option<u16>, RedisClusterError> {
+        let mut slots = keys.iter().map(|key| key_slot(key.as_bytes()));
+        let Some(slot) = slots.next() else {
+            return Ok(None);
+        };
+        match slots.all(|other| other == slot) {
+            true => Ok(Some(slot)),
+            false => Err(RedisClusterError::CrossSlot),
+        }
+    }
+
+    /// Change the state of a slot (`CLUSTER SETSLOT`) and save the nodes file.
+    /// The slot is only assigned to another node once this node holds no keys of it.
+    pub(crate) fn set_slot(
+        &self,
+        slot: u16,
+        slot_state: SlotState,
+        keys_in_slot: usize,
+    ) -> anyhow::Result<()> {
+        let mut state = self.lock();
+        let owned = state.owners[slot as usize] == Some(state.myself);
+        match slot_state {
+            SlotState::Importing(id) => {
+                let index = state.index_of(&id)?;
+                if owned {
+                    bail!("I'm already the owner of hash slot {}", slot);
+                }
+                state.importing.insert(slot, index);
+            }
+            SlotState::Migrating(id) => {
+                let index = state.index_of(&id)?;
+                if !owned {
+                    bail!("I'm not the owner of hash slot {}", slot);
+                }
+                if index == state.myself {
+                    bail!("Can't migrate hash slot {} to myself", slot);
+                }
+                state.migrating.insert(slot, index);
+            }
+            SlotState::Node(id) => {
+                let index = state.index_of(&id)?;
+                if owned && index != state.myself && keys_in_slot > 0 {
+                    bail!(
+                        "Can't assign hashslot {} to a different node while I still hold keys for this hash slot.",
+                        slot
+                    );
+                }
+                state.owners[slot as usize] = Some(index);
+                state.migrating.remove(&slot);
+                state.importing.remove(&slot);
+                state.update_ranges();
+            }
+            SlotState::Stable => {
+                state.migrating.remove(&slot);
+                state.importing.remove(&slot);
+            }
+        }
+        Self::write(&self.path, &state)
+    }
+
+    /// Ranges of slots with their nodes, ordered by the slots (`CLUSTER SLOTS`).
+    pub(crate) fn slots(&self) -> Vec<SlotRange> {
+        let state = self.lock();
+        let mut ranges: Vec<SlotRange> = state
+            .nodes
+            .iter()
+            .flat_map(|node| {
+                node.slots.iter().map(|&(start, end)| SlotRange {
+                    start,
+                    end,
+                    host: node.host.clone(),
+                    port: node.port,
+                    id: node.id.clone(),
+                })
+            })
+            .collect();
+        ranges.sort_by_key(|range| range.start);
+        ranges
+    }
+
+    /// Nodes of the cluster, each a shard of its own (`CLUSTER SHARDS`).
+    pub(crate) fn nodes(&self) -> Vec<ClusterNode> {
+        self.lock().nodes.clone()
+    }
+
+    /// Nodes in the format of the nodes file (`CLUSTER NODES`).
+    pub(crate) fn nodes_info(&self) -> String {
+        Self::nodes_text(&self.lock())
+    }
+}
@@ -3,11 +3,31 @@ use std::time::Duration;
 
 use bytes::Bytes;
 
-use crate::common::bytes_to_string;
+use crate::cluster::SlotState;
+use crate::common::{bytes_to_i64, bytes_to_string};
+use crate::db::{
+    now_ms, ClaimOptions, InsertPosition, ListEnd, PendingRange, ScoreBound, SetOperation,
+    StreamId, StreamTrim, XAddId, ZAddOptions, ZRangeBy,
+};
 use crate::err::RedisCommandError;
 use crate::frame::Frame;
 
-#[derive(Debug)]
+mod cluster;
+mod consumer_group;
+mod encode;
+mod hash;
+mod list;
+mod migration;
+mod pubsub;
+mod replication;
+mod set;
+mod sorted_set;
+mod stream;
+mod transaction;
+
+pub(crate) use stream::parse_stream_id;
+
+#[derive(Debug, Clone)]
 pub enum Command {
     Get {
         key: String,
@@ -20,6 +40,10 @@ pub enum Command {
     Ping {
         msg: Option<String>,
     },
+    /// `HELLO [protover]`, switches the protocol of the connection to RESP2 or RESP3.
+    Hello {
+        protocol: Option<u64>,
+    },
     Del {
         keys: Vec<String>,
     },
@@ -34,16 +58,407 @@ pub enum Command {
     },
     FlushDB,
     DBSize,
+    Save,
+    BgSave,
+    BgRewriteAof,
+    LastSave,
     Unknown(String),
     Lolwut(Vec<Frame>), // Custom command
+    /// `EXPIRE`, `PEXPIRE`, `EXPIREAT` and `PEXPIREAT`. Absolute times are converted
+    /// to the remaining duration when the command is parsed.
     Expire {
         key: String,
-        seconds: u64,
+        duration: Duration,
     },
     #[allow(clippy::upper_case_acronyms)]
     TTL {
         key: String,
     },
+    Push {
+        key: String,
+        values: Vec<Bytes>,
+        end: ListEnd,
+    },
+    Pop {
+        key: String,
+        end: ListEnd,
+        count: Option<u64>,
+    },
+    /// `BLPOP` and `BRPOP`. A timeout of `None` blocks indefinitely.
+    BPop {
+        keys: Vec<String>,
+        end: ListEnd,
+        timeout: Option<Duration>,
+    },
+    /// `BLMOVE` and `BRPOPLPUSH`. A timeout of `None` blocks indefinitely.
+    BLMove {
+        source: String,
+        destination: String,
+        from: ListEnd,
+        to: ListEnd,
+        timeout: Option<Duration>,
+    },
+    LRange {
+        key: String,
+        start: i64,
+        stop: i64,
+    },
+    LLen {
+        key: String,
+    },
+    LIndex {
+        key: String,
+        index: i64,
+    },
+    LSet {
+        key: String,
+        index: i64,
+        value: Bytes,
+    },
+    LTrim {
+        key: String,
+        start: i64,
+        stop: i64,
+    },
+    LRem {
+        key: String,
+        count: i64,
+        value: Bytes,
+    },
+    LInsert {
+        key: String,
+        position: InsertPosition,
+        pivot: Bytes,
+        value: Bytes,
+    },
+    HSet {
+        key: String,
+        fields: Vec<(Bytes, Bytes)>,
+    },
+    HSetNx {
+        key: String,
+        field: Bytes,
+        value: Bytes,
+    },
+    HGet {
+        key: String,
+        field: Bytes,
+    },
+    HMGet {
+        key: String,
+        fields: Vec<Bytes>,
+    },
+    HDel {
+        key: String,
+        fields: Vec<Bytes>,
+    },
+    HGetAll {
+        key: String,
+    },
+    HIncrBy {
+        key: String,
+        field: Bytes,
+        increment: i64,
+    },
+    HKeys {
+        key: String,
+    },
+    HVals {
+        key: String,
+    },
+    HLen {
+        key: String,
+    },
+    HExists {
+        key: String,
+        field: Bytes,
+    },
+    SAdd {
+        key: String,
+        members: Vec<Bytes>,
+    },
+    SRem {
+        key: String,
+        members: Vec<Bytes>,
+    },
+    SMembers {
+        key: String,
+    },
+    SIsMember {
+        key: String,
+        member: Bytes,
+    },
+    SCard {
+        key: String,
+    },
+    SPop {
+        key: String,
+        count: Option<u64>,
+    },
+    SRandMember {
+        key: String,
+        count: Option<i64>,
+    },
+    SetOp {
+        operation: SetOperation,
+        keys: Vec<String>,
+    },
+    SetOpStore {
+        operation: SetOperation,
+        destination: String,
+        keys: Vec<String>,
+    },
+    ZAdd {
+        key: String,
+        options: ZAddOptions,
+        members: Vec<(f64, Bytes)>,
+    },
+    ZRange {
+        key: String,
+        by: ZRangeBy,
+        rev: bool,
+        limit: Option<(i64, i64)>,
+        with_scores: bool,
+    },
+    ZRank {
+        key: String,
+        member: Bytes,
+    },
+    ZScore {
+        key: String,
+        member: Bytes,
+    },
+    ZRem {
+        key: String,
+        members: Vec<Bytes>,
+    },
+    ZIncrBy {
+        key: String,
+        increment: f64,
+        member: Bytes,
+    },
+    ZCount {
+        key: String,
+        min: ScoreBound,
+        max: ScoreBound,
+    },
+    ZCard {
+        key: String,
+    },
+    ZPop {
+        key: String,
+        count: Option<u64>,
+        highest: bool,
+    },
+    XAdd {
+        key: String,
+        id: XAddId,
+        fields: Vec<(Bytes, Bytes)>,
+        trim: Option<StreamTrim>,
+        /// `false` with `NOMKSTREAM`
+        create: bool,
+    },
+    XRange {
+        key: String,
+        start: StreamId,
+        end: StreamId,
+        rev: bool,
+        count: Option<u64>,
+    },
+    XLen {
+        key: String,
+    },
+    XTrim {
+        key: String,
+        trim: StreamTrim,
+    },
+    XSetId {
+        key: String,
+        last_id: StreamId,
+    },
+    XDel {
+        key: String,
+        ids: Vec<StreamId>,
+    },
+    XRead {
+        /// Stream keys with the last seen ID, `None` stands for `$`.
+        streams: Vec<(String, Option<StreamId>)>,
+        count: Option<u64>,
+    },
+    XGroupCreate {
+        key: String,
+        group: String,
+        /// `None` stands for `$`
+        id: Option<StreamId>,
+        /// `true` with `MKSTREAM`
+        create: bool,
+    },
+    XGroupDestroy {
+        key: String,
+        group: String,
+    },
+    XGroupCreateConsumer {
+        key: String,
+        group: String,
+        consumer: String,
+    },
+    XGroupDelConsumer {
+        key: String,
+        group: String,
+        consumer: String,
+    },
+    XGroupSetId {
+        key: String,
+        group: String,
+        /// `None` stands for `$`
+        id: Option<StreamId>,
+    },
+    XReadGroup {
+        group: String,
+        consumer: String,
+        /// Stream keys with the last seen ID, `None` stands for `>`.
+        streams: Vec<(String, Option<StreamId>)>,
+        count: Option<u64>,
+        noack: bool,
+    },
+    XAck {
+        key: String,
+        group: String,
+        ids: Vec<StreamId>,
+    },
+    XPending {
+        key: String,
+        group: String,
+        /// Extended form, the summary is returned without a range.
+        range: Option<PendingRange>,
+    },
+    XClaim {
+        key: String,
+        group: String,
+        consumer: String,
+        min_idle: u64,
+        ids: Vec<StreamId>,
+        just_id: bool,
+        options: ClaimOptions,
+    },
+    XAutoClaim {
+        key: String,
+        group: String,
+        consumer: String,
+        min_idle: u64,
+        start: StreamId,
+        count: Option<u64>,
+        just_id: bool,
+    },
+    XInfoStream {
+        key: String,
+    },
+    XInfoGroups {
+        key: String,
+    },
+    XInfoConsumers {
+        key: String,
+        group: String,
+    },
+    Subscribe {
+        channels: Vec<String>,
+    },
+    /// Unsubscribe from all channels if `channels` is empty.
+    Unsubscribe {
+        channels: Vec<String>,
+    },
+    PSubscribe {
+        patterns: Vec<String>,
+    },
+    /// Unsubscribe from all patterns if `patterns` is empty.
+    PUnsubscribe {
+        patterns: Vec<String>,
+    },
+    Publish {
+        channel: String,
+        message: Bytes,
+    },
+    PubSubChannels {
+        pattern: Option<String>,
+    },
+    PubSubNumSub {
+        channels: Vec<String>,
+    },
+    PubSubNumPat,
+    Multi,
+    Exec,
+    Discard,
+    Watch {
+        keys: Vec<String>,
+    },
+    Unwatch,
+    /// `REPLICAOF host port`, or `REPLICAOF NO ONE` to stop the replication.
+    ReplicaOf {
+        primary: Option<(String, u16)>,
+    },
+    /// Options exchanged by a replica and its primary, e.g., `listening-port 6380`.
+    ReplConf {
+        options: Vec<(String, String)>,
+    },
+    Sync,
+    PSync {
+        replication_id: String,
+        offset: i64,
+    },
+    Role,
+    /// Wait until `numreplicas` replicas acknowledged the writes, `None` waits indefinitely.
+    Wait {
+        numreplicas: u64,
+        timeout: Option<Duration>,
+    },
+    /// Wait until the writes are synced to the local append-only file and to those
+    /// of `numreplicas` replicas.
+    WaitAof {
+        numlocal: u64,
+        numreplicas: u64,
+        timeout: Option<Duration>,
+    },
+    ClusterSlots,
+    ClusterShards,
+    ClusterNodes,
+    ClusterMyId,
+    ClusterKeySlot {
+        key: String,
+    },
+    ClusterCountKeysInSlot {
+        slot: u16,
+    },
+    ClusterGetKeysInSlot {
+        slot: u16,
+        count: u64,
+    },
+    ClusterSetSlot {
+        slot: u16,
+        state: SlotState,
+    },
+    /// Serve the next command even if its slot is still being imported from another node.
+    Asking,
+    Dump {
+        key: String,
+    },
+    /// Create a key from a payload of `DUMP`. The TTL is in milliseconds, `0` for no expiration,
+    /// or a Unix time in milliseconds with `absttl`.
+    Restore {
+        key: String,
+        ttl: u64,
+        payload: Bytes,
+        replace: bool,
+        absttl: bool,
+    },
+    /// Move keys to another server, they are removed here unless `copy` is set.
+    Migrate {
+        host: String,
+        port: u16,
+        keys: Vec<String>,
+        timeout: Duration,
+        copy: bool,
+        replace: bool,
+    },
 }
 
 impl Command {
@@ -94,26 +509,16 @@ impl Command {
                                 parts.len(),
                             ));
                         }
-                        let expiration = Self::bulk_to_string(parts.pop_front().unwrap())?;
-                        match expiration.to_uppercase().as_str() {
-                            "PX" => {
-                                let px = Self::bulk_to_u64(parts.pop_front().unwrap())?;
-                                Ok(Command::Set {
-                                    key,
-                                    val,
-                                    expiration: Some(Duration::from_millis(px)),
-                                })
-                            }
-                            "EX" => {
-                                let ex = Self::bulk_to_u64(parts.pop_front().unwrap())?;
-                                Ok(Command::Set {
-                                    key,
-                                    val,
-                                    expiration: Some(Duration::from_secs(ex)),
-                                })
-                            }
-                            _ => Err(RedisCommandError::NotImplemented(
-                                "Expected EX <seconds> or PX <milliseconds>".to_string(),
+                        let option = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                        let time = Self::bulk_to_u64(parts.pop_front().unwrap())?;
+                        match Self::expiration(&option.to_uppercase(), time) {
+                            Some(expiration) => Ok(Command::Set {
+                                key,
+                                val,
+                                expiration: Some(expiration),
+                            }),
+                            None => Err(RedisCommandError::NotImplemented(
+                                "Expected EX <seconds>, PX <milliseconds>, EXAT <unix-time-seconds> or PXAT <unix-time-milliseconds>".to_string(),
                             )),
                         }
                     }
@@ -124,12 +529,33 @@ impl Command {
                             let msg = Self::bulk_to_string(parts.pop_front().unwrap())?;
                             Ok(Command::Ping { msg: Some(msg) })
                         } else {
-                            return Err(Self::wrong_number_of_arguments(
+                            Err(Self::wrong_number_of_arguments(
                                 "PING",
                                 "0 or 1",
                                 parts.len(),
-                            ));
+                            ))
+                        }
+                    }
+                    "HELLO" => {
+                        if parts.len() > 1 {
+                            return match Self::bulk_to_string(parts.remove(1).unwrap())?
+                                .to_uppercase()
+                                .as_str()
+                            {
+                                "AUTH" | "SETNAME" => Err(RedisCommandError::NotImplemented(
+                                    "HELLO with AUTH or SETNAME".to_string(),
+                                )),
+                                option => Err(RedisCommandError::InvalidCommand(format!(
+                                    "Syntax error in HELLO option '{}'",
+                                    option
+                                ))),
+                            };
                         }
+                        let protocol = match parts.pop_front() {
+                            Some(version) => Some(Self::bulk_to_u64(version)?),
+                            None => None,
+                        };
+                        Ok(Command::Hello { protocol })
                     }
                     "INCR" => {
                         if parts.len() != 1 {
@@ -152,6 +578,17 @@ impl Command {
                             Err(Self::wrong_number_of_arguments("DBSIZE", "0", parts.len()))
                         }
                     }
+                    name @ ("SAVE" | "BGSAVE" | "LASTSAVE" | "BGREWRITEAOF") => {
+                        if !parts.is_empty() {
+                            return Err(Self::wrong_number_of_arguments(name, "0", parts.len()));
+                        }
+                        match name {
+                            "SAVE" => Ok(Command::Save),
+                            "BGSAVE" => Ok(Command::BgSave),
+                            "BGREWRITEAOF" => Ok(Command::BgRewriteAof),
+                            _ => Ok(Command::LastSave),
+                        }
+                    }
                     "DEL" => {
                         if parts.is_empty() {
                             return Err(Self::wrong_number_of_arguments("DEL", ">0", parts.len()));
@@ -191,23 +628,23 @@ impl Command {
                             Ok(Command::Lolwut(parts.into()))
                         }
                     }
-                    "EXPIRE" => {
+                    name @ ("EXPIRE" | "PEXPIRE" | "EXPIREAT" | "PEXPIREAT") => {
                         if parts.len() != 2 {
-                            return Err(Self::wrong_number_of_arguments(
-                                "EXPIRE",
-                                "2",
-                                parts.len(),
-                            ));
+                            return Err(Self::wrong_number_of_arguments(name, "2", parts.len()));
                         }
                         let key = Self::bulk_to_string(parts.pop_front().unwrap())?;
-                        let seconds = Self::bulk_to_string(parts.pop_front().unwrap())?;
-                        let seconds = seconds.parse::<u64>().map_err(|_| {
-                            RedisCommandError::ParseDecimalError(format!(
-                                "Invalid seconds: {}",
-                                seconds
-                            ))
+                        let time = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                        let time = time.parse::<u64>().map_err(|_| {
+                            RedisCommandError::ParseDecimalError(format!("Invalid time: {}", time))
                         })?;
-                        Ok(Command::Expire { key, seconds })
+                        let option = match name {
+                            "EXPIRE" => "EX",
+                            "PEXPIRE" => "PX",
+                            "EXPIREAT" => "EXAT",
+                            _ => "PXAT",
+                        };
+                        let duration = Self::expiration(option, time).unwrap();
+                        Ok(Command::Expire { key, duration })
                     }
                     "TTL" => {
                         if parts.len() != 1 {
@@ -216,6 +653,35 @@ impl Command {
                         let key = Self::bulk_to_string(parts.pop_front().unwrap())?;
                         Ok(Command::TTL { key })
                     }
+                    name @ ("LPUSH" | "RPUSH" | "LPOP" | "RPOP" | "LRANGE" | "LLEN" | "LINDEX"
+                    | "LSET" | "LTRIM" | "LREM" | "LINSERT" | "BLPOP" | "BRPOP"
+                    | "BLMOVE" | "BRPOPLPUSH") => Self::parse_list(name, parts),
+                    name @ ("HSET" | "HSETNX" | "HGET" | "HMGET" | "HDEL" | "HGETALL"
+                    | "HINCRBY" | "HKEYS" | "HVALS" | "HLEN" | "HEXISTS") => {
+                        Self::parse_hash(name, parts)
+                    }
+                    name @ ("SADD" | "SREM" | "SMEMBERS" | "SISMEMBER" | "SCARD" | "SPOP"
+                    | "SRANDMEMBER" | "SINTER" | "SUNION" | "SDIFF" | "SINTERSTORE"
+                    | "SUNIONSTORE" | "SDIFFSTORE") => Self::parse_set(name, parts),
+                    name @ ("ZADD" | "ZRANGE" | "ZRANK" | "ZSCORE" | "ZREM" | "ZINCRBY"
+                    | "ZCOUNT" | "ZCARD" | "ZPOPMIN" | "ZPOPMAX") => {
+                        Self::parse_sorted_set(name, parts)
+                    }
+                    name @ ("XADD" | "XRANGE" | "XREVRANGE" | "XLEN" | "XTRIM" | "XDEL"
+                    | "XREAD" | "XSETID") => Self::parse_stream(name, parts),
+                    name @ ("XGROUP" | "XREADGROUP" | "XACK" | "XPENDING" | "XCLAIM"
+                    | "XAUTOCLAIM" | "XINFO") => Self::parse_consumer_group(name, parts),
+                    name @ ("SUBSCRIBE" | "UNSUBSCRIBE" | "PSUBSCRIBE" | "PUNSUBSCRIBE"
+                    | "PUBLISH" | "PUBSUB") => Self::parse_pubsub(name, parts),
+                    name @ ("MULTI" | "EXEC" | "DISCARD" | "WATCH" | "UNWATCH") => {
+                        Self::parse_transaction(name, parts)
+                    }
+                    name @ ("REPLICAOF" | "SLAVEOF" | "REPLCONF" | "SYNC" | "PSYNC" | "ROLE"
+                    | "WAIT" | "WAITAOF") => Self::parse_replication(name, parts),
+                    "CLUSTER" => Self::parse_cluster(parts),
+                    name @ ("DUMP" | "RESTORE" | "MIGRATE" | "ASKING") => {
+                        Self::parse_migration(name, parts)
+                    }
                     _ => Ok(Command::Unknown(command)),
                 }
             }
@@ -226,6 +692,159 @@ impl Command {
         }
     }
 
+    /// Commands that change the subscriptions of the connection.
+    pub fn is_subscription(&self) -> bool {
+        matches!(
+            self,
+            Command::Subscribe { .. }
+                | Command::Unsubscribe { .. }
+                | Command::PSubscribe { .. }
+                | Command::PUnsubscribe { .. }
+        )
+    }
+
+    /// Blocking commands may wait for other clients, e.g., `BLPOP`, or replicas, e.g., `WAIT`.
+    pub fn is_blocking(&self) -> bool {
+        matches!(
+            self,
+            Command::BPop { .. }
+                | Command::BLMove { .. }
+                | Command::Wait { .. }
+                | Command::WaitAof { .. }
+        )
+    }
+
+    /// Commands that control the transaction of the connection, e.g., `MULTI` or `WATCH`.
+    pub fn is_transaction(&self) -> bool {
+        matches!(
+            self,
+            Command::Multi
+                | Command::Exec
+                | Command::Discard
+                | Command::Watch { .. }
+                | Command::Unwatch
+        )
+    }
+
+    /// Commands that may modify the database.
+    pub fn is_write(&self) -> bool {
+        matches!(self, Command::FlushDB) || !self.modified_keys().is_empty()
+    }
+
+    /// Keys that the command reads or modifies, used to route it to the node
+    /// of the cluster serving them.
+    pub fn keys(&self) -> Vec<&str> {
+        match self {
+            Command::Get { key }
+            | Command::TTL { key }
+            | Command::LRange { key, .. }
+            | Command::LLen { key }
+            | Command::LIndex { key, .. }
+            | Command::HGet { key, .. }
+            | Command::HMGet { key, .. }
+            | Command::HGetAll { key }
+            | Command::HKeys { key }
+            | Command::HVals { key }
+            | Command::HLen { key }
+            | Command::HExists { key, .. }
+            | Command::SMembers { key }
+            | Command::SIsMember { key, .. }
+            | Command::SCard { key }
+            | Command::SRandMember { key, .. }
+            | Command::ZRange { key, .. }
+            | Command::ZRank { key, .. }
+            | Command::ZScore { key, .. }
+            | Command::ZCount { key, .. }
+            | Command::ZCard { key }
+            | Command::XRange { key, .. }
+            | Command::XLen { key }
+            | Command::XPending { key, .. }
+            | Command::XInfoStream { key }
+            | Command::XInfoGroups { key }
+            | Command::XInfoConsumers { key, .. }
+            | Command::Dump { key } => vec![key],
+            Command::Exists { keys } | Command::SetOp { keys, .. } | Command::Watch { keys } => {
+                keys.iter().map(String::as_str).collect()
+            }
+            Command::SetOpStore {
+                destination, keys, ..
+            } => std::iter::once(destination)
+                .chain(keys)
+                .map(String::as_str)
+                .collect(),
+            Command::XRead { streams, .. } => streams.iter().map(|(key, _)| key.as_str()).collect(),
+            _ => self.modified_keys(),
+        }
+    }
+
+    /// Keys that the command may modify, used to abort transactions watching them.
+    ///
+    /// `FLUSHDB` is not included, the database marks all watched keys itself.
+    pub fn modified_keys(&self) -> Vec<&str> {
+        match self {
+            Command::Set { key, .. }
+            | Command::Increment { key }
+            | Command::Expire { key, .. }
+            | Command::Push { key, .. }
+            | Command::Pop { key, .. }
+            | Command::LSet { key, .. }
+            | Command::LTrim { key, .. }
+            | Command::LRem { key, .. }
+            | Command::LInsert { key, .. }
+            | Command::HSet { key, .. }
+            | Command::HSetNx { key, .. }
+            | Command::HDel { key, .. }
+            | Command::HIncrBy { key, .. }
+            | Command::SAdd { key, .. }
+            | Command::SRem { key, .. }
+            | Command::SPop { key, .. }
+            | Command::ZAdd { key, .. }
+            | Command::ZRem { key, .. }
+            | Command::ZIncrBy { key, .. }
+            | Command::ZPop { key, .. }
+            | Command::XAdd { key, .. }
+            | Command::XTrim { key, .. }
+            | Command::XSetId { key, .. }
+            | Command::XDel { key, .. }
+            | Command::XGroupCreate { key, .. }
+            | Command::XGroupDestroy { key, .. }
+            | Command::XGroupCreateConsumer { key, .. }
+            | Command::XGroupDelConsumer { key, .. }
+            | Command::XGroupSetId { key, .. }
+            | Command::XAck { key, .. }
+            | Command::XClaim { key, .. }
+            | Command::XAutoClaim { key, .. }
+            | Command::Restore { key, .. } => vec![key],
+            Command::Del { keys } | Command::BPop { keys, .. } | Command::Migrate { keys, .. } => {
+                keys.iter().map(String::as_str).collect()
+            }
+            Command::BLMove {
+                source,
+                destination,
+                ..
+            } => vec![source, destination],
+            Command::SetOpStore { destination, .. } => vec![destination],
+            Command::XReadGroup { streams, .. } => {
+                streams.iter().map(|(key, _)| key.as_str()).collect()
+            }
+            _ => vec![],
+        }
+    }
+
+    /// Convert the time of the `EX`, `PX`, `EXAT` or `PXAT` option to the remaining duration.
+    /// Unix times in the past give a zero duration, i.e., the key expires immediately.
+    fn expiration(option: &str, time: u64) -> Option<Duration> {
+        match option {
+            "EX" => Some(Duration::from_secs(time)),
+            "PX" => Some(Duration::from_millis(time)),
+            "EXAT" => Some(Duration::from_millis(
+                time.saturating_mul(1000).saturating_sub(now_ms()),
+            )),
+            "PXAT" => Some(Duration::from_millis(time.saturating_sub(now_ms()))),
+            _ => None,
+        }
+    }
+
     fn bulk_to_u64(frame: Frame) -> anyhow::Result<u64, RedisCommandError> {
         match frame {
             Frame::Bulk(bytes) => bytes_to_string(&bytes)?
@@ -237,6 +856,15 @@ impl Command {
         }
     }
 
+    fn bulk_to_i64(frame: Frame) -> anyhow::Result<i64, RedisCommandError> {
+        match frame {
+            Frame::Bulk(bytes) => bytes_to_i64(&bytes),
+            _ => Err(RedisCommandError::InvalidFrame(
+                "Expected bulk string".to_string(),
+            )),
+        }
+    }
+
     fn wrong_number_of_arguments(
         command: &str,
         expected: &str,
@@ -254,6 +882,10 @@ impl Command {
         }
     }
 
+    fn bulks_to_strings(parts: VecDeque<Frame>) -> anyhow::Result<Vec<String>, RedisCommandError> {
+        parts.into_iter().map(Self::bulk_to_string).collect()
+    }
+
     fn bulk_to_bytes(frame: Frame) -> anyhow::Result<Bytes, RedisCommandError> {
         match frame {
             Frame::Bulk(bytes) => Ok(bytes),
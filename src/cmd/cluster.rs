@@ -0,0 +1,67 @@
+use std::collections::VecDeque;
+
+use super::Command;
+use crate::cluster::{SlotState, SLOTS};
+use crate::err::RedisCommandError;
+use crate::frame::Frame;
+
+impl Command {
+    /// Parse `CLUSTER` subcommands, e.g., `CLUSTER SLOTS`, `CLUSTER KEYSLOT key`
+    /// or `CLUSTER SETSLOT slot MIGRATING node-id`.
+    pub(super) fn parse_cluster(
+        mut parts: VecDeque<Frame>,
+    ) -> anyhow::Result<Command, RedisCommandError> {
+        let Some(subcommand) = parts.pop_front() else {
+            return Err(Self::wrong_number_of_arguments("CLUSTER", ">0", 0));
+        };
+        let subcommand = Self::bulk_to_string(subcommand)?.to_uppercase();
+        match (subcommand.as_str(), parts.len()) {
+            ("SLOTS", 0) => Ok(Command::ClusterSlots),
+            ("SHARDS", 0) => Ok(Command::ClusterShards),
+            ("NODES", 0) => Ok(Command::ClusterNodes),
+            ("MYID", 0) => Ok(Command::ClusterMyId),
+            ("KEYSLOT", 1) => Ok(Command::ClusterKeySlot {
+                key: Self::bulk_to_string(parts.pop_front().unwrap())?,
+            }),
+            ("COUNTKEYSINSLOT", 1) => Ok(Command::ClusterCountKeysInSlot {
+                slot: Self::parse_slot(parts.pop_front().unwrap())?,
+            }),
+            ("GETKEYSINSLOT", 2) => Ok(Command::ClusterGetKeysInSlot {
+                slot: Self::parse_slot(parts.pop_front().unwrap())?,
+                count: Self::bulk_to_u64(parts.pop_front().unwrap())?,
+            }),
+            ("SETSLOT", 2 | 3) => {
+                let slot = Self::parse_slot(parts.pop_front().unwrap())?;
+                let state = Self::bulk_to_string(parts.pop_front().unwrap())?.to_uppercase();
+                let node = parts.pop_front().map(Self::bulk_to_string).transpose()?;
+                let state = match (state.as_str(), node) {
+                    ("IMPORTING", Some(node)) => SlotState::Importing(node),
+                    ("MIGRATING", Some(node)) => SlotState::Migrating(node),
+                    ("NODE", Some(node)) => SlotState::Node(node),
+                    ("STABLE", None) => SlotState::Stable,
+                    _ => {
+                        return Err(RedisCommandError::InvalidCommand(
+                            "Invalid CLUSTER SETSLOT action or number of arguments".to_string(),
+                        ))
+                    }
+                };
+                Ok(Command::ClusterSetSlot { slot, state })
+            }
+            _ => Err(RedisCommandError::InvalidCommand(format!(
+                "unknown subcommand or wrong number of arguments for 'CLUSTER {}'",
+                subcommand
+            ))),
+        }
+    }
+
+    /// Parse a hash slot, i.e., a number less than 16384.
+    fn parse_slot(frame: Frame) -> anyhow::Result<u16, RedisCommandError> {
+        match Self::bulk_to_u64(frame)? {
+            slot if slot < SLOTS as u64 => Ok(slot as u16),
+            slot => Err(RedisCommandError::InvalidCommand(format!(
+                "Invalid slot: {}",
+                slot
+            ))),
+        }
+    }
+}
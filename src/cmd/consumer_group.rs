@@ -0,0 +1,312 @@
+use std::collections::VecDeque;
+
+use super::stream::{parse_range_bound, parse_stream_id};
+use super::Command;
+use crate::db::{now_ms, ClaimOptions, PendingRange, StreamId};
+use crate::err::RedisCommandError;
+use crate::frame::Frame;
+
+fn syntax_error() -> RedisCommandError {
+    RedisCommandError::InvalidCommand("syntax error".to_string())
+}
+
+/// Parse the ID of `XGROUP CREATE` and `XGROUP SETID`. `$` is parsed as `None`.
+fn parse_group_id(id: &str) -> Result<Option<StreamId>, RedisCommandError> {
+    match id {
+        "$" => Ok(None),
+        id => Ok(Some(parse_stream_id(id, 0)?)),
+    }
+}
+
+impl Command {
+    /// Parse stream consumer group commands, e.g., `XGROUP`, `XREADGROUP` or `XACK`.
+    ///
+    /// Expects the command name in uppercase and the remaining parts of the frame.
+    pub(super) fn parse_consumer_group(
+        name: &str,
+        mut parts: VecDeque<Frame>,
+    ) -> anyhow::Result<Command, RedisCommandError> {
+        match name {
+            "XGROUP" => {
+                if parts.len() < 3 {
+                    return Err(Self::wrong_number_of_arguments(name, ">2", parts.len()));
+                }
+                let subcommand = Self::bulk_to_string(parts.pop_front().unwrap())?.to_uppercase();
+                let key = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let group = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                match (subcommand.as_str(), parts.len()) {
+                    ("CREATE", 1..) => {
+                        let id =
+                            parse_group_id(&Self::bulk_to_string(parts.pop_front().unwrap())?)?;
+                        let mut create = false;
+                        while let Some(option) = parts.pop_front() {
+                            match Self::bulk_to_string(option)?.to_uppercase().as_str() {
+                                "MKSTREAM" => create = true,
+                                // Lag tracking is not supported, the value is ignored
+                                "ENTRIESREAD" if !parts.is_empty() => {
+                                    parts.pop_front();
+                                }
+                                _ => return Err(syntax_error()),
+                            }
+                        }
+                        Ok(Command::XGroupCreate {
+                            key,
+                            group,
+                            id,
+                            create,
+                        })
+                    }
+                    ("DESTROY", 0) => Ok(Command::XGroupDestroy { key, group }),
+                    ("CREATECONSUMER" | "DELCONSUMER", 1) => {
+                        let consumer = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                        if subcommand == "CREATECONSUMER" {
+                            Ok(Command::XGroupCreateConsumer {
+                                key,
+                                group,
+                                consumer,
+                            })
+                        } else {
+                            Ok(Command::XGroupDelConsumer {
+                                key,
+                                group,
+                                consumer,
+                            })
+                        }
+                    }
+                    ("SETID", 1) => {
+                        let id =
+                            parse_group_id(&Self::bulk_to_string(parts.pop_front().unwrap())?)?;
+                        Ok(Command::XGroupSetId { key, group, id })
+                    }
+                    _ => Err(RedisCommandError::InvalidCommand(format!(
+                        "unknown subcommand or wrong number of arguments for 'XGROUP {}'",
+                        subcommand
+                    ))),
+                }
+            }
+            "XREADGROUP" => {
+                if parts.len() < 6 {
+                    return Err(Self::wrong_number_of_arguments(name, ">5", parts.len()));
+                }
+                if Self::bulk_to_string(parts.pop_front().unwrap())?.to_uppercase() != "GROUP" {
+                    return Err(syntax_error());
+                }
+                let group = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let consumer = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let mut count = None;
+                let mut noack = false;
+                loop {
+                    let Some(option) = parts.pop_front() else {
+                        return Err(syntax_error());
+                    };
+                    match Self::bulk_to_string(option)?.to_uppercase().as_str() {
+                        "COUNT" if !parts.is_empty() => {
+                            count = Some(Self::bulk_to_u64(parts.pop_front().unwrap())?)
+                        }
+                        "NOACK" => noack = true,
+                        "BLOCK" => {
+                            return Err(RedisCommandError::NotImplemented(
+                                "XREADGROUP BLOCK".to_string(),
+                            ))
+                        }
+                        "STREAMS" => break,
+                        _ => return Err(syntax_error()),
+                    }
+                }
+                let streams = Self::parse_streams(name, parts, ">")?;
+                Ok(Command::XReadGroup {
+                    group,
+                    consumer,
+                    streams,
+                    count,
+                    noack,
+                })
+            }
+            "XACK" => {
+                if parts.len() < 3 {
+                    return Err(Self::wrong_number_of_arguments(name, ">2", parts.len()));
+                }
+                let key = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let group = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let ids = Self::parse_stream_ids(parts)?;
+                Ok(Command::XAck { key, group, ids })
+            }
+            "XPENDING" => {
+                if parts.len() < 2 {
+                    return Err(Self::wrong_number_of_arguments(name, ">1", parts.len()));
+                }
+                let key = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let group = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                if parts.is_empty() {
+                    return Ok(Command::XPending {
+                        key,
+                        group,
+                        range: None,
+                    });
+                }
+
+                // Extended form: [IDLE min-idle-time] start end count [consumer]
+                let mut min_idle = None;
+                if let Some(Frame::Bulk(option)) = parts.front() {
+                    if option.eq_ignore_ascii_case(b"IDLE") {
+                        parts.pop_front();
+                        let idle = parts.pop_front().ok_or_else(syntax_error)?;
+                        min_idle = Some(Self::bulk_to_u64(idle)?);
+                    }
+                }
+                if parts.len() != 3 && parts.len() != 4 {
+                    return Err(syntax_error());
+                }
+                let start =
+                    parse_range_bound(&Self::bulk_to_string(parts.pop_front().unwrap())?, true)?;
+                let end =
+                    parse_range_bound(&Self::bulk_to_string(parts.pop_front().unwrap())?, false)?;
+                let count = Self::bulk_to_u64(parts.pop_front().unwrap())? as usize;
+                let consumer = parts.pop_front().map(Self::bulk_to_string).transpose()?;
+                Ok(Command::XPending {
+                    key,
+                    group,
+                    range: Some(PendingRange {
+                        min_idle,
+                        start,
+                        end,
+                        count,
+                        consumer,
+                    }),
+                })
+            }
+            "XCLAIM" => {
+                if parts.len() < 5 {
+                    return Err(Self::wrong_number_of_arguments(name, ">4", parts.len()));
+                }
+                let key = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let group = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let consumer = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let min_idle = Self::bulk_to_u64(parts.pop_front().unwrap())?;
+                let (ids, just_id, options) = Self::parse_claim_options(parts)?;
+                Ok(Command::XClaim {
+                    key,
+                    group,
+                    consumer,
+                    min_idle,
+                    ids,
+                    just_id,
+                    options,
+                })
+            }
+            "XAUTOCLAIM" => {
+                if parts.len() < 5 {
+                    return Err(Self::wrong_number_of_arguments(name, ">4", parts.len()));
+                }
+                let key = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let group = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let consumer = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let min_idle = Self::bulk_to_u64(parts.pop_front().unwrap())?;
+                let start =
+                    parse_range_bound(&Self::bulk_to_string(parts.pop_front().unwrap())?, true)?;
+                let just_id = Self::pop_just_id(&mut parts);
+                let count = match parts.pop_front() {
+                    Some(option) => {
+                        if Self::bulk_to_string(option)?.to_uppercase() != "COUNT" {
+                            return Err(syntax_error());
+                        }
+                        let count = parts.pop_front().ok_or_else(syntax_error)?;
+                        Some(Self::bulk_to_u64(count)?)
+                    }
+                    None => None,
+                };
+                if !parts.is_empty() {
+                    return Err(syntax_error());
+                }
+                Ok(Command::XAutoClaim {
+                    key,
+                    group,
+                    consumer,
+                    min_idle,
+                    start,
+                    count,
+                    just_id,
+                })
+            }
+            "XINFO" => {
+                if parts.len() < 2 {
+                    return Err(Self::wrong_number_of_arguments(name, ">1", parts.len()));
+                }
+                let subcommand = Self::bulk_to_string(parts.pop_front().unwrap())?.to_uppercase();
+                let key = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                match (subcommand.as_str(), parts.len()) {
+                    ("STREAM", 0) => Ok(Command::XInfoStream { key }),
+                    ("GROUPS", 0) => Ok(Command::XInfoGroups { key }),
+                    ("CONSUMERS", 1) => {
+                        let group = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                        Ok(Command::XInfoConsumers { key, group })
+                    }
+                    _ => Err(RedisCommandError::InvalidCommand(format!(
+                        "unknown subcommand or wrong number of arguments for 'XINFO {}'",
+                        subcommand
+                    ))),
+                }
+            }
+            _ => Ok(Command::Unknown(name.to_string())),
+        }
+    }
+
+    fn parse_stream_ids(parts: VecDeque<Frame>) -> Result<Vec<StreamId>, RedisCommandError> {
+        parts
+            .into_iter()
+            .map(|id| parse_stream_id(&Self::bulk_to_string(id)?, 0))
+            .collect()
+    }
+
+    /// Parse the IDs of `XCLAIM` followed by the options
+    /// `[IDLE ms] [TIME unix-time-ms] [RETRYCOUNT count] [FORCE] [JUSTID] [LASTID id]`.
+    /// `IDLE` is converted to the absolute delivery time, `LASTID` is ignored.
+    fn parse_claim_options(
+        parts: VecDeque<Frame>,
+    ) -> Result<(Vec<StreamId>, bool, ClaimOptions), RedisCommandError> {
+        let mut parts = parts.into_iter();
+        let mut ids = Vec::new();
+        let mut just_id = false;
+        let mut options = ClaimOptions::default();
+        let mut has_options = false;
+        while let Some(arg) = parts.next() {
+            let arg = Self::bulk_to_string(arg)?;
+            let mut value = || parts.next().ok_or_else(syntax_error);
+            match arg.to_uppercase().as_str() {
+                "IDLE" => {
+                    let idle = Self::bulk_to_u64(value()?)?;
+                    options.delivery_time = Some(now_ms().saturating_sub(idle));
+                }
+                "TIME" => options.delivery_time = Some(Self::bulk_to_u64(value()?)?),
+                "RETRYCOUNT" => options.retry_count = Some(Self::bulk_to_u64(value()?)?),
+                "LASTID" => {
+                    value()?;
+                }
+                "FORCE" => options.force = true,
+                "JUSTID" => just_id = true,
+                // IDs precede the options
+                _ if !has_options => {
+                    ids.push(parse_stream_id(&arg, 0)?);
+                    continue;
+                }
+                _ => return Err(syntax_error()),
+            }
+            has_options = true;
+        }
+        if ids.is_empty() {
+            return Err(syntax_error());
+        }
+        Ok((ids, just_id, options))
+    }
+
+    /// Remove the trailing `JUSTID` option. Returns `true` if it was present.
+    fn pop_just_id(parts: &mut VecDeque<Frame>) -> bool {
+        match parts.back() {
+            Some(Frame::Bulk(option)) if option.eq_ignore_ascii_case(b"JUSTID") => {
+                parts.pop_back();
+                true
+            }
+            _ => false,
+        }
+    }
+}
@@ -0,0 +1,800 @@
+use std::time::Duration;
+
+use super::Command;
+use crate::bulk;
+use crate::cluster::SlotState;
+use crate::db::{
+    InsertPosition, ListEnd, SetOperation, StreamId, StreamTrim, XAddId, ZAddComparison,
+    ZAddCondition, ZRangeBy,
+};
+use crate::frame::{format_double, Frame};
+
+impl Command {
+    /// Convert a command to a request frame, e.g., to send it to the server
+    /// or to log it to the append-only file.
+    pub(crate) fn into_frame(self) -> anyhow::Result<Frame> {
+        let frame = match self {
+            Command::Get { key } => Frame::Array(vec![bulk!("GET"), bulk!(key)]),
+            Command::Set {
+                key,
+                val,
+                expiration,
+            } => {
+                let mut cmd = Frame::Array(vec![bulk!("SET"), bulk!(key), bulk!(val)]);
+                if let Some(expiration) = expiration {
+                    cmd.append(bulk!("PX"))?;
+                    cmd.append(bulk!(expiration.as_millis().to_string()))?;
+                }
+                cmd
+            }
+            Command::Ping { msg } => match msg {
+                Some(message) => Frame::Array(vec![bulk!("PING"), bulk!(message)]),
+                None => Frame::Array(vec![bulk!("PING")]),
+            },
+            Command::Hello { protocol } => match protocol {
+                Some(protocol) => Frame::Array(vec![bulk!("HELLO"), bulk!(protocol.to_string())]),
+                None => Frame::Array(vec![bulk!("HELLO")]),
+            },
+            Command::Del { keys } => {
+                let mut frames = vec![bulk!("DEL")];
+                frames.extend(keys.into_iter().map(|key| bulk!(key)));
+                Frame::Array(frames)
+            }
+            Command::Exists { keys } => {
+                let mut frames = vec![bulk!("EXISTS")];
+                frames.extend(keys.into_iter().map(|key| bulk!(key)));
+                Frame::Array(frames)
+            }
+            Command::Increment { key } => Frame::Array(vec![bulk!("INCR"), bulk!(key)]),
+            Command::FlushDB => Frame::Array(vec![bulk!("FLUSHDB")]),
+            Command::DBSize => Frame::Array(vec![bulk!("DBSIZE")]),
+            Command::Keys { pattern } => Frame::Array(vec![bulk!("KEYS"), bulk!(pattern)]),
+            Command::Unknown(cmd) => Frame::Array(vec![bulk!(cmd)]),
+            Command::Lolwut(frames) => Frame::Array(vec![bulk!("LOLWUT"), Frame::Array(frames)]),
+            Command::Expire { key, duration } => Frame::Array(vec![
+                bulk!("PEXPIRE"),
+                bulk!(key),
+                bulk!(duration.as_millis().to_string()),
+            ]),
+            Command::TTL { key } => Frame::Array(vec![bulk!("TTL"), bulk!(key)]),
+            Command::Push { key, values, end } => {
+                let name = match end {
+                    ListEnd::Left => "LPUSH",
+                    ListEnd::Right => "RPUSH",
+                };
+                let mut frames = vec![bulk!(name), bulk!(key)];
+                frames.extend(values.into_iter().map(|value| bulk!(value)));
+                Frame::Array(frames)
+            }
+            Command::Pop { key, end, count } => {
+                let name = match end {
+                    ListEnd::Left => "LPOP",
+                    ListEnd::Right => "RPOP",
+                };
+                let mut frames = vec![bulk!(name), bulk!(key)];
+                if let Some(count) = count {
+                    frames.push(bulk!(count.to_string()));
+                }
+                Frame::Array(frames)
+            }
+            Command::BPop { keys, end, timeout } => {
+                let name = match end {
+                    ListEnd::Left => "BLPOP",
+                    ListEnd::Right => "BRPOP",
+                };
+                let mut frames = vec![bulk!(name)];
+                frames.extend(keys.into_iter().map(|key| bulk!(key)));
+                frames.push(Self::block_timeout(timeout));
+                Frame::Array(frames)
+            }
+            Command::BLMove {
+                source,
+                destination,
+                from,
+                to,
+                timeout,
+            } => Frame::Array(vec![
+                bulk!("BLMOVE"),
+                bulk!(source),
+                bulk!(destination),
+                Self::list_end(from),
+                Self::list_end(to),
+                Self::block_timeout(timeout),
+            ]),
+            Command::LRange { key, start, stop } => Frame::Array(vec![
+                bulk!("LRANGE"),
+                bulk!(key),
+                bulk!(start.to_string()),
+                bulk!(stop.to_string()),
+            ]),
+            Command::LLen { key } => Frame::Array(vec![bulk!("LLEN"), bulk!(key)]),
+            Command::LIndex { key, index } => {
+                Frame::Array(vec![bulk!("LINDEX"), bulk!(key), bulk!(index.to_string())])
+            }
+            Command::LSet { key, index, value } => Frame::Array(vec![
+                bulk!("LSET"),
+                bulk!(key),
+                bulk!(index.to_string()),
+                bulk!(value),
+            ]),
+            Command::LTrim { key, start, stop } => Frame::Array(vec![
+                bulk!("LTRIM"),
+                bulk!(key),
+                bulk!(start.to_string()),
+                bulk!(stop.to_string()),
+            ]),
+            Command::LRem { key, count, value } => Frame::Array(vec![
+                bulk!("LREM"),
+                bulk!(key),
+                bulk!(count.to_string()),
+                bulk!(value),
+            ]),
+            Command::LInsert {
+                key,
+                position,
+                pivot,
+                value,
+            } => {
+                let position = match position {
+                    InsertPosition::Before => "BEFORE",
+                    InsertPosition::After => "AFTER",
+                };
+                Frame::Array(vec![
+                    bulk!("LINSERT"),
+                    bulk!(key),
+                    bulk!(position),
+                    bulk!(pivot),
+                    bulk!(value),
+                ])
+            }
+            Command::HSet { key, fields } => {
+                let mut frames = vec![bulk!("HSET"), bulk!(key)];
+                for (field, value) in fields {
+                    frames.push(bulk!(field));
+                    frames.push(bulk!(value));
+                }
+                Frame::Array(frames)
+            }
+            Command::HSetNx { key, field, value } => Frame::Array(vec![
+                bulk!("HSETNX"),
+                bulk!(key),
+                bulk!(field),
+                bulk!(value),
+            ]),
+            Command::HGet { key, field } => {
+                Frame::Array(vec![bulk!("HGET"), bulk!(key), bulk!(field)])
+            }
+            Command::HMGet { key, fields } => {
+                let mut frames = vec![bulk!("HMGET"), bulk!(key)];
+                frames.extend(fields.into_iter().map(|field| bulk!(field)));
+                Frame::Array(frames)
+            }
+            Command::HDel { key, fields } => {
+                let mut frames = vec![bulk!("HDEL"), bulk!(key)];
+                frames.extend(fields.into_iter().map(|field| bulk!(field)));
+                Frame::Array(frames)
+            }
+            Command::HGetAll { key } => Frame::Array(vec![bulk!("HGETALL"), bulk!(key)]),
+            Command::HIncrBy {
+                key,
+                field,
+                increment,
+            } => Frame::Array(vec![
+                bulk!("HINCRBY"),
+                bulk!(key),
+                bulk!(field),
+                bulk!(increment.to_string()),
+            ]),
+            Command::HKeys { key } => Frame::Array(vec![bulk!("HKEYS"), bulk!(key)]),
+            Command::HVals { key } => Frame::Array(vec![bulk!("HVALS"), bulk!(key)]),
+            Command::HLen { key } => Frame::Array(vec![bulk!("HLEN"), bulk!(key)]),
+            Command::HExists { key, field } => {
+                Frame::Array(vec![bulk!("HEXISTS"), bulk!(key), bulk!(field)])
+            }
+            Command::SAdd { key, members } => {
+                let mut frames = vec![bulk!("SADD"), bulk!(key)];
+                frames.extend(members.into_iter().map(|member| bulk!(member)));
+                Frame::Array(frames)
+            }
+            Command::SRem { key, members } => {
+                let mut frames = vec![bulk!("SREM"), bulk!(key)];
+                frames.extend(members.into_iter().map(|member| bulk!(member)));
+                Frame::Array(frames)
+            }
+            Command::SMembers { key } => Frame::Array(vec![bulk!("SMEMBERS"), bulk!(key)]),
+            Command::SIsMember { key, member } => {
+                Frame::Array(vec![bulk!("SISMEMBER"), bulk!(key), bulk!(member)])
+            }
+            Command::SCard { key } => Frame::Array(vec![bulk!("SCARD"), bulk!(key)]),
+            Command::SPop { key, count } => {
+                let mut frames = vec![bulk!("SPOP"), bulk!(key)];
+                if let Some(count) = count {
+                    frames.push(bulk!(count.to_string()));
+                }
+                Frame::Array(frames)
+            }
+            Command::SRandMember { key, count } => {
+                let mut frames = vec![bulk!("SRANDMEMBER"), bulk!(key)];
+                if let Some(count) = count {
+                    frames.push(bulk!(count.to_string()));
+                }
+                Frame::Array(frames)
+            }
+            Command::SetOp { operation, keys } => {
+                let name = match operation {
+                    SetOperation::Inter => "SINTER",
+                    SetOperation::Union => "SUNION",
+                    SetOperation::Diff => "SDIFF",
+                };
+                let mut frames = vec![bulk!(name)];
+                frames.extend(keys.into_iter().map(|key| bulk!(key)));
+                Frame::Array(frames)
+            }
+            Command::SetOpStore {
+                operation,
+                destination,
+                keys,
+            } => {
+                let name = match operation {
+                    SetOperation::Inter => "SINTERSTORE",
+                    SetOperation::Union => "SUNIONSTORE",
+                    SetOperation::Diff => "SDIFFSTORE",
+                };
+                let mut frames = vec![bulk!(name), bulk!(destination)];
+                frames.extend(keys.into_iter().map(|key| bulk!(key)));
+                Frame::Array(frames)
+            }
+            Command::ZAdd {
+                key,
+                options,
+                members,
+            } => {
+                let mut frames = vec![bulk!("ZADD"), bulk!(key)];
+                match options.condition {
+                    Some(ZAddCondition::Nx) => frames.push(bulk!("NX")),
+                    Some(ZAddCondition::Xx) => frames.push(bulk!("XX")),
+                    None => {}
+                }
+                match options.comparison {
+                    Some(ZAddComparison::Gt) => frames.push(bulk!("GT")),
+                    Some(ZAddComparison::Lt) => frames.push(bulk!("LT")),
+                    None => {}
+                }
+                if options.changed {
+                    frames.push(bulk!("CH"));
+                }
+                if options.increment {
+                    frames.push(bulk!("INCR"));
+                }
+                for (score, member) in members {
+                    frames.push(bulk!(format_double(score)));
+                    frames.push(bulk!(member));
+                }
+                Frame::Array(frames)
+            }
+            Command::ZRange {
+                key,
+                by,
+                rev,
+                limit,
+                with_scores,
+            } => {
+                let mut frames = vec![bulk!("ZRANGE"), bulk!(key)];
+                let (start, stop, by) = match by {
+                    ZRangeBy::Index { start, stop } => {
+                        (bulk!(start.to_string()), bulk!(stop.to_string()), None)
+                    }
+                    ZRangeBy::Score { min, max } => (
+                        bulk!(min.to_bytes()),
+                        bulk!(max.to_bytes()),
+                        Some("BYSCORE"),
+                    ),
+                    ZRangeBy::Lex { min, max } => {
+                        (bulk!(min.to_bytes()), bulk!(max.to_bytes()), Some("BYLEX"))
+                    }
+                };
+                // With `REV`, the bounds of score and lex ranges are sent as `max min`.
+                if rev && by.is_some() {
+                    frames.extend([stop, start]);
+                } else {
+                    frames.extend([start, stop]);
+                }
+                if let Some(by) = by {
+                    frames.push(bulk!(by));
+                }
+                if rev {
+                    frames.push(bulk!("REV"));
+                }
+                if let Some((offset, count)) = limit {
+                    frames.push(bulk!("LIMIT"));
+                    frames.push(bulk!(offset.to_string()));
+                    frames.push(bulk!(count.to_string()));
+                }
+                if with_scores {
+                    frames.push(bulk!("WITHSCORES"));
+                }
+                Frame::Array(frames)
+            }
+            Command::ZRank { key, member } => {
+                Frame::Array(vec![bulk!("ZRANK"), bulk!(key), bulk!(member)])
+            }
+            Command::ZScore { key, member } => {
+                Frame::Array(vec![bulk!("ZSCORE"), bulk!(key), bulk!(member)])
+            }
+            Command::ZRem { key, members } => {
+                let mut frames = vec![bulk!("ZREM"), bulk!(key)];
+                frames.extend(members.into_iter().map(|member| bulk!(member)));
+                Frame::Array(frames)
+            }
+            Command::ZIncrBy {
+                key,
+                increment,
+                member,
+            } => Frame::Array(vec![
+                bulk!("ZINCRBY"),
+                bulk!(key),
+                bulk!(format_double(increment)),
+                bulk!(member),
+            ]),
+            Command::ZCount { key, min, max } => Frame::Array(vec![
+                bulk!("ZCOUNT"),
+                bulk!(key),
+                bulk!(min.to_bytes()),
+                bulk!(max.to_bytes()),
+            ]),
+            Command::ZCard { key } => Frame::Array(vec![bulk!("ZCARD"), bulk!(key)]),
+            Command::ZPop {
+                key,
+                count,
+                highest,
+            } => {
+                let name = if highest { "ZPOPMAX" } else { "ZPOPMIN" };
+                let mut frames = vec![bulk!(name), bulk!(key)];
+                if let Some(count) = count {
+                    frames.push(bulk!(count.to_string()));
+                }
+                Frame::Array(frames)
+            }
+            Command::XAdd {
+                key,
+                id,
+                fields,
+                trim,
+                create,
+            } => {
+                let mut frames = vec![bulk!("XADD"), bulk!(key)];
+                if !create {
+                    frames.push(bulk!("NOMKSTREAM"));
+                }
+                if let Some(trim) = trim {
+                    frames.extend(Self::stream_trim_frames(trim));
+                }
+                frames.push(match id {
+                    XAddId::Auto => bulk!("*"),
+                    XAddId::Partial(ms) => bulk!(format!("{}-*", ms)),
+                    XAddId::Explicit(id) => bulk!(id.to_string()),
+                });
+                for (field, value) in fields {
+                    frames.push(bulk!(field));
+                    frames.push(bulk!(value));
+                }
+                Frame::Array(frames)
+            }
+            Command::XRange {
+                key,
+                start,
+                end,
+                rev,
+                count,
+            } => {
+                let mut frames = if rev {
+                    vec![
+                        bulk!("XREVRANGE"),
+                        bulk!(key),
+                        bulk!(end.to_string()),
+                        bulk!(start.to_string()),
+                    ]
+                } else {
+                    vec![
+                        bulk!("XRANGE"),
+                        bulk!(key),
+                        bulk!(start.to_string()),
+                        bulk!(end.to_string()),
+                    ]
+                };
+                if let Some(count) = count {
+                    frames.push(bulk!("COUNT"));
+                    frames.push(bulk!(count.to_string()));
+                }
+                Frame::Array(frames)
+            }
+            Command::XLen { key } => Frame::Array(vec![bulk!("XLEN"), bulk!(key)]),
+            Command::XTrim { key, trim } => {
+                let mut frames = vec![bulk!("XTRIM"), bulk!(key)];
+                frames.extend(Self::stream_trim_frames(trim));
+                Frame::Array(frames)
+            }
+            Command::XSetId { key, last_id } => Frame::Array(vec![
+                bulk!("XSETID"),
+                bulk!(key),
+                bulk!(last_id.to_string()),
+            ]),
+            Command::XDel { key, ids } => {
+                let mut frames = vec![bulk!("XDEL"), bulk!(key)];
+                frames.extend(ids.into_iter().map(|id| bulk!(id.to_string())));
+                Frame::Array(frames)
+            }
+            Command::XRead { streams, count } => {
+                let mut frames = vec![bulk!("XREAD")];
+                if let Some(count) = count {
+                    frames.push(bulk!("COUNT"));
+                    frames.push(bulk!(count.to_string()));
+                }
+                frames.push(bulk!("STREAMS"));
+                let (keys, ids): (Vec<_>, Vec<_>) = streams.into_iter().unzip();
+                frames.extend(keys.into_iter().map(|key| bulk!(key)));
+                frames.extend(ids.into_iter().map(|id| Self::id_or(id, "$")));
+                Frame::Array(frames)
+            }
+            Command::XGroupCreate {
+                key,
+                group,
+                id,
+                create,
+            } => {
+                let mut frames = vec![
+                    bulk!("XGROUP"),
+                    bulk!("CREATE"),
+                    bulk!(key),
+                    bulk!(group),
+                    Self::id_or(id, "$"),
+                ];
+                if create {
+                    frames.push(bulk!("MKSTREAM"));
+                }
+                Frame::Array(frames)
+            }
+            Command::XGroupDestroy { key, group } => Frame::Array(vec![
+                bulk!("XGROUP"),
+                bulk!("DESTROY"),
+                bulk!(key),
+                bulk!(group),
+            ]),
+            Command::XGroupCreateConsumer {
+                key,
+                group,
+                consumer,
+            } => Frame::Array(vec![
+                bulk!("XGROUP"),
+                bulk!("CREATECONSUMER"),
+                bulk!(key),
+                bulk!(group),
+                bulk!(consumer),
+            ]),
+            Command::XGroupDelConsumer {
+                key,
+                group,
+                consumer,
+            } => Frame::Array(vec![
+                bulk!("XGROUP"),
+                bulk!("DELCONSUMER"),
+                bulk!(key),
+                bulk!(group),
+                bulk!(consumer),
+            ]),
+            Command::XGroupSetId { key, group, id } => Frame::Array(vec![
+                bulk!("XGROUP"),
+                bulk!("SETID"),
+                bulk!(key),
+                bulk!(group),
+                Self::id_or(id, "$"),
+            ]),
+            Command::XReadGroup {
+                group,
+                consumer,
+                streams,
+                count,
+                noack,
+            } => {
+                let mut frames = vec![
+                    bulk!("XREADGROUP"),
+                    bulk!("GROUP"),
+                    bulk!(group),
+                    bulk!(consumer),
+                ];
+                if let Some(count) = count {
+                    frames.push(bulk!("COUNT"));
+                    frames.push(bulk!(count.to_string()));
+                }
+                if noack {
+                    frames.push(bulk!("NOACK"));
+                }
+                frames.push(bulk!("STREAMS"));
+                let (keys, ids): (Vec<_>, Vec<_>) = streams.into_iter().unzip();
+                frames.extend(keys.into_iter().map(|key| bulk!(key)));
+                frames.extend(ids.into_iter().map(|id| Self::id_or(id, ">")));
+                Frame::Array(frames)
+            }
+            Command::XAck { key, group, ids } => {
+                let mut frames = vec![bulk!("XACK"), bulk!(key), bulk!(group)];
+                frames.extend(ids.into_iter().map(|id| bulk!(id.to_string())));
+                Frame::Array(frames)
+            }
+            Command::XPending { key, group, range } => {
+                let mut frames = vec![bulk!("XPENDING"), bulk!(key), bulk!(group)];
+                if let Some(range) = range {
+                    if let Some(min_idle) = range.min_idle {
+                        frames.push(bulk!("IDLE"));
+                        frames.push(bulk!(min_idle.to_string()));
+                    }
+                    frames.push(bulk!(range.start.to_string()));
+                    frames.push(bulk!(range.end.to_string()));
+                    frames.push(bulk!(range.count.to_string()));
+                    if let Some(consumer) = range.consumer {
+                        frames.push(bulk!(consumer));
+                    }
+                }
+                Frame::Array(frames)
+            }
+            Command::XClaim {
+                key,
+                group,
+                consumer,
+                min_idle,
+                ids,
+                just_id,
+                options,
+            } => {
+                let mut frames = vec![
+                    bulk!("XCLAIM"),
+                    bulk!(key),
+                    bulk!(group),
+                    bulk!(consumer),
+                    bulk!(min_idle.to_string()),
+                ];
+                frames.extend(ids.into_iter().map(|id| bulk!(id.to_string())));
+                if let Some(delivery_time) = options.delivery_time {
+                    frames.push(bulk!("TIME"));
+                    frames.push(bulk!(delivery_time.to_string()));
+                }
+                if let Some(retry_count) = options.retry_count {
+                    frames.push(bulk!("RETRYCOUNT"));
+                    frames.push(bulk!(retry_count.to_string()));
+                }
+                if options.force {
+                    frames.push(bulk!("FORCE"));
+                }
+                if just_id {
+                    frames.push(bulk!("JUSTID"));
+                }
+                Frame::Array(frames)
+            }
+            Command::XAutoClaim {
+                key,
+                group,
+                consumer,
+                min_idle,
+                start,
+                count,
+                just_id,
+            } => {
+                let mut frames = vec![
+                    bulk!("XAUTOCLAIM"),
+                    bulk!(key),
+                    bulk!(group),
+                    bulk!(consumer),
+                    bulk!(min_idle.to_string()),
+                    bulk!(start.to_string()),
+                ];
+                if let Some(count) = count {
+                    frames.push(bulk!("COUNT"));
+                    frames.push(bulk!(count.to_string()));
+                }
+                if just_id {
+                    frames.push(bulk!("JUSTID"));
+                }
+                Frame::Array(frames)
+            }
+            Command::XInfoStream { key } => {
+                Frame::Array(vec![bulk!("XINFO"), bulk!("STREAM"), bulk!(key)])
+            }
+            Command::XInfoGroups { key } => {
+                Frame::Array(vec![bulk!("XINFO"), bulk!("GROUPS"), bulk!(key)])
+            }
+            Command::XInfoConsumers { key, group } => Frame::Array(vec![
+                bulk!("XINFO"),
+                bulk!("CONSUMERS"),
+                bulk!(key),
+                bulk!(group),
+            ]),
+            Command::Subscribe { channels } => Self::names_frame("SUBSCRIBE", channels),
+            Command::Unsubscribe { channels } => Self::names_frame("UNSUBSCRIBE", channels),
+            Command::PSubscribe { patterns } => Self::names_frame("PSUBSCRIBE", patterns),
+            Command::PUnsubscribe { patterns } => Self::names_frame("PUNSUBSCRIBE", patterns),
+            Command::Publish { channel, message } => {
+                Frame::Array(vec![bulk!("PUBLISH"), bulk!(channel), bulk!(message)])
+            }
+            Command::PubSubChannels { pattern } => {
+                let mut frames = vec![bulk!("PUBSUB"), bulk!("CHANNELS")];
+                frames.extend(pattern.map(|pattern| bulk!(pattern)));
+                Frame::Array(frames)
+            }
+            Command::PubSubNumSub { channels } => {
+                let mut frames = vec![bulk!("PUBSUB"), bulk!("NUMSUB")];
+                frames.extend(channels.into_iter().map(|channel| bulk!(channel)));
+                Frame::Array(frames)
+            }
+            Command::PubSubNumPat => Frame::Array(vec![bulk!("PUBSUB"), bulk!("NUMPAT")]),
+            Command::Save => Frame::Array(vec![bulk!("SAVE")]),
+            Command::BgSave => Frame::Array(vec![bulk!("BGSAVE")]),
+            Command::LastSave => Frame::Array(vec![bulk!("LASTSAVE")]),
+            Command::BgRewriteAof => Frame::Array(vec![bulk!("BGREWRITEAOF")]),
+            Command::Multi => Frame::Array(vec![bulk!("MULTI")]),
+            Command::Exec => Frame::Array(vec![bulk!("EXEC")]),
+            Command::Discard => Frame::Array(vec![bulk!("DISCARD")]),
+            Command::Watch { keys } => Self::names_frame("WATCH", keys),
+            Command::Unwatch => Frame::Array(vec![bulk!("UNWATCH")]),
+            Command::ReplicaOf { primary } => match primary {
+                Some((host, port)) => Frame::Array(vec![
+                    bulk!("REPLICAOF"),
+                    bulk!(host),
+                    bulk!(port.to_string()),
+                ]),
+                None => Frame::Array(vec![bulk!("REPLICAOF"), bulk!("NO"), bulk!("ONE")]),
+            },
+            Command::ReplConf { options } => {
+                let mut frames = vec![bulk!("REPLCONF")];
+                for (option, value) in options {
+                    frames.push(bulk!(option));
+                    frames.push(bulk!(value));
+                }
+                Frame::Array(frames)
+            }
+            Command::Sync => Frame::Array(vec![bulk!("SYNC")]),
+            Command::PSync {
+                replication_id,
+                offset,
+            } => Frame::Array(vec![
+                bulk!("PSYNC"),
+                bulk!(replication_id),
+                bulk!(offset.to_string()),
+            ]),
+            Command::Role => Frame::Array(vec![bulk!("ROLE")]),
+            Command::Wait {
+                numreplicas,
+                timeout,
+            } => Frame::Array(vec![
+                bulk!("WAIT"),
+                bulk!(numreplicas.to_string()),
+                Self::wait_timeout(timeout),
+            ]),
+            Command::WaitAof {
+                numlocal,
+                numreplicas,
+                timeout,
+            } => Frame::Array(vec![
+                bulk!("WAITAOF"),
+                bulk!(numlocal.to_string()),
+                bulk!(numreplicas.to_string()),
+                Self::wait_timeout(timeout),
+            ]),
+            Command::ClusterSlots => Frame::Array(vec![bulk!("CLUSTER"), bulk!("SLOTS")]),
+            Command::ClusterShards => Frame::Array(vec![bulk!("CLUSTER"), bulk!("SHARDS")]),
+            Command::ClusterNodes => Frame::Array(vec![bulk!("CLUSTER"), bulk!("NODES")]),
+            Command::ClusterMyId => Frame::Array(vec![bulk!("CLUSTER"), bulk!("MYID")]),
+            Command::ClusterKeySlot { key } => {
+                Frame::Array(vec![bulk!("CLUSTER"), bulk!("KEYSLOT"), bulk!(key)])
+            }
+            Command::ClusterCountKeysInSlot { slot } => Frame::Array(vec![
+                bulk!("CLUSTER"),
+                bulk!("COUNTKEYSINSLOT"),
+                bulk!(slot.to_string()),
+            ]),
+            Command::ClusterGetKeysInSlot { slot, count } => Frame::Array(vec![
+                bulk!("CLUSTER"),
+                bulk!("GETKEYSINSLOT"),
+                bulk!(slot.to_string()),
+                bulk!(count.to_string()),
+            ]),
+            Command::ClusterSetSlot { slot, state } => {
+                let mut frames = vec![bulk!("CLUSTER"), bulk!("SETSLOT"), bulk!(slot.to_string())];
+                match state {
+                    SlotState::Importing(node) => frames.extend([bulk!("IMPORTING"), bulk!(node)]),
+                    SlotState::Migrating(node) => frames.extend([bulk!("MIGRATING"), bulk!(node)]),
+                    SlotState::Node(node) => frames.extend([bulk!("NODE"), bulk!(node)]),
+                    SlotState::Stable => frames.push(bulk!("STABLE")),
+                }
+                Frame::Array(frames)
+            }
+            Command::Asking => Frame::Array(vec![bulk!("ASKING")]),
+            Command::Dump { key } => Frame::Array(vec![bulk!("DUMP"), bulk!(key)]),
+            Command::Restore {
+                key,
+                ttl,
+                payload,
+                replace,
+                absttl,
+            } => {
+                let mut frames = vec![
+                    bulk!("RESTORE"),
+                    bulk!(key),
+                    bulk!(ttl.to_string()),
+                    bulk!(payload),
+                ];
+                if replace {
+                    frames.push(bulk!("REPLACE"));
+                }
+                if absttl {
+                    frames.push(bulk!("ABSTTL"));
+                }
+                Frame::Array(frames)
+            }
+            Command::Migrate {
+                host,
+                port,
+                keys,
+                timeout,
+                copy,
+                replace,
+            } => {
+                let mut frames = vec![
+                    bulk!("MIGRATE"),
+                    bulk!(host),
+                    bulk!(port.to_string()),
+                    bulk!(""),
+                    bulk!("0"),
+                    bulk!(timeout.as_millis().to_string()),
+                ];
+                if copy {
+                    frames.push(bulk!("COPY"));
+                }
+                if replace {
+                    frames.push(bulk!("REPLACE"));
+                }
+                frames.push(bulk!("KEYS"));
+                frames.extend(keys.into_iter().map(|key| bulk!(key)));
+                Frame::Array(frames)
+            }
+        };
+        Ok(frame)
+    }
+
+    /// Command followed by channel, pattern or key names, e.g., `SUBSCRIBE`.
+    fn names_frame(name: &str, names: Vec<String>) -> Frame {
+        let mut frames = vec![bulk!(name.to_string())];
+        frames.extend(names.into_iter().map(|name| bulk!(name)));
+        Frame::Array(frames)
+    }
+
+    fn list_end(end: ListEnd) -> Frame {
+        match end {
+            ListEnd::Left => bulk!("LEFT"),
+            ListEnd::Right => bulk!("RIGHT"),
+        }
+    }
+
+    /// Timeout of blocking commands in seconds, `0` blocks indefinitely.
+    fn block_timeout(timeout: Option<Duration>) -> Frame {
+        bulk!(timeout.map_or(0.0, |t| t.as_secs_f64()).to_string())
+    }
+
+    /// Timeout of `WAIT` and `WAITAOF` in milliseconds, `0` waits indefinitely.
+    fn wait_timeout(timeout: Option<Duration>) -> Frame {
+        bulk!(timeout.map_or(0, |t| t.as_millis()).to_string())
+    }
+
+    /// Stream ID, or the `special` ID (`$` or `>`) for `None`.
+    fn id_or(id: Option<StreamId>, special: &str) -> Frame {
+        match id {
+            Some(id) => bulk!(id.to_string()),
+            None => bulk!(special),
+        }
+    }
+
+    fn stream_trim_frames(trim: StreamTrim) -> [Frame; 2] {
+        match trim {
+            StreamTrim::MaxLen(max_len) => [bulk!("MAXLEN"), bulk!(max_len.to_string())],
+            StreamTrim::MinId(min_id) => [bulk!("MINID"), bulk!(min_id.to_string())],
+        }
+    }
+}
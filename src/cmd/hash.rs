@@ -0,0 +1,95 @@
+use std::collections::VecDeque;
+
+use super::Command;
+use crate::err::RedisCommandError;
+use crate::frame::Frame;
+
+impl Command {
+    /// Parse hash commands, e.g., `HSET`, `HGETALL` or `HINCRBY`.
+    ///
+    /// Expects the command name in uppercase and the remaining parts of the frame.
+    pub(super) fn parse_hash(
+        name: &str,
+        mut parts: VecDeque<Frame>,
+    ) -> anyhow::Result<Command, RedisCommandError> {
+        match name {
+            "HSET" => {
+                if parts.len() < 3 || parts.len().is_multiple_of(2) {
+                    return Err(Self::wrong_number_of_arguments(
+                        name,
+                        "key and field-value pairs",
+                        parts.len(),
+                    ));
+                }
+                let key = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let mut fields = Vec::with_capacity(parts.len() / 2);
+                while let (Some(field), Some(value)) = (parts.pop_front(), parts.pop_front()) {
+                    fields.push((Self::bulk_to_bytes(field)?, Self::bulk_to_bytes(value)?));
+                }
+                Ok(Command::HSet { key, fields })
+            }
+            "HSETNX" => {
+                if parts.len() != 3 {
+                    return Err(Self::wrong_number_of_arguments(name, "3", parts.len()));
+                }
+                let key = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let field = Self::bulk_to_bytes(parts.pop_front().unwrap())?;
+                let value = Self::bulk_to_bytes(parts.pop_front().unwrap())?;
+                Ok(Command::HSetNx { key, field, value })
+            }
+            "HGET" | "HEXISTS" => {
+                if parts.len() != 2 {
+                    return Err(Self::wrong_number_of_arguments(name, "2", parts.len()));
+                }
+                let key = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let field = Self::bulk_to_bytes(parts.pop_front().unwrap())?;
+                if name == "HGET" {
+                    Ok(Command::HGet { key, field })
+                } else {
+                    Ok(Command::HExists { key, field })
+                }
+            }
+            "HMGET" | "HDEL" => {
+                if parts.len() < 2 {
+                    return Err(Self::wrong_number_of_arguments(name, ">1", parts.len()));
+                }
+                let key = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let fields = parts
+                    .into_iter()
+                    .map(Self::bulk_to_bytes)
+                    .collect::<Result<Vec<_>, RedisCommandError>>()?;
+                if name == "HMGET" {
+                    Ok(Command::HMGet { key, fields })
+                } else {
+                    Ok(Command::HDel { key, fields })
+                }
+            }
+            "HINCRBY" => {
+                if parts.len() != 3 {
+                    return Err(Self::wrong_number_of_arguments(name, "3", parts.len()));
+                }
+                let key = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let field = Self::bulk_to_bytes(parts.pop_front().unwrap())?;
+                let increment = Self::bulk_to_i64(parts.pop_front().unwrap())?;
+                Ok(Command::HIncrBy {
+                    key,
+                    field,
+                    increment,
+                })
+            }
+            "HGETALL" | "HKEYS" | "HVALS" | "HLEN" => {
+                if parts.len() != 1 {
+                    return Err(Self::wrong_number_of_arguments(name, "1", parts.len()));
+                }
+                let key = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                match name {
+                    "HGETALL" => Ok(Command::HGetAll { key }),
+                    "HKEYS" => Ok(Command::HKeys { key }),
+                    "HVALS" => Ok(Command::HVals { key }),
+                    _ => Ok(Command::HLen { key }),
+                }
+            }
+            _ => Ok(Command::Unknown(name.to_string())),
+        }
+    }
+}
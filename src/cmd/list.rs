@@ -0,0 +1,203 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use super::Command;
+use crate::db::{InsertPosition, ListEnd};
+use crate::err::RedisCommandError;
+use crate::frame::Frame;
+
+impl Command {
+    /// Parse list commands, e.g., `LPUSH`, `LRANGE` or `LINSERT`.
+    ///
+    /// Expects the command name in uppercase and the remaining parts of the frame.
+    pub(super) fn parse_list(
+        name: &str,
+        mut parts: VecDeque<Frame>,
+    ) -> anyhow::Result<Command, RedisCommandError> {
+        match name {
+            "LPUSH" | "RPUSH" => {
+                if parts.len() < 2 {
+                    return Err(Self::wrong_number_of_arguments(name, ">1", parts.len()));
+                }
+                let key = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let values = parts
+                    .into_iter()
+                    .map(Self::bulk_to_bytes)
+                    .collect::<Result<Vec<_>, RedisCommandError>>()?;
+                let end = if name == "LPUSH" {
+                    ListEnd::Left
+                } else {
+                    ListEnd::Right
+                };
+                Ok(Command::Push { key, values, end })
+            }
+            "LPOP" | "RPOP" => {
+                if parts.is_empty() || parts.len() > 2 {
+                    return Err(Self::wrong_number_of_arguments(name, "1 or 2", parts.len()));
+                }
+                let key = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let count = parts.pop_front().map(Self::bulk_to_u64).transpose()?;
+                let end = if name == "LPOP" {
+                    ListEnd::Left
+                } else {
+                    ListEnd::Right
+                };
+                Ok(Command::Pop { key, end, count })
+            }
+            "LRANGE" | "LTRIM" => {
+                if parts.len() != 3 {
+                    return Err(Self::wrong_number_of_arguments(name, "3", parts.len()));
+                }
+                let key = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let start = Self::bulk_to_i64(parts.pop_front().unwrap())?;
+                let stop = Self::bulk_to_i64(parts.pop_front().unwrap())?;
+                if name == "LRANGE" {
+                    Ok(Command::LRange { key, start, stop })
+                } else {
+                    Ok(Command::LTrim { key, start, stop })
+                }
+            }
+            "LLEN" => {
+                if parts.len() != 1 {
+                    return Err(Self::wrong_number_of_arguments(name, "1", parts.len()));
+                }
+                let key = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                Ok(Command::LLen { key })
+            }
+            "LINDEX" => {
+                if parts.len() != 2 {
+                    return Err(Self::wrong_number_of_arguments(name, "2", parts.len()));
+                }
+                let key = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let index = Self::bulk_to_i64(parts.pop_front().unwrap())?;
+                Ok(Command::LIndex { key, index })
+            }
+            "LSET" => {
+                if parts.len() != 3 {
+                    return Err(Self::wrong_number_of_arguments(name, "3", parts.len()));
+                }
+                let key = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let index = Self::bulk_to_i64(parts.pop_front().unwrap())?;
+                let value = Self::bulk_to_bytes(parts.pop_front().unwrap())?;
+                Ok(Command::LSet { key, index, value })
+            }
+            "LREM" => {
+                if parts.len() != 3 {
+                    return Err(Self::wrong_number_of_arguments(name, "3", parts.len()));
+                }
+                let key = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let count = Self::bulk_to_i64(parts.pop_front().unwrap())?;
+                let value = Self::bulk_to_bytes(parts.pop_front().unwrap())?;
+                Ok(Command::LRem { key, count, value })
+            }
+            "LINSERT" => {
+                if parts.len() != 4 {
+                    return Err(Self::wrong_number_of_arguments(name, "4", parts.len()));
+                }
+                let key = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let position = match Self::bulk_to_string(parts.pop_front().unwrap())?
+                    .to_uppercase()
+                    .as_str()
+                {
+                    "BEFORE" => InsertPosition::Before,
+                    "AFTER" => InsertPosition::After,
+                    _ => {
+                        return Err(RedisCommandError::InvalidCommand(
+                            "Expected BEFORE or AFTER".to_string(),
+                        ))
+                    }
+                };
+                let pivot = Self::bulk_to_bytes(parts.pop_front().unwrap())?;
+                let value = Self::bulk_to_bytes(parts.pop_front().unwrap())?;
+                Ok(Command::LInsert {
+                    key,
+                    position,
+                    pivot,
+                    value,
+                })
+            }
+            "BLPOP" | "BRPOP" => {
+                if parts.len() < 2 {
+                    return Err(Self::wrong_number_of_arguments(name, ">1", parts.len()));
+                }
+                let timeout = Self::parse_block_timeout(parts.pop_back().unwrap())?;
+                let keys = parts
+                    .into_iter()
+                    .map(Self::bulk_to_string)
+                    .collect::<Result<Vec<_>, RedisCommandError>>()?;
+                let end = if name == "BLPOP" {
+                    ListEnd::Left
+                } else {
+                    ListEnd::Right
+                };
+                Ok(Command::BPop { keys, end, timeout })
+            }
+            "BLMOVE" => {
+                if parts.len() != 5 {
+                    return Err(Self::wrong_number_of_arguments(name, "5", parts.len()));
+                }
+                let source = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let destination = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let from = Self::parse_list_end(parts.pop_front().unwrap())?;
+                let to = Self::parse_list_end(parts.pop_front().unwrap())?;
+                let timeout = Self::parse_block_timeout(parts.pop_front().unwrap())?;
+                Ok(Command::BLMove {
+                    source,
+                    destination,
+                    from,
+                    to,
+                    timeout,
+                })
+            }
+            "BRPOPLPUSH" => {
+                if parts.len() != 3 {
+                    return Err(Self::wrong_number_of_arguments(name, "3", parts.len()));
+                }
+                let source = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let destination = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let timeout = Self::parse_block_timeout(parts.pop_front().unwrap())?;
+                Ok(Command::BLMove {
+                    source,
+                    destination,
+                    from: ListEnd::Right,
+                    to: ListEnd::Left,
+                    timeout,
+                })
+            }
+            _ => Ok(Command::Unknown(name.to_string())),
+        }
+    }
+
+    fn parse_list_end(frame: Frame) -> anyhow::Result<ListEnd, RedisCommandError> {
+        match Self::bulk_to_string(frame)?.to_uppercase().as_str() {
+            "LEFT" => Ok(ListEnd::Left),
+            "RIGHT" => Ok(ListEnd::Right),
+            _ => Err(RedisCommandError::InvalidCommand(
+                "syntax error".to_string(),
+            )),
+        }
+    }
+
+    /// Parse the timeout of blocking commands in seconds, e.g., `0.5`. Zero means no timeout.
+    fn parse_block_timeout(frame: Frame) -> anyhow::Result<Option<Duration>, RedisCommandError> {
+        let timeout = Self::bulk_to_string(frame)?;
+        let timeout = timeout.parse::<f64>().map_err(|_| {
+            RedisCommandError::ParseDecimalError(
+                "timeout is not a float or out of range".to_string(),
+            )
+        })?;
+        if timeout < 0.0 {
+            return Err(RedisCommandError::InvalidCommand(
+                "timeout is negative".to_string(),
+            ));
+        }
+        if timeout == 0.0 {
+            return Ok(None);
+        }
+        Duration::try_from_secs_f64(timeout).map(Some).map_err(|_| {
+            RedisCommandError::ParseDecimalError(
+                "timeout is not a float or out of range".to_string(),
+            )
+        })
+    }
+}
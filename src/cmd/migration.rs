@@ -0,0 +1,116 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use super::Command;
+use crate::err::RedisCommandError;
+use crate::frame::Frame;
+
+fn syntax_error() -> RedisCommandError {
+    RedisCommandError::InvalidCommand("syntax error".to_string())
+}
+
+impl Command {
+    /// Parse commands that move keys between servers, i.e., `DUMP`, `RESTORE`, `MIGRATE`
+    /// and `ASKING`.
+    ///
+    /// Expects the command name in uppercase and the remaining parts of the frame.
+    pub(super) fn parse_migration(
+        name: &str,
+        mut parts: VecDeque<Frame>,
+    ) -> anyhow::Result<Command, RedisCommandError> {
+        match name {
+            "DUMP" => {
+                if parts.len() != 1 {
+                    return Err(Self::wrong_number_of_arguments(name, "1", parts.len()));
+                }
+                let key = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                Ok(Command::Dump { key })
+            }
+            "RESTORE" => {
+                if parts.len() < 3 {
+                    return Err(Self::wrong_number_of_arguments(name, ">2", parts.len()));
+                }
+                let key = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let ttl = Self::bulk_to_u64(parts.pop_front().unwrap())?;
+                let payload = Self::bulk_to_bytes(parts.pop_front().unwrap())?;
+                let (mut replace, mut absttl) = (false, false);
+                while let Some(option) = parts.pop_front() {
+                    match Self::bulk_to_string(option)?.to_uppercase().as_str() {
+                        "REPLACE" => replace = true,
+                        "ABSTTL" => absttl = true,
+                        // Keys are not evicted, so their idle time and frequency are ignored
+                        "IDLETIME" | "FREQ" => {
+                            Self::bulk_to_u64(parts.pop_front().ok_or_else(syntax_error)?)?;
+                        }
+                        _ => return Err(syntax_error()),
+                    }
+                }
+                Ok(Command::Restore {
+                    key,
+                    ttl,
+                    payload,
+                    replace,
+                    absttl,
+                })
+            }
+            "MIGRATE" => {
+                if parts.len() < 5 {
+                    return Err(Self::wrong_number_of_arguments(name, ">4", parts.len()));
+                }
+                let host = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let port = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let port = port.parse::<u16>().map_err(|_| {
+                    RedisCommandError::ParseIntegerError(format!("Invalid port: {}", port))
+                })?;
+                let key = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                if Self::bulk_to_u64(parts.pop_front().unwrap())? != 0 {
+                    return Err(RedisCommandError::NotImplemented(
+                        "MIGRATE to databases other than 0".to_string(),
+                    ));
+                }
+                // Zero or negative timeouts default to one second as in Redis
+                let timeout = match Self::bulk_to_i64(parts.pop_front().unwrap())? {
+                    millis if millis > 0 => Duration::from_millis(millis as u64),
+                    _ => Duration::from_secs(1),
+                };
+                let (mut copy, mut replace) = (false, false);
+                let mut keys = vec![];
+                while let Some(option) = parts.pop_front() {
+                    match Self::bulk_to_string(option)?.to_uppercase().as_str() {
+                        "COPY" => copy = true,
+                        "REPLACE" => replace = true,
+                        "KEYS" if key.is_empty() && !parts.is_empty() => {
+                            keys = Self::bulks_to_strings(std::mem::take(&mut parts))?;
+                        }
+                        "AUTH" | "AUTH2" => {
+                            return Err(RedisCommandError::NotImplemented(
+                                "MIGRATE with authentication".to_string(),
+                            ))
+                        }
+                        _ => return Err(syntax_error()),
+                    }
+                }
+                if !key.is_empty() {
+                    keys.push(key);
+                } else if keys.is_empty() {
+                    return Err(syntax_error());
+                }
+                Ok(Command::Migrate {
+                    host,
+                    port,
+                    keys,
+                    timeout,
+                    copy,
+                    replace,
+                })
+            }
+            "ASKING" => {
+                if !parts.is_empty() {
+                    return Err(Self::wrong_number_of_arguments(name, "0", parts.len()));
+                }
+                Ok(Command::Asking)
+            }
+            _ => Ok(Command::Unknown(name.to_string())),
+        }
+    }
+}
@@ -0,0 +1,63 @@
+use std::collections::VecDeque;
+
+use super::Command;
+use crate::err::RedisCommandError;
+use crate::frame::Frame;
+
+impl Command {
+    /// Parse publish/subscribe commands, e.g., `SUBSCRIBE`, `PUBLISH` or `PUBSUB`.
+    ///
+    /// Expects the command name in uppercase and the remaining parts of the frame.
+    pub(super) fn parse_pubsub(
+        name: &str,
+        mut parts: VecDeque<Frame>,
+    ) -> anyhow::Result<Command, RedisCommandError> {
+        match name {
+            "SUBSCRIBE" | "PSUBSCRIBE" => {
+                if parts.is_empty() {
+                    return Err(Self::wrong_number_of_arguments(name, ">0", parts.len()));
+                }
+                let names = Self::bulks_to_strings(parts)?;
+                if name == "SUBSCRIBE" {
+                    Ok(Command::Subscribe { channels: names })
+                } else {
+                    Ok(Command::PSubscribe { patterns: names })
+                }
+            }
+            "UNSUBSCRIBE" => Ok(Command::Unsubscribe {
+                channels: Self::bulks_to_strings(parts)?,
+            }),
+            "PUNSUBSCRIBE" => Ok(Command::PUnsubscribe {
+                patterns: Self::bulks_to_strings(parts)?,
+            }),
+            "PUBLISH" => {
+                if parts.len() != 2 {
+                    return Err(Self::wrong_number_of_arguments(name, "2", parts.len()));
+                }
+                let channel = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let message = Self::bulk_to_bytes(parts.pop_front().unwrap())?;
+                Ok(Command::Publish { channel, message })
+            }
+            "PUBSUB" => {
+                if parts.is_empty() {
+                    return Err(Self::wrong_number_of_arguments(name, ">0", parts.len()));
+                }
+                let subcommand = Self::bulk_to_string(parts.pop_front().unwrap())?.to_uppercase();
+                match (subcommand.as_str(), parts.len()) {
+                    ("CHANNELS", 0 | 1) => Ok(Command::PubSubChannels {
+                        pattern: parts.pop_front().map(Self::bulk_to_string).transpose()?,
+                    }),
+                    ("NUMSUB", _) => Ok(Command::PubSubNumSub {
+                        channels: Self::bulks_to_strings(parts)?,
+                    }),
+                    ("NUMPAT", 0) => Ok(Command::PubSubNumPat),
+                    _ => Err(RedisCommandError::InvalidCommand(format!(
+                        "unknown subcommand or wrong number of arguments for 'PUBSUB {}'",
+                        subcommand
+                    ))),
+                }
+            }
+            _ => Ok(Command::Unknown(name.to_string())),
+        }
+    }
+}
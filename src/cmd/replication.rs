@@ -0,0 +1,106 @@
+use std::collections::VecDeque;
+use std::time::Duration;
+
+use super::Command;
+use crate::err::RedisCommandError;
+use crate::frame::Frame;
+
+impl Command {
+    /// Parse replication commands, i.e., `REPLICAOF` (`SLAVEOF`), `REPLCONF`, `SYNC`,
+    /// `PSYNC`, `ROLE`, `WAIT` and `WAITAOF`.
+    ///
+    /// Expects the command name in uppercase and the remaining parts of the frame.
+    pub(super) fn parse_replication(
+        name: &str,
+        mut parts: VecDeque<Frame>,
+    ) -> anyhow::Result<Command, RedisCommandError> {
+        match name {
+            "REPLICAOF" | "SLAVEOF" => {
+                if parts.len() != 2 {
+                    return Err(Self::wrong_number_of_arguments(name, "2", parts.len()));
+                }
+                let host = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let port = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                if host.eq_ignore_ascii_case("NO") && port.eq_ignore_ascii_case("ONE") {
+                    return Ok(Command::ReplicaOf { primary: None });
+                }
+                let port = port.parse::<u16>().map_err(|_| {
+                    RedisCommandError::ParseIntegerError(format!("Invalid port: {}", port))
+                })?;
+                Ok(Command::ReplicaOf {
+                    primary: Some((host, port)),
+                })
+            }
+            "REPLCONF" => {
+                if parts.is_empty() || !parts.len().is_multiple_of(2) {
+                    return Err(Self::wrong_number_of_arguments(
+                        name,
+                        "an even number of",
+                        parts.len(),
+                    ));
+                }
+                let mut options = Vec::with_capacity(parts.len() / 2);
+                while let (Some(option), Some(value)) = (parts.pop_front(), parts.pop_front()) {
+                    options.push((
+                        Self::bulk_to_string(option)?.to_lowercase(),
+                        Self::bulk_to_string(value)?,
+                    ));
+                }
+                Ok(Command::ReplConf { options })
+            }
+            "PSYNC" => {
+                if parts.len() != 2 {
+                    return Err(Self::wrong_number_of_arguments(name, "2", parts.len()));
+                }
+                let replication_id = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let offset = Self::bulk_to_i64(parts.pop_front().unwrap())?;
+                Ok(Command::PSync {
+                    replication_id,
+                    offset,
+                })
+            }
+            "SYNC" | "ROLE" => {
+                if !parts.is_empty() {
+                    return Err(Self::wrong_number_of_arguments(name, "0", parts.len()));
+                }
+                match name {
+                    "SYNC" => Ok(Command::Sync),
+                    _ => Ok(Command::Role),
+                }
+            }
+            "WAIT" => {
+                if parts.len() != 2 {
+                    return Err(Self::wrong_number_of_arguments(name, "2", parts.len()));
+                }
+                let numreplicas = Self::bulk_to_u64(parts.pop_front().unwrap())?;
+                let timeout = Self::parse_wait_timeout(parts.pop_front().unwrap())?;
+                Ok(Command::Wait {
+                    numreplicas,
+                    timeout,
+                })
+            }
+            "WAITAOF" => {
+                if parts.len() != 3 {
+                    return Err(Self::wrong_number_of_arguments(name, "3", parts.len()));
+                }
+                let numlocal = Self::bulk_to_u64(parts.pop_front().unwrap())?;
+                let numreplicas = Self::bulk_to_u64(parts.pop_front().unwrap())?;
+                let timeout = Self::parse_wait_timeout(parts.pop_front().unwrap())?;
+                Ok(Command::WaitAof {
+                    numlocal,
+                    numreplicas,
+                    timeout,
+                })
+            }
+            _ => Ok(Command::Unknown(name.to_string())),
+        }
+    }
+
+    /// Parse the timeout of `WAIT` and `WAITAOF` in milliseconds. Zero means no timeout.
+    fn parse_wait_timeout(frame: Frame) -> anyhow::Result<Option<Duration>, RedisCommandError> {
+        match Self::bulk_to_u64(frame)? {
+            0 => Ok(None),
+            millis => Ok(Some(Duration::from_millis(millis))),
+        }
+    }
+}
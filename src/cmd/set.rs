@@ -0,0 +1,115 @@
+use std::collections::VecDeque;
+
+use super::Command;
+use crate::db::SetOperation;
+use crate::err::RedisCommandError;
+use crate::frame::Frame;
+
+impl Command {
+    /// Parse set commands, e.g., `SADD`, `SMEMBERS` or `SINTERSTORE`.
+    ///
+    /// Expects the command name in uppercase and the remaining parts of the frame.
+    pub(super) fn parse_set(
+        name: &str,
+        mut parts: VecDeque<Frame>,
+    ) -> anyhow::Result<Command, RedisCommandError> {
+        match name {
+            "SADD" | "SREM" => {
+                if parts.len() < 2 {
+                    return Err(Self::wrong_number_of_arguments(name, ">1", parts.len()));
+                }
+                let key = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let members = parts
+                    .into_iter()
+                    .map(Self::bulk_to_bytes)
+                    .collect::<Result<Vec<_>, RedisCommandError>>()?;
+                if name == "SADD" {
+                    Ok(Command::SAdd { key, members })
+                } else {
+                    Ok(Command::SRem { key, members })
+                }
+            }
+            "SMEMBERS" | "SCARD" => {
+                if parts.len() != 1 {
+                    return Err(Self::wrong_number_of_arguments(name, "1", parts.len()));
+                }
+                let key = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                if name == "SMEMBERS" {
+                    Ok(Command::SMembers { key })
+                } else {
+                    Ok(Command::SCard { key })
+                }
+            }
+            "SISMEMBER" => {
+                if parts.len() != 2 {
+                    return Err(Self::wrong_number_of_arguments(name, "2", parts.len()));
+                }
+                let key = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let member = Self::bulk_to_bytes(parts.pop_front().unwrap())?;
+                Ok(Command::SIsMember { key, member })
+            }
+            "SPOP" => {
+                if parts.is_empty() || parts.len() > 2 {
+                    return Err(Self::wrong_number_of_arguments(name, "1 or 2", parts.len()));
+                }
+                let key = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let count = parts.pop_front().map(Self::bulk_to_u64).transpose()?;
+                Ok(Command::SPop { key, count })
+            }
+            "SRANDMEMBER" => {
+                if parts.is_empty() || parts.len() > 2 {
+                    return Err(Self::wrong_number_of_arguments(name, "1 or 2", parts.len()));
+                }
+                let key = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let count = parts.pop_front().map(Self::bulk_to_i64).transpose()?;
+                // As in Redis, the number of repeated members is limited
+                if count.is_some_and(|count| count <= -(i64::MAX / 2)) {
+                    return Err(RedisCommandError::ParseIntegerError(
+                        "value is out of range".to_string(),
+                    ));
+                }
+                Ok(Command::SRandMember { key, count })
+            }
+            "SINTER" | "SUNION" | "SDIFF" => {
+                if parts.is_empty() {
+                    return Err(Self::wrong_number_of_arguments(name, ">0", parts.len()));
+                }
+                let keys = parts
+                    .into_iter()
+                    .map(Self::bulk_to_string)
+                    .collect::<Result<Vec<_>, RedisCommandError>>()?;
+                Ok(Command::SetOp {
+                    operation: set_operation(name),
+                    keys,
+                })
+            }
+            "SINTERSTORE" | "SUNIONSTORE" | "SDIFFSTORE" => {
+                if parts.len() < 2 {
+                    return Err(Self::wrong_number_of_arguments(name, ">1", parts.len()));
+                }
+                let destination = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let keys = parts
+                    .into_iter()
+                    .map(Self::bulk_to_string)
+                    .collect::<Result<Vec<_>, RedisCommandError>>()?;
+                Ok(Command::SetOpStore {
+                    operation: set_operation(name),
+                    destination,
+                    keys,
+                })
+            }
+            _ => Ok(Command::Unknown(name.to_string())),
+        }
+    }
+}
+
+/// Get the set operation from the command name, e.g., `SINTERSTORE` -> `SetOperation::Inter`.
+fn set_operation(name: &str) -> SetOperation {
+    if name.starts_with("SINTER") {
+        SetOperation::Inter
+    } else if name.starts_with("SUNION") {
+        SetOperation::Union
+    } else {
+        SetOperation::Diff
+    }
+}
@@ -0,0 +1,277 @@
+use std::collections::VecDeque;
+
+use bytes::Bytes;
+
+use super::Command;
+use crate::common::bytes_to_string;
+use crate::db::{LexBound, ScoreBound, ZAddComparison, ZAddCondition, ZAddOptions, ZRangeBy};
+use crate::err::RedisCommandError;
+use crate::frame::{format_double, Frame};
+
+impl ScoreBound {
+    /// Parse a score bound, e.g., `1.5`, `(1.5`, `-inf` or `+inf`.
+    pub(crate) fn from_bytes(bytes: &Bytes) -> anyhow::Result<Self, RedisCommandError> {
+        let bound = bytes_to_string(bytes)?;
+        match bound.strip_prefix('(') {
+            Some(score) => Ok(ScoreBound::Exclusive(parse_score(score)?)),
+            None => Ok(ScoreBound::Inclusive(parse_score(&bound)?)),
+        }
+    }
+
+    pub(crate) fn to_bytes(self) -> Bytes {
+        match self {
+            ScoreBound::Inclusive(score) => Bytes::from(format_double(score)),
+            ScoreBound::Exclusive(score) => Bytes::from(format!("({}", format_double(score))),
+        }
+    }
+}
+
+impl LexBound {
+    /// Parse a lexicographical bound, e.g., `[a`, `(b`, `-` or `+`.
+    pub(crate) fn from_bytes(bytes: &Bytes) -> anyhow::Result<Self, RedisCommandError> {
+        match bytes.first() {
+            Some(b'-') if bytes.len() == 1 => Ok(LexBound::Min),
+            Some(b'+') if bytes.len() == 1 => Ok(LexBound::Max),
+            Some(b'[') => Ok(LexBound::Inclusive(bytes.slice(1..))),
+            Some(b'(') => Ok(LexBound::Exclusive(bytes.slice(1..))),
+            _ => Err(RedisCommandError::InvalidCommand(
+                "min or max not valid string range item".to_string(),
+            )),
+        }
+    }
+
+    pub(crate) fn to_bytes(&self) -> Bytes {
+        match self {
+            LexBound::Min => Bytes::from("-"),
+            LexBound::Max => Bytes::from("+"),
+            LexBound::Inclusive(value) => [b"[".as_slice(), value].concat().into(),
+            LexBound::Exclusive(value) => [b"(".as_slice(), value].concat().into(),
+        }
+    }
+}
+
+/// Parse a score, accepting `inf`, `+inf` and `-inf`. `NaN` is rejected.
+pub(crate) fn parse_score(score: &str) -> anyhow::Result<f64, RedisCommandError> {
+    match score.parse::<f64>() {
+        Ok(score) if !score.is_nan() => Ok(score),
+        _ => Err(RedisCommandError::ParseDecimalError(
+            "value is not a valid float".to_string(),
+        )),
+    }
+}
+
+impl Command {
+    /// Parse sorted set commands, e.g., `ZADD`, `ZRANGE` or `ZPOPMIN`.
+    ///
+    /// Expects the command name in uppercase and the remaining parts of the frame.
+    pub(super) fn parse_sorted_set(
+        name: &str,
+        mut parts: VecDeque<Frame>,
+    ) -> anyhow::Result<Command, RedisCommandError> {
+        match name {
+            "ZADD" => {
+                if parts.len() < 3 {
+                    return Err(Self::wrong_number_of_arguments(name, ">2", parts.len()));
+                }
+                let key = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let mut options = ZAddOptions::default();
+                while let Some(Frame::Bulk(option)) = parts.front() {
+                    match option.to_ascii_uppercase().as_slice() {
+                        b"NX" => options.condition = Some(ZAddCondition::Nx),
+                        b"XX" => options.condition = Some(ZAddCondition::Xx),
+                        b"GT" => options.comparison = Some(ZAddComparison::Gt),
+                        b"LT" => options.comparison = Some(ZAddComparison::Lt),
+                        b"CH" => options.changed = true,
+                        b"INCR" => options.increment = true,
+                        _ => break,
+                    }
+                    parts.pop_front();
+                }
+                if options.condition == Some(ZAddCondition::Nx) && options.comparison.is_some() {
+                    return Err(RedisCommandError::InvalidCommand(
+                        "GT, LT, and/or NX options at the same time are not compatible".to_string(),
+                    ));
+                }
+                if parts.is_empty() || !parts.len().is_multiple_of(2) {
+                    return Err(Self::wrong_number_of_arguments(
+                        name,
+                        "score-member pairs",
+                        parts.len(),
+                    ));
+                }
+                if options.increment && parts.len() != 2 {
+                    return Err(RedisCommandError::InvalidCommand(
+                        "INCR option supports a single increment-element pair".to_string(),
+                    ));
+                }
+                let mut members = Vec::with_capacity(parts.len() / 2);
+                while let (Some(score), Some(member)) = (parts.pop_front(), parts.pop_front()) {
+                    members.push((Self::bulk_to_f64(score)?, Self::bulk_to_bytes(member)?));
+                }
+                Ok(Command::ZAdd {
+                    key,
+                    options,
+                    members,
+                })
+            }
+            "ZRANGE" => {
+                if parts.len() < 3 {
+                    return Err(Self::wrong_number_of_arguments(name, ">2", parts.len()));
+                }
+                let key = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let start = Self::bulk_to_bytes(parts.pop_front().unwrap())?;
+                let stop = Self::bulk_to_bytes(parts.pop_front().unwrap())?;
+
+                let (mut by_score, mut by_lex, mut rev, mut with_scores) =
+                    (false, false, false, false);
+                let mut limit = None;
+                while let Some(option) = parts.pop_front() {
+                    match Self::bulk_to_string(option)?.to_uppercase().as_str() {
+                        "BYSCORE" => by_score = true,
+                        "BYLEX" => by_lex = true,
+                        "REV" => rev = true,
+                        "WITHSCORES" => with_scores = true,
+                        "LIMIT" => {
+                            if parts.len() < 2 {
+                                return Err(RedisCommandError::InvalidCommand(
+                                    "LIMIT expects offset and count".to_string(),
+                                ));
+                            }
+                            let offset = Self::bulk_to_i64(parts.pop_front().unwrap())?;
+                            let count = Self::bulk_to_i64(parts.pop_front().unwrap())?;
+                            limit = Some((offset, count));
+                        }
+                        option => {
+                            return Err(RedisCommandError::InvalidCommand(format!(
+                                "Unknown ZRANGE option: {}",
+                                option
+                            )))
+                        }
+                    }
+                }
+                if by_score && by_lex {
+                    return Err(RedisCommandError::InvalidCommand(
+                        "BYSCORE and BYLEX options are not compatible".to_string(),
+                    ));
+                }
+                if limit.is_some() && !by_score && !by_lex {
+                    return Err(RedisCommandError::InvalidCommand(
+                        "LIMIT is only supported in combination with either BYSCORE or BYLEX"
+                            .to_string(),
+                    ));
+                }
+                if with_scores && by_lex {
+                    return Err(RedisCommandError::InvalidCommand(
+                        "WITHSCORES not supported in combination with BYLEX".to_string(),
+                    ));
+                }
+
+                // With `REV`, the bounds of score and lex ranges are given as `max min`.
+                let (min, max) = if rev {
+                    (&stop, &start)
+                } else {
+                    (&start, &stop)
+                };
+                let by = if by_score {
+                    ZRangeBy::Score {
+                        min: ScoreBound::from_bytes(min)?,
+                        max: ScoreBound::from_bytes(max)?,
+                    }
+                } else if by_lex {
+                    ZRangeBy::Lex {
+                        min: LexBound::from_bytes(min)?,
+                        max: LexBound::from_bytes(max)?,
+                    }
+                } else {
+                    ZRangeBy::Index {
+                        start: Self::bulk_to_i64(Frame::Bulk(start))?,
+                        stop: Self::bulk_to_i64(Frame::Bulk(stop))?,
+                    }
+                };
+                Ok(Command::ZRange {
+                    key,
+                    by,
+                    rev,
+                    limit,
+                    with_scores,
+                })
+            }
+            "ZRANK" | "ZSCORE" => {
+                if parts.len() != 2 {
+                    return Err(Self::wrong_number_of_arguments(name, "2", parts.len()));
+                }
+                let key = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let member = Self::bulk_to_bytes(parts.pop_front().unwrap())?;
+                if name == "ZRANK" {
+                    Ok(Command::ZRank { key, member })
+                } else {
+                    Ok(Command::ZScore { key, member })
+                }
+            }
+            "ZREM" => {
+                if parts.len() < 2 {
+                    return Err(Self::wrong_number_of_arguments(name, ">1", parts.len()));
+                }
+                let key = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let members = parts
+                    .into_iter()
+                    .map(Self::bulk_to_bytes)
+                    .collect::<Result<Vec<_>, RedisCommandError>>()?;
+                Ok(Command::ZRem { key, members })
+            }
+            "ZINCRBY" => {
+                if parts.len() != 3 {
+                    return Err(Self::wrong_number_of_arguments(name, "3", parts.len()));
+                }
+                let key = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let increment = Self::bulk_to_f64(parts.pop_front().unwrap())?;
+                let member = Self::bulk_to_bytes(parts.pop_front().unwrap())?;
+                Ok(Command::ZIncrBy {
+                    key,
+                    increment,
+                    member,
+                })
+            }
+            "ZCOUNT" => {
+                if parts.len() != 3 {
+                    return Err(Self::wrong_number_of_arguments(name, "3", parts.len()));
+                }
+                let key = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let min =
+                    ScoreBound::from_bytes(&Self::bulk_to_bytes(parts.pop_front().unwrap())?)?;
+                let max =
+                    ScoreBound::from_bytes(&Self::bulk_to_bytes(parts.pop_front().unwrap())?)?;
+                Ok(Command::ZCount { key, min, max })
+            }
+            "ZCARD" => {
+                if parts.len() != 1 {
+                    return Err(Self::wrong_number_of_arguments(name, "1", parts.len()));
+                }
+                let key = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                Ok(Command::ZCard { key })
+            }
+            "ZPOPMIN" | "ZPOPMAX" => {
+                if parts.is_empty() || parts.len() > 2 {
+                    return Err(Self::wrong_number_of_arguments(name, "1 or 2", parts.len()));
+                }
+                let key = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let count = parts.pop_front().map(Self::bulk_to_u64).transpose()?;
+                Ok(Command::ZPop {
+                    key,
+                    count,
+                    highest: name == "ZPOPMAX",
+                })
+            }
+            _ => Ok(Command::Unknown(name.to_string())),
+        }
+    }
+
+    fn bulk_to_f64(frame: Frame) -> anyhow::Result<f64, RedisCommandError> {
+        match frame {
+            Frame::Bulk(bytes) => parse_score(&bytes_to_string(&bytes)?),
+            _ => Err(RedisCommandError::InvalidFrame(
+                "Expected bulk string".to_string(),
+            )),
+        }
+    }
+}
@@ -0,0 +1,281 @@
+use std::collections::VecDeque;
+
+use super::Command;
+use crate::db::{StreamId, StreamTrim, XAddId};
+use crate::err::RedisCommandError;
+use crate::frame::Frame;
+
+fn invalid_stream_id() -> RedisCommandError {
+    RedisCommandError::InvalidCommand(
+        "Invalid stream ID specified as stream command argument".to_string(),
+    )
+}
+
+/// Parse a stream ID, e.g., `1526919030474-0`. If the sequence number is omitted,
+/// e.g., `1526919030474`, `default_seq` is used.
+pub(crate) fn parse_stream_id(id: &str, default_seq: u64) -> Result<StreamId, RedisCommandError> {
+    let (ms, seq) = match id.split_once('-') {
+        Some((ms, seq)) => (ms, seq.parse().map_err(|_| invalid_stream_id())?),
+        None => (id, default_seq),
+    };
+    let ms = ms.parse().map_err(|_| invalid_stream_id())?;
+    Ok(StreamId::new(ms, seq))
+}
+
+/// Parse an inclusive bound of `XRANGE`, i.e., `-`, `+`, an ID, or an exclusive ID, e.g., `(1-0`.
+pub(super) fn parse_range_bound(
+    bound: &str,
+    is_start: bool,
+) -> Result<StreamId, RedisCommandError> {
+    match bound {
+        "-" => Ok(StreamId::MIN),
+        "+" => Ok(StreamId::MAX),
+        _ => {
+            let default_seq = if is_start { 0 } else { u64::MAX };
+            match bound.strip_prefix('(') {
+                Some(id) => {
+                    let id = parse_stream_id(id, default_seq)?;
+                    let id = if is_start { id.next() } else { id.prev() };
+                    id.ok_or_else(|| {
+                        RedisCommandError::InvalidCommand(
+                            "invalid start or end ID for an exclusive range".to_string(),
+                        )
+                    })
+                }
+                None => parse_stream_id(bound, default_seq),
+            }
+        }
+    }
+}
+
+impl Command {
+    /// Parse stream commands, e.g., `XADD`, `XRANGE` or `XREAD`.
+    ///
+    /// Expects the command name in uppercase and the remaining parts of the frame.
+    pub(super) fn parse_stream(
+        name: &str,
+        mut parts: VecDeque<Frame>,
+    ) -> anyhow::Result<Command, RedisCommandError> {
+        match name {
+            "XADD" => {
+                if parts.len() < 4 {
+                    return Err(Self::wrong_number_of_arguments(name, ">3", parts.len()));
+                }
+                let key = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let mut create = true;
+                let mut trim = None;
+                let id = loop {
+                    let Some(arg) = parts.pop_front() else {
+                        return Err(RedisCommandError::InvalidCommand(
+                            "syntax error".to_string(),
+                        ));
+                    };
+                    let arg = Self::bulk_to_string(arg)?;
+                    match arg.to_uppercase().as_str() {
+                        "NOMKSTREAM" => create = false,
+                        "MAXLEN" | "MINID" => {
+                            trim = Some(Self::parse_stream_trim(&arg, &mut parts)?)
+                        }
+                        _ => break Self::parse_xadd_id(&arg)?,
+                    }
+                };
+                if parts.is_empty() || !parts.len().is_multiple_of(2) {
+                    return Err(Self::wrong_number_of_arguments(
+                        name,
+                        "field-value pairs",
+                        parts.len(),
+                    ));
+                }
+                let mut fields = Vec::with_capacity(parts.len() / 2);
+                while let (Some(field), Some(value)) = (parts.pop_front(), parts.pop_front()) {
+                    fields.push((Self::bulk_to_bytes(field)?, Self::bulk_to_bytes(value)?));
+                }
+                Ok(Command::XAdd {
+                    key,
+                    id,
+                    fields,
+                    trim,
+                    create,
+                })
+            }
+            "XRANGE" | "XREVRANGE" => {
+                if parts.len() != 3 && parts.len() != 5 {
+                    return Err(Self::wrong_number_of_arguments(name, "3 or 5", parts.len()));
+                }
+                let key = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let first = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let second = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let rev = name == "XREVRANGE";
+                // `XREVRANGE` takes the end first
+                let (start, end) = if rev {
+                    (second, first)
+                } else {
+                    (first, second)
+                };
+                let start = parse_range_bound(&start, true)?;
+                let end = parse_range_bound(&end, false)?;
+                let count = match parts.pop_front() {
+                    Some(option) => {
+                        if Self::bulk_to_string(option)?.to_uppercase() != "COUNT" {
+                            return Err(RedisCommandError::InvalidCommand(
+                                "syntax error".to_string(),
+                            ));
+                        }
+                        Some(Self::bulk_to_u64(parts.pop_front().unwrap())?)
+                    }
+                    None => None,
+                };
+                Ok(Command::XRange {
+                    key,
+                    start,
+                    end,
+                    rev,
+                    count,
+                })
+            }
+            "XLEN" => {
+                if parts.len() != 1 {
+                    return Err(Self::wrong_number_of_arguments(name, "1", parts.len()));
+                }
+                let key = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                Ok(Command::XLen { key })
+            }
+            "XTRIM" => {
+                if parts.len() < 3 {
+                    return Err(Self::wrong_number_of_arguments(name, ">2", parts.len()));
+                }
+                let key = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let strategy = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let trim = Self::parse_stream_trim(&strategy, &mut parts)?;
+                if !parts.is_empty() {
+                    return Err(RedisCommandError::InvalidCommand(
+                        "syntax error".to_string(),
+                    ));
+                }
+                Ok(Command::XTrim { key, trim })
+            }
+            "XSETID" => {
+                if parts.len() != 2 {
+                    return Err(Self::wrong_number_of_arguments(name, "2", parts.len()));
+                }
+                let key = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let last_id =
+                    parse_stream_id(&Self::bulk_to_string(parts.pop_front().unwrap())?, 0)?;
+                Ok(Command::XSetId { key, last_id })
+            }
+            "XDEL" => {
+                if parts.len() < 2 {
+                    return Err(Self::wrong_number_of_arguments(name, ">1", parts.len()));
+                }
+                let key = Self::bulk_to_string(parts.pop_front().unwrap())?;
+                let ids = parts
+                    .into_iter()
+                    .map(|id| parse_stream_id(&Self::bulk_to_string(id)?, 0))
+                    .collect::<Result<Vec<_>, RedisCommandError>>()?;
+                Ok(Command::XDel { key, ids })
+            }
+            "XREAD" => {
+                let mut count = None;
+                loop {
+                    let Some(option) = parts.pop_front() else {
+                        return Err(RedisCommandError::InvalidCommand(
+                            "XREAD expects the STREAMS option".to_string(),
+                        ));
+                    };
+                    match Self::bulk_to_string(option)?.to_uppercase().as_str() {
+                        "COUNT" if !parts.is_empty() => {
+                            count = Some(Self::bulk_to_u64(parts.pop_front().unwrap())?)
+                        }
+                        "BLOCK" => {
+                            return Err(RedisCommandError::NotImplemented(
+                                "XREAD BLOCK".to_string(),
+                            ))
+                        }
+                        "STREAMS" => break,
+                        _ => {
+                            return Err(RedisCommandError::InvalidCommand(
+                                "syntax error".to_string(),
+                            ))
+                        }
+                    }
+                }
+                let streams = Self::parse_streams(name, parts, "$")?;
+                Ok(Command::XRead { streams, count })
+            }
+            _ => Ok(Command::Unknown(name.to_string())),
+        }
+    }
+
+    /// Parse the keys and IDs following the `STREAMS` option of `XREAD` and `XREADGROUP`.
+    /// The `special` ID (`$` or `>`) is parsed as `None`.
+    pub(super) fn parse_streams(
+        name: &str,
+        mut parts: VecDeque<Frame>,
+        special: &str,
+    ) -> Result<Vec<(String, Option<StreamId>)>, RedisCommandError> {
+        if parts.is_empty() || !parts.len().is_multiple_of(2) {
+            return Err(RedisCommandError::InvalidCommand(format!(
+                "Unbalanced '{}' list of streams: for each stream key an ID or '{}' must be specified",
+                name.to_lowercase(),
+                special
+            )));
+        }
+        let ids = parts.split_off(parts.len() / 2);
+        let mut streams = Vec::with_capacity(parts.len());
+        for (key, id) in parts.into_iter().zip(ids) {
+            let key = Self::bulk_to_string(key)?;
+            let id = Self::bulk_to_string(id)?;
+            let id = if id == special {
+                None
+            } else {
+                Some(parse_stream_id(&id, 0)?)
+            };
+            streams.push((key, id));
+        }
+        Ok(streams)
+    }
+
+    /// Parse the `XADD` ID, i.e., `*`, `<ms>-*` or `<ms>-<seq>`.
+    fn parse_xadd_id(id: &str) -> Result<XAddId, RedisCommandError> {
+        if id == "*" {
+            return Ok(XAddId::Auto);
+        }
+        match id.strip_suffix("-*") {
+            Some(ms) => Ok(XAddId::Partial(
+                ms.parse().map_err(|_| invalid_stream_id())?,
+            )),
+            None => Ok(XAddId::Explicit(parse_stream_id(id, 0)?)),
+        }
+    }
+
+    /// Parse `MAXLEN|MINID [=|~] threshold`. The strategy is expected to be already consumed.
+    /// Approximate trimming (`~`) is accepted, but the stream is always trimmed exactly.
+    fn parse_stream_trim(
+        strategy: &str,
+        parts: &mut VecDeque<Frame>,
+    ) -> Result<StreamTrim, RedisCommandError> {
+        let mut threshold = parts
+            .pop_front()
+            .map(Self::bulk_to_string)
+            .transpose()?
+            .ok_or_else(|| RedisCommandError::InvalidCommand("syntax error".to_string()))?;
+        if threshold == "=" || threshold == "~" {
+            threshold = parts
+                .pop_front()
+                .map(Self::bulk_to_string)
+                .transpose()?
+                .ok_or_else(|| RedisCommandError::InvalidCommand("syntax error".to_string()))?;
+        }
+        match strategy.to_uppercase().as_str() {
+            "MAXLEN" => Ok(StreamTrim::MaxLen(threshold.parse().map_err(|_| {
+                RedisCommandError::ParseIntegerError(
+                    "value is not an integer or out of range".to_string(),
+                )
+            })?)),
+            "MINID" => Ok(StreamTrim::MinId(parse_stream_id(&threshold, 0)?)),
+            _ => Err(RedisCommandError::InvalidCommand(
+                "syntax error".to_string(),
+            )),
+        }
+    }
+}
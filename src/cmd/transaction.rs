@@ -0,0 +1,34 @@
+use std::collections::VecDeque;
+
+use super::Command;
+use crate::err::RedisCommandError;
+use crate::frame::Frame;
+
+impl Command {
+    /// Parse transaction commands, i.e., `MULTI`, `EXEC`, `DISCARD`, `WATCH` and `UNWATCH`.
+    ///
+    /// Expects the command name in uppercase and the remaining parts of the frame.
+    pub(super) fn parse_transaction(
+        name: &str,
+        parts: VecDeque<Frame>,
+    ) -> anyhow::Result<Command, RedisCommandError> {
+        if name == "WATCH" {
+            if parts.is_empty() {
+                return Err(Self::wrong_number_of_arguments(name, ">0", parts.len()));
+            }
+            return Ok(Command::Watch {
+                keys: Self::bulks_to_strings(parts)?,
+            });
+        }
+        if !parts.is_empty() {
+            return Err(Self::wrong_number_of_arguments(name, "0", parts.len()));
+        }
+        match name {
+            "MULTI" => Ok(Command::Multi),
+            "EXEC" => Ok(Command::Exec),
+            "DISCARD" => Ok(Command::Discard),
+            "UNWATCH" => Ok(Command::Unwatch),
+            _ => Ok(Command::Unknown(name.to_string())),
+        }
+    }
+}
@@ -0,0 +1,317 @@
+use anyhow::{bail, Context};
+use bytes::{Buf, Bytes, BytesMut};
+use std::io::Cursor;
+use tokio_util::codec::{Decoder, Encoder};
+
+use crate::constants::MAX_PREALLOCATED_ELEMENTS;
+use crate::err::RedisProtocolError;
+use crate::frame::{check_blob_end, get_count, get_length, Frame, Protocol, ProtocolLimits};
+
+/// Codec of RESP frames, e.g., to read and write frames of any `AsyncRead` and `AsyncWrite`
+/// with `tokio_util::codec::Framed`.
+///
+/// Frames are decoded incrementally, i.e., the decoded elements of aggregate frames are kept
+/// across reads, so the buffered data is scanned only once. Blobs, e.g., bulk strings, are split
+/// from the buffer without copying. Lines without a RESP type prefix are decoded as inline
+/// commands, empty lines are skipped. Frames over the `ProtocolLimits` are rejected.
+/// Frames are encoded in the protocol of the connection, RESP2 unless switched by `HELLO`.
+///
+/// ## Example
+/// ```no_run
+/// use futures_util::SinkExt;
+/// use tokio::io::{AsyncRead, AsyncWrite};
+/// use tokio_stream::StreamExt;
+/// use tokio_util::codec::Framed;
+///
+/// # async fn example(stream: impl AsyncRead + AsyncWrite + Unpin) -> anyhow::Result<()> {
+/// let mut framed = Framed::new(stream, redis_clone::RespCodec::default());
+/// framed.send(&redis_clone::array!(redis_clone::bulk!("PING"))).await?;
+/// let pong = framed.next().await.transpose()?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Default)]
+pub struct RespCodec {
+    protocol: Protocol,
+    limits: ProtocolLimits,
+    /// The next frame is a payload of a full resynchronization, see `Connection::read_payload`.
+    payload: bool,
+    /// Aggregate frames being decoded, the innermost last.
+    aggregates: Vec<Aggregate>,
+    /// Type and length of a blob whose header was decoded, waiting for its data.
+    blob: Option<(u8, usize)>,
+    /// Length of the start of an inline command searched for the end of the line.
+    inline_scanned: usize,
+    /// Size of the decoded data of the frame being decoded, i.e., of the headers
+    /// and of the elements kept in `aggregates`, counted against the query buffer limit.
+    buffered: usize,
+}
+
+/// Aggregate frame, e.g., an array, with the elements decoded so far.
+#[derive(Debug)]
+struct Aggregate {
+    prefix: u8,
+    /// Number of frames of the aggregate, i.e., two per pair of a map
+    /// and the described frame of an attribute.
+    len: usize,
+    frames: Vec<Frame>,
+}
+
+impl Aggregate {
+    fn new(prefix: u8, count: usize) -> Self {
+        let len = match prefix {
+            b'%' => count * 2,
+            b'|' => count * 2 + 1,
+            _ => count,
+        };
+        Self {
+            prefix,
+            len,
+            // The length is not trusted until the elements are received
+            frames: Vec::with_capacity(len.min(MAX_PREALLOCATED_ELEMENTS)),
+        }
+    }
+
+    fn is_complete(&self) -> bool {
+        self.frames.len() == self.len
+    }
+
+    fn into_frame(self) -> Frame {
+        let mut frames = self.frames;
+        match self.prefix {
+            b'~' => Frame::Set(frames),
+            b'>' => Frame::Push(frames),
+            b'%' => Frame::Map(into_pairs(frames)),
+            b'|' => {
+                let frame = frames.pop().unwrap();
+                Frame::Attribute(into_pairs(frames), Box::new(frame))
+            }
+            _ => Frame::Array(frames),
+        }
+    }
+}
+
+fn into_pairs(frames: Vec<Frame>) -> Vec<(Frame, Frame)> {
+    let mut frames = frames.into_iter();
+    let mut pairs = Vec::with_capacity(frames.len() / 2);
+    while let (Some(key), Some(value)) = (frames.next(), frames.next()) {
+        pairs.push((key, value));
+    }
+    pairs
+}
+
+/// Frame of a blob, i.e., `$`, `!` or `=` followed by the data.
+fn blob_frame(prefix: u8, mut data: Bytes) -> anyhow::Result<Frame, RedisProtocolError> {
+    match prefix {
+        b'!' => Ok(Frame::BlobError(data)),
+        b'=' => {
+            // The data is prefixed by a format of three bytes, e.g., `txt:`
+            if data.len() < 4 || data[3] != b':' {
+                return Err(RedisProtocolError::ConversionError(
+                    String::from_utf8_lossy(&data).to_string(),
+                ));
+            }
+            let format = String::from_utf8_lossy(&data[..3]).to_string();
+            Ok(Frame::Verbatim(format, data.split_off(4)))
+        }
+        _ => Ok(Frame::Bulk(data)),
+    }
+}
+
+impl RespCodec {
+    pub fn new(protocol: Protocol) -> Self {
+        Self {
+            protocol,
+            ..Default::default()
+        }
+    }
+
+    /// Protocol of the encoded frames.
+    pub fn protocol(&self) -> Protocol {
+        self.protocol
+    }
+
+    pub fn set_protocol(&mut self, protocol: Protocol) {
+        self.protocol = protocol;
+    }
+
+    /// Set the limits of the decoded frames.
+    pub fn with_limits(mut self, limits: ProtocolLimits) -> Self {
+        self.limits = limits;
+        self
+    }
+
+    /// Decode the next frame as a payload, i.e., a bulk string without the trailing CRLF.
+    pub(crate) fn expect_payload(&mut self) {
+        self.payload = true;
+    }
+
+    fn decode_payload(&mut self, src: &mut BytesMut) -> anyhow::Result<Option<Frame>> {
+        // The primary may send newlines to keep the connection alive
+        while src.first() == Some(&b'\n') {
+            src.advance(1);
+        }
+        let Some(end) = src.windows(2).position(|w| w == b"\r\n") else {
+            return Ok(None);
+        };
+        if src[0] != b'$' {
+            bail!("Expected a payload, got: {:?}", &src[..end]);
+        }
+        let len = std::str::from_utf8(&src[1..end])?
+            .parse::<usize>()
+            .context("Invalid payload length.")?;
+        if src.len() < end + 2 + len {
+            src.reserve(end + 2 + len - src.len());
+            return Ok(None);
+        }
+        src.advance(end + 2);
+        self.payload = false;
+        Ok(Some(Frame::Bulk(src.split_to(len).freeze())))
+    }
+
+    /// Decode the next element of the frame, i.e., a frame without elements or a header
+    /// of an aggregate or of a blob. Headers are kept in the state of the codec,
+    /// `None` is returned for them and for empty inline commands.
+    ///
+    /// The decoded data is discarded from the buffer.
+    fn decode_element(
+        &mut self,
+        src: &mut BytesMut,
+    ) -> anyhow::Result<Option<Frame>, RedisProtocolError> {
+        if let Some((prefix, len)) = self.blob {
+            if src.len() < len + 2 {
+                // Read the rest of the data at once
+                src.reserve(len + 2 - src.len());
+                return Err(RedisProtocolError::NotEnoughData);
+            }
+            check_blob_end(src, len, len)?;
+            self.blob = None;
+            let data = src.split_to(len).freeze();
+            src.advance(2);
+            return blob_frame(prefix, data).map(Some);
+        }
+
+        let Some(&first) = src.first() else {
+            return Err(RedisProtocolError::NotEnoughData);
+        };
+        let mut cursor = Cursor::new(&src[..]);
+        let element = match first {
+            // Inline commands are not nested in other frames
+            byte if Frame::is_inline(byte) && self.aggregates.is_empty() => {
+                // The search for the end of the line resumes where the last read ended
+                let scanned = self.inline_scanned;
+                let Some(end) = src[scanned..].iter().position(|&b| b == b'\n') else {
+                    self.inline_scanned = src.len();
+                    self.limits.check_inline_len(src.len())?;
+                    return Err(RedisProtocolError::NotEnoughData);
+                };
+                self.inline_scanned = 0;
+                self.limits.check_inline_len(scanned + end)?;
+                // Empty lines are skipped
+                Some(Frame::parse_inline(&mut cursor)?)
+                    .filter(|frame| *frame != Frame::Array(vec![]))
+            }
+            prefix @ (b'*' | b'~' | b'>' | b'%' | b'|') => {
+                cursor.advance(1);
+                let count = get_count(&mut cursor)?;
+                self.limits
+                    .check_aggregate(count, self.aggregates.len() + 1)?;
+                match count {
+                    0 if prefix != b'|' => Some(Aggregate::new(prefix, 0).into_frame()),
+                    count => {
+                        self.aggregates.push(Aggregate::new(prefix, count));
+                        None
+                    }
+                }
+            }
+            prefix @ (b'$' | b'!' | b'=') => {
+                cursor.advance(1);
+                match get_length(&mut cursor)? {
+                    // Null bulk string
+                    -1 if prefix == b'$' => Some(Frame::Null),
+                    len => {
+                        // Checked before the buffer is reserved for the data
+                        self.limits.check_bulk_len(len)?;
+                        self.blob = Some((prefix, len as usize));
+                        None
+                    }
+                }
+            }
+            _ => Some(Frame::parse(&mut cursor)?),
+        };
+        src.advance(cursor.position() as usize);
+        Ok(element)
+    }
+}
+
+impl Decoder for RespCodec {
+    type Item = Frame;
+    type Error = anyhow::Error;
+
+    fn decode(&mut self, src: &mut BytesMut) -> anyhow::Result<Option<Frame>> {
+        if self.payload {
+            return self.decode_payload(src);
+        }
+        loop {
+            let len = src.len();
+            let element = self.decode_element(src);
+            self.buffered += len - src.len();
+            let mut frame = match element {
+                Ok(Some(frame)) => frame,
+                // Empty inline commands are not a part of a frame
+                Ok(None) if self.aggregates.is_empty() && self.blob.is_none() => {
+                    self.buffered = 0;
+                    continue;
+                }
+                // The header of an aggregate or of a blob was decoded
+                Ok(None) => continue,
+                Err(RedisProtocolError::NotEnoughData) => {
+                    // The decoded elements are counted, so that a frame cannot be sent
+                    // in parts to exceed the limit
+                    let size = self.buffered + src.len();
+                    if size > self.limits.max_query_buffer {
+                        bail!(RedisProtocolError::QueryBufferExceeded(size));
+                    }
+                    return Ok(None);
+                }
+                Err(e) => return Err(e.into()),
+            };
+            // Complete the aggregates that end with the decoded frame
+            loop {
+                let Some(aggregate) = self.aggregates.last_mut() else {
+                    self.buffered = 0;
+                    return Ok(Some(frame));
+                };
+                aggregate.frames.push(frame);
+                self.buffered += size_of::<Frame>();
+                if !aggregate.is_complete() {
+                    break;
+                }
+                frame = self.aggregates.pop().unwrap().into_frame();
+            }
+        }
+    }
+
+    fn decode_eof(&mut self, src: &mut BytesMut) -> anyhow::Result<Option<Frame>> {
+        match self.decode(src)? {
+            Some(frame) => Ok(Some(frame)),
+            None if src.is_empty() => Ok(None),
+            // The stream was closed while sending a frame
+            None if self.payload => bail!("Connection closed while reading a payload."),
+            None => bail!("Connection reset by client."),
+        }
+    }
+}
+
+impl Encoder<&Frame> for RespCodec {
+    type Error = anyhow::Error;
+
+    /// RESP3 frames are encoded as the closest RESP2 frames for RESP2 connections.
+    fn encode(&mut self, frame: &Frame, dst: &mut BytesMut) -> anyhow::Result<()> {
+        match self.protocol {
+            Protocol::Resp2 => frame.encode(dst),
+            Protocol::Resp3 => frame.encode_resp3(dst),
+        }
+        Ok(())
+    }
+}
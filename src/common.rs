@@ -1,6 +1,7 @@
 use core::str;
 
 use bytes::Bytes;
+use rand::Rng;
 
 use crate::err::RedisCommandError;
 
@@ -15,3 +16,11 @@ pub fn bytes_to_i64(bytes: &Bytes) -> anyhow::Result<i64, RedisCommandError> {
     s.parse::<i64>()
         .map_err(|e| RedisCommandError::ParseDecimalError(e.to_string()))
 }
+
+/// Random 40 characters long hexadecimal ID, e.g., of a replication history or a cluster node.
+pub(crate) fn random_id() -> String {
+    let mut rng = rand::thread_rng();
+    (0..40)
+        .map(|_| char::from_digit(rng.gen_range(0..16), 16).unwrap())
+        .collect()
+}
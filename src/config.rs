@@ -0,0 +1,247 @@
+use std::path::PathBuf;
+
+use anyhow::{anyhow, bail};
+
+use crate::constants::PROTO_MAX_BULK_LEN;
+use crate::frame::ProtocolLimits;
+
+/// Take a snapshot if at least `changes` writes happened within `seconds`, e.g., `save 900 1`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SaveRule {
+    pub seconds: u64,
+    pub changes: u64,
+}
+
+impl SaveRule {
+    /// Parse save rules in the `redis.conf` syntax, e.g., `"900 1 300 10"`.
+    /// An empty string disables automatic snapshots.
+    pub fn parse_rules(rules: &str) -> anyhow::Result<Vec<SaveRule>> {
+        let numbers = rules
+            .split_whitespace()
+            .map(|number| {
+                number
+                    .parse::<u64>()
+                    .map_err(|_| anyhow!("invalid save rule: {}", rules))
+            })
+            .collect::<anyhow::Result<Vec<u64>>>()?;
+        if numbers.len() % 2 != 0 {
+            bail!("invalid save rule: {}", rules);
+        }
+        Ok(numbers
+            .chunks(2)
+            .map(|pair| SaveRule {
+                seconds: pair[0],
+                changes: pair[1],
+            })
+            .collect())
+    }
+}
+
+/// When the append-only file is synced to disk, i.e., how many writes may be lost on a crash.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum AppendFsync {
+    /// After every write command.
+    Always,
+    /// Once per second in the background.
+    #[default]
+    EverySec,
+    /// Left to the operating system.
+    No,
+}
+
+impl AppendFsync {
+    /// Parse the `redis.conf` value, i.e., `always`, `everysec` or `no`.
+    pub fn parse(value: &str) -> anyhow::Result<Self> {
+        match value.to_lowercase().as_str() {
+            "always" => Ok(AppendFsync::Always),
+            "everysec" => Ok(AppendFsync::EverySec),
+            "no" => Ok(AppendFsync::No),
+            _ => bail!("invalid appendfsync policy: {}", value),
+        }
+    }
+}
+
+/// Server configuration. Option names follow `redis.conf`.
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// Directory of the persistence files.
+    pub dir: PathBuf,
+    /// Name of the snapshot file in `dir`.
+    pub dbfilename: String,
+    /// Rules of automatic snapshots (`BGSAVE`), disabled if empty.
+    pub save: Vec<SaveRule>,
+    /// Log write commands to the append-only file. The file is loaded
+    /// on startup instead of the snapshot.
+    pub appendonly: bool,
+    /// Name of the append-only file in `dir`.
+    pub appendfilename: String,
+    pub appendfsync: AppendFsync,
+    /// Rewrite the append-only file when it grew by this percentage
+    /// since the last rewrite, disabled if zero.
+    pub auto_aof_rewrite_percentage: u64,
+    /// Minimum size of the append-only file in bytes to rewrite it automatically.
+    pub auto_aof_rewrite_min_size: u64,
+    /// Host and port of the primary, the server starts as its read-only replica.
+    pub replicaof: Option<(String, u16)>,
+    /// Size of the replication backlog in bytes, i.e., how much of the replication
+    /// stream is kept for the partial resynchronization of replicas.
+    pub repl_backlog_size: u64,
+    /// Serve only the hash slots assigned to this node by the nodes file
+    /// and redirect other keys to their nodes.
+    pub cluster_enabled: bool,
+    /// Name of the nodes file in `dir`, created with this node serving
+    /// all slots if it does not exist.
+    pub cluster_config_file: String,
+    /// Maximum length of a bulk string sent by a client in bytes.
+    pub proto_max_bulk_len: u64,
+    /// Maximum size of the buffer of a client in bytes, i.e., of a command
+    /// that was not received as a whole yet.
+    pub client_query_buffer_limit: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            dir: PathBuf::from("."),
+            dbfilename: "dump.snap".to_string(),
+            save: Vec::new(),
+            appendonly: false,
+            appendfilename: "appendonly.aof".to_string(),
+            appendfsync: AppendFsync::default(),
+            auto_aof_rewrite_percentage: 100,
+            auto_aof_rewrite_min_size: 64 * 1024 * 1024,
+            replicaof: None,
+            repl_backlog_size: 1024 * 1024,
+            cluster_enabled: false,
+            cluster_config_file: "nodes.conf".to_string(),
+            proto_max_bulk_len: PROTO_MAX_BULK_LEN as u64,
+            client_query_buffer_limit: 1024 * 1024 * 1024,
+        }
+    }
+}
+
+/// Parse a size in bytes with an optional unit, e.g., `64mb`.
+fn parse_memory(value: &str) -> anyhow::Result<u64> {
+    let lowercase = value.to_lowercase();
+    let (number, unit) = match lowercase.find(|c: char| !c.is_ascii_digit()) {
+        Some(index) => lowercase.split_at(index),
+        None => (lowercase.as_str(), ""),
+    };
+    let unit = match unit {
+        "" | "b" => 1,
+        "k" => 1000,
+        "kb" => 1024,
+        "m" => 1000 * 1000,
+        "mb" => 1024 * 1024,
+        "g" => 1000 * 1000 * 1000,
+        "gb" => 1024 * 1024 * 1024,
+        _ => bail!("invalid memory size: {}", value),
+    };
+    number
+        .parse::<u64>()
+        .ok()
+        .and_then(|number| number.checked_mul(unit))
+        .ok_or_else(|| anyhow!("invalid memory size: {}", value))
+}
+
+/// Parse a protocol limit in bytes, which must be at least 1mb as in Redis.
+fn parse_limit(name: &str, value: &str) -> anyhow::Result<u64> {
+    let limit = parse_memory(value)?;
+    if limit < 1024 * 1024 || limit > usize::MAX as u64 {
+        bail!("invalid {}: {}", name, value);
+    }
+    Ok(limit)
+}
+
+impl Config {
+    /// Parse command line options, e.g., `--dir /tmp --save "900 1"`.
+    /// Options that are not given keep their default value.
+    pub fn from_args(args: impl IntoIterator<Item = String>) -> anyhow::Result<Self> {
+        let mut config = Self::default();
+        let mut args = args.into_iter();
+        while let Some(option) = args.next() {
+            let Some(name) = option.strip_prefix("--") else {
+                bail!("expected an option, got: {}", option);
+            };
+            let value = args
+                .next()
+                .ok_or_else(|| anyhow!("missing value of option: {}", option))?;
+            match name {
+                "dir" => config.dir = PathBuf::from(value),
+                "dbfilename" => config.dbfilename = value,
+                "save" => config.save = SaveRule::parse_rules(&value)?,
+                "appendonly" => {
+                    config.appendonly = match value.to_lowercase().as_str() {
+                        "yes" => true,
+                        "no" => false,
+                        _ => bail!("invalid appendonly value: {}", value),
+                    }
+                }
+                "appendfilename" => config.appendfilename = value,
+                "appendfsync" => config.appendfsync = AppendFsync::parse(&value)?,
+                "auto-aof-rewrite-percentage" => {
+                    config.auto_aof_rewrite_percentage = value
+                        .parse()
+                        .map_err(|_| anyhow!("invalid auto-aof-rewrite-percentage: {}", value))?
+                }
+                "auto-aof-rewrite-min-size" => {
+                    config.auto_aof_rewrite_min_size = parse_memory(&value)?
+                }
+                "replicaof" => {
+                    config.replicaof = match value.split_whitespace().collect::<Vec<_>>()[..] {
+                        [host, port] => Some((
+                            host.to_string(),
+                            port.parse()
+                                .map_err(|_| anyhow!("invalid replicaof port: {}", port))?,
+                        )),
+                        _ => bail!("invalid replicaof value, expected \"host port\": {}", value),
+                    }
+                }
+                "cluster-enabled" => {
+                    config.cluster_enabled = match value.to_lowercase().as_str() {
+                        "yes" => true,
+                        "no" => false,
+                        _ => bail!("invalid cluster-enabled value: {}", value),
+                    }
+                }
+                "cluster-config-file" => config.cluster_config_file = value,
+                "repl-backlog-size" => {
+                    config.repl_backlog_size = parse_memory(&value)?;
+                    if config.repl_backlog_size == 0 {
+                        bail!("invalid repl-backlog-size: {}", value);
+                    }
+                }
+                "proto-max-bulk-len" => config.proto_max_bulk_len = parse_limit(name, &value)?,
+                "client-query-buffer-limit" => {
+                    config.client_query_buffer_limit = parse_limit(name, &value)?
+                }
+                _ => bail!("unknown option: {}", option),
+            }
+        }
+        Ok(config)
+    }
+
+    /// Path of the snapshot file.
+    pub fn snapshot_path(&self) -> PathBuf {
+        self.dir.join(&self.dbfilename)
+    }
+
+    /// Path of the append-only file.
+    pub fn aof_path(&self) -> PathBuf {
+        self.dir.join(&self.appendfilename)
+    }
+
+    /// Limits of the frames received from clients.
+    pub fn protocol_limits(&self) -> ProtocolLimits {
+        ProtocolLimits {
+            max_bulk_len: self.proto_max_bulk_len as usize,
+            max_query_buffer: self.client_query_buffer_limit as usize,
+            ..ProtocolLimits::default()
+        }
+    }
+
+    /// Path of the nodes file of the cluster.
+    pub fn cluster_config_path(&self) -> PathBuf {
+        self.dir.join(&self.cluster_config_file)
+    }
+}
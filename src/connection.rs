@@ -1,154 +1,112 @@
 use anyhow::{bail, Context};
-use bytes::{Buf, BytesMut};
-use std::io::Cursor;
-use tokio::io::{AsyncReadExt, AsyncWriteExt, BufWriter};
+use bytes::{BufMut, Bytes};
+use futures_util::SinkExt;
+use tokio::io::{AsyncRead, AsyncWrite};
 use tokio::net::TcpStream;
+use tokio_stream::StreamExt;
+use tokio_util::codec::{Decoder, Framed};
 
-use crate::err::RedisProtocolError;
-use crate::frame::Frame;
+use crate::codec::RespCodec;
+use crate::frame::{Frame, Protocol, ProtocolLimits};
 
-/// Client connection to the Redis server. Handles reading and writing frames.
+/// Client connection to the Redis server. Handles reading and writing frames
+/// of a stream, e.g., a TCP socket, with [`RespCodec`].
 ///
 /// Inspired by https://tokio.rs/tokio/tutorial/framing
-pub struct Connection {
-    stream: BufWriter<TcpStream>,
-    buffer: BytesMut,
+pub struct Connection<S = TcpStream> {
+    framed: Framed<S, RespCodec>,
     online: bool,
 }
 
-impl Connection {
-    pub fn new(stream: TcpStream) -> Connection {
+impl<S: AsyncRead + AsyncWrite + Unpin> Connection<S> {
+    pub fn new(stream: S) -> Connection<S> {
+        Self::with_limits(stream, ProtocolLimits::default())
+    }
+
+    /// Create a connection that rejects received frames over the `limits`.
+    pub fn with_limits(stream: S, limits: ProtocolLimits) -> Connection<S> {
         Connection {
-            stream: BufWriter::new(stream),
-            buffer: BytesMut::with_capacity(1024),
+            framed: Framed::new(stream, RespCodec::default().with_limits(limits)),
             online: true,
         }
     }
 
+    /// Protocol of the written frames, RESP2 unless switched by `HELLO`.
+    pub fn protocol(&self) -> Protocol {
+        self.framed.codec().protocol()
+    }
+
+    pub fn set_protocol(&mut self, protocol: Protocol) {
+        self.framed.codec_mut().set_protocol(protocol);
+    }
+
     /// Read a frame from the connection.
     ///
     /// Returns `None` if EOF is reached
     pub async fn read_frame(&mut self) -> anyhow::Result<Option<Frame>> {
-        loop {
-            // Attempt to parse a frame from the buffered data. If
-            // enough data has been buffered, the frame is
-            // returned.
-            if let Some(frame) = self.parse_frame()? {
-                return Ok(Some(frame));
-            }
-
-            // There is not enough buffered data to read a frame.
-            // Attempt to read more data from the socket.
-            //
-            // On success, the number of bytes is returned. `0`
-            // indicates "end of stream".
-            if 0 == self.stream.read_buf(&mut self.buffer).await? {
-                // The remote closed the connection. For this to be
-                // a clean shutdown, there should be no data in the
-                // read buffer. If there is, this means that the
-                // client closed the socket while sending a frame.
-                if self.buffer.is_empty() {
-                    return Ok(None);
-                } else {
-                    bail!("Connection reset by client.");
-                }
-            }
-        }
+        self.framed.next().await.transpose()
     }
 
     /// Write a frame to the connection.
     pub async fn write_frame(&mut self, frame: &Frame) -> anyhow::Result<()> {
-        self.write_value(frame).await?;
+        self.feed_frame(frame).await?;
 
         // Ensure that the written data is flushed to the socket.
-        self.stream
-            .flush()
-            .await
-            .context("Failed to flush the stream.")
+        self.flush().await
     }
 
-    /// Write a single frame value to the connection.
-    async fn write_value(&mut self, frame: &Frame) -> anyhow::Result<()> {
-        // We use `Box::pin` to allow recursive calls.
-        Box::pin(async move {
-            match frame {
-                Frame::Simple(value) => {
-                    self.stream.write_u8(b'+').await?;
-                    self.stream.write_all(value.as_bytes()).await?;
-                    self.stream.write_all(b"\r\n").await?;
-                }
-                Frame::Error(value) => {
-                    self.stream.write_u8(b'-').await?;
-                    self.stream.write_all(value.as_bytes()).await?;
-                    self.stream.write_all(b"\r\n").await?;
-                }
-                Frame::Integer(value) => {
-                    self.stream.write_u8(b':').await?;
-                    self.stream.write_all(value.to_string().as_bytes()).await?;
-                    self.stream.write_all(b"\r\n").await?;
-                }
-                Frame::Bulk(value) => {
-                    self.stream.write_u8(b'$').await?;
-                    self.stream
-                        .write_all(value.len().to_string().as_bytes())
-                        .await?;
-                    self.stream.write_all(b"\r\n").await?;
-                    self.stream.write_all(value).await?;
-                    self.stream.write_all(b"\r\n").await?;
-                }
-                Frame::Null => {
-                    self.stream.write_all(b"$-1\r\n").await?;
-                }
-                Frame::Array(vec_) => {
-                    // Write the array length
-                    self.stream.write_u8(b'*').await?;
-                    self.stream
-                        .write_all(vec_.len().to_string().as_bytes())
-                        .await?;
-                    self.stream.write_all(b"\r\n").await?;
-
-                    // Write each element in the array
-                    for value in vec_ {
-                        // Recursively write the value
-                        self.write_value(value).await?;
-                    }
-                }
-            }
-            Ok::<_, anyhow::Error>(())
-        })
-        .await
+    /// Write a frame to the buffer of the connection without flushing it, e.g., to send
+    /// the replies of pipelined commands at once. The buffer is written to the socket
+    /// when it is full or by [`Connection::flush`].
+    pub async fn feed_frame(&mut self, frame: &Frame) -> anyhow::Result<()> {
+        self.framed.feed(frame).await
     }
 
-    /// Parse a frame from the buffered data.
-    pub fn parse_frame(&mut self) -> anyhow::Result<Option<Frame>> {
-        let mut buf = Cursor::new(&self.buffer[..]);
-
-        // Check if enough data has been buffered to parse a single frame.
-        // (Without allocations of data structures.)
-        match Frame::is_parsable(&mut buf) {
-            Ok(()) => {
-                // `Frame::is_parsable` advances the cursor to the end of the frame.
-                // We use this to discard the read buffer.
-                let frame_len = buf.position() as usize;
-
-                // Reset the cursor position.
-                buf.set_position(0);
+    /// Flush the buffered frames to the socket.
+    pub async fn flush(&mut self) -> anyhow::Result<()> {
+        SinkExt::<&Frame>::flush(&mut self.framed)
+            .await
+            .context("Failed to flush the stream.")
+    }
 
-                let frame = Frame::parse(&mut buf)?;
+    /// Write raw bytes to the connection, e.g., encoded frames of the replication stream.
+    pub async fn write_bytes(&mut self, data: &[u8]) -> anyhow::Result<()> {
+        self.framed.write_buffer_mut().put_slice(data);
+        self.flush().await
+    }
 
-                // Discard the parsed data from the read buffer.
-                self.buffer.advance(frame_len);
+    /// Write a payload of a full resynchronization, i.e., a snapshot sent by a primary
+    /// to its replica. The format is a bulk string without the trailing CRLF.
+    pub async fn write_payload(&mut self, data: &[u8]) -> anyhow::Result<()> {
+        self.framed
+            .write_buffer_mut()
+            .put_slice(format!("${}\r\n", data.len()).as_bytes());
+        self.write_bytes(data).await
+    }
 
-                Ok(Some(frame))
-            }
-            Err(RedisProtocolError::NotEnoughData) => Ok(None),
-            Err(e) => Err(e.into()),
+    /// Read a payload written by [`Connection::write_payload`].
+    pub async fn read_payload(&mut self) -> anyhow::Result<Bytes> {
+        self.framed.codec_mut().expect_payload();
+        match self.read_frame().await? {
+            Some(Frame::Bulk(payload)) => Ok(payload),
+            Some(frame) => bail!("Expected a payload, got: {:?}", frame),
+            None => bail!("Connection closed while reading a payload."),
         }
     }
 
+    /// Parse a frame from the buffered data without reading from the socket.
+    ///
+    /// Lines without a RESP type prefix are parsed as inline commands, empty lines are skipped.
+    pub fn parse_frame(&mut self) -> anyhow::Result<Option<Frame>> {
+        // The codec is taken out of the connection to borrow the buffer at the same time
+        let mut codec = std::mem::take(self.framed.codec_mut());
+        let frame = codec.decode(self.framed.read_buffer_mut());
+        *self.framed.codec_mut() = codec;
+        frame
+    }
+
     pub async fn shutdown(&mut self) -> anyhow::Result<()> {
-        self.stream
-            .shutdown()
+        SinkExt::<&Frame>::close(&mut self.framed)
             .await
             .context("Failed to shutdown the stream.")?;
         self.online = false;
@@ -5,3 +5,11 @@ pub const CLIENT_CONNECTION_TIMEOUT: Duration = Duration::from_secs(2);
 pub const SERVER_SHUTDOWN_CONNECTION_TIMEOUT: Duration = Duration::from_millis(500);
 pub const DB_EXPIRATION_CHECK_INTERVAL: Duration = Duration::from_millis(100);
 pub const MAX_CLIENTS: usize = 50;
+pub const REPL_PING_PERIOD: Duration = Duration::from_secs(10);
+pub const REPL_TIMEOUT: Duration = Duration::from_secs(60);
+pub const REPL_RETRY_INTERVAL: Duration = Duration::from_secs(1);
+pub const REPL_ACK_PERIOD: Duration = Duration::from_secs(1);
+pub const CLUSTER_MAX_REDIRECTIONS: usize = 16;
+pub const CLUSTER_RETRY_DELAY: Duration = Duration::from_millis(100);
+pub const MAX_PREALLOCATED_ELEMENTS: usize = 4096;
+pub const PROTO_MAX_BULK_LEN: usize = 512 * 1024 * 1024;
@@ -1,4 +1,5 @@
-use std::collections::{BinaryHeap, HashMap};
+use std::collections::{BinaryHeap, HashMap, HashSet, VecDeque};
+use std::ops::{Deref, DerefMut};
 use std::str;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
@@ -6,9 +7,36 @@ use std::time::{Duration, Instant};
 use anyhow::{anyhow, bail};
 use bytes::Bytes;
 use tokio::sync::mpsc::{channel, Receiver, Sender};
-use tokio::sync::Mutex; // async mutex, because of the `expiration_task`
+use tokio::sync::{Mutex, MutexGuard, OwnedMutexGuard}; // async mutex, because of the `expiration_task`
 
+use crate::cluster::key_slot;
 use crate::constants::DB_EXPIRATION_CHECK_INTERVAL;
+use crate::err::RedisDatabaseError;
+
+mod blocking;
+mod consumer_group;
+mod hash;
+mod list;
+mod rdb;
+mod rewrite;
+mod set;
+mod snapshot;
+mod sorted_set;
+mod stream;
+mod watch;
+
+pub use blocking::{BlockingPop, ListWaiter};
+pub use consumer_group::{
+    AutoClaim, ClaimOptions, ConsumerInfo, GroupInfo, PendingEntry, PendingRange, PendingSummary,
+    StreamInfo,
+};
+pub use list::{InsertPosition, ListEnd};
+pub use set::SetOperation;
+pub use snapshot::Snapshot;
+pub use sorted_set::{LexBound, ScoreBound, ZAddComparison, ZAddCondition, ZAddOptions, ZRangeBy};
+pub(crate) use stream::now_ms;
+pub use stream::{StreamEntry, StreamId, StreamTrim, XAddId};
+pub use watch::Watch;
 
 #[derive(Clone, Debug)]
 struct ExpirationEntry {
@@ -38,16 +66,132 @@ impl PartialOrd for ExpirationEntry {
     }
 }
 
+/// Value stored under a key. Each variant represents one Redis data type.
+#[derive(Debug, Clone)]
+enum Value {
+    String(Bytes),
+    List(VecDeque<Bytes>),
+    Hash(HashMap<Bytes, Bytes>),
+    Set(HashSet<Bytes>),
+    SortedSet(sorted_set::SortedSet),
+    Stream(stream::Stream),
+}
+
+impl Value {
+    fn as_string(&self) -> Result<&Bytes, RedisDatabaseError> {
+        match self {
+            Value::String(value) => Ok(value),
+            _ => Err(RedisDatabaseError::WrongType),
+        }
+    }
+}
+
 #[derive(Debug, Clone)]
 struct DBItem {
-    value: Bytes,
+    value: Value,
     expiration: Option<Instant>,
 }
 
 impl DBItem {
-    fn new(value: Bytes, expiration: Option<Instant>) -> Self {
+    fn new(value: Value, expiration: Option<Instant>) -> Self {
         Self { value, expiration }
     }
+
+    fn is_expired(&self) -> bool {
+        self.expiration.is_some_and(|exp| Instant::now() >= exp)
+    }
+}
+
+/// Keys and values of the database.
+struct Store {
+    items: HashMap<String, DBItem>,
+    /// Versions of keys watched by `WATCH`, so that keys removed on expiry are marked as modified.
+    watched: Arc<std::sync::Mutex<watch::WatchedKeys>>,
+}
+
+impl Deref for Store {
+    type Target = HashMap<String, DBItem>;
+
+    fn deref(&self) -> &HashMap<String, DBItem> {
+        &self.items
+    }
+}
+
+impl DerefMut for Store {
+    fn deref_mut(&mut self) -> &mut HashMap<String, DBItem> {
+        &mut self.items
+    }
+}
+
+/// Guard of the data store. A locked database (see `DB::lock`) reuses the lock it holds.
+enum DataGuard<'a> {
+    Locked(MutexGuard<'a, Store>),
+    Held(MutexGuard<'a, OwnedMutexGuard<Store>>),
+}
+
+impl Deref for DataGuard<'_> {
+    type Target = Store;
+
+    fn deref(&self) -> &Store {
+        match self {
+            DataGuard::Locked(guard) => guard,
+            DataGuard::Held(guard) => guard,
+        }
+    }
+}
+
+impl DerefMut for DataGuard<'_> {
+    fn deref_mut(&mut self) -> &mut Store {
+        match self {
+            DataGuard::Locked(guard) => guard,
+            DataGuard::Held(guard) => guard,
+        }
+    }
+}
+
+/// Get a mutable reference to a key that has not expired yet.
+/// Expired keys are removed lazily.
+fn get_live_mut<'a>(data_store: &'a mut Store, key: &str) -> Option<&'a mut DBItem> {
+    if data_store.get(key).is_some_and(DBItem::is_expired) {
+        data_store.remove(key);
+        // The expiry is a modification, e.g., for transactions watching the key
+        data_store.watched.lock().unwrap().touch(key);
+    }
+    data_store.get_mut(key)
+}
+
+/// Get a mutable reference to a key that has not expired yet.
+/// If the key does not exist, it is created with the `default` value.
+fn get_or_insert_mut<'a>(
+    data_store: &'a mut Store,
+    key: &str,
+    default: impl FnOnce() -> Value,
+) -> &'a mut DBItem {
+    if get_live_mut(data_store, key).is_none() {
+        data_store.insert(key.to_string(), DBItem::new(default(), None));
+    }
+    data_store.get_mut(key).unwrap()
+}
+
+/// Convert Redis-style `start` and `stop` indices (negative indices count from the end)
+/// to an inclusive range of valid indices. Returns `None` if the range is empty.
+fn normalize_range(start: i64, stop: i64, len: usize) -> Option<(usize, usize)> {
+    let len = len as i64;
+    let start = if start < 0 {
+        (len + start).max(0)
+    } else {
+        start
+    };
+    let stop = if stop < 0 {
+        len + stop
+    } else {
+        stop.min(len - 1)
+    };
+    if start > stop || start >= len {
+        None
+    } else {
+        Some((start as usize, stop as usize))
+    }
 }
 
 /// Redis cache database shared between tasks and threads.
@@ -59,26 +203,40 @@ impl DBItem {
 /// let result = runtime.block_on(async {
 ///     let db = redis_clone::DB::new();
 ///     db.set("apples".to_string(), bytes::Bytes::from("10"), None).await;
-///     let apples = db.get("apples").await.unwrap();
+///     let apples = db.get("apples").await.unwrap().unwrap();
 ///     std::str::from_utf8(&apples).unwrap().to_string()
 /// });
 /// assert_eq!(&result, "10");
 /// ```
 #[derive(Clone)]
 pub struct DB {
-    data: Arc<Mutex<HashMap<String, DBItem>>>,
+    data: Arc<Mutex<Store>>,
+    /// Lock of `data` held by a locked database, see `DB::lock`.
+    held: Option<Arc<Mutex<OwnedMutexGuard<Store>>>>,
     expiration_queue: Arc<Mutex<BinaryHeap<ExpirationEntry>>>,
     expiration_sender: Sender<()>,
+    /// Wakeup registry of clients blocked on list keys, e.g., by `BLPOP`.
+    /// Only locked while the data lock is held or for a quick cleanup, never across an await.
+    blocked: Arc<std::sync::Mutex<blocking::BlockedClients>>,
+    /// Versions of keys watched by `WATCH`.
+    watched: Arc<std::sync::Mutex<watch::WatchedKeys>>,
 }
 
 impl DB {
     /// Initialize a new database.
     pub fn new() -> Self {
         let (sender, receiver) = channel(1);
+        let watched = Arc::new(std::sync::Mutex::new(watch::WatchedKeys::default()));
         let db = Self {
-            data: Arc::new(Mutex::new(HashMap::new())),
+            data: Arc::new(Mutex::new(Store {
+                items: HashMap::new(),
+                watched: Arc::clone(&watched),
+            })),
+            held: None,
             expiration_queue: Arc::new(Mutex::new(BinaryHeap::new())),
             expiration_sender: sender,
+            blocked: Arc::new(std::sync::Mutex::new(HashMap::new())),
+            watched,
         };
         db.start_expiration_task(receiver);
         db
@@ -89,6 +247,7 @@ impl DB {
     fn start_expiration_task(&self, mut receiver: Receiver<()>) {
         let data = Arc::clone(&self.data);
         let expiration_queue = Arc::clone(&self.expiration_queue);
+        let watched = Arc::clone(&self.watched);
 
         // Spawn a Tokio task for key expiration
         tokio::spawn(async move {
@@ -119,22 +278,48 @@ impl DB {
                 }
                 drop(queue);
 
-                // Remove expired keys from data store
+                // Remove expired keys from data store. The key might have been
+                // overwritten in the meantime, so we check the expiration again.
                 let mut data_store = data.lock().await;
                 for key in expired_keys {
-                    data_store.remove(&key);
+                    if data_store.get(&key).is_some_and(DBItem::is_expired) {
+                        data_store.remove(&key);
+                        watched.lock().unwrap().touch(&key);
+                    }
                 }
                 drop(data_store);
             }
         });
     }
 
+    async fn lock_data(&self) -> DataGuard<'_> {
+        match &self.held {
+            Some(held) => DataGuard::Held(held.lock().await),
+            None => DataGuard::Locked(self.data.lock().await),
+        }
+    }
+
+    /// Lock the data store for a sequence of operations, e.g., a transaction (`EXEC`).
+    ///
+    /// The returned handle performs operations under the lock it holds,
+    /// other handles wait until it is dropped. A locked handle returns a clone of itself.
+    pub async fn lock(&self) -> DB {
+        if self.held.is_some() {
+            return self.clone();
+        }
+        let guard = Arc::clone(&self.data).lock_owned().await;
+        DB {
+            held: Some(Arc::new(Mutex::new(guard))),
+            ..self.clone()
+        }
+    }
+
     pub async fn set(&self, key: String, value: Bytes, duration: Option<Duration>) {
         let expiration = duration.map(|d| Instant::now() + d);
 
         // Lock and insert into data store
-        let mut data_store = self.data.lock().await;
-        data_store.insert(key.clone(), DBItem { value, expiration });
+        let mut data_store = self.lock_data().await;
+        data_store.insert(key.clone(), DBItem::new(Value::String(value), expiration));
 
         // If there's an expiration, add to queue
         if let Some(expire) = expiration {
@@ -146,22 +331,20 @@ impl DB {
         }
     }
 
-    pub async fn get(&self, key: &str) -> Option<Bytes> {
-        let data_store = self.data.lock().await;
-        data_store.get(key).and_then(|item| {
+    /// Get a string value. Returns an error if the key holds a different data type.
+    pub async fn get(&self, key: &str) -> anyhow::Result<Option<Bytes>> {
+        let data_store = self.lock_data().await;
+        match data_store.get(key) {
             // Check if not expired
-            if item.expiration.map_or(true, |exp| Instant::now() < exp) {
-                Some(item.value.clone())
-            } else {
-                None
-            }
-        })
+            Some(item) if !item.is_expired() => Ok(Some(item.value.as_string()?.clone())),
+            _ => Ok(None),
+        }
     }
 
     pub async fn expire(&self, key: &str, duration: Duration) -> bool {
-        let mut data_store = self.data.lock().await;
+        let mut data_store = self.lock_data().await;
 
-        if let Some(item) = data_store.get_mut(key) {
+        if let Some(item) = get_live_mut(&mut data_store, key) {
             let new_expiration = Instant::now() + duration;
             item.expiration = Some(new_expiration);
 
@@ -178,14 +361,17 @@ impl DB {
         }
     }
 
-    /// Check if a key exists in the database.
+    /// Check if a key (of any data type) exists in the database.
     pub async fn exists(&self, key: &str) -> bool {
-        self.get(key).await.is_some()
+        let data_store = self.lock_data().await;
+        data_store.get(key).is_some_and(|item| !item.is_expired())
     }
 
     /// Remove a key from the database.
-    pub async fn remove(&self, key: &str) -> Option<Bytes> {
-        let mut db_guard = self.data.lock().await;
+    ///
+    /// Returns `true` if the key existed.
+    pub async fn remove(&self, key: &str) -> bool {
+        let mut db_guard = self.lock_data().await;
         let value = db_guard.remove(key);
 
         // Remove from expiration queue
@@ -198,18 +384,19 @@ impl DB {
         drop(db_guard);
         drop(queue_guard);
 
-        value.map(|item| item.value)
+        value.is_some_and(|item| !item.is_expired())
     }
 
     /// Get the number of key-value pairs in the database.
     pub async fn size(&self) -> usize {
-        let db_guard = self.data.lock().await;
+        let db_guard = self.lock_data().await;
         db_guard.len()
     }
 
     /// Clear the database.
     pub async fn flush(&self) {
-        let mut db_guard = self.data.lock().await;
+        let mut db_guard = self.lock_data().await;
+        self.watched.lock().unwrap().touch_existing(&db_guard);
         db_guard.clear(); // Remove all key-value pairs.
         db_guard.shrink_to_fit(); // Free up unused memory.
 
@@ -223,7 +410,7 @@ impl DB {
     /// Get all keys matching a pattern.
     pub async fn keys(&self, pattern: &str) -> anyhow::Result<Vec<String>> {
         let glob_pattern = glob::Pattern::new(pattern)?;
-        let db_guard = self.data.lock().await;
+        let db_guard = self.lock_data().await;
         Ok(db_guard
             .keys()
             .filter(|key| glob_pattern.matches(key))
@@ -231,12 +418,77 @@ impl DB {
             .collect())
     }
 
+    /// Get at most `count` keys of the hash slot (`CLUSTER GETKEYSINSLOT`).
+    pub async fn keys_in_slot(&self, slot: u16, count: usize) -> Vec<String> {
+        let db_guard = self.lock_data().await;
+        db_guard
+            .keys()
+            .filter(|key| key_slot(key.as_bytes()) == slot)
+            .take(count)
+            .cloned()
+            .collect()
+    }
+
+    /// Count the keys of the hash slot (`CLUSTER COUNTKEYSINSLOT`).
+    pub async fn count_keys_in_slot(&self, slot: u16) -> usize {
+        let db_guard = self.lock_data().await;
+        db_guard
+            .keys()
+            .filter(|key| key_slot(key.as_bytes()) == slot)
+            .count()
+    }
+
+    /// Serialize the value of a key (`DUMP`). The payload can be restored
+    /// by another server, e.g., when migrating keys between nodes of a cluster.
+    pub async fn dump(&self, key: &str) -> Option<Bytes> {
+        let data_store = self.lock_data().await;
+        data_store
+            .get(key)
+            .filter(|item| !item.is_expired())
+            .map(|item| item.value.dump())
+    }
+
+    /// Create a key from a payload of `DUMP` (`RESTORE`), expiring at the Unix time
+    /// in milliseconds `expires_at`. An existing key is only replaced with `replace`.
+    pub async fn restore(
+        &self,
+        key: String,
+        payload: &[u8],
+        expires_at: Option<u64>,
+        replace: bool,
+    ) -> Result<(), RedisDatabaseError> {
+        let value = Value::restore(payload)?;
+        let mut data_store = self.lock_data().await;
+        if !replace && get_live_mut(&mut data_store, &key).is_some() {
+            return Err(RedisDatabaseError::BusyKey);
+        }
+        let now_ms = now_ms();
+        let expiration = match expires_at {
+            // A key that already expired is not created
+            Some(expires_at) if expires_at <= now_ms => {
+                data_store.remove(&key);
+                return Ok(());
+            }
+            Some(expires_at) => Some(Instant::now() + Duration::from_millis(expires_at - now_ms)),
+            None => None,
+        };
+        data_store.insert(key.clone(), DBItem::new(value, expiration));
+        if let Some(expiration_time) = expiration {
+            let mut queue = self.expiration_queue.lock().await;
+            queue.push(ExpirationEntry {
+                key,
+                expiration_time,
+            });
+        }
+        Ok(())
+    }
+
     /// Increment a value of key-value pair in the database.
     pub async fn increment(&self, key: &str) -> anyhow::Result<Bytes> {
-        let mut db_guard = self.data.lock().await;
+        let mut db_guard = self.lock_data().await;
         let item = db_guard
             .entry(key.to_string())
-            .or_insert(DBItem::new(Bytes::from("0"), None));
+            .or_insert(DBItem::new(Value::String(Bytes::from("0")), None));
 
         // Check expiration
         if let Some(expiration) = item.expiration {
@@ -246,7 +498,7 @@ impl DB {
             }
         }
 
-        let new_value = match str::from_utf8(item.value.as_ref()) {
+        let new_value = match str::from_utf8(item.value.as_string()?) {
             Ok(s) => s
                 .parse::<i64>()
                 .map_err(|e| anyhow!(e))?
@@ -256,7 +508,7 @@ impl DB {
         };
 
         // Modify the value in-place
-        item.value = Bytes::from(new_value.to_string());
+        item.value = Value::String(Bytes::from(new_value.to_string()));
 
         drop(db_guard);
 
@@ -264,7 +516,7 @@ impl DB {
     }
 
     pub async fn ttl(&self, key: &str) -> Result<Option<Duration>, ()> {
-        let db_guard = self.data.lock().await;
+        let db_guard = self.lock_data().await;
         let maybe_item = db_guard.get(key);
         match maybe_item {
             Some(item) => match item.expiration {
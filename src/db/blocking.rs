@@ -0,0 +1,174 @@
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex as StdMutex};
+
+use anyhow::anyhow;
+use bytes::Bytes;
+use tokio::sync::oneshot;
+
+use super::list::{pop_element, push_element, ListEnd};
+use super::{get_live_mut, Store, Value, DB};
+use crate::err::RedisDatabaseError;
+
+/// Element delivered to a blocked client together with the key it was popped from.
+type Delivery = Result<(String, Bytes), RedisDatabaseError>;
+
+/// Sender shared by the registry entries of all keys a client is blocked on.
+/// Whoever takes it first, i.e., a push serving the client or the client giving up, wins.
+type SharedSender = Arc<StdMutex<Option<oneshot::Sender<Delivery>>>>;
+
+/// A client blocked by `BLPOP`, `BRPOP`, `BLMOVE` or `BRPOPLPUSH`.
+#[derive(Debug)]
+pub(super) struct BlockedClient {
+    end: ListEnd,
+    /// Destination of `BLMOVE`, the popped element is pushed there before it is delivered.
+    destination: Option<(String, ListEnd)>,
+    sender: SharedSender,
+}
+
+/// Blocked clients waiting for each list key, in the order they blocked.
+pub(super) type BlockedClients = HashMap<String, VecDeque<BlockedClient>>;
+
+/// Result of `DB::blocking_pop`.
+#[derive(Debug)]
+pub enum BlockingPop {
+    /// An element was available, returned with the key it was popped from.
+    Ready(String, Bytes),
+    /// All lists were empty, the client waits for a push.
+    Blocked(ListWaiter),
+}
+
+/// A client registered in the wakeup registry of the database.
+///
+/// Dropping the waiter removes the client from the registry.
+#[derive(Debug)]
+pub struct ListWaiter {
+    keys: Vec<String>,
+    sender: SharedSender,
+    receiver: oneshot::Receiver<Delivery>,
+    registry: Arc<StdMutex<BlockedClients>>,
+}
+
+impl ListWaiter {
+    /// Wait until a push serves this client.
+    pub async fn wait(&mut self) -> anyhow::Result<(String, Bytes)> {
+        match (&mut self.receiver).await {
+            Ok(delivery) => Ok(delivery?),
+            Err(_) => Err(anyhow!("blocked client was dropped from the registry")),
+        }
+    }
+
+    /// Stop waiting, e.g., when the timeout elapsed.
+    ///
+    /// Returns the element if a push served this client in the meantime.
+    pub async fn cancel(mut self) -> anyhow::Result<Option<(String, Bytes)>> {
+        if self.sender.lock().unwrap().take().is_some() {
+            return Ok(None);
+        }
+        // The client was served concurrently, the element is on its way
+        self.wait().await.map(Some)
+    }
+}
+
+impl Drop for ListWaiter {
+    fn drop(&mut self) {
+        self.sender.lock().unwrap().take();
+        let mut registry = self.registry.lock().unwrap();
+        for key in &self.keys {
+            if let Some(queue) = registry.get_mut(key) {
+                queue.retain(|client| !Arc::ptr_eq(&client.sender, &self.sender));
+                if queue.is_empty() {
+                    registry.remove(key);
+                }
+            }
+        }
+    }
+}
+
+/// Serve clients blocked on `key` in FIFO order while the list has elements.
+/// Must be called with the data lock held, right after elements were pushed to `key`.
+///
+/// Elements moved by `BLMOVE` may in turn serve clients blocked on the destination,
+/// the destination is marked as modified for `WATCH`.
+pub(super) fn serve_blocked(data_store: &mut Store, blocked: &mut BlockedClients, key: &str) {
+    let mut ready = vec![key.to_string()];
+    while let Some(key) = ready.pop() {
+        let Some(queue) = blocked.get_mut(&key) else {
+            continue;
+        };
+        while !queue.is_empty() {
+            let has_elements = get_live_mut(data_store, &key)
+                .is_some_and(|item| matches!(&item.value, Value::List(list) if !list.is_empty()));
+            if !has_elements {
+                break;
+            }
+            let client = queue.pop_front().unwrap();
+            // Clients that timed out or were served through another key are skipped
+            let Some(sender) = client.sender.lock().unwrap().take() else {
+                continue;
+            };
+            let delivery = pop_element(data_store, &key, client.end, &client.destination)
+                .map(|value| (key.clone(), value.expect("the list is not empty")));
+            if let (Ok(_), Some((destination, _))) = (&delivery, &client.destination) {
+                data_store.watched.lock().unwrap().touch(destination);
+                ready.push(destination.clone());
+            }
+            if let Err(Ok((key, value))) = sender.send(delivery) {
+                // The client went away after it was served, return the element to the list
+                if client.destination.is_none() {
+                    let _ = push_element(data_store, &key, value, client.end);
+                }
+            }
+        }
+        if queue.is_empty() {
+            blocked.remove(&key);
+        }
+    }
+}
+
+impl DB {
+    /// Pop an element from the first non-empty list of `keys`. With a `destination`,
+    /// the element is pushed to the destination list (`BLMOVE`).
+    ///
+    /// If all lists are empty, the client is registered in the wakeup registry and
+    /// the returned `ListWaiter` resolves once a push serves it. Clients are served in FIFO order.
+    pub async fn blocking_pop(
+        &self,
+        keys: &[String],
+        end: ListEnd,
+        destination: Option<(String, ListEnd)>,
+    ) -> anyhow::Result<BlockingPop> {
+        let mut data_store = self.lock_data().await;
+        for key in keys {
+            if let Some(value) = pop_element(&mut data_store, key, end, &destination)? {
+                self.touch(&[key]);
+                if let Some((destination, _)) = &destination {
+                    self.touch(&[destination]);
+                    let mut blocked = self.blocked.lock().unwrap();
+                    serve_blocked(&mut data_store, &mut blocked, destination);
+                }
+                return Ok(BlockingPop::Ready(key.clone(), value));
+            }
+        }
+
+        // Register while the data lock is held, so that no push can be missed
+        let (sender, receiver) = oneshot::channel();
+        let sender = Arc::new(StdMutex::new(Some(sender)));
+        let mut blocked = self.blocked.lock().unwrap();
+        for key in keys {
+            blocked
+                .entry(key.clone())
+                .or_default()
+                .push_back(BlockedClient {
+                    end,
+                    destination: destination.clone(),
+                    sender: Arc::clone(&sender),
+                });
+        }
+        Ok(BlockingPop::Blocked(ListWaiter {
+            keys: keys.to_vec(),
+            sender,
+            receiver,
+            registry: Arc::clone(&self.blocked),
+        }))
+    }
+}
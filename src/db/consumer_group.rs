@@ -0,0 +1,766 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use anyhow::{anyhow, bail};
+use bytes::Bytes;
+
+use super::rdb::{RdbDecoder, RdbEncoder};
+use super::snapshot::{Decoder, Encoder};
+use super::stream::{now_ms, Stream, StreamEntry, StreamId};
+use super::{get_live_mut, get_or_insert_mut, Store, Value, DB};
+use crate::err::RedisDatabaseError;
+use crate::frame::Frame;
+use crate::{array, bulk};
+
+type Entries = BTreeMap<StreamId, Vec<(Bytes, Bytes)>>;
+
+/// Entry of a pending entries list (PEL), i.e., an entry delivered but not yet acknowledged.
+#[derive(Debug, Clone)]
+struct Nack {
+    consumer: String,
+    /// Wall-clock time of the last delivery in milliseconds.
+    delivery_time: u64,
+    delivery_count: u64,
+}
+
+impl Nack {
+    fn idle(&self, now: u64) -> u64 {
+        now.saturating_sub(self.delivery_time)
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Consumer {
+    /// IDs of the entries delivered to this consumer and not yet acknowledged.
+    pending: BTreeSet<StreamId>,
+    /// Wall-clock time of the last interaction in milliseconds.
+    seen_time: u64,
+}
+
+impl Consumer {
+    fn new() -> Self {
+        Self {
+            pending: BTreeSet::new(),
+            seen_time: now_ms(),
+        }
+    }
+}
+
+/// Consumer group of a stream with the pending entries of its consumers.
+#[derive(Debug, Clone)]
+pub(super) struct ConsumerGroup {
+    last_delivered_id: StreamId,
+    /// Pending entries of all consumers of the group.
+    pending: BTreeMap<StreamId, Nack>,
+    consumers: HashMap<String, Consumer>,
+}
+
+impl ConsumerGroup {
+    fn new(last_delivered_id: StreamId) -> Self {
+        Self {
+            last_delivered_id,
+            pending: BTreeMap::new(),
+            consumers: HashMap::new(),
+        }
+    }
+
+    /// Serialize the group for a snapshot. The pending entries of each consumer
+    /// are not stored, they are rebuilt from the pending entries of the group.
+    pub(super) fn encode(&self, encoder: &mut Encoder) {
+        encoder.stream_id(self.last_delivered_id);
+        encoder.len(self.consumers.len());
+        for (name, consumer) in &self.consumers {
+            encoder.string(name);
+            encoder.u64(consumer.seen_time);
+        }
+        encoder.len(self.pending.len());
+        for (id, nack) in &self.pending {
+            encoder.stream_id(*id);
+            encoder.string(&nack.consumer);
+            encoder.u64(nack.delivery_time);
+            encoder.u64(nack.delivery_count);
+        }
+    }
+
+    pub(super) fn decode(decoder: &mut Decoder) -> anyhow::Result<Self> {
+        let mut group = Self::new(decoder.stream_id()?);
+        for _ in 0..decoder.len()? {
+            let name = decoder.string()?;
+            let consumer = Consumer {
+                pending: BTreeSet::new(),
+                seen_time: decoder.u64()?,
+            };
+            group.consumers.insert(name, consumer);
+        }
+        for _ in 0..decoder.len()? {
+            let id = decoder.stream_id()?;
+            let nack = Nack {
+                consumer: decoder.string()?,
+                delivery_time: decoder.u64()?,
+                delivery_count: decoder.u64()?,
+            };
+            let Some(consumer) = group.consumers.get_mut(&nack.consumer) else {
+                bail!("pending entry of unknown consumer '{}'", nack.consumer);
+            };
+            consumer.pending.insert(id);
+            group.pending.insert(id, nack);
+        }
+        Ok(group)
+    }
+
+    /// Serialize the group for an RDB file, i.e., the last delivered ID, the pending entries
+    /// of the group and the consumers with the IDs of their pending entries.
+    pub(super) fn encode_rdb(&self, encoder: &mut RdbEncoder) {
+        encoder.len(self.last_delivered_id.ms);
+        encoder.len(self.last_delivered_id.seq);
+        encoder.len(self.pending.len() as u64);
+        for (id, nack) in &self.pending {
+            encoder.raw_stream_id(*id);
+            encoder.millis(nack.delivery_time);
+            encoder.len(nack.delivery_count);
+        }
+        encoder.len(self.consumers.len() as u64);
+        for (name, consumer) in &self.consumers {
+            encoder.string(name.as_bytes());
+            encoder.millis(consumer.seen_time);
+            encoder.len(consumer.pending.len() as u64);
+            for id in &consumer.pending {
+                encoder.raw_stream_id(*id);
+            }
+        }
+    }
+
+    /// Deserialize a group of a stream of the given listpacks version, see `RdbDecoder::stream`.
+    pub(super) fn decode_rdb(decoder: &mut RdbDecoder, version: u8) -> anyhow::Result<Self> {
+        let mut group = Self::new(decoder.stream_id()?);
+        if version >= 2 {
+            decoder.len()?; // entries read
+        }
+        let mut deliveries = BTreeMap::new();
+        for _ in 0..decoder.count()? {
+            let id = decoder.raw_stream_id()?;
+            deliveries.insert(id, (decoder.millis()?, decoder.len()?));
+        }
+        for _ in 0..decoder.count()? {
+            let name = String::from_utf8(decoder.string()?.to_vec())
+                .map_err(|_| anyhow!("consumer name is not valid UTF-8"))?;
+            let mut consumer = Consumer {
+                pending: BTreeSet::new(),
+                seen_time: decoder.millis()?,
+            };
+            if version >= 3 {
+                decoder.millis()?; // active time
+            }
+            for _ in 0..decoder.count()? {
+                let id = decoder.raw_stream_id()?;
+                let Some((delivery_time, delivery_count)) = deliveries.remove(&id) else {
+                    bail!(
+                        "pending entry {} of consumer '{}' is not in the group",
+                        id,
+                        name
+                    );
+                };
+                consumer.pending.insert(id);
+                let nack = Nack {
+                    consumer: name.clone(),
+                    delivery_time,
+                    delivery_count,
+                };
+                group.pending.insert(id, nack);
+            }
+            group.consumers.insert(name, consumer);
+        }
+        if let Some(id) = deliveries.keys().next() {
+            bail!("pending entry {} without a consumer", id);
+        }
+        Ok(group)
+    }
+
+    /// Append the commands that rebuild the group to a rewritten append-only file.
+    ///
+    /// Pending entries are restored by `XCLAIM ... FORCE` with their delivery time and count.
+    /// Pending entries deleted from the stream are not restored.
+    pub(super) fn rewrite(
+        &self,
+        key: &str,
+        name: &str,
+        entries: &Entries,
+        commands: &mut Vec<Frame>,
+    ) {
+        commands.push(array!(
+            bulk!("XGROUP"),
+            bulk!("CREATE"),
+            bulk!(key),
+            bulk!(name),
+            bulk!(self.last_delivered_id.to_string())
+        ));
+        for consumer in self.consumers.keys() {
+            commands.push(array!(
+                bulk!("XGROUP"),
+                bulk!("CREATECONSUMER"),
+                bulk!(key),
+                bulk!(name),
+                bulk!(consumer.as_str())
+            ));
+        }
+        for (id, nack) in &self.pending {
+            if !entries.contains_key(id) {
+                continue;
+            }
+            commands.push(array!(
+                bulk!("XCLAIM"),
+                bulk!(key),
+                bulk!(name),
+                bulk!(nack.consumer.as_str()),
+                bulk!("0"),
+                bulk!(id.to_string()),
+                bulk!("TIME"),
+                bulk!(nack.delivery_time.to_string()),
+                bulk!("RETRYCOUNT"),
+                bulk!(nack.delivery_count.to_string()),
+                bulk!("FORCE"),
+                bulk!("JUSTID")
+            ));
+        }
+    }
+
+    /// Get a consumer, creating it if it does not exist, and update its seen time.
+    fn consumer_mut(&mut self, name: &str) -> &mut Consumer {
+        let consumer = self
+            .consumers
+            .entry(name.to_string())
+            .or_insert_with(Consumer::new);
+        consumer.seen_time = now_ms();
+        consumer
+    }
+
+    /// Assign a pending entry to `consumer`, removing it from the previous owner.
+    fn assign(&mut self, id: StreamId, consumer: &str, delivery_count: u64) {
+        let now = now_ms();
+        if let Some(nack) = self.pending.get(&id) {
+            if let Some(owner) = self.consumers.get_mut(&nack.consumer) {
+                owner.pending.remove(&id);
+            }
+        }
+        self.consumer_mut(consumer).pending.insert(id);
+        self.pending.insert(
+            id,
+            Nack {
+                consumer: consumer.to_string(),
+                delivery_time: now,
+                delivery_count,
+            },
+        );
+    }
+
+    /// Remove an entry from the pending entries list. Returns `true` if it was pending.
+    fn acknowledge(&mut self, id: &StreamId) -> bool {
+        match self.pending.remove(id) {
+            Some(nack) => {
+                if let Some(consumer) = self.consumers.get_mut(&nack.consumer) {
+                    consumer.pending.remove(id);
+                }
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+/// Summary of the pending entries of a consumer group returned by `XPENDING key group`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PendingSummary {
+    pub count: usize,
+    pub min_id: Option<StreamId>,
+    pub max_id: Option<StreamId>,
+    /// Number of pending entries of each consumer with at least one pending entry.
+    pub consumers: Vec<(String, usize)>,
+}
+
+/// Pending entry returned by the extended form of `XPENDING`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingEntry {
+    pub id: StreamId,
+    pub consumer: String,
+    /// Milliseconds since the last delivery.
+    pub idle: u64,
+    pub delivery_count: u64,
+}
+
+/// Range and filters of the extended form of `XPENDING`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PendingRange {
+    /// Only entries idle for at least this many milliseconds (`IDLE`).
+    pub min_idle: Option<u64>,
+    pub start: StreamId,
+    pub end: StreamId,
+    pub count: usize,
+    pub consumer: Option<String>,
+}
+
+/// Options of `XCLAIM` besides `JUSTID`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ClaimOptions {
+    /// Wall-clock time of the last delivery in milliseconds (`IDLE` or `TIME`), now by default.
+    pub delivery_time: Option<u64>,
+    /// Delivery count (`RETRYCOUNT`), by default incremented unless `JUSTID` is given.
+    pub retry_count: Option<u64>,
+    /// Add entries that are not pending yet to the pending entries list (`FORCE`).
+    pub force: bool,
+}
+
+/// Result of `XAUTOCLAIM`.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AutoClaim {
+    /// Start ID of the next scan, `0-0` when the whole pending entries list was scanned.
+    pub next_id: StreamId,
+    pub entries: Vec<StreamEntry>,
+    /// IDs of pending entries that no longer exist in the stream and were removed from the PEL.
+    pub deleted: Vec<StreamId>,
+}
+
+/// Stream information returned by `XINFO STREAM`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamInfo {
+    pub length: usize,
+    pub last_generated_id: StreamId,
+    pub groups: usize,
+    pub first_entry: Option<StreamEntry>,
+    pub last_entry: Option<StreamEntry>,
+}
+
+/// Consumer group information returned by `XINFO GROUPS`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GroupInfo {
+    pub name: String,
+    pub consumers: usize,
+    pub pending: usize,
+    pub last_delivered_id: StreamId,
+}
+
+/// Consumer information returned by `XINFO CONSUMERS`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConsumerInfo {
+    pub name: String,
+    pub pending: usize,
+    /// Milliseconds since the last interaction.
+    pub idle: u64,
+}
+
+/// Get the stream stored at `key`, or `None` if the key does not exist.
+fn get_stream_mut<'a>(
+    data_store: &'a mut Store,
+    key: &str,
+) -> Result<Option<&'a mut Stream>, RedisDatabaseError> {
+    match get_live_mut(data_store, key) {
+        Some(item) => Ok(Some(item.value.as_stream_mut()?)),
+        None => Ok(None),
+    }
+}
+
+/// Get the consumer group `group` of the stream stored at `key`.
+fn get_group_mut<'a>(
+    data_store: &'a mut Store,
+    key: &str,
+    group: &str,
+) -> Result<(&'a mut ConsumerGroup, &'a Entries), RedisDatabaseError> {
+    let no_group = || RedisDatabaseError::NoGroup(key.to_string(), group.to_string());
+    let stream = get_stream_mut(data_store, key)?.ok_or_else(no_group)?;
+    let Stream {
+        entries, groups, ..
+    } = stream;
+    let group = groups.get_mut(group).ok_or_else(no_group)?;
+    Ok((group, entries))
+}
+
+fn to_entry(id: StreamId, entries: &Entries) -> Option<StreamEntry> {
+    entries.get(&id).map(|fields| StreamEntry {
+        id,
+        fields: fields.clone(),
+    })
+}
+
+impl DB {
+    /// Create a consumer group of the stream stored at `key` that starts after `id`.
+    /// The ID `None` stands for `$`, i.e., the last ID of the stream.
+    /// With `create` (`MKSTREAM`), an empty stream is created if it does not exist.
+    pub async fn xgroup_create(
+        &self,
+        key: &str,
+        group: &str,
+        id: Option<StreamId>,
+        create: bool,
+    ) -> anyhow::Result<()> {
+        let mut data_store = self.lock_data().await;
+        let stream = if create {
+            get_or_insert_mut(&mut data_store, key, || Value::Stream(Stream::default()))
+                .value
+                .as_stream_mut()?
+        } else {
+            get_stream_mut(&mut data_store, key)?.ok_or(RedisDatabaseError::StreamKeyRequired)?
+        };
+        if stream.groups.contains_key(group) {
+            return Err(RedisDatabaseError::BusyGroup.into());
+        }
+        let id = id.unwrap_or(stream.last_id);
+        stream
+            .groups
+            .insert(group.to_string(), ConsumerGroup::new(id));
+        Ok(())
+    }
+
+    /// Destroy a consumer group. Returns `true` if the group existed.
+    pub async fn xgroup_destroy(&self, key: &str, group: &str) -> anyhow::Result<bool> {
+        let mut data_store = self.lock_data().await;
+        let stream =
+            get_stream_mut(&mut data_store, key)?.ok_or(RedisDatabaseError::StreamKeyRequired)?;
+        Ok(stream.groups.remove(group).is_some())
+    }
+
+    /// Create a consumer in a consumer group. Returns `true` if the consumer was created.
+    pub async fn xgroup_create_consumer(
+        &self,
+        key: &str,
+        group: &str,
+        consumer: &str,
+    ) -> anyhow::Result<bool> {
+        let mut data_store = self.lock_data().await;
+        let (group, _) = get_group_mut(&mut data_store, key, group)?;
+        if group.consumers.contains_key(consumer) {
+            return Ok(false);
+        }
+        group.consumer_mut(consumer);
+        Ok(true)
+    }
+
+    /// Delete a consumer from a consumer group together with its pending entries.
+    ///
+    /// Returns the number of pending entries the consumer had.
+    pub async fn xgroup_del_consumer(
+        &self,
+        key: &str,
+        group: &str,
+        consumer: &str,
+    ) -> anyhow::Result<usize> {
+        let mut data_store = self.lock_data().await;
+        let (group, _) = get_group_mut(&mut data_store, key, group)?;
+        let Some(consumer) = group.consumers.remove(consumer) else {
+            return Ok(0);
+        };
+        for id in &consumer.pending {
+            group.pending.remove(id);
+        }
+        Ok(consumer.pending.len())
+    }
+
+    /// Set the last delivered ID of a consumer group. `None` stands for `$`.
+    pub async fn xgroup_set_id(
+        &self,
+        key: &str,
+        group: &str,
+        id: Option<StreamId>,
+    ) -> anyhow::Result<()> {
+        let mut data_store = self.lock_data().await;
+        let no_group = || RedisDatabaseError::NoGroup(key.to_string(), group.to_string());
+        let stream = get_stream_mut(&mut data_store, key)?.ok_or_else(no_group)?;
+        let id = id.unwrap_or(stream.last_id);
+        stream
+            .groups
+            .get_mut(group)
+            .ok_or_else(no_group)?
+            .last_delivered_id = id;
+        Ok(())
+    }
+
+    /// Read entries from multiple streams on behalf of `consumer` of `group`.
+    ///
+    /// - The ID `None` (`>`) reads entries never delivered to the group. Unless `noack`,
+    ///   the entries are added to the pending entries list of the consumer.
+    /// - Any other ID reads the pending entries of the consumer with a greater ID.
+    ///   Entries deleted from the stream are returned without fields.
+    ///
+    /// New entries are returned only for streams that have some,
+    /// pending entries are returned for every stream.
+    pub async fn xreadgroup(
+        &self,
+        group: &str,
+        consumer: &str,
+        streams: &[(String, Option<StreamId>)],
+        count: Option<usize>,
+        noack: bool,
+    ) -> anyhow::Result<Vec<(String, Vec<StreamEntry>)>> {
+        let mut data_store = self.lock_data().await;
+
+        // Check all the groups first, so that nothing is delivered on error
+        for (key, _) in streams {
+            get_group_mut(&mut data_store, key, group)?;
+        }
+
+        let count = count.unwrap_or(usize::MAX);
+        let mut result = Vec::new();
+        for (key, id) in streams {
+            let (group, entries) = get_group_mut(&mut data_store, key, group)?;
+            match id {
+                None => {
+                    let Some(start) = group.last_delivered_id.next() else {
+                        continue;
+                    };
+                    let delivered: Vec<_> = entries
+                        .range(start..)
+                        .take(count)
+                        .filter_map(|(id, _)| to_entry(*id, entries))
+                        .collect();
+                    group.consumer_mut(consumer);
+                    if let Some(last) = delivered.last() {
+                        group.last_delivered_id = last.id;
+                    }
+                    if !noack {
+                        for entry in &delivered {
+                            group.assign(entry.id, consumer, 1);
+                        }
+                    }
+                    if !delivered.is_empty() {
+                        result.push((key.clone(), delivered));
+                    }
+                }
+                Some(id) => {
+                    let ids: Vec<_> = match id.next() {
+                        Some(start) => group
+                            .consumer_mut(consumer)
+                            .pending
+                            .range(start..)
+                            .take(count)
+                            .copied()
+                            .collect(),
+                        None => Vec::new(),
+                    };
+                    let mut history = Vec::with_capacity(ids.len());
+                    for id in ids {
+                        let delivery_count = group.pending[&id].delivery_count + 1;
+                        group.assign(id, consumer, delivery_count);
+                        history.push(to_entry(id, entries).unwrap_or(StreamEntry {
+                            id,
+                            fields: Vec::new(),
+                        }));
+                    }
+                    result.push((key.clone(), history));
+                }
+            }
+        }
+        Ok(result)
+    }
+
+    /// Acknowledge entries, i.e., remove them from the pending entries list of a consumer group.
+    ///
+    /// Returns the number of acknowledged entries.
+    pub async fn xack(&self, key: &str, group: &str, ids: &[StreamId]) -> anyhow::Result<usize> {
+        let mut data_store = self.lock_data().await;
+        match get_group_mut(&mut data_store, key, group) {
+            Ok((group, _)) => Ok(ids.iter().filter(|id| group.acknowledge(id)).count()),
+            Err(RedisDatabaseError::NoGroup(..)) => Ok(0),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Get the summary of the pending entries of a consumer group.
+    pub async fn xpending(&self, key: &str, group: &str) -> anyhow::Result<PendingSummary> {
+        let mut data_store = self.lock_data().await;
+        let (group, _) = get_group_mut(&mut data_store, key, group)?;
+        let mut consumers: Vec<_> = group
+            .consumers
+            .iter()
+            .filter(|(_, consumer)| !consumer.pending.is_empty())
+            .map(|(name, consumer)| (name.clone(), consumer.pending.len()))
+            .collect();
+        consumers.sort();
+        Ok(PendingSummary {
+            count: group.pending.len(),
+            min_id: group.pending.keys().next().copied(),
+            max_id: group.pending.keys().next_back().copied(),
+            consumers,
+        })
+    }
+
+    /// Get the pending entries of a consumer group within a range.
+    pub async fn xpending_range(
+        &self,
+        key: &str,
+        group: &str,
+        range: &PendingRange,
+    ) -> anyhow::Result<Vec<PendingEntry>> {
+        let mut data_store = self.lock_data().await;
+        let (group, _) = get_group_mut(&mut data_store, key, group)?;
+        if range.start > range.end {
+            return Ok(Vec::new());
+        }
+        let now = now_ms();
+        Ok(group
+            .pending
+            .range(range.start..=range.end)
+            .filter(|(_, nack)| {
+                range
+                    .min_idle
+                    .is_none_or(|min_idle| nack.idle(now) >= min_idle)
+            })
+            .filter(|(_, nack)| {
+                range
+                    .consumer
+                    .as_ref()
+                    .is_none_or(|consumer| &nack.consumer == consumer)
+            })
+            .take(range.count)
+            .map(|(id, nack)| PendingEntry {
+                id: *id,
+                consumer: nack.consumer.clone(),
+                idle: nack.idle(now),
+                delivery_count: nack.delivery_count,
+            })
+            .collect())
+    }
+
+    /// Transfer the ownership of pending entries idle for at least `min_idle` milliseconds
+    /// to `consumer`. Pending entries deleted from the stream are removed from the PEL.
+    ///
+    /// Returns the claimed entries. With `just_id`, the delivery count is not incremented.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn xclaim(
+        &self,
+        key: &str,
+        group: &str,
+        consumer: &str,
+        min_idle: u64,
+        ids: &[StreamId],
+        just_id: bool,
+        options: ClaimOptions,
+    ) -> anyhow::Result<Vec<StreamEntry>> {
+        let mut data_store = self.lock_data().await;
+        let (group, entries) = get_group_mut(&mut data_store, key, group)?;
+        let now = now_ms();
+        group.consumer_mut(consumer);
+        let mut claimed = Vec::new();
+        for id in ids {
+            let delivery_count = match group.pending.get(id) {
+                Some(nack) if nack.idle(now) < min_idle => continue,
+                Some(nack) => nack.delivery_count,
+                None if options.force && entries.contains_key(id) => 0,
+                None => continue,
+            };
+            let Some(entry) = to_entry(*id, entries) else {
+                group.acknowledge(id);
+                continue;
+            };
+            let delivery_count = options
+                .retry_count
+                .unwrap_or(delivery_count + u64::from(!just_id));
+            group.assign(*id, consumer, delivery_count);
+            if let Some(delivery_time) = options.delivery_time {
+                group.pending.get_mut(id).unwrap().delivery_time = delivery_time;
+            }
+            claimed.push(entry);
+        }
+        Ok(claimed)
+    }
+
+    /// Scan up to `count` pending entries starting at `start` and claim those idle
+    /// for at least `min_idle` milliseconds like `xclaim`.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn xautoclaim(
+        &self,
+        key: &str,
+        group: &str,
+        consumer: &str,
+        min_idle: u64,
+        start: StreamId,
+        count: usize,
+        just_id: bool,
+    ) -> anyhow::Result<AutoClaim> {
+        let mut data_store = self.lock_data().await;
+        let (group, entries) = get_group_mut(&mut data_store, key, group)?;
+        let now = now_ms();
+        group.consumer_mut(consumer);
+
+        let mut scanned = group.pending.range(start..).map(|(id, _)| *id);
+        let candidates: Vec<_> = scanned.by_ref().take(count).collect();
+        let next_id = scanned.next().unwrap_or(StreamId::MIN);
+
+        let mut result = AutoClaim {
+            next_id,
+            ..Default::default()
+        };
+        for id in candidates {
+            let nack = &group.pending[&id];
+            if nack.idle(now) < min_idle {
+                continue;
+            }
+            let Some(entry) = to_entry(id, entries) else {
+                group.acknowledge(&id);
+                result.deleted.push(id);
+                continue;
+            };
+            let delivery_count = nack.delivery_count + u64::from(!just_id);
+            group.assign(id, consumer, delivery_count);
+            result.entries.push(entry);
+        }
+        Ok(result)
+    }
+
+    /// Get information about the stream stored at `key`.
+    pub async fn xinfo_stream(&self, key: &str) -> anyhow::Result<StreamInfo> {
+        let mut data_store = self.lock_data().await;
+        let stream = get_stream_mut(&mut data_store, key)?.ok_or(RedisDatabaseError::NoSuchKey)?;
+        Ok(StreamInfo {
+            length: stream.len(),
+            last_generated_id: stream.last_id,
+            groups: stream.groups.len(),
+            first_entry: stream
+                .entries
+                .keys()
+                .next()
+                .and_then(|id| to_entry(*id, &stream.entries)),
+            last_entry: stream
+                .entries
+                .keys()
+                .next_back()
+                .and_then(|id| to_entry(*id, &stream.entries)),
+        })
+    }
+
+    /// Get information about the consumer groups of the stream stored at `key`.
+    pub async fn xinfo_groups(&self, key: &str) -> anyhow::Result<Vec<GroupInfo>> {
+        let mut data_store = self.lock_data().await;
+        let stream = get_stream_mut(&mut data_store, key)?.ok_or(RedisDatabaseError::NoSuchKey)?;
+        let mut groups: Vec<_> = stream
+            .groups
+            .iter()
+            .map(|(name, group)| GroupInfo {
+                name: name.clone(),
+                consumers: group.consumers.len(),
+                pending: group.pending.len(),
+                last_delivered_id: group.last_delivered_id,
+            })
+            .collect();
+        groups.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(groups)
+    }
+
+    /// Get information about the consumers of a consumer group.
+    pub async fn xinfo_consumers(
+        &self,
+        key: &str,
+        group: &str,
+    ) -> anyhow::Result<Vec<ConsumerInfo>> {
+        let mut data_store = self.lock_data().await;
+        let (group, _) = get_group_mut(&mut data_store, key, group)?;
+        let now = now_ms();
+        let mut consumers: Vec<_> = group
+            .consumers
+            .iter()
+            .map(|(name, consumer)| ConsumerInfo {
+                name: name.clone(),
+                pending: consumer.pending.len(),
+                idle: now.saturating_sub(consumer.seen_time),
+            })
+            .collect();
+        consumers.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(consumers)
+    }
+}
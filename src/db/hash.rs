@@ -0,0 +1,173 @@
+use std::collections::HashMap;
+use std::str;
+
+use bytes::Bytes;
+
+use super::{get_live_mut, get_or_insert_mut, Value, DB};
+use crate::err::RedisDatabaseError;
+
+impl Value {
+    fn as_hash(&self) -> Result<&HashMap<Bytes, Bytes>, RedisDatabaseError> {
+        match self {
+            Value::Hash(hash) => Ok(hash),
+            _ => Err(RedisDatabaseError::WrongType),
+        }
+    }
+
+    fn as_hash_mut(&mut self) -> Result<&mut HashMap<Bytes, Bytes>, RedisDatabaseError> {
+        match self {
+            Value::Hash(hash) => Ok(hash),
+            _ => Err(RedisDatabaseError::WrongType),
+        }
+    }
+}
+
+impl DB {
+    /// Set `fields` of the hash stored at `key`. The hash is created if it does not exist.
+    ///
+    /// Returns the number of fields that were added (not updated).
+    pub async fn hset(&self, key: &str, fields: Vec<(Bytes, Bytes)>) -> anyhow::Result<usize> {
+        let mut data_store = self.lock_data().await;
+        let hash = get_or_insert_mut(&mut data_store, key, || Value::Hash(HashMap::new()))
+            .value
+            .as_hash_mut()?;
+        let mut added = 0;
+        for (field, value) in fields {
+            if hash.insert(field, value).is_none() {
+                added += 1;
+            }
+        }
+        Ok(added)
+    }
+
+    /// Set `field` of the hash stored at `key` only if the field does not exist yet.
+    ///
+    /// Returns `true` if the field was set.
+    pub async fn hsetnx(&self, key: &str, field: Bytes, value: Bytes) -> anyhow::Result<bool> {
+        let mut data_store = self.lock_data().await;
+        let hash = get_or_insert_mut(&mut data_store, key, || Value::Hash(HashMap::new()))
+            .value
+            .as_hash_mut()?;
+        if hash.contains_key(&field) {
+            return Ok(false);
+        }
+        hash.insert(field, value);
+        Ok(true)
+    }
+
+    /// Get the value of `field` of the hash stored at `key`.
+    pub async fn hget(&self, key: &str, field: &Bytes) -> anyhow::Result<Option<Bytes>> {
+        let mut data_store = self.lock_data().await;
+        match get_live_mut(&mut data_store, key) {
+            Some(item) => Ok(item.value.as_hash()?.get(field).cloned()),
+            None => Ok(None),
+        }
+    }
+
+    /// Get the values of `fields` of the hash stored at `key`.
+    pub async fn hmget(&self, key: &str, fields: &[Bytes]) -> anyhow::Result<Vec<Option<Bytes>>> {
+        let mut data_store = self.lock_data().await;
+        match get_live_mut(&mut data_store, key) {
+            Some(item) => {
+                let hash = item.value.as_hash()?;
+                Ok(fields
+                    .iter()
+                    .map(|field| hash.get(field).cloned())
+                    .collect())
+            }
+            None => Ok(vec![None; fields.len()]),
+        }
+    }
+
+    /// Remove `fields` from the hash stored at `key`. Empty hashes are removed from the database.
+    ///
+    /// Returns the number of removed fields.
+    pub async fn hdel(&self, key: &str, fields: &[Bytes]) -> anyhow::Result<usize> {
+        let mut data_store = self.lock_data().await;
+        let Some(item) = get_live_mut(&mut data_store, key) else {
+            return Ok(0);
+        };
+        let hash = item.value.as_hash_mut()?;
+        let removed = fields
+            .iter()
+            .filter(|field| hash.remove(*field).is_some())
+            .count();
+        if hash.is_empty() {
+            data_store.remove(key);
+        }
+        Ok(removed)
+    }
+
+    /// Get all field-value pairs of the hash stored at `key`.
+    pub async fn hgetall(&self, key: &str) -> anyhow::Result<Vec<(Bytes, Bytes)>> {
+        let mut data_store = self.lock_data().await;
+        match get_live_mut(&mut data_store, key) {
+            Some(item) => Ok(item
+                .value
+                .as_hash()?
+                .iter()
+                .map(|(field, value)| (field.clone(), value.clone()))
+                .collect()),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Increment the integer value of `field` of the hash stored at `key` by `increment`.
+    /// Missing hashes and fields are treated as `0`.
+    ///
+    /// Returns the value after the increment.
+    pub async fn hincrby(&self, key: &str, field: Bytes, increment: i64) -> anyhow::Result<i64> {
+        let mut data_store = self.lock_data().await;
+        let hash = get_or_insert_mut(&mut data_store, key, || Value::Hash(HashMap::new()))
+            .value
+            .as_hash_mut()?;
+        let current = match hash.get(&field) {
+            Some(value) => str::from_utf8(value)
+                .ok()
+                .and_then(|s| s.parse::<i64>().ok())
+                .ok_or(RedisDatabaseError::HashValueNotInteger)?,
+            None => 0,
+        };
+        let new_value = current
+            .checked_add(increment)
+            .ok_or(RedisDatabaseError::Overflow)?;
+        hash.insert(field, Bytes::from(new_value.to_string()));
+        Ok(new_value)
+    }
+
+    /// Get all field names of the hash stored at `key`.
+    pub async fn hkeys(&self, key: &str) -> anyhow::Result<Vec<Bytes>> {
+        let mut data_store = self.lock_data().await;
+        match get_live_mut(&mut data_store, key) {
+            Some(item) => Ok(item.value.as_hash()?.keys().cloned().collect()),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Get all values of the hash stored at `key`.
+    pub async fn hvals(&self, key: &str) -> anyhow::Result<Vec<Bytes>> {
+        let mut data_store = self.lock_data().await;
+        match get_live_mut(&mut data_store, key) {
+            Some(item) => Ok(item.value.as_hash()?.values().cloned().collect()),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Get the number of fields of the hash stored at `key`.
+    pub async fn hlen(&self, key: &str) -> anyhow::Result<usize> {
+        let mut data_store = self.lock_data().await;
+        match get_live_mut(&mut data_store, key) {
+            Some(item) => Ok(item.value.as_hash()?.len()),
+            None => Ok(0),
+        }
+    }
+
+    /// Check if `field` exists in the hash stored at `key`.
+    pub async fn hexists(&self, key: &str, field: &Bytes) -> anyhow::Result<bool> {
+        let mut data_store = self.lock_data().await;
+        match get_live_mut(&mut data_store, key) {
+            Some(item) => Ok(item.value.as_hash()?.contains_key(field)),
+            None => Ok(false),
+        }
+    }
+}
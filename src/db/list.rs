@@ -0,0 +1,280 @@
+use std::collections::VecDeque;
+
+use bytes::Bytes;
+
+use super::blocking::serve_blocked;
+use super::{get_live_mut, get_or_insert_mut, normalize_range, Store, Value, DB};
+use crate::err::RedisDatabaseError;
+
+/// End of a list, e.g., `LPUSH` pushes to the `Left` end.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListEnd {
+    Left,
+    Right,
+}
+
+/// Where to insert an element relative to the pivot in `LINSERT`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InsertPosition {
+    Before,
+    After,
+}
+
+impl Value {
+    fn as_list(&self) -> Result<&VecDeque<Bytes>, RedisDatabaseError> {
+        match self {
+            Value::List(list) => Ok(list),
+            _ => Err(RedisDatabaseError::WrongType),
+        }
+    }
+
+    fn as_list_mut(&mut self) -> Result<&mut VecDeque<Bytes>, RedisDatabaseError> {
+        match self {
+            Value::List(list) => Ok(list),
+            _ => Err(RedisDatabaseError::WrongType),
+        }
+    }
+}
+
+/// Convert a Redis-style index (negative indices count from the end) to a valid index.
+fn normalize_index(index: i64, len: usize) -> Option<usize> {
+    let index = if index < 0 { len as i64 + index } else { index };
+    (0..len as i64).contains(&index).then_some(index as usize)
+}
+
+/// Push `value` to the list stored at `key`. The list is created if it does not exist.
+pub(super) fn push_element(
+    data_store: &mut Store,
+    key: &str,
+    value: Bytes,
+    end: ListEnd,
+) -> Result<(), RedisDatabaseError> {
+    let list = get_or_insert_mut(data_store, key, || Value::List(VecDeque::new()))
+        .value
+        .as_list_mut()?;
+    match end {
+        ListEnd::Left => list.push_front(value),
+        ListEnd::Right => list.push_back(value),
+    }
+    Ok(())
+}
+
+/// Pop an element from the list stored at `key`. With a `destination`, the element
+/// is pushed to the destination list, e.g., `BLMOVE`. Empty lists are removed from the database.
+///
+/// Returns `None` if the key does not exist.
+pub(super) fn pop_element(
+    data_store: &mut Store,
+    key: &str,
+    end: ListEnd,
+    destination: &Option<(String, ListEnd)>,
+) -> Result<Option<Bytes>, RedisDatabaseError> {
+    // Check the destination first, so that the element is not lost
+    if let Some((destination, _)) = destination {
+        if let Some(item) = get_live_mut(data_store, destination) {
+            item.value.as_list()?;
+        }
+    }
+    let Some(item) = get_live_mut(data_store, key) else {
+        return Ok(None);
+    };
+    let list = item.value.as_list_mut()?;
+    let value = match end {
+        ListEnd::Left => list.pop_front(),
+        ListEnd::Right => list.pop_back(),
+    };
+    if list.is_empty() {
+        data_store.remove(key);
+    }
+    if let (Some(value), Some((destination, to))) = (&value, destination) {
+        push_element(data_store, destination, value.clone(), *to)?;
+    }
+    Ok(value)
+}
+
+impl DB {
+    /// Push `values` to the list stored at `key`. The list is created if it does not exist.
+    ///
+    /// Clients blocked on the list are served right after the push.
+    ///
+    /// Returns the length of the list after the push.
+    pub async fn push(&self, key: &str, values: Vec<Bytes>, end: ListEnd) -> anyhow::Result<usize> {
+        let mut data_store = self.lock_data().await;
+        let list = get_or_insert_mut(&mut data_store, key, || Value::List(VecDeque::new()))
+            .value
+            .as_list_mut()?;
+        for value in values {
+            match end {
+                ListEnd::Left => list.push_front(value),
+                ListEnd::Right => list.push_back(value),
+            }
+        }
+        let len = list.len();
+        serve_blocked(&mut data_store, &mut self.blocked.lock().unwrap(), key);
+        Ok(len)
+    }
+
+    /// Pop up to `count` values from the list stored at `key`.
+    /// Empty lists are removed from the database.
+    ///
+    /// Returns `None` if the key does not exist.
+    pub async fn pop(
+        &self,
+        key: &str,
+        end: ListEnd,
+        count: usize,
+    ) -> anyhow::Result<Option<Vec<Bytes>>> {
+        let mut data_store = self.lock_data().await;
+        let Some(item) = get_live_mut(&mut data_store, key) else {
+            return Ok(None);
+        };
+        let list = item.value.as_list_mut()?;
+        let count = count.min(list.len());
+        let values = match end {
+            ListEnd::Left => list.drain(..count).collect(),
+            ListEnd::Right => list.drain(list.len() - count..).rev().collect(),
+        };
+        if list.is_empty() {
+            data_store.remove(key);
+        }
+        Ok(Some(values))
+    }
+
+    /// Get the elements of the list stored at `key` between `start` and `stop` (inclusive).
+    pub async fn lrange(&self, key: &str, start: i64, stop: i64) -> anyhow::Result<Vec<Bytes>> {
+        let mut data_store = self.lock_data().await;
+        let Some(item) = get_live_mut(&mut data_store, key) else {
+            return Ok(Vec::new());
+        };
+        let list = item.value.as_list()?;
+        match normalize_range(start, stop, list.len()) {
+            Some((start, stop)) => Ok(list.range(start..=stop).cloned().collect()),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Get the length of the list stored at `key`.
+    pub async fn llen(&self, key: &str) -> anyhow::Result<usize> {
+        let mut data_store = self.lock_data().await;
+        match get_live_mut(&mut data_store, key) {
+            Some(item) => Ok(item.value.as_list()?.len()),
+            None => Ok(0),
+        }
+    }
+
+    /// Get the element at `index` of the list stored at `key`.
+    pub async fn lindex(&self, key: &str, index: i64) -> anyhow::Result<Option<Bytes>> {
+        let mut data_store = self.lock_data().await;
+        let Some(item) = get_live_mut(&mut data_store, key) else {
+            return Ok(None);
+        };
+        let list = item.value.as_list()?;
+        Ok(normalize_index(index, list.len()).map(|index| list[index].clone()))
+    }
+
+    /// Set the element at `index` of the list stored at `key`.
+    pub async fn lset(&self, key: &str, index: i64, value: Bytes) -> anyhow::Result<()> {
+        let mut data_store = self.lock_data().await;
+        let item = get_live_mut(&mut data_store, key).ok_or(RedisDatabaseError::NoSuchKey)?;
+        let list = item.value.as_list_mut()?;
+        let index =
+            normalize_index(index, list.len()).ok_or(RedisDatabaseError::IndexOutOfRange)?;
+        list[index] = value;
+        Ok(())
+    }
+
+    /// Trim the list stored at `key` to the elements between `start` and `stop` (inclusive).
+    pub async fn ltrim(&self, key: &str, start: i64, stop: i64) -> anyhow::Result<()> {
+        let mut data_store = self.lock_data().await;
+        let Some(item) = get_live_mut(&mut data_store, key) else {
+            return Ok(());
+        };
+        let list = item.value.as_list_mut()?;
+        match normalize_range(start, stop, list.len()) {
+            Some((start, stop)) => {
+                list.truncate(stop + 1);
+                list.drain(..start);
+            }
+            None => {
+                data_store.remove(key);
+            }
+        }
+        Ok(())
+    }
+
+    /// Remove elements equal to `value` from the list stored at `key`.
+    ///
+    /// - `count > 0`: Remove up to `count` elements moving from head to tail.
+    /// - `count < 0`: Remove up to `|count|` elements moving from tail to head.
+    /// - `count = 0`: Remove all matching elements.
+    ///
+    /// Returns the number of removed elements.
+    pub async fn lrem(&self, key: &str, count: i64, value: &Bytes) -> anyhow::Result<usize> {
+        let mut data_store = self.lock_data().await;
+        let Some(item) = get_live_mut(&mut data_store, key) else {
+            return Ok(0);
+        };
+        let list = item.value.as_list_mut()?;
+        let limit = match count {
+            0 => usize::MAX,
+            count => count.unsigned_abs() as usize,
+        };
+
+        let mut removed = 0;
+        if count >= 0 {
+            let mut index = 0;
+            while index < list.len() && removed < limit {
+                if list[index] == value {
+                    list.remove(index);
+                    removed += 1;
+                } else {
+                    index += 1;
+                }
+            }
+        } else {
+            let mut index = list.len();
+            while index > 0 && removed < limit {
+                index -= 1;
+                if list[index] == value {
+                    list.remove(index);
+                    removed += 1;
+                }
+            }
+        }
+
+        if list.is_empty() {
+            data_store.remove(key);
+        }
+        Ok(removed)
+    }
+
+    /// Insert `value` before or after the first occurrence of `pivot`
+    /// in the list stored at `key`.
+    ///
+    /// Returns the length of the list after the insert, `-1` if the pivot
+    /// was not found and `0` if the key does not exist.
+    pub async fn linsert(
+        &self,
+        key: &str,
+        position: InsertPosition,
+        pivot: &Bytes,
+        value: Bytes,
+    ) -> anyhow::Result<i64> {
+        let mut data_store = self.lock_data().await;
+        let Some(item) = get_live_mut(&mut data_store, key) else {
+            return Ok(0);
+        };
+        let list = item.value.as_list_mut()?;
+        match list.iter().position(|element| element == pivot) {
+            Some(index) => {
+                let index = match position {
+                    InsertPosition::Before => index,
+                    InsertPosition::After => index + 1,
+                };
+                list.insert(index, value);
+                Ok(list.len() as i64)
+            }
+            None => Ok(-1),
+        }
+    }
+}
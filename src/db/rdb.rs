@@ -0,0 +1,970 @@
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+use anyhow::{anyhow, bail};
+use atoi::atoi;
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+use super::consumer_group::ConsumerGroup;
+use super::snapshot::{Snapshot, SnapshotItem};
+use super::sorted_set::SortedSet;
+use super::stream::{Stream, StreamId};
+use super::Value;
+use crate::constants::{MAX_PREALLOCATED_ELEMENTS, PROTO_MAX_BULK_LEN};
+use crate::err::RedisDatabaseError;
+
+/// Identifies RDB files, followed by the format version as four ASCII digits.
+pub(super) const MAGIC: &[u8] = b"REDIS";
+/// Version of written files. Version 9 (Redis 5.0) is the oldest one with streams.
+const VERSION: u32 = 9;
+/// Latest known version (Redis 7.4).
+const MAX_VERSION: u32 = 12;
+/// Oldest version that ends with a checksum.
+const CHECKSUM_VERSION: u32 = 5;
+
+const OPCODE_SLOT_INFO: u8 = 0xF4;
+const OPCODE_FUNCTION: u8 = 0xF5;
+const OPCODE_FUNCTION_PRE_GA: u8 = 0xF6;
+const OPCODE_MODULE_AUX: u8 = 0xF7;
+const OPCODE_IDLE: u8 = 0xF8;
+const OPCODE_FREQ: u8 = 0xF9;
+const OPCODE_AUX: u8 = 0xFA;
+const OPCODE_RESIZEDB: u8 = 0xFB;
+const OPCODE_EXPIRETIME_MS: u8 = 0xFC;
+const OPCODE_EXPIRETIME: u8 = 0xFD;
+const OPCODE_SELECTDB: u8 = 0xFE;
+const OPCODE_EOF: u8 = 0xFF;
+
+const TYPE_STRING: u8 = 0;
+const TYPE_LIST: u8 = 1;
+const TYPE_SET: u8 = 2;
+const TYPE_ZSET: u8 = 3;
+const TYPE_HASH: u8 = 4;
+const TYPE_ZSET_2: u8 = 5;
+const TYPE_MODULE_PRE_GA: u8 = 6;
+const TYPE_MODULE_2: u8 = 7;
+const TYPE_HASH_ZIPMAP: u8 = 9;
+const TYPE_LIST_ZIPLIST: u8 = 10;
+const TYPE_SET_INTSET: u8 = 11;
+const TYPE_ZSET_ZIPLIST: u8 = 12;
+const TYPE_HASH_ZIPLIST: u8 = 13;
+const TYPE_LIST_QUICKLIST: u8 = 14;
+const TYPE_STREAM_LISTPACKS: u8 = 15;
+const TYPE_HASH_LISTPACK: u8 = 16;
+const TYPE_ZSET_LISTPACK: u8 = 17;
+const TYPE_LIST_QUICKLIST_2: u8 = 18;
+const TYPE_STREAM_LISTPACKS_2: u8 = 19;
+const TYPE_SET_LISTPACK: u8 = 20;
+const TYPE_STREAM_LISTPACKS_3: u8 = 21;
+/// Hashes with field expiration (`HEXPIRE`), types 22 to 25.
+const TYPE_HASH_FIELD_EXPIRATION: std::ops::RangeInclusive<u8> = 22..=25;
+
+/// Special encodings of strings, the two most significant bits of the length are set.
+const ENC_INT8: u8 = 0;
+const ENC_INT16: u8 = 1;
+const ENC_INT32: u8 = 2;
+const ENC_LZF: u8 = 3;
+
+const QUICKLIST_NODE_PLAIN: u64 = 1;
+const QUICKLIST_NODE_PACKED: u64 = 2;
+
+const STREAM_ITEM_FLAG_DELETED: i64 = 1;
+const STREAM_ITEM_FLAG_SAMEFIELDS: i64 = 2;
+/// Entries per listpack of a written stream, the default of `stream-node-max-entries`.
+const STREAM_NODE_MAX_ENTRIES: usize = 100;
+
+/// CRC-64/Jones (reflected) used by Redis for the checksum at the end of the file.
+const CRC64_TABLE: [u64; 256] = crc64_table();
+
+const fn crc64_table() -> [u64; 256] {
+    let mut table = [0; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u64;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0x95AC_9329_AC4B_C9B5
+            } else {
+                crc >> 1
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+}
+
+fn crc64(data: &[u8]) -> u64 {
+    data.iter().fold(0, |crc, &byte| {
+        CRC64_TABLE[((crc ^ byte as u64) & 0xFF) as usize] ^ (crc >> 8)
+    })
+}
+
+impl Snapshot {
+    /// Serialize the snapshot to the Redis RDB format, readable by Redis 5.0 and later.
+    ///
+    /// Values use the plain encodings, streams are written as listpacks.
+    pub fn encode_rdb(&self) -> Bytes {
+        let mut encoder = RdbEncoder::default();
+        encoder.buf.put_slice(MAGIC);
+        encoder.buf.put_slice(format!("{:04}", VERSION).as_bytes());
+        encoder.buf.put_u8(OPCODE_SELECTDB);
+        encoder.len(0);
+        encoder.buf.put_u8(OPCODE_RESIZEDB);
+        encoder.len(self.items.len() as u64);
+        let expiring = self.items.iter().filter(|item| item.expires_at.is_some());
+        encoder.len(expiring.count() as u64);
+        for item in &self.items {
+            if let Some(expires_at) = item.expires_at {
+                encoder.buf.put_u8(OPCODE_EXPIRETIME_MS);
+                encoder.millis(expires_at);
+            }
+            encoder.value_type(&item.value);
+            encoder.string(item.key.as_bytes());
+            encoder.value(&item.value);
+        }
+        encoder.buf.put_u8(OPCODE_EOF);
+        let checksum = crc64(&encoder.buf);
+        encoder.buf.put_u64_le(checksum);
+        encoder.buf.freeze()
+    }
+
+    /// Deserialize a snapshot from the Redis RDB format (versions up to 12, i.e., Redis 7.4).
+    ///
+    /// Fails on values that the database cannot represent, i.e., module types and hashes
+    /// with field expiration, on functions and on keys of databases other than 0.
+    /// Idle times and access frequencies of keys are ignored.
+    pub fn decode_rdb(data: &[u8]) -> anyhow::Result<Self> {
+        let mut decoder = RdbDecoder { data };
+        if decoder.take(MAGIC.len())? != MAGIC {
+            bail!("not an RDB file");
+        }
+        let version = std::str::from_utf8(decoder.take(4)?)
+            .ok()
+            .and_then(|version| version.parse::<u32>().ok())
+            .ok_or_else(|| anyhow!("invalid RDB version"))?;
+        if !(1..=MAX_VERSION).contains(&version) {
+            bail!("unsupported RDB version {}", version);
+        }
+
+        let mut items = Vec::new();
+        let mut expires_at = None;
+        loop {
+            let opcode = decoder.u8()?;
+            match opcode {
+                OPCODE_EOF => break,
+                OPCODE_SELECTDB => {
+                    let db = decoder.len()?;
+                    if db != 0 {
+                        bail!("keys of database {} are not supported, only database 0", db);
+                    }
+                }
+                OPCODE_RESIZEDB => {
+                    decoder.len()?;
+                    decoder.len()?;
+                }
+                OPCODE_SLOT_INFO => {
+                    for _ in 0..3 {
+                        decoder.len()?;
+                    }
+                }
+                OPCODE_AUX => {
+                    let name = decoder.string()?;
+                    let value = decoder.string()?;
+                    log::debug!(
+                        "RDB auxiliary field {}: {}",
+                        String::from_utf8_lossy(&name),
+                        String::from_utf8_lossy(&value)
+                    );
+                }
+                OPCODE_EXPIRETIME => expires_at = Some(decoder.u32()? as u64 * 1000),
+                OPCODE_EXPIRETIME_MS => expires_at = Some(decoder.millis()?),
+                OPCODE_IDLE => {
+                    decoder.len()?;
+                }
+                OPCODE_FREQ => {
+                    decoder.u8()?;
+                }
+                OPCODE_MODULE_AUX => bail!("module auxiliary data is not supported"),
+                OPCODE_FUNCTION | OPCODE_FUNCTION_PRE_GA => bail!("functions are not supported"),
+                value_type => {
+                    let key = decoder.string()?;
+                    let key = String::from_utf8(key.to_vec())
+                        .map_err(|_| anyhow!("key {:?} is not valid UTF-8", key))?;
+                    let value = decoder
+                        .value(value_type)
+                        .map_err(|e| anyhow!("value of key '{}': {}", key, e))?;
+                    items.push(SnapshotItem {
+                        key,
+                        value,
+                        expires_at: expires_at.take(),
+                    });
+                }
+            }
+        }
+
+        if version >= CHECKSUM_VERSION {
+            let end = data.len() - decoder.data.len();
+            let checksum = decoder.take(8)?.get_u64_le();
+            // Zero if the checksum was disabled (`rdbchecksum no`)
+            if checksum != 0 && checksum != crc64(&data[..end]) {
+                bail!("wrong RDB checksum");
+            }
+        }
+        if !decoder.data.is_empty() {
+            bail!("unexpected data after the end of the RDB file");
+        }
+        Ok(Self { items })
+    }
+}
+
+impl Value {
+    /// Serialize the value in the format of `DUMP`, i.e., the RDB type and encoding
+    /// of the value followed by the RDB version (2 bytes) and the CRC64 of both (8 bytes).
+    pub(super) fn dump(&self) -> Bytes {
+        let mut encoder = RdbEncoder::default();
+        encoder.value_type(self);
+        encoder.value(self);
+        encoder.buf.put_u16_le(VERSION as u16);
+        let checksum = crc64(&encoder.buf);
+        encoder.buf.put_u64_le(checksum);
+        encoder.buf.freeze()
+    }
+
+    /// Deserialize a `DUMP` payload of RDB versions up to 12, i.e., Redis 7.4.
+    pub(super) fn restore(payload: &[u8]) -> Result<Self, RedisDatabaseError> {
+        if payload.len() < 10 {
+            return Err(RedisDatabaseError::DumpPayload);
+        }
+        let (data, mut footer) = payload.split_at(payload.len() - 10);
+        let version = footer.get_u16_le();
+        let checksum = footer.get_u64_le();
+        // Zero if the checksum was disabled (`rdbchecksum no`)
+        let checksum_valid = checksum == 0 || checksum == crc64(&payload[..payload.len() - 8]);
+        if version as u32 > MAX_VERSION || !checksum_valid {
+            return Err(RedisDatabaseError::DumpPayload);
+        }
+        let mut decoder = RdbDecoder { data };
+        let value = decoder
+            .u8()
+            .and_then(|value_type| decoder.value(value_type))
+            .map_err(|e| {
+                log::debug!("Invalid DUMP payload: {}", e);
+                RedisDatabaseError::BadDataFormat
+            })?;
+        if !decoder.data.is_empty() {
+            return Err(RedisDatabaseError::BadDataFormat);
+        }
+        Ok(value)
+    }
+}
+
+#[derive(Default)]
+pub(super) struct RdbEncoder {
+    buf: BytesMut,
+}
+
+impl RdbEncoder {
+    /// Length with the variable-size encoding, the two most significant bits
+    /// of the first byte determine its size.
+    pub(super) fn len(&mut self, len: u64) {
+        if len < 1 << 6 {
+            self.buf.put_u8(len as u8);
+        } else if len < 1 << 14 {
+            self.buf.put_u16(0x4000 | len as u16);
+        } else if len <= u32::MAX as u64 {
+            self.buf.put_u8(0x80);
+            self.buf.put_u32(len as u32);
+        } else {
+            self.buf.put_u8(0x81);
+            self.buf.put_u64(len);
+        }
+    }
+
+    pub(super) fn string(&mut self, value: &[u8]) {
+        self.len(value.len() as u64);
+        self.buf.put_slice(value);
+    }
+
+    /// Unix time in milliseconds.
+    pub(super) fn millis(&mut self, ms: u64) {
+        self.buf.put_u64_le(ms);
+    }
+
+    /// Stream ID as 16 big-endian bytes, the key of the radix tree in Redis.
+    pub(super) fn raw_stream_id(&mut self, id: StreamId) {
+        self.buf.put_u64(id.ms);
+        self.buf.put_u64(id.seq);
+    }
+
+    fn value_type(&mut self, value: &Value) {
+        self.buf.put_u8(match value {
+            Value::String(_) => TYPE_STRING,
+            Value::List(_) => TYPE_LIST,
+            Value::Hash(_) => TYPE_HASH,
+            Value::Set(_) => TYPE_SET,
+            Value::SortedSet(_) => TYPE_ZSET_2,
+            Value::Stream(_) => TYPE_STREAM_LISTPACKS,
+        });
+    }
+
+    fn value(&mut self, value: &Value) {
+        match value {
+            Value::String(value) => self.string(value),
+            Value::List(list) => {
+                self.len(list.len() as u64);
+                list.iter().for_each(|value| self.string(value));
+            }
+            Value::Hash(hash) => {
+                self.len(hash.len() as u64);
+                for (field, value) in hash {
+                    self.string(field);
+                    self.string(value);
+                }
+            }
+            Value::Set(set) => {
+                self.len(set.len() as u64);
+                set.iter().for_each(|member| self.string(member));
+            }
+            Value::SortedSet(sorted_set) => {
+                self.len(sorted_set.len() as u64);
+                for (member, score) in sorted_set.iter() {
+                    self.string(member);
+                    self.buf.put_f64_le(score);
+                }
+            }
+            Value::Stream(stream) => self.stream(stream),
+        }
+    }
+
+    fn stream(&mut self, stream: &Stream) {
+        let entries: Vec<_> = stream.entries.iter().collect();
+        let nodes = entries.chunks(STREAM_NODE_MAX_ENTRIES);
+        self.len(nodes.len() as u64);
+        for node in nodes {
+            let (&master_id, master_fields) = node[0];
+            let master_fields: Vec<&Bytes> = master_fields.iter().map(|(field, _)| field).collect();
+            let mut listpack = Listpack::default();
+            listpack.integer(node.len() as i64);
+            listpack.integer(0); // deleted entries
+            listpack.integer(master_fields.len() as i64);
+            master_fields
+                .iter()
+                .for_each(|field| listpack.string(field));
+            listpack.integer(0);
+            for (id, fields) in node {
+                let same_fields = fields.len() == master_fields.len()
+                    && fields
+                        .iter()
+                        .zip(&master_fields)
+                        .all(|((field, _), master_field)| field == *master_field);
+                let flags = if same_fields {
+                    STREAM_ITEM_FLAG_SAMEFIELDS
+                } else {
+                    0
+                };
+                listpack.integer(flags);
+                listpack.integer(id.ms.wrapping_sub(master_id.ms) as i64);
+                listpack.integer(id.seq.wrapping_sub(master_id.seq) as i64);
+                let mut lp_count = fields.len() + 3;
+                if same_fields {
+                    fields.iter().for_each(|(_, value)| listpack.string(value));
+                } else {
+                    listpack.integer(fields.len() as i64);
+                    for (field, value) in fields.iter() {
+                        listpack.string(field);
+                        listpack.string(value);
+                    }
+                    lp_count += fields.len() + 1;
+                }
+                listpack.integer(lp_count as i64);
+            }
+            let mut key = RdbEncoder::default();
+            key.raw_stream_id(master_id);
+            self.string(&key.buf);
+            self.string(&listpack.finish());
+        }
+        self.len(stream.len() as u64);
+        self.len(stream.last_id.ms);
+        self.len(stream.last_id.seq);
+        self.len(stream.groups.len() as u64);
+        for (name, group) in &stream.groups {
+            self.string(name.as_bytes());
+            group.encode_rdb(self);
+        }
+    }
+}
+
+/// Length or the special encoding of a string.
+enum Length {
+    Len(u64),
+    Encoded(u8),
+}
+
+pub(super) struct RdbDecoder<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> RdbDecoder<'a> {
+    fn take(&mut self, len: usize) -> anyhow::Result<&'a [u8]> {
+        if self.data.len() < len {
+            bail!("unexpected end of the RDB file");
+        }
+        let (value, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(value)
+    }
+
+    fn u8(&mut self) -> anyhow::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> anyhow::Result<u32> {
+        Ok(self.take(4)?.get_u32_le())
+    }
+
+    /// Unix time in milliseconds.
+    pub(super) fn millis(&mut self) -> anyhow::Result<u64> {
+        Ok(self.take(8)?.get_u64_le())
+    }
+
+    fn length(&mut self) -> anyhow::Result<Length> {
+        let first = self.u8()?;
+        let length = match first >> 6 {
+            0 => Length::Len((first & 0x3F) as u64),
+            1 => Length::Len(((first & 0x3F) as u64) << 8 | self.u8()? as u64),
+            2 => match first {
+                0x80 => Length::Len(self.take(4)?.get_u32() as u64),
+                0x81 => Length::Len(self.take(8)?.get_u64()),
+                _ => bail!("invalid length encoding {:#x}", first),
+            },
+            _ => Length::Encoded(first & 0x3F),
+        };
+        Ok(length)
+    }
+
+    pub(super) fn len(&mut self) -> anyhow::Result<u64> {
+        match self.length()? {
+            Length::Len(len) => Ok(len),
+            Length::Encoded(_) => bail!("expected a length, got an encoded string"),
+        }
+    }
+
+    /// Number of elements of a collection. Checked against the remaining data,
+    /// so that a corrupted length does not cause a huge allocation.
+    pub(super) fn count(&mut self) -> anyhow::Result<usize> {
+        let count = self.len()?;
+        if count > self.data.len() as u64 {
+            bail!("invalid length {} in the RDB file", count);
+        }
+        Ok(count as usize)
+    }
+
+    /// String, possibly encoded as an integer or compressed by LZF.
+    pub(super) fn string(&mut self) -> anyhow::Result<Bytes> {
+        let value = match self.length()? {
+            Length::Len(len) => {
+                let len = usize::try_from(len)?;
+                Bytes::copy_from_slice(self.take(len)?)
+            }
+            Length::Encoded(ENC_INT8) => Bytes::from((self.u8()? as i8).to_string()),
+            Length::Encoded(ENC_INT16) => Bytes::from(self.take(2)?.get_i16_le().to_string()),
+            Length::Encoded(ENC_INT32) => Bytes::from(self.take(4)?.get_i32_le().to_string()),
+            Length::Encoded(ENC_LZF) => {
+                let compressed_len = usize::try_from(self.len()?)?;
+                let len = usize::try_from(self.len()?)?;
+                if len > PROTO_MAX_BULK_LEN {
+                    bail!("invalid LZF length {} in the RDB file", len);
+                }
+                Bytes::from(lzf_decompress(self.take(compressed_len)?, len)?)
+            }
+            Length::Encoded(encoding) => bail!("unknown string encoding {}", encoding),
+        };
+        Ok(value)
+    }
+
+    /// Stream ID as 16 big-endian bytes.
+    pub(super) fn raw_stream_id(&mut self) -> anyhow::Result<StreamId> {
+        let mut raw = self.take(16)?;
+        Ok(StreamId::new(raw.get_u64(), raw.get_u64()))
+    }
+
+    /// Stream ID as two lengths.
+    pub(super) fn stream_id(&mut self) -> anyhow::Result<StreamId> {
+        Ok(StreamId::new(self.len()?, self.len()?))
+    }
+
+    /// Score of `TYPE_ZSET`, a string with a length of one byte
+    /// or a special value for NaN and infinities.
+    fn string_score(&mut self) -> anyhow::Result<f64> {
+        match self.u8()? {
+            253 => bail!("invalid score NaN"),
+            254 => Ok(f64::INFINITY),
+            255 => Ok(f64::NEG_INFINITY),
+            len => parse_score(self.take(len as usize)?),
+        }
+    }
+
+    fn value(&mut self, value_type: u8) -> anyhow::Result<Value> {
+        let value = match value_type {
+            TYPE_STRING => Value::String(self.string()?),
+            TYPE_LIST => {
+                let len = self.count()?;
+                let mut list = VecDeque::with_capacity(len);
+                for _ in 0..len {
+                    list.push_back(self.string()?);
+                }
+                Value::List(list)
+            }
+            TYPE_LIST_ZIPLIST => Value::List(ziplist(&self.string()?)?.into()),
+            TYPE_LIST_QUICKLIST | TYPE_LIST_QUICKLIST_2 => {
+                let mut list = VecDeque::new();
+                for _ in 0..self.count()? {
+                    if value_type == TYPE_LIST_QUICKLIST {
+                        list.extend(ziplist(&self.string()?)?);
+                        continue;
+                    }
+                    match self.len()? {
+                        QUICKLIST_NODE_PLAIN => list.push_back(self.string()?),
+                        QUICKLIST_NODE_PACKED => list.extend(listpack(&self.string()?)?),
+                        container => bail!("unknown quicklist container {}", container),
+                    }
+                }
+                Value::List(list)
+            }
+            TYPE_SET => {
+                let len = self.count()?;
+                let mut set = HashSet::with_capacity(len);
+                for _ in 0..len {
+                    set.insert(self.string()?);
+                }
+                Value::Set(set)
+            }
+            TYPE_SET_INTSET => Value::Set(intset(&self.string()?)?.into_iter().collect()),
+            TYPE_SET_LISTPACK => Value::Set(listpack(&self.string()?)?.into_iter().collect()),
+            TYPE_ZSET | TYPE_ZSET_2 => {
+                let mut sorted_set = SortedSet::default();
+                for _ in 0..self.count()? {
+                    let member = self.string()?;
+                    let score = if value_type == TYPE_ZSET {
+                        self.string_score()?
+                    } else {
+                        self.take(8)?.get_f64_le()
+                    };
+                    sorted_set.insert(member, score);
+                }
+                Value::SortedSet(sorted_set)
+            }
+            TYPE_ZSET_ZIPLIST | TYPE_ZSET_LISTPACK => {
+                let elements = if value_type == TYPE_ZSET_ZIPLIST {
+                    ziplist(&self.string()?)?
+                } else {
+                    listpack(&self.string()?)?
+                };
+                let mut sorted_set = SortedSet::default();
+                for (member, score) in pairs(elements)? {
+                    sorted_set.insert(member, parse_score(&score)?);
+                }
+                Value::SortedSet(sorted_set)
+            }
+            TYPE_HASH => {
+                let len = self.count()?;
+                let mut hash = HashMap::with_capacity(len);
+                for _ in 0..len {
+                    hash.insert(self.string()?, self.string()?);
+                }
+                Value::Hash(hash)
+            }
+            TYPE_HASH_ZIPMAP => Value::Hash(zipmap(&self.string()?)?.into_iter().collect()),
+            TYPE_HASH_ZIPLIST => Value::Hash(pairs(ziplist(&self.string()?)?)?.collect()),
+            TYPE_HASH_LISTPACK => Value::Hash(pairs(listpack(&self.string()?)?)?.collect()),
+            TYPE_STREAM_LISTPACKS => Value::Stream(self.stream(1)?),
+            TYPE_STREAM_LISTPACKS_2 => Value::Stream(self.stream(2)?),
+            TYPE_STREAM_LISTPACKS_3 => Value::Stream(self.stream(3)?),
+            TYPE_MODULE_PRE_GA | TYPE_MODULE_2 => bail!("module values are not supported"),
+            value_type if TYPE_HASH_FIELD_EXPIRATION.contains(&value_type) => {
+                bail!("hashes with field expiration are not supported")
+            }
+            _ => bail!("unknown RDB value type {}", value_type),
+        };
+        Ok(value)
+    }
+
+    /// Stream stored as listpacks. Version 2 (Redis 7.0) adds the first ID, the greatest
+    /// deleted ID and the number of added entries, which are ignored, and version 3 (Redis 7.2)
+    /// adds the active time of consumers.
+    fn stream(&mut self, version: u8) -> anyhow::Result<Stream> {
+        let mut stream = Stream::default();
+        for _ in 0..self.count()? {
+            let master_key = self.string()?;
+            if master_key.len() != 16 {
+                bail!("invalid stream listpack key");
+            }
+            let master_id = RdbDecoder { data: &master_key }.raw_stream_id()?;
+            stream_listpack(master_id, listpack(&self.string()?)?, &mut stream.entries)?;
+        }
+        let len = self.len()?;
+        if len != stream.len() as u64 {
+            bail!(
+                "stream length {} does not match its {} entries",
+                len,
+                stream.len()
+            );
+        }
+        stream.last_id = self.stream_id()?;
+        if version >= 2 {
+            self.stream_id()?; // first ID
+            self.stream_id()?; // greatest deleted ID
+            self.len()?; // added entries
+        }
+        for _ in 0..self.count()? {
+            let name = String::from_utf8(self.string()?.to_vec())
+                .map_err(|_| anyhow!("consumer group name is not valid UTF-8"))?;
+            let group = ConsumerGroup::decode_rdb(self, version)?;
+            stream.groups.insert(name, group);
+        }
+        Ok(stream)
+    }
+}
+
+/// Parse a decimal score, e.g., of a listpack.
+fn parse_score(score: &[u8]) -> anyhow::Result<f64> {
+    std::str::from_utf8(score)
+        .ok()
+        .and_then(|score| score.parse::<f64>().ok())
+        .filter(|score| !score.is_nan())
+        .ok_or_else(|| anyhow!("invalid score {:?}", Bytes::copy_from_slice(score)))
+}
+
+fn parse_integer(value: &[u8]) -> anyhow::Result<i64> {
+    atoi::<i64>(value).ok_or_else(|| anyhow!("invalid integer {:?}", Bytes::copy_from_slice(value)))
+}
+
+/// Consecutive elements as pairs, e.g., fields and values of a hash.
+fn pairs(elements: Vec<Bytes>) -> anyhow::Result<impl Iterator<Item = (Bytes, Bytes)>> {
+    if !elements.len().is_multiple_of(2) {
+        bail!("odd number of elements");
+    }
+    let mut elements = elements.into_iter();
+    Ok(std::iter::from_fn(move || {
+        Some((elements.next()?, elements.next()?))
+    }))
+}
+
+/// Decompress LZF data with the expected length of the output.
+///
+/// Each chunk starts with a control byte, values below 32 are the length of a literal
+/// run minus one, otherwise the chunk is a back reference to the output.
+fn lzf_decompress(input: &[u8], len: usize) -> anyhow::Result<Vec<u8>> {
+    let invalid = || anyhow!("invalid LZF data");
+    let mut output = Vec::with_capacity(len.min(MAX_PREALLOCATED_ELEMENTS));
+    let mut input = input.iter().copied();
+    while let Some(ctrl) = input.next() {
+        let ctrl = ctrl as usize;
+        if ctrl < 32 {
+            for _ in 0..=ctrl {
+                output.push(input.next().ok_or_else(invalid)?);
+            }
+        } else {
+            let mut run = ctrl >> 5;
+            if run == 7 {
+                run += input.next().ok_or_else(invalid)? as usize;
+            }
+            run += 2;
+            let offset = ((ctrl & 0x1F) << 8 | input.next().ok_or_else(invalid)? as usize) + 1;
+            let start = output.len().checked_sub(offset).ok_or_else(invalid)?;
+            // The reference may overlap the output it produces
+            for i in start..start + run {
+                output.push(output[i]);
+            }
+        }
+        if output.len() > len {
+            return Err(invalid());
+        }
+    }
+    if output.len() != len {
+        return Err(invalid());
+    }
+    Ok(output)
+}
+
+/// Elements of a ziplist, the compact encoding of small collections before Redis 7.0.
+fn ziplist(data: &[u8]) -> anyhow::Result<Vec<Bytes>> {
+    let mut decoder = RdbDecoder { data };
+    decoder.take(10)?; // total bytes, offset of the tail and number of elements
+    let mut elements = Vec::new();
+    loop {
+        match decoder.u8()? {
+            0xFF => break,
+            // Length of the previous entry does not fit into one byte
+            0xFE => {
+                decoder.take(4)?;
+            }
+            _ => {}
+        }
+        let encoding = decoder.u8()?;
+        let element = match encoding >> 6 {
+            0 => Bytes::copy_from_slice(decoder.take((encoding & 0x3F) as usize)?),
+            1 => {
+                let len = ((encoding & 0x3F) as usize) << 8 | decoder.u8()? as usize;
+                Bytes::copy_from_slice(decoder.take(len)?)
+            }
+            2 => {
+                let len = decoder.take(4)?.get_u32() as usize;
+                Bytes::copy_from_slice(decoder.take(len)?)
+            }
+            _ => {
+                let value = match encoding {
+                    0xC0 => decoder.take(2)?.get_i16_le() as i64,
+                    0xD0 => decoder.take(4)?.get_i32_le() as i64,
+                    0xE0 => decoder.take(8)?.get_i64_le(),
+                    0xF0 => decoder.take(3)?.get_int_le(3) << 40 >> 40,
+                    0xFE => decoder.u8()? as i8 as i64,
+                    0xF1..=0xFD => (encoding & 0x0F) as i64 - 1,
+                    _ => bail!("invalid ziplist encoding {:#x}", encoding),
+                };
+                Bytes::from(value.to_string())
+            }
+        };
+        elements.push(element);
+    }
+    Ok(elements)
+}
+
+/// Size of the length of a listpack entry stored after it, so that it can be traversed backwards.
+fn backlen_size(len: usize) -> usize {
+    match len {
+        0..=127 => 1,
+        128..=16382 => 2,
+        16383..=2097150 => 3,
+        2097151..=268435454 => 4,
+        _ => 5,
+    }
+}
+
+/// Elements of a listpack, the compact encoding of small collections since Redis 7.0.
+fn listpack(data: &[u8]) -> anyhow::Result<Vec<Bytes>> {
+    let mut decoder = RdbDecoder { data };
+    decoder.take(6)?; // total bytes and number of elements
+    let mut elements = Vec::new();
+    loop {
+        let encoding = decoder.u8()?;
+        let (element, len) = match encoding {
+            0xFF => break,
+            0x00..=0x7F => (Bytes::from(encoding.to_string()), 1),
+            0x80..=0xBF => {
+                let len = (encoding & 0x3F) as usize;
+                (Bytes::copy_from_slice(decoder.take(len)?), 1 + len)
+            }
+            0xC0..=0xDF => {
+                let value = ((encoding & 0x1F) as i64) << 8 | decoder.u8()? as i64;
+                // 13-bit two's complement
+                let value = if value >= 1 << 12 {
+                    value - (1 << 13)
+                } else {
+                    value
+                };
+                (Bytes::from(value.to_string()), 2)
+            }
+            0xE0..=0xEF => {
+                let len = ((encoding & 0x0F) as usize) << 8 | decoder.u8()? as usize;
+                (Bytes::copy_from_slice(decoder.take(len)?), 2 + len)
+            }
+            0xF0 => {
+                let len = decoder.u32()? as usize;
+                (Bytes::copy_from_slice(decoder.take(len)?), 5 + len)
+            }
+            0xF1..=0xF4 => {
+                let size = match encoding {
+                    0xF1 => 2,
+                    0xF2 => 3,
+                    0xF3 => 4,
+                    _ => 8,
+                };
+                let value = decoder.take(size)?.get_int_le(size);
+                let shift = 64 - 8 * size as u32;
+                let value = value << shift >> shift;
+                (Bytes::from(value.to_string()), 1 + size)
+            }
+            _ => bail!("invalid listpack encoding {:#x}", encoding),
+        };
+        decoder.take(backlen_size(len))?;
+        elements.push(element);
+    }
+    Ok(elements)
+}
+
+/// Members of an intset, i.e., a sorted array of integers of the same size.
+fn intset(data: &[u8]) -> anyhow::Result<Vec<Bytes>> {
+    let mut decoder = RdbDecoder { data };
+    let size = decoder.u32()? as usize;
+    if ![2, 4, 8].contains(&size) {
+        bail!("invalid intset encoding {}", size);
+    }
+    let len = decoder.u32()?;
+    (0..len)
+        .map(|_| {
+            let value = decoder.take(size)?.get_int_le(size);
+            let shift = 64 - 8 * size as u32;
+            Ok(Bytes::from((value << shift >> shift).to_string()))
+        })
+        .collect()
+}
+
+/// Fields and values of a zipmap, the compact encoding of small hashes before Redis 2.6.
+fn zipmap(data: &[u8]) -> anyhow::Result<Vec<(Bytes, Bytes)>> {
+    let mut decoder = RdbDecoder { data };
+    decoder.u8()?; // number of entries
+    let len = |decoder: &mut RdbDecoder, first: u8| -> anyhow::Result<usize> {
+        match first {
+            0..=253 => Ok(first as usize),
+            254 => Ok(decoder.u32()? as usize),
+            _ => bail!("invalid zipmap length"),
+        }
+    };
+    let mut entries = Vec::new();
+    loop {
+        let first = decoder.u8()?;
+        if first == 0xFF {
+            break;
+        }
+        let field_len = len(&mut decoder, first)?;
+        let field = Bytes::copy_from_slice(decoder.take(field_len)?);
+        let first = decoder.u8()?;
+        let value_len = len(&mut decoder, first)?;
+        let free = decoder.u8()? as usize;
+        let value = Bytes::copy_from_slice(decoder.take(value_len)?);
+        decoder.take(free)?;
+        entries.push((field, value));
+    }
+    Ok(entries)
+}
+
+/// Add the entries of a stream listpack. The listpack starts with a master entry
+/// with the field names of the first entry, entries with the same fields only store
+/// their values. IDs are stored relative to `master_id`.
+fn stream_listpack(
+    master_id: StreamId,
+    elements: Vec<Bytes>,
+    entries: &mut BTreeMap<StreamId, Vec<(Bytes, Bytes)>>,
+) -> anyhow::Result<()> {
+    let mut elements = elements.into_iter();
+    let mut next = || {
+        elements
+            .next()
+            .ok_or_else(|| anyhow!("unexpected end of a stream listpack"))
+    };
+    let count = parse_integer(&next()?)? + parse_integer(&next()?)?;
+    let master_fields = (0..parse_integer(&next()?)?)
+        .map(|_| next())
+        .collect::<anyhow::Result<Vec<_>>>()?;
+    next()?; // end of the master entry
+    for _ in 0..count {
+        let flags = parse_integer(&next()?)?;
+        let id = StreamId::new(
+            master_id.ms.wrapping_add(parse_integer(&next()?)? as u64),
+            master_id.seq.wrapping_add(parse_integer(&next()?)? as u64),
+        );
+        let fields = if flags & STREAM_ITEM_FLAG_SAMEFIELDS != 0 {
+            master_fields
+                .iter()
+                .map(|field| Ok((field.clone(), next()?)))
+                .collect::<anyhow::Result<Vec<_>>>()?
+        } else {
+            (0..parse_integer(&next()?)?)
+                .map(|_| Ok((next()?, next()?)))
+                .collect::<anyhow::Result<Vec<_>>>()?
+        };
+        next()?; // number of elements of the entry
+        if flags & STREAM_ITEM_FLAG_DELETED == 0 {
+            entries.insert(id, fields);
+        }
+    }
+    Ok(())
+}
+
+/// Listpack with the encodings Redis uses for new elements.
+#[derive(Default)]
+struct Listpack {
+    buf: Vec<u8>,
+    len: usize,
+}
+
+impl Listpack {
+    fn push(&mut self, entry: &[u8]) {
+        self.buf.extend_from_slice(entry);
+        let len = entry.len();
+        let backlen: Vec<u8> = match backlen_size(len) {
+            1 => vec![len as u8],
+            size => (0..size)
+                .rev()
+                .map(|i| {
+                    let byte = (len >> (7 * i)) as u8 & 127;
+                    // Every byte but the most significant one has the continuation bit
+                    if i == size - 1 {
+                        byte
+                    } else {
+                        byte | 128
+                    }
+                })
+                .collect(),
+        };
+        self.buf.extend_from_slice(&backlen);
+        self.len += 1;
+    }
+
+    fn integer(&mut self, value: i64) {
+        let mut entry = Vec::with_capacity(9);
+        match value {
+            0..=127 => entry.push(value as u8),
+            -4096..=4095 => {
+                let value = (value as u16) & 0x1FFF;
+                entry.extend_from_slice(&[0xC0 | (value >> 8) as u8, value as u8]);
+            }
+            -32768..=32767 => {
+                entry.push(0xF1);
+                entry.extend_from_slice(&(value as i16).to_le_bytes());
+            }
+            -8388608..=8388607 => {
+                entry.push(0xF2);
+                entry.extend_from_slice(&(value as i32).to_le_bytes()[..3]);
+            }
+            value if i32::try_from(value).is_ok() => {
+                entry.push(0xF3);
+                entry.extend_from_slice(&(value as i32).to_le_bytes());
+            }
+            _ => {
+                entry.push(0xF4);
+                entry.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+        self.push(&entry);
+    }
+
+    fn string(&mut self, value: &[u8]) {
+        let len = value.len();
+        let mut entry = Vec::with_capacity(len + 5);
+        if len < 1 << 6 {
+            entry.push(0x80 | len as u8);
+        } else if len < 1 << 12 {
+            entry.extend_from_slice(&[0xE0 | (len >> 8) as u8, len as u8]);
+        } else {
+            entry.push(0xF0);
+            entry.extend_from_slice(&(len as u32).to_le_bytes());
+        }
+        entry.extend_from_slice(value);
+        self.push(&entry);
+    }
+
+    /// Add the header with the total size and the number of elements,
+    /// which saturates at `u16::MAX`, and the end marker.
+    fn finish(self) -> Vec<u8> {
+        let mut listpack = Vec::with_capacity(self.buf.len() + 7);
+        listpack.extend_from_slice(&(self.buf.len() as u32 + 7).to_le_bytes());
+        listpack.extend_from_slice(&(self.len.min(u16::MAX as usize) as u16).to_le_bytes());
+        listpack.extend_from_slice(&self.buf);
+        listpack.push(0xFF);
+        listpack
+    }
+}
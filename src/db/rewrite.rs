@@ -0,0 +1,112 @@
+use super::snapshot::Snapshot;
+use super::stream::{Stream, StreamId};
+use super::Value;
+use crate::frame::{format_double, Frame};
+use crate::{array, bulk};
+
+/// Maximum number of elements, fields or members of a single rewritten command,
+/// so that replaying a large collection does not build a huge frame.
+const ITEMS_PER_COMMAND: usize = 64;
+
+/// Commands of the form `NAME key item...` with at most `ITEMS_PER_COMMAND` items each.
+/// An item may consist of multiple arguments, e.g., a field and its value.
+fn batched<I>(name: &str, key: &str, items: I) -> Vec<Frame>
+where
+    I: IntoIterator<Item = Vec<Frame>>,
+{
+    let mut commands = Vec::new();
+    let mut items = items.into_iter().peekable();
+    while items.peek().is_some() {
+        let mut frames = vec![bulk!(name), bulk!(key)];
+        for item in items.by_ref().take(ITEMS_PER_COMMAND) {
+            frames.extend(item);
+        }
+        commands.push(Frame::Array(frames));
+    }
+    commands
+}
+
+fn stream_commands(key: &str, stream: &Stream, commands: &mut Vec<Frame>) {
+    if stream.entries.is_empty() {
+        // An empty stream can only be created by adding an entry and trimming it right away
+        let id = stream.last_id.max(StreamId::new(0, 1));
+        commands.push(array!(
+            bulk!("XADD"),
+            bulk!(key),
+            bulk!("MAXLEN"),
+            bulk!("0"),
+            bulk!(id.to_string()),
+            bulk!(""),
+            bulk!("")
+        ));
+    }
+    for (id, fields) in &stream.entries {
+        let mut frames = vec![bulk!("XADD"), bulk!(key), bulk!(id.to_string())];
+        for (field, value) in fields {
+            frames.push(bulk!(field.clone()));
+            frames.push(bulk!(value.clone()));
+        }
+        commands.push(Frame::Array(frames));
+    }
+    // Entries with greater IDs may have been deleted
+    if stream.entries.last_key_value().map(|(id, _)| *id) != Some(stream.last_id) {
+        commands.push(array!(
+            bulk!("XSETID"),
+            bulk!(key),
+            bulk!(stream.last_id.to_string())
+        ));
+    }
+    for (name, group) in &stream.groups {
+        group.rewrite(key, name, &stream.entries, commands);
+    }
+}
+
+impl Snapshot {
+    /// Get the shortest sequence of commands that rebuilds the data of the snapshot,
+    /// used to rewrite the append-only file (`BGREWRITEAOF`).
+    ///
+    /// Expirations are written as absolute Unix times (`PEXPIREAT`).
+    pub(crate) fn rewrite_commands(&self) -> Vec<Frame> {
+        let mut commands = Vec::new();
+        for item in &self.items {
+            let key = item.key.as_str();
+            match &item.value {
+                Value::String(value) => {
+                    commands.push(array!(bulk!("SET"), bulk!(key), bulk!(value.clone())))
+                }
+                Value::List(list) => commands.extend(batched(
+                    "RPUSH",
+                    key,
+                    list.iter().map(|value| vec![bulk!(value.clone())]),
+                )),
+                Value::Hash(hash) => commands.extend(batched(
+                    "HSET",
+                    key,
+                    hash.iter()
+                        .map(|(field, value)| vec![bulk!(field.clone()), bulk!(value.clone())]),
+                )),
+                Value::Set(set) => commands.extend(batched(
+                    "SADD",
+                    key,
+                    set.iter().map(|member| vec![bulk!(member.clone())]),
+                )),
+                Value::SortedSet(sorted_set) => commands.extend(batched(
+                    "ZADD",
+                    key,
+                    sorted_set.iter().map(|(member, score)| {
+                        vec![bulk!(format_double(score)), bulk!(member.clone())]
+                    }),
+                )),
+                Value::Stream(stream) => stream_commands(key, stream, &mut commands),
+            }
+            if let Some(expires_at) = item.expires_at {
+                commands.push(array!(
+                    bulk!("PEXPIREAT"),
+                    bulk!(key),
+                    bulk!(expires_at.to_string())
+                ));
+            }
+        }
+        commands
+    }
+}
@@ -0,0 +1,227 @@
+use std::collections::HashSet;
+
+use bytes::Bytes;
+use rand::seq::IteratorRandom;
+use rand::Rng;
+
+use super::{get_live_mut, get_or_insert_mut, DBItem, Store, Value, DB};
+use crate::constants::MAX_PREALLOCATED_ELEMENTS;
+use crate::err::RedisDatabaseError;
+
+/// Set algebra operation used by `SINTER`, `SUNION` and `SDIFF` (and their `*STORE` forms).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SetOperation {
+    Inter,
+    Union,
+    Diff,
+}
+
+impl Value {
+    fn as_set(&self) -> Result<&HashSet<Bytes>, RedisDatabaseError> {
+        match self {
+            Value::Set(set) => Ok(set),
+            _ => Err(RedisDatabaseError::WrongType),
+        }
+    }
+
+    fn as_set_mut(&mut self) -> Result<&mut HashSet<Bytes>, RedisDatabaseError> {
+        match self {
+            Value::Set(set) => Ok(set),
+            _ => Err(RedisDatabaseError::WrongType),
+        }
+    }
+}
+
+/// Compute the result of a set operation over `keys`. Missing keys are treated as empty sets.
+fn compute_set_operation(
+    data_store: &mut Store,
+    operation: SetOperation,
+    keys: &[String],
+) -> Result<HashSet<Bytes>, RedisDatabaseError> {
+    // Check the keys first, so that expired keys are removed
+    // and a wrong type is reported for any of the keys.
+    for key in keys {
+        if let Some(item) = get_live_mut(data_store, key) {
+            item.value.as_set()?;
+        }
+    }
+    let empty = HashSet::new();
+    let sets: Vec<&HashSet<Bytes>> = keys
+        .iter()
+        .map(|key| match data_store.get(key) {
+            Some(item) => item.value.as_set(),
+            None => Ok(&empty),
+        })
+        .collect::<Result<_, _>>()?;
+
+    let Some((first, others)) = sets.split_first() else {
+        return Ok(HashSet::new());
+    };
+    Ok(match operation {
+        SetOperation::Inter => {
+            // Only the members of the smallest set are checked
+            let smallest = sets.iter().min_by_key(|set| set.len()).unwrap();
+            smallest
+                .iter()
+                .filter(|member| sets.iter().all(|set| set.contains(*member)))
+                .cloned()
+                .collect()
+        }
+        SetOperation::Union => sets.iter().flat_map(|set| set.iter()).cloned().collect(),
+        SetOperation::Diff => first
+            .iter()
+            .filter(|member| !others.iter().any(|set| set.contains(*member)))
+            .cloned()
+            .collect(),
+    })
+}
+
+impl DB {
+    /// Add `members` to the set stored at `key`. The set is created if it does not exist.
+    ///
+    /// Returns the number of members that were added.
+    pub async fn sadd(&self, key: &str, members: Vec<Bytes>) -> anyhow::Result<usize> {
+        let mut data_store = self.lock_data().await;
+        let set = get_or_insert_mut(&mut data_store, key, || Value::Set(HashSet::new()))
+            .value
+            .as_set_mut()?;
+        Ok(members
+            .into_iter()
+            .filter(|member| set.insert(member.clone()))
+            .count())
+    }
+
+    /// Remove `members` from the set stored at `key`. Empty sets are removed from the database.
+    ///
+    /// Returns the number of removed members.
+    pub async fn srem(&self, key: &str, members: &[Bytes]) -> anyhow::Result<usize> {
+        let mut data_store = self.lock_data().await;
+        let Some(item) = get_live_mut(&mut data_store, key) else {
+            return Ok(0);
+        };
+        let set = item.value.as_set_mut()?;
+        let removed = members.iter().filter(|member| set.remove(*member)).count();
+        if set.is_empty() {
+            data_store.remove(key);
+        }
+        Ok(removed)
+    }
+
+    /// Get all members of the set stored at `key`.
+    pub async fn smembers(&self, key: &str) -> anyhow::Result<Vec<Bytes>> {
+        let mut data_store = self.lock_data().await;
+        match get_live_mut(&mut data_store, key) {
+            Some(item) => Ok(item.value.as_set()?.iter().cloned().collect()),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Check if `member` is a member of the set stored at `key`.
+    pub async fn sismember(&self, key: &str, member: &Bytes) -> anyhow::Result<bool> {
+        let mut data_store = self.lock_data().await;
+        match get_live_mut(&mut data_store, key) {
+            Some(item) => Ok(item.value.as_set()?.contains(member)),
+            None => Ok(false),
+        }
+    }
+
+    /// Get the number of members of the set stored at `key`.
+    pub async fn scard(&self, key: &str) -> anyhow::Result<usize> {
+        let mut data_store = self.lock_data().await;
+        match get_live_mut(&mut data_store, key) {
+            Some(item) => Ok(item.value.as_set()?.len()),
+            None => Ok(0),
+        }
+    }
+
+    /// Remove and return up to `count` random members of the set stored at `key`.
+    /// Empty sets are removed from the database.
+    pub async fn spop(&self, key: &str, count: usize) -> anyhow::Result<Vec<Bytes>> {
+        let mut data_store = self.lock_data().await;
+        let Some(item) = get_live_mut(&mut data_store, key) else {
+            return Ok(Vec::new());
+        };
+        let set = item.value.as_set_mut()?;
+        if count >= set.len() {
+            let members = std::mem::take(set).into_iter().collect();
+            data_store.remove(key);
+            return Ok(members);
+        }
+        // The set has no random access, the members are sampled in a single pass
+        let members: Vec<Bytes> = set
+            .iter()
+            .choose_multiple(&mut rand::thread_rng(), count)
+            .into_iter()
+            .cloned()
+            .collect();
+        for member in &members {
+            set.remove(member);
+        }
+        Ok(members)
+    }
+
+    /// Get random members of the set stored at `key`.
+    ///
+    /// - `count > 0`: Return up to `count` distinct members.
+    /// - `count < 0`: Return exactly `|count|` members that may repeat.
+    pub async fn srandmember(&self, key: &str, count: i64) -> anyhow::Result<Vec<Bytes>> {
+        let mut data_store = self.lock_data().await;
+        let Some(item) = get_live_mut(&mut data_store, key) else {
+            return Ok(Vec::new());
+        };
+        let set = item.value.as_set()?;
+        let mut rng = rand::thread_rng();
+        if count >= 0 {
+            // `choose_multiple` preallocates `count` slots
+            let count = (count as usize).min(set.len());
+            return Ok(set
+                .iter()
+                .choose_multiple(&mut rng, count)
+                .into_iter()
+                .cloned()
+                .collect());
+        }
+        // Members that may repeat are drawn from a single copy of the set
+        let members: Vec<&Bytes> = set.iter().collect();
+        let count = count.unsigned_abs() as usize;
+        let mut random = Vec::with_capacity(count.min(MAX_PREALLOCATED_ELEMENTS));
+        random.extend((0..count).map(|_| members[rng.gen_range(0..members.len())].clone()));
+        Ok(random)
+    }
+
+    /// Compute a set operation over `keys` under a single lock.
+    pub async fn set_operation(
+        &self,
+        operation: SetOperation,
+        keys: &[String],
+    ) -> anyhow::Result<Vec<Bytes>> {
+        let mut data_store = self.lock_data().await;
+        let result = compute_set_operation(&mut data_store, operation, keys)?;
+        Ok(result.into_iter().collect())
+    }
+
+    /// Compute a set operation over `keys` and store the result at `destination`
+    /// (overwriting any existing value) under a single lock.
+    /// An empty result removes the `destination` key.
+    ///
+    /// Returns the number of members of the resulting set.
+    pub async fn set_operation_store(
+        &self,
+        operation: SetOperation,
+        destination: &str,
+        keys: &[String],
+    ) -> anyhow::Result<usize> {
+        let mut data_store = self.lock_data().await;
+        let result = compute_set_operation(&mut data_store, operation, keys)?;
+        let len = result.len();
+        if result.is_empty() {
+            data_store.remove(destination);
+        } else {
+            data_store.insert(
+                destination.to_string(),
+                DBItem::new(Value::Set(result), None),
+            );
+        }
+        Ok(len)
+    }
+}
@@ -0,0 +1,369 @@
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::path::Path;
+use std::time::{Duration, Instant};
+
+use anyhow::{anyhow, bail};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+
+use super::consumer_group::ConsumerGroup;
+use super::rdb;
+use super::sorted_set::SortedSet;
+use super::stream::{now_ms, Stream, StreamId};
+use super::{DBItem, ExpirationEntry, Value, DB};
+
+/// Identifies snapshot files, followed by the format version.
+const MAGIC: &[u8] = b"RCSNAP";
+const VERSION: u8 = 1;
+
+const TYPE_STRING: u8 = 0;
+const TYPE_LIST: u8 = 1;
+const TYPE_HASH: u8 = 2;
+const TYPE_SET: u8 = 3;
+const TYPE_SORTED_SET: u8 = 4;
+const TYPE_STREAM: u8 = 5;
+const END: u8 = 0xFF;
+
+/// Key with its value and the absolute expiration time in Unix milliseconds.
+#[derive(Debug, Clone)]
+pub(super) struct SnapshotItem {
+    pub(super) key: String,
+    pub(super) value: Value,
+    pub(super) expires_at: Option<u64>,
+}
+
+/// Point-in-time copy of the database, see `DB::snapshot`.
+///
+/// Expirations are stored as absolute wall-clock times, because `Instant` is only meaningful
+/// within a single process. The binary format is:
+///
+/// ```text
+/// "RCSNAP" <version: u8>
+/// (<type: u8> <key> <expires at in ms: u64, 0 if none> <value>)*
+/// 0xFF
+/// ```
+///
+/// Integers are little-endian, strings are prefixed with their length as `u64`.
+#[derive(Debug, Clone, Default)]
+pub struct Snapshot {
+    pub(super) items: Vec<SnapshotItem>,
+}
+
+impl Snapshot {
+    /// Number of keys in the snapshot.
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+
+    /// Serialize the snapshot to the binary format.
+    pub fn encode(&self) -> Bytes {
+        let mut encoder = Encoder::default();
+        encoder.buf.put_slice(MAGIC);
+        encoder.buf.put_u8(VERSION);
+        for item in &self.items {
+            encoder.value_type(&item.value);
+            encoder.string(&item.key);
+            encoder.u64(item.expires_at.unwrap_or(0));
+            encoder.value(&item.value);
+        }
+        encoder.buf.put_u8(END);
+        encoder.buf.freeze()
+    }
+
+    /// Deserialize a snapshot from the binary format.
+    pub fn decode(data: &[u8]) -> anyhow::Result<Self> {
+        let mut decoder = Decoder { data };
+        if decoder.take(MAGIC.len())? != MAGIC {
+            bail!("not a snapshot file");
+        }
+        let version = decoder.u8()?;
+        if version != VERSION {
+            bail!("unsupported snapshot version {}", version);
+        }
+        let mut items = Vec::new();
+        loop {
+            let value_type = decoder.u8()?;
+            if value_type == END {
+                break;
+            }
+            let key = decoder.string()?;
+            let expires_at = Some(decoder.u64()?).filter(|&ms| ms != 0);
+            let value = decoder.value(value_type)?;
+            items.push(SnapshotItem {
+                key,
+                value,
+                expires_at,
+            });
+        }
+        if !decoder.data.is_empty() {
+            bail!("unexpected data after the end of the snapshot");
+        }
+        Ok(Self { items })
+    }
+
+    /// Write the snapshot to a file. The data is written to a temporary file first,
+    /// which then replaces `path`, so that a crash never leaves a partial snapshot behind.
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        let temp_path = path.with_extension(format!("tmp-{}", std::process::id()));
+        std::fs::write(&temp_path, self.encode())?;
+        std::fs::rename(&temp_path, path)?;
+        Ok(())
+    }
+
+    /// Read a snapshot from a file, either in the binary format or a Redis RDB file.
+    /// Returns `None` if the file does not exist.
+    pub fn load(path: &Path) -> anyhow::Result<Option<Self>> {
+        match std::fs::read(path) {
+            Ok(data) if data.starts_with(rdb::MAGIC) => Ok(Some(Self::decode_rdb(&data)?)),
+            Ok(data) => Ok(Some(Self::decode(&data)?)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(None),
+            Err(e) => Err(e.into()),
+        }
+    }
+}
+
+#[derive(Default)]
+pub(super) struct Encoder {
+    buf: BytesMut,
+}
+
+impl Encoder {
+    pub(super) fn u64(&mut self, value: u64) {
+        self.buf.put_u64_le(value);
+    }
+
+    pub(super) fn len(&mut self, len: usize) {
+        self.u64(len as u64);
+    }
+
+    pub(super) fn bytes(&mut self, value: &[u8]) {
+        self.len(value.len());
+        self.buf.put_slice(value);
+    }
+
+    pub(super) fn string(&mut self, value: &str) {
+        self.bytes(value.as_bytes());
+    }
+
+    pub(super) fn stream_id(&mut self, id: StreamId) {
+        self.u64(id.ms);
+        self.u64(id.seq);
+    }
+
+    fn value_type(&mut self, value: &Value) {
+        self.buf.put_u8(match value {
+            Value::String(_) => TYPE_STRING,
+            Value::List(_) => TYPE_LIST,
+            Value::Hash(_) => TYPE_HASH,
+            Value::Set(_) => TYPE_SET,
+            Value::SortedSet(_) => TYPE_SORTED_SET,
+            Value::Stream(_) => TYPE_STREAM,
+        });
+    }
+
+    fn value(&mut self, value: &Value) {
+        match value {
+            Value::String(value) => self.bytes(value),
+            Value::List(list) => {
+                self.len(list.len());
+                list.iter().for_each(|value| self.bytes(value));
+            }
+            Value::Hash(hash) => {
+                self.len(hash.len());
+                for (field, value) in hash {
+                    self.bytes(field);
+                    self.bytes(value);
+                }
+            }
+            Value::Set(set) => {
+                self.len(set.len());
+                set.iter().for_each(|member| self.bytes(member));
+            }
+            Value::SortedSet(sorted_set) => {
+                self.len(sorted_set.len());
+                for (member, score) in sorted_set.iter() {
+                    self.bytes(member);
+                    self.buf.put_f64_le(score);
+                }
+            }
+            Value::Stream(stream) => {
+                self.stream_id(stream.last_id);
+                self.len(stream.entries.len());
+                for (id, fields) in &stream.entries {
+                    self.stream_id(*id);
+                    self.len(fields.len());
+                    for (field, value) in fields {
+                        self.bytes(field);
+                        self.bytes(value);
+                    }
+                }
+                self.len(stream.groups.len());
+                for (name, group) in &stream.groups {
+                    self.string(name);
+                    group.encode(self);
+                }
+            }
+        }
+    }
+}
+
+pub(super) struct Decoder<'a> {
+    data: &'a [u8],
+}
+
+impl Decoder<'_> {
+    fn take(&mut self, len: usize) -> anyhow::Result<&[u8]> {
+        if self.data.len() < len {
+            bail!("unexpected end of the snapshot");
+        }
+        let (value, rest) = self.data.split_at(len);
+        self.data = rest;
+        Ok(value)
+    }
+
+    fn u8(&mut self) -> anyhow::Result<u8> {
+        Ok(self.take(1)?[0])
+    }
+
+    pub(super) fn u64(&mut self) -> anyhow::Result<u64> {
+        Ok(self.take(8)?.get_u64_le())
+    }
+
+    fn f64(&mut self) -> anyhow::Result<f64> {
+        Ok(self.take(8)?.get_f64_le())
+    }
+
+    /// Length of a string or a collection. Checked against the remaining data,
+    /// so that a corrupted length does not cause a huge allocation.
+    pub(super) fn len(&mut self) -> anyhow::Result<usize> {
+        let len = self.u64()?;
+        if len > self.data.len() as u64 {
+            bail!("invalid length {} in the snapshot", len);
+        }
+        Ok(len as usize)
+    }
+
+    pub(super) fn bytes(&mut self) -> anyhow::Result<Bytes> {
+        let len = self.len()?;
+        Ok(Bytes::copy_from_slice(self.take(len)?))
+    }
+
+    pub(super) fn string(&mut self) -> anyhow::Result<String> {
+        String::from_utf8(self.bytes()?.to_vec()).map_err(|_| anyhow!("invalid UTF-8 key"))
+    }
+
+    pub(super) fn stream_id(&mut self) -> anyhow::Result<StreamId> {
+        Ok(StreamId::new(self.u64()?, self.u64()?))
+    }
+
+    fn value(&mut self, value_type: u8) -> anyhow::Result<Value> {
+        let value = match value_type {
+            TYPE_STRING => Value::String(self.bytes()?),
+            TYPE_LIST => {
+                let len = self.len()?;
+                let mut list = VecDeque::with_capacity(len);
+                for _ in 0..len {
+                    list.push_back(self.bytes()?);
+                }
+                Value::List(list)
+            }
+            TYPE_HASH => {
+                let len = self.len()?;
+                let mut hash = HashMap::with_capacity(len);
+                for _ in 0..len {
+                    hash.insert(self.bytes()?, self.bytes()?);
+                }
+                Value::Hash(hash)
+            }
+            TYPE_SET => {
+                let len = self.len()?;
+                let mut set = HashSet::with_capacity(len);
+                for _ in 0..len {
+                    set.insert(self.bytes()?);
+                }
+                Value::Set(set)
+            }
+            TYPE_SORTED_SET => {
+                let mut sorted_set = SortedSet::default();
+                for _ in 0..self.len()? {
+                    let member = self.bytes()?;
+                    sorted_set.insert(member, self.f64()?);
+                }
+                Value::SortedSet(sorted_set)
+            }
+            TYPE_STREAM => {
+                let mut stream = Stream {
+                    last_id: self.stream_id()?,
+                    ..Stream::default()
+                };
+                for _ in 0..self.len()? {
+                    let id = self.stream_id()?;
+                    let len = self.len()?;
+                    let mut fields = Vec::with_capacity(len);
+                    for _ in 0..len {
+                        fields.push((self.bytes()?, self.bytes()?));
+                    }
+                    stream.entries.insert(id, fields);
+                }
+                for _ in 0..self.len()? {
+                    let name = self.string()?;
+                    stream.groups.insert(name, ConsumerGroup::decode(self)?);
+                }
+                Value::Stream(stream)
+            }
+            _ => bail!("unknown value type {} in the snapshot", value_type),
+        };
+        Ok(value)
+    }
+}
+
+impl DB {
+    /// Take a point-in-time copy of all keys that have not expired yet.
+    ///
+    /// The data is only cloned under the lock, so it can be encoded and written
+    /// to disk without blocking other clients (`BGSAVE`).
+    pub async fn snapshot(&self) -> Snapshot {
+        let data_store = self.lock_data().await;
+        let (now, now_ms) = (Instant::now(), now_ms());
+        let items = data_store
+            .iter()
+            .filter(|(_, item)| !item.is_expired())
+            .map(|(key, item)| SnapshotItem {
+                key: key.clone(),
+                value: item.value.clone(),
+                expires_at: item.expiration.map(|expiration| {
+                    now_ms + expiration.saturating_duration_since(now).as_millis() as u64
+                }),
+            })
+            .collect();
+        Snapshot { items }
+    }
+
+    /// Replace the content of the database with a snapshot.
+    /// Keys whose expiration time has passed in the meantime are skipped.
+    pub async fn load_snapshot(&self, snapshot: Snapshot) {
+        let mut data_store = self.lock_data().await;
+        let mut queue = self.expiration_queue.lock().await;
+        self.watched.lock().unwrap().touch_existing(&data_store);
+        data_store.clear();
+        queue.clear();
+        let (now, now_ms) = (Instant::now(), now_ms());
+        for item in snapshot.items {
+            let expiration = match item.expires_at {
+                Some(expires_at) if expires_at <= now_ms => continue,
+                Some(expires_at) => Some(now + Duration::from_millis(expires_at - now_ms)),
+                None => None,
+            };
+            if let Some(expiration_time) = expiration {
+                queue.push(ExpirationEntry {
+                    key: item.key.clone(),
+                    expiration_time,
+                });
+            }
+            data_store.insert(item.key, DBItem::new(item.value, expiration));
+        }
+    }
+}
@@ -0,0 +1,531 @@
+use std::cmp::Ordering;
+use std::collections::{BTreeSet, HashMap};
+use std::ops::Bound;
+
+use bytes::Bytes;
+
+use super::{get_live_mut, get_or_insert_mut, normalize_range, Value, DB};
+use crate::err::RedisDatabaseError;
+
+/// Score bound used by `ZRANGE ... BYSCORE` and `ZCOUNT`, e.g., `(1.5` or `-inf`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ScoreBound {
+    Inclusive(f64),
+    Exclusive(f64),
+}
+
+/// Lexicographical bound used by `ZRANGE ... BYLEX`, e.g., `[a`, `(b`, `-` or `+`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LexBound {
+    Inclusive(Bytes),
+    Exclusive(Bytes),
+    Min,
+    Max,
+}
+
+/// Type of the range of `ZRANGE`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ZRangeBy {
+    Index { start: i64, stop: i64 },
+    Score { min: ScoreBound, max: ScoreBound },
+    Lex { min: LexBound, max: LexBound },
+}
+
+/// Only update existing members (`XX`) or only add new members (`NX`).
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZAddCondition {
+    Nx,
+    Xx,
+}
+
+/// Only update the score if the new score is greater (`GT`) or less (`LT`) than the current one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZAddComparison {
+    Gt,
+    Lt,
+}
+
+/// Options of `ZADD`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ZAddOptions {
+    pub condition: Option<ZAddCondition>,
+    pub comparison: Option<ZAddComparison>,
+    /// Count changed members instead of added members (`CH`).
+    pub changed: bool,
+    /// Increment the score instead of setting it (`INCR`).
+    pub increment: bool,
+}
+
+impl ScoreBound {
+    /// Start of the entries with a score above the bound, `None` if there are none.
+    fn start(&self) -> Option<Bound<Entry>> {
+        let first_above = match *self {
+            ScoreBound::Inclusive(min) => min + 0.0,
+            ScoreBound::Exclusive(min) if min == f64::INFINITY => return None,
+            ScoreBound::Exclusive(min) => (min + 0.0).next_up(),
+        };
+        // The empty member precedes all members of the same score
+        Some(Bound::Included((Score(first_above), Bytes::new())))
+    }
+
+    /// End of the entries with a score below the bound, `None` if there are none.
+    fn end(&self) -> Option<Bound<Entry>> {
+        let first_above = match *self {
+            ScoreBound::Exclusive(max) => max + 0.0,
+            ScoreBound::Inclusive(max) if max == f64::INFINITY => return Some(Bound::Unbounded),
+            ScoreBound::Inclusive(max) => (max + 0.0).next_up(),
+        };
+        Some(Bound::Excluded((Score(first_above), Bytes::new())))
+    }
+}
+
+impl LexBound {
+    /// Start of the members of `score` above the bound, `None` if there are none.
+    fn start(&self, score: Score) -> Option<Bound<Entry>> {
+        match self {
+            LexBound::Inclusive(min) => Some(Bound::Included((score, min.clone()))),
+            LexBound::Exclusive(min) => Some(Bound::Excluded((score, min.clone()))),
+            LexBound::Min => Some(Bound::Included((score, Bytes::new()))),
+            LexBound::Max => None,
+        }
+    }
+
+    /// End of the members of `score` below the bound, `None` if there are none.
+    fn end(&self, score: Score) -> Option<Bound<Entry>> {
+        match self {
+            LexBound::Inclusive(max) => Some(Bound::Included((score, max.clone()))),
+            LexBound::Exclusive(max) => Some(Bound::Excluded((score, max.clone()))),
+            LexBound::Min => None,
+            LexBound::Max => ScoreBound::Inclusive(score.0).end(),
+        }
+    }
+}
+
+/// Score with a total order, so that it can be used as a key of the B-tree.
+#[derive(Debug, Clone, Copy)]
+struct Score(f64);
+
+impl PartialEq for Score {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Score {}
+
+impl PartialOrd for Score {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Score {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+/// Entry of the B-tree of a sorted set.
+type Entry = (Score, Bytes);
+
+/// Sorted set backed by a B-tree ordered by `(score, member)` and a member to score map.
+///
+/// Score and lex ranges are looked up in the B-tree, i.e., in logarithmic time plus
+/// the length of the range. The B-tree does not count the entries of its subtrees,
+/// so ranks and index ranges are linear in the position of the member.
+#[derive(Debug, Clone, Default)]
+pub(super) struct SortedSet {
+    scores: HashMap<Bytes, f64>,
+    ordered: BTreeSet<Entry>,
+}
+
+impl SortedSet {
+    pub(super) fn len(&self) -> usize {
+        self.scores.len()
+    }
+
+    pub(super) fn is_empty(&self) -> bool {
+        self.scores.is_empty()
+    }
+
+    pub(super) fn score(&self, member: &Bytes) -> Option<f64> {
+        self.scores.get(member).copied()
+    }
+
+    /// Insert or update a member. Returns the previous score.
+    pub(super) fn insert(&mut self, member: Bytes, score: f64) -> Option<f64> {
+        // Normalize `-0.0` to `0.0`
+        let score = score + 0.0;
+        let previous = self.scores.insert(member.clone(), score);
+        if let Some(previous) = previous {
+            self.ordered.remove(&(Score(previous), member.clone()));
+        }
+        self.ordered.insert((Score(score), member));
+        previous
+    }
+
+    pub(super) fn remove(&mut self, member: &Bytes) -> bool {
+        match self.scores.remove(member) {
+            Some(score) => {
+                self.ordered.remove(&(Score(score), member.clone()));
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Iterate over `(member, score)` pairs in ascending order.
+    pub(super) fn iter(&self) -> impl DoubleEndedIterator<Item = (&Bytes, f64)> {
+        self.ordered.iter().map(|(score, member)| (member, score.0))
+    }
+
+    /// Iterate over the entries between the bounds in ascending order.
+    /// Bounds that exclude all entries, e.g., a start after the end, yield no entries.
+    fn range(
+        &self,
+        start: Option<Bound<Entry>>,
+        end: Option<Bound<Entry>>,
+    ) -> impl DoubleEndedIterator<Item = (&Bytes, f64)> {
+        let range = start.zip(end).filter(|(start, end)| match (start, end) {
+            (Bound::Included(s), Bound::Included(e)) => s <= e,
+            (Bound::Included(s) | Bound::Excluded(s), Bound::Included(e) | Bound::Excluded(e)) => {
+                s < e
+            }
+            _ => true,
+        });
+        range
+            .map(|range| self.ordered.range(range))
+            .into_iter()
+            .flatten()
+            .map(|(score, member)| (member, score.0))
+    }
+
+    /// Iterate over the members with a score between `min` and `max` in ascending order.
+    fn range_by_score(
+        &self,
+        min: &ScoreBound,
+        max: &ScoreBound,
+    ) -> impl DoubleEndedIterator<Item = (&Bytes, f64)> {
+        self.range(min.start(), max.end())
+    }
+
+    /// Iterate over the members between `min` and `max` in lexicographical order.
+    ///
+    /// As in Redis, all members are expected to have the same score. Otherwise,
+    /// only the members with the lowest score are considered.
+    fn range_by_lex(
+        &self,
+        min: &LexBound,
+        max: &LexBound,
+    ) -> impl DoubleEndedIterator<Item = (&Bytes, f64)> {
+        let score = self.ordered.first().map(|(score, _)| *score);
+        self.range(
+            score.and_then(|score| min.start(score)),
+            score.and_then(|score| max.end(score)),
+        )
+    }
+
+    /// Get the 0-based position of a member in ascending order.
+    fn rank(&self, member: &Bytes) -> Option<usize> {
+        let score = self.score(member)?;
+        Some(self.ordered.range(..(Score(score), member.clone())).count())
+    }
+
+    /// Remove and return up to `count` members with the lowest (or highest) scores.
+    fn pop(&mut self, count: usize, highest: bool) -> Vec<(Bytes, f64)> {
+        let mut popped = Vec::with_capacity(count.min(self.len()));
+        while popped.len() < count {
+            let entry = if highest {
+                self.ordered.pop_last()
+            } else {
+                self.ordered.pop_first()
+            };
+            let Some((score, member)) = entry else {
+                break;
+            };
+            self.scores.remove(&member);
+            popped.push((member, score.0));
+        }
+        popped
+    }
+}
+
+impl Value {
+    fn as_sorted_set(&self) -> Result<&SortedSet, RedisDatabaseError> {
+        match self {
+            Value::SortedSet(sorted_set) => Ok(sorted_set),
+            _ => Err(RedisDatabaseError::WrongType),
+        }
+    }
+
+    fn as_sorted_set_mut(&mut self) -> Result<&mut SortedSet, RedisDatabaseError> {
+        match self {
+            Value::SortedSet(sorted_set) => Ok(sorted_set),
+            _ => Err(RedisDatabaseError::WrongType),
+        }
+    }
+}
+
+/// Add a single member according to `ZADD` options.
+///
+/// Returns the new score (`None` if the update was skipped),
+/// whether the member was added, and whether the score was changed.
+fn zadd_member(
+    sorted_set: &mut SortedSet,
+    score: f64,
+    member: Bytes,
+    options: &ZAddOptions,
+) -> Result<(Option<f64>, bool, bool), RedisDatabaseError> {
+    let current = sorted_set.score(&member);
+    match (current, options.condition) {
+        (Some(_), Some(ZAddCondition::Nx)) | (None, Some(ZAddCondition::Xx)) => {
+            return Ok((None, false, false))
+        }
+        _ => {}
+    }
+
+    let new_score = match current {
+        Some(current) if options.increment => current + score,
+        _ => score,
+    };
+    if new_score.is_nan() {
+        return Err(RedisDatabaseError::ScoreNaN);
+    }
+
+    if let Some(current) = current {
+        let skip = match options.comparison {
+            Some(ZAddComparison::Gt) => new_score <= current,
+            Some(ZAddComparison::Lt) => new_score >= current,
+            None => false,
+        };
+        if skip {
+            return Ok((None, false, false));
+        }
+        if new_score == current {
+            return Ok((Some(new_score), false, false));
+        }
+    }
+
+    let added = sorted_set.insert(member, new_score).is_none();
+    Ok((Some(new_score), added, true))
+}
+
+impl DB {
+    /// Add `members` with their scores to the sorted set stored at `key`.
+    /// The sorted set is created if it does not exist (unless `XX` is specified).
+    ///
+    /// Returns the number of added members, or the number of changed members with `CH`.
+    pub async fn zadd(
+        &self,
+        key: &str,
+        members: Vec<(f64, Bytes)>,
+        options: ZAddOptions,
+    ) -> anyhow::Result<usize> {
+        let mut data_store = self.lock_data().await;
+        if options.condition == Some(ZAddCondition::Xx)
+            && get_live_mut(&mut data_store, key).is_none()
+        {
+            return Ok(0);
+        }
+        let sorted_set = get_or_insert_mut(&mut data_store, key, || {
+            Value::SortedSet(SortedSet::default())
+        })
+        .value
+        .as_sorted_set_mut()?;
+
+        let mut count = 0;
+        for (score, member) in members {
+            let (_, added, changed) = zadd_member(sorted_set, score, member, &options)?;
+            if added || (options.changed && changed) {
+                count += 1;
+            }
+        }
+        if sorted_set.is_empty() {
+            data_store.remove(key);
+        }
+        Ok(count)
+    }
+
+    /// Increment the score of `member` in the sorted set stored at `key` (`ZADD ... INCR`).
+    ///
+    /// Returns the new score, or `None` if the update was skipped because of the options.
+    pub async fn zadd_incr(
+        &self,
+        key: &str,
+        increment: f64,
+        member: Bytes,
+        options: ZAddOptions,
+    ) -> anyhow::Result<Option<f64>> {
+        let mut data_store = self.lock_data().await;
+        if options.condition == Some(ZAddCondition::Xx)
+            && get_live_mut(&mut data_store, key).is_none()
+        {
+            return Ok(None);
+        }
+        let sorted_set = get_or_insert_mut(&mut data_store, key, || {
+            Value::SortedSet(SortedSet::default())
+        })
+        .value
+        .as_sorted_set_mut()?;
+        let options = ZAddOptions {
+            increment: true,
+            ..options
+        };
+        let (score, _, _) = zadd_member(sorted_set, increment, member, &options)?;
+        if sorted_set.is_empty() {
+            data_store.remove(key);
+        }
+        Ok(score)
+    }
+
+    /// Increment the score of `member` in the sorted set stored at `key`.
+    ///
+    /// Returns the new score.
+    pub async fn zincrby(&self, key: &str, increment: f64, member: Bytes) -> anyhow::Result<f64> {
+        let score = self
+            .zadd_incr(key, increment, member, ZAddOptions::default())
+            .await?;
+        // Without conditions, the score is always updated.
+        Ok(score.unwrap_or(increment))
+    }
+
+    /// Get `(member, score)` pairs of the sorted set stored at `key` in the given range.
+    ///
+    /// - `rev`: Order from the highest to the lowest score. For score and lex ranges,
+    ///   `min` and `max` are still the lower and upper bounds.
+    /// - `limit`: `(offset, count)` applied after the range; negative `count` returns all.
+    pub async fn zrange(
+        &self,
+        key: &str,
+        by: &ZRangeBy,
+        rev: bool,
+        limit: Option<(i64, i64)>,
+    ) -> anyhow::Result<Vec<(Bytes, f64)>> {
+        let mut data_store = self.lock_data().await;
+        let Some(item) = get_live_mut(&mut data_store, key) else {
+            return Ok(Vec::new());
+        };
+        let sorted_set = item.value.as_sorted_set()?;
+
+        let (offset, count) = match limit {
+            Some((offset, count)) if offset >= 0 => (offset as usize, count),
+            Some(_) => return Ok(Vec::new()),
+            None => (0, -1),
+        };
+        let count = if count < 0 {
+            usize::MAX
+        } else {
+            count as usize
+        };
+        let entries: Box<dyn Iterator<Item = (&Bytes, f64)>> = match by {
+            ZRangeBy::Index { start, stop } => {
+                let Some((start, stop)) = normalize_range(*start, *stop, sorted_set.len()) else {
+                    return Ok(Vec::new());
+                };
+                let count = stop - start + 1;
+                if rev {
+                    Box::new(sorted_set.iter().rev().skip(start).take(count))
+                } else {
+                    Box::new(sorted_set.iter().skip(start).take(count))
+                }
+            }
+            ZRangeBy::Score { min, max } if rev => {
+                Box::new(sorted_set.range_by_score(min, max).rev())
+            }
+            ZRangeBy::Score { min, max } => Box::new(sorted_set.range_by_score(min, max)),
+            ZRangeBy::Lex { min, max } if rev => Box::new(sorted_set.range_by_lex(min, max).rev()),
+            ZRangeBy::Lex { min, max } => Box::new(sorted_set.range_by_lex(min, max)),
+        };
+        // The range is iterated only until the limit is reached
+        Ok(entries
+            .skip(offset)
+            .take(count)
+            .map(|(member, score)| (member.clone(), score))
+            .collect())
+    }
+
+    /// Get the rank (0-based position by ascending score) of `member`.
+    pub async fn zrank(&self, key: &str, member: &Bytes) -> anyhow::Result<Option<usize>> {
+        let mut data_store = self.lock_data().await;
+        match get_live_mut(&mut data_store, key) {
+            Some(item) => Ok(item.value.as_sorted_set()?.rank(member)),
+            None => Ok(None),
+        }
+    }
+
+    /// Get the score of `member`.
+    pub async fn zscore(&self, key: &str, member: &Bytes) -> anyhow::Result<Option<f64>> {
+        let mut data_store = self.lock_data().await;
+        match get_live_mut(&mut data_store, key) {
+            Some(item) => Ok(item.value.as_sorted_set()?.score(member)),
+            None => Ok(None),
+        }
+    }
+
+    /// Get the number of members of the sorted set stored at `key`.
+    pub async fn zcard(&self, key: &str) -> anyhow::Result<usize> {
+        let mut data_store = self.lock_data().await;
+        match get_live_mut(&mut data_store, key) {
+            Some(item) => Ok(item.value.as_sorted_set()?.len()),
+            None => Ok(0),
+        }
+    }
+
+    /// Remove `members` from the sorted set stored at `key`.
+    /// Empty sorted sets are removed from the database.
+    ///
+    /// Returns the number of removed members.
+    pub async fn zrem(&self, key: &str, members: &[Bytes]) -> anyhow::Result<usize> {
+        let mut data_store = self.lock_data().await;
+        let Some(item) = get_live_mut(&mut data_store, key) else {
+            return Ok(0);
+        };
+        let sorted_set = item.value.as_sorted_set_mut()?;
+        let removed = members
+            .iter()
+            .filter(|member| sorted_set.remove(member))
+            .count();
+        if sorted_set.is_empty() {
+            data_store.remove(key);
+        }
+        Ok(removed)
+    }
+
+    /// Count the members with a score between `min` and `max`.
+    pub async fn zcount(
+        &self,
+        key: &str,
+        min: ScoreBound,
+        max: ScoreBound,
+    ) -> anyhow::Result<usize> {
+        let mut data_store = self.lock_data().await;
+        let Some(item) = get_live_mut(&mut data_store, key) else {
+            return Ok(0);
+        };
+        Ok(item
+            .value
+            .as_sorted_set()?
+            .range_by_score(&min, &max)
+            .count())
+    }
+
+    /// Remove and return up to `count` members with the lowest (`ZPOPMIN`)
+    /// or highest (`ZPOPMAX`) scores. Empty sorted sets are removed from the database.
+    pub async fn zpop(
+        &self,
+        key: &str,
+        count: usize,
+        highest: bool,
+    ) -> anyhow::Result<Vec<(Bytes, f64)>> {
+        let mut data_store = self.lock_data().await;
+        let Some(item) = get_live_mut(&mut data_store, key) else {
+            return Ok(Vec::new());
+        };
+        let sorted_set = item.value.as_sorted_set_mut()?;
+        let popped = sorted_set.pop(count, highest);
+        if sorted_set.is_empty() {
+            data_store.remove(key);
+        }
+        Ok(popped)
+    }
+}
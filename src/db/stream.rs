@@ -0,0 +1,327 @@
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use bytes::Bytes;
+
+use super::consumer_group::ConsumerGroup;
+use super::{get_live_mut, DBItem, Value, DB};
+use crate::err::RedisDatabaseError;
+
+/// ID of a stream entry, `<milliseconds>-<sequence>`, e.g., `1526919030474-0`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct StreamId {
+    pub ms: u64,
+    pub seq: u64,
+}
+
+impl StreamId {
+    pub const MIN: StreamId = StreamId { ms: 0, seq: 0 };
+    pub const MAX: StreamId = StreamId {
+        ms: u64::MAX,
+        seq: u64::MAX,
+    };
+
+    pub fn new(ms: u64, seq: u64) -> Self {
+        Self { ms, seq }
+    }
+
+    /// The smallest ID greater than this one.
+    pub fn next(&self) -> Option<StreamId> {
+        match self.seq.checked_add(1) {
+            Some(seq) => Some(StreamId::new(self.ms, seq)),
+            None => self.ms.checked_add(1).map(|ms| StreamId::new(ms, 0)),
+        }
+    }
+
+    /// The greatest ID smaller than this one.
+    pub fn prev(&self) -> Option<StreamId> {
+        match self.seq.checked_sub(1) {
+            Some(seq) => Some(StreamId::new(self.ms, seq)),
+            None => self.ms.checked_sub(1).map(|ms| StreamId::new(ms, u64::MAX)),
+        }
+    }
+}
+
+impl fmt::Display for StreamId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}-{}", self.ms, self.seq)
+    }
+}
+
+/// ID argument of `XADD`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum XAddId {
+    /// `*`: Generate the ID from the current time.
+    Auto,
+    /// `<ms>-*`: Generate the sequence number for the given time.
+    Partial(u64),
+    /// `<ms>-<seq>`
+    Explicit(StreamId),
+}
+
+/// Trimming strategy of `XADD` and `XTRIM`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamTrim {
+    /// Keep at most the given number of the newest entries.
+    MaxLen(usize),
+    /// Remove entries with an ID lower than the given one.
+    MinId(StreamId),
+}
+
+/// Entry of a stream, i.e., its ID and field-value pairs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StreamEntry {
+    pub id: StreamId,
+    pub fields: Vec<(Bytes, Bytes)>,
+}
+
+/// Current wall-clock time in milliseconds since the Unix epoch.
+pub(crate) fn now_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// Append-only log of entries stored in a B-tree keyed by the entry ID.
+#[derive(Debug, Clone, Default)]
+pub(super) struct Stream {
+    pub(super) entries: BTreeMap<StreamId, Vec<(Bytes, Bytes)>>,
+    /// ID of the last added entry. New IDs must be greater, even if entries were deleted.
+    pub(super) last_id: StreamId,
+    pub(super) groups: HashMap<String, ConsumerGroup>,
+}
+
+impl Stream {
+    pub(super) fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Resolve the ID of a new entry. The ID must be greater than the last ID.
+    fn next_id(&self, id: XAddId) -> Result<StreamId, RedisDatabaseError> {
+        let id = match id {
+            XAddId::Auto => {
+                let now = now_ms();
+                if now > self.last_id.ms {
+                    StreamId::new(now, 0)
+                } else {
+                    self.last_id
+                        .next()
+                        .ok_or(RedisDatabaseError::StreamIdTooSmall)?
+                }
+            }
+            XAddId::Partial(ms) if ms == self.last_id.ms => self
+                .last_id
+                .next()
+                .filter(|id| id.ms == ms)
+                .ok_or(RedisDatabaseError::StreamIdTooSmall)?,
+            // `0-0` is not a valid ID, so the first sequence number at 0 ms is 1
+            XAddId::Partial(ms) => StreamId::new(ms, if ms == 0 { 1 } else { 0 }),
+            XAddId::Explicit(id) => id,
+        };
+        if id == StreamId::MIN {
+            return Err(RedisDatabaseError::StreamIdZero);
+        }
+        if id <= self.last_id {
+            return Err(RedisDatabaseError::StreamIdTooSmall);
+        }
+        Ok(id)
+    }
+
+    fn add(
+        &mut self,
+        id: XAddId,
+        fields: Vec<(Bytes, Bytes)>,
+    ) -> Result<StreamId, RedisDatabaseError> {
+        let id = self.next_id(id)?;
+        self.entries.insert(id, fields);
+        self.last_id = id;
+        Ok(id)
+    }
+
+    /// Trim the stream. Returns the number of removed entries.
+    fn trim(&mut self, trim: StreamTrim) -> usize {
+        let before = self.entries.len();
+        match trim {
+            StreamTrim::MaxLen(max_len) => {
+                while self.entries.len() > max_len {
+                    self.entries.pop_first();
+                }
+            }
+            StreamTrim::MinId(min_id) => self.entries = self.entries.split_off(&min_id),
+        }
+        before - self.entries.len()
+    }
+
+    /// Get entries with IDs within the inclusive range `start..=end`.
+    pub(super) fn range(
+        &self,
+        start: StreamId,
+        end: StreamId,
+        rev: bool,
+        count: Option<usize>,
+    ) -> Vec<StreamEntry> {
+        if start > end {
+            return Vec::new();
+        }
+        let entries = self
+            .entries
+            .range(start..=end)
+            .map(|(id, fields)| StreamEntry {
+                id: *id,
+                fields: fields.clone(),
+            });
+        let count = count.unwrap_or(usize::MAX);
+        if rev {
+            entries.rev().take(count).collect()
+        } else {
+            entries.take(count).collect()
+        }
+    }
+}
+
+impl Value {
+    pub(super) fn as_stream(&self) -> Result<&Stream, RedisDatabaseError> {
+        match self {
+            Value::Stream(stream) => Ok(stream),
+            _ => Err(RedisDatabaseError::WrongType),
+        }
+    }
+
+    pub(super) fn as_stream_mut(&mut self) -> Result<&mut Stream, RedisDatabaseError> {
+        match self {
+            Value::Stream(stream) => Ok(stream),
+            _ => Err(RedisDatabaseError::WrongType),
+        }
+    }
+}
+
+impl DB {
+    /// Append an entry to the stream stored at `key` and optionally trim the stream.
+    /// Unlike other collections, a stream is kept in the database even when it is empty.
+    ///
+    /// Returns the ID of the added entry, or `None` if the stream does not exist
+    /// and `create` is `false` (`NOMKSTREAM`).
+    pub async fn xadd(
+        &self,
+        key: &str,
+        id: XAddId,
+        fields: Vec<(Bytes, Bytes)>,
+        trim: Option<StreamTrim>,
+        create: bool,
+    ) -> anyhow::Result<Option<StreamId>> {
+        let mut data_store = self.lock_data().await;
+        let item = match get_live_mut(&mut data_store, key) {
+            Some(item) => item,
+            None if create => {
+                // Validate the ID before creating the stream
+                Stream::default().next_id(id)?;
+                data_store.insert(
+                    key.to_string(),
+                    DBItem::new(Value::Stream(Stream::default()), None),
+                );
+                data_store.get_mut(key).unwrap()
+            }
+            None => return Ok(None),
+        };
+        let stream = item.value.as_stream_mut()?;
+        let id = stream.add(id, fields)?;
+        if let Some(trim) = trim {
+            stream.trim(trim);
+        }
+        Ok(Some(id))
+    }
+
+    /// Get entries of the stream stored at `key` within the inclusive range `start..=end`.
+    /// With `rev`, the entries are returned from the newest to the oldest.
+    pub async fn xrange(
+        &self,
+        key: &str,
+        start: StreamId,
+        end: StreamId,
+        rev: bool,
+        count: Option<usize>,
+    ) -> anyhow::Result<Vec<StreamEntry>> {
+        let mut data_store = self.lock_data().await;
+        match get_live_mut(&mut data_store, key) {
+            Some(item) => Ok(item.value.as_stream()?.range(start, end, rev, count)),
+            None => Ok(Vec::new()),
+        }
+    }
+
+    /// Get the number of entries of the stream stored at `key`.
+    pub async fn xlen(&self, key: &str) -> anyhow::Result<usize> {
+        let mut data_store = self.lock_data().await;
+        match get_live_mut(&mut data_store, key) {
+            Some(item) => Ok(item.value.as_stream()?.len()),
+            None => Ok(0),
+        }
+    }
+
+    /// Trim the stream stored at `key`. Returns the number of removed entries.
+    pub async fn xtrim(&self, key: &str, trim: StreamTrim) -> anyhow::Result<usize> {
+        let mut data_store = self.lock_data().await;
+        match get_live_mut(&mut data_store, key) {
+            Some(item) => Ok(item.value.as_stream_mut()?.trim(trim)),
+            None => Ok(0),
+        }
+    }
+
+    /// Delete entries from the stream stored at `key`. Returns the number of deleted entries.
+    pub async fn xdel(&self, key: &str, ids: &[StreamId]) -> anyhow::Result<usize> {
+        let mut data_store = self.lock_data().await;
+        let Some(item) = get_live_mut(&mut data_store, key) else {
+            return Ok(0);
+        };
+        let stream = item.value.as_stream_mut()?;
+        Ok(ids
+            .iter()
+            .filter(|id| stream.entries.remove(id).is_some())
+            .count())
+    }
+
+    /// Set the last ID of the stream stored at `key`, i.e., new entries must have a greater ID.
+    /// The ID must not be smaller than the ID of the last entry.
+    pub async fn xsetid(&self, key: &str, last_id: StreamId) -> anyhow::Result<()> {
+        let mut data_store = self.lock_data().await;
+        let item = get_live_mut(&mut data_store, key).ok_or(RedisDatabaseError::NoSuchKey)?;
+        let stream = item.value.as_stream_mut()?;
+        if stream
+            .entries
+            .last_key_value()
+            .is_some_and(|(id, _)| *id > last_id)
+        {
+            return Err(RedisDatabaseError::StreamIdSmallerThanTop.into());
+        }
+        stream.last_id = last_id;
+        Ok(())
+    }
+
+    /// Read entries with an ID greater than the given one from multiple streams under a single lock.
+    /// The ID `None` stands for `$`, i.e., the last ID of the stream.
+    ///
+    /// Returns only the streams with new entries, in the order of `streams`.
+    pub async fn xread(
+        &self,
+        streams: &[(String, Option<StreamId>)],
+        count: Option<usize>,
+    ) -> anyhow::Result<Vec<(String, Vec<StreamEntry>)>> {
+        let mut data_store = self.lock_data().await;
+        let mut result = Vec::new();
+        for (key, id) in streams {
+            let Some(item) = get_live_mut(&mut data_store, key) else {
+                continue;
+            };
+            let stream = item.value.as_stream()?;
+            let Some(start) = id.unwrap_or(stream.last_id).next() else {
+                continue;
+            };
+            let entries = stream.range(start, StreamId::MAX, false, count);
+            if !entries.is_empty() {
+                result.push((key.clone(), entries));
+            }
+        }
+        Ok(result)
+    }
+}
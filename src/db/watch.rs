@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex as StdMutex};
+
+use super::{get_live_mut, Store, DB};
+
+/// Version of a watched key and the number of connections watching it.
+#[derive(Debug)]
+struct WatchedKey {
+    version: u64,
+    watchers: usize,
+}
+
+/// Versions of the keys watched by at least one connection.
+/// Keys that nobody watches are not tracked.
+#[derive(Debug, Default)]
+pub(super) struct WatchedKeys {
+    keys: HashMap<String, WatchedKey>,
+    last_version: u64,
+}
+
+impl WatchedKeys {
+    /// Mark `key` as modified.
+    pub(super) fn touch(&mut self, key: &str) {
+        if let Some(watched) = self.keys.get_mut(key) {
+            self.last_version += 1;
+            watched.version = self.last_version;
+        }
+    }
+
+    /// Mark all watched keys present in the data store as modified, e.g., on `FLUSHDB`.
+    pub(super) fn touch_existing(&mut self, data_store: &Store) {
+        for (key, watched) in self.keys.iter_mut() {
+            if data_store.contains_key(key) {
+                self.last_version += 1;
+                watched.version = self.last_version;
+            }
+        }
+    }
+}
+
+/// Keys watched by a single connection (`WATCH`), with their versions at the time they were watched.
+///
+/// Dropping the handle unwatches all keys.
+#[derive(Debug)]
+pub struct Watch {
+    registry: Arc<StdMutex<WatchedKeys>>,
+    keys: HashMap<String, u64>,
+}
+
+impl Watch {
+    /// Watch `key`. Watching a key again keeps its original version.
+    pub fn watch(&mut self, key: &str) {
+        if self.keys.contains_key(key) {
+            return;
+        }
+        let mut registry = self.registry.lock().unwrap();
+        let watched = registry.keys.entry(key.to_string()).or_insert(WatchedKey {
+            version: 0,
+            watchers: 0,
+        });
+        watched.watchers += 1;
+        self.keys.insert(key.to_string(), watched.version);
+    }
+
+    /// Check if any of the watched keys was modified since it was watched.
+    pub fn is_dirty(&self) -> bool {
+        let registry = self.registry.lock().unwrap();
+        self.keys
+            .iter()
+            .any(|(key, version)| registry.keys[key].version != *version)
+    }
+
+    /// Unwatch all keys.
+    pub fn unwatch(&mut self) {
+        let mut registry = self.registry.lock().unwrap();
+        for key in self.keys.keys() {
+            let watched = registry.keys.get_mut(key).unwrap();
+            watched.watchers -= 1;
+            if watched.watchers == 0 {
+                registry.keys.remove(key);
+            }
+        }
+        self.keys.clear();
+    }
+}
+
+impl Drop for Watch {
+    fn drop(&mut self) {
+        self.unwatch();
+    }
+}
+
+impl DB {
+    /// Create an empty set of watched keys for a connection.
+    pub fn watcher(&self) -> Watch {
+        Watch {
+            registry: Arc::clone(&self.watched),
+            keys: HashMap::new(),
+        }
+    }
+
+    /// Watch `keys` for a connection. Keys that already expired are removed first,
+    /// so that their removal does not abort the transaction.
+    pub async fn watch(&self, watch: &mut Watch, keys: &[String]) {
+        let mut data_store = self.lock_data().await;
+        for key in keys {
+            get_live_mut(&mut data_store, key);
+            watch.watch(key);
+        }
+    }
+
+    /// Remove the watched keys that expired since they were watched, marking them as modified.
+    ///
+    /// To be called while the data store is locked (see `DB::lock`) before `Watch::is_dirty`,
+    /// as expired keys are otherwise removed only when they are accessed.
+    pub async fn remove_expired_watched(&self, watch: &Watch) {
+        let mut data_store = self.lock_data().await;
+        for key in watch.keys.keys() {
+            get_live_mut(&mut data_store, key);
+        }
+    }
+
+    /// Mark keys as modified, so that transactions watching them are aborted.
+    ///
+    /// To be called by writers while the data store is locked (see `DB::lock`),
+    /// otherwise a transaction could run between the write and the touch.
+    pub fn touch<S: AsRef<str>>(&self, keys: &[S]) {
+        let mut registry = self.watched.lock().unwrap();
+        for key in keys {
+            registry.touch(key.as_ref());
+        }
+    }
+}
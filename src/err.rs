@@ -16,6 +16,27 @@ pub enum RedisProtocolError {
 
     #[error("Not enough data has been buffered to parse the frame.")]
     NotEnoughData,
+
+    #[error("Protocol error: unbalanced quotes in inline command")]
+    UnbalancedQuotes,
+
+    #[error("Protocol error: too big inline request")]
+    InlineTooLong,
+
+    #[error("Protocol error: invalid bulk length `{0}`")]
+    BulkTooLong(u64),
+
+    #[error("Protocol error: invalid multibulk length `{0}`")]
+    ArrayTooLong(usize),
+
+    #[error("Protocol error: aggregate frames nested deeper than {0}")]
+    TooDeeplyNested(usize),
+
+    #[error("Protocol error: query buffer of {0} bytes reached the limit")]
+    QueryBufferExceeded(usize),
+
+    #[error("Protocol error: blob of {0} bytes not terminated by CRLF")]
+    UnterminatedBlob(usize),
 }
 
 #[derive(Error, Debug)]
@@ -41,3 +62,90 @@ pub enum RedisCommandError {
     #[error("Not implemented error: {0}")]
     NotImplemented(String),
 }
+
+#[derive(Error, Debug)]
+pub enum RedisDatabaseError {
+    #[error("WRONGTYPE Operation against a key holding the wrong kind of value")]
+    WrongType,
+
+    #[error("ERR no such key")]
+    NoSuchKey,
+
+    #[error("ERR index out of range")]
+    IndexOutOfRange,
+
+    #[error("ERR hash value is not an integer")]
+    HashValueNotInteger,
+
+    #[error("ERR increment or decrement would overflow")]
+    Overflow,
+
+    #[error("ERR resulting score is not a number (NaN)")]
+    ScoreNaN,
+
+    #[error("ERR The ID specified in XADD is equal or smaller than the target stream top item")]
+    StreamIdTooSmall,
+
+    #[error("ERR The ID specified in XADD must be greater than 0-0")]
+    StreamIdZero,
+
+    #[error("ERR The ID specified in XSETID is smaller than the target stream top item")]
+    StreamIdSmallerThanTop,
+
+    #[error("NOGROUP No such key '{0}' or consumer group '{1}'")]
+    NoGroup(String, String),
+
+    #[error("BUSYGROUP Consumer Group name already exists")]
+    BusyGroup,
+
+    #[error("ERR The XGROUP subcommand requires the key to exist. Note that for CREATE you may want to use the MKSTREAM option to create an empty stream automatically.")]
+    StreamKeyRequired,
+
+    #[error("BUSYKEY Target key name already exists.")]
+    BusyKey,
+
+    #[error("ERR DUMP payload version or checksum are wrong")]
+    DumpPayload,
+
+    #[error("ERR Bad data format")]
+    BadDataFormat,
+}
+
+#[derive(Error, Debug)]
+pub enum RedisClusterError {
+    #[error("MOVED {0} {1}")]
+    Moved(u16, String),
+
+    #[error("ASK {0} {1}")]
+    Ask(u16, String),
+
+    #[error("TRYAGAIN Multiple keys request during rehashing of slot")]
+    TryAgain,
+
+    #[error("CROSSSLOT Keys in request don't hash to the same slot")]
+    CrossSlot,
+
+    #[error("CLUSTERDOWN Hash slot not served")]
+    SlotNotServed,
+
+    #[error("ERR This instance has cluster support disabled")]
+    Disabled,
+}
+
+#[derive(Error, Debug)]
+pub enum RedisClientError {
+    #[error("{0}")]
+    ServerError(String),
+
+    #[error("Unexpected response: {0}")]
+    UnexpectedResponse(String),
+
+    #[error("Connection closed by server")]
+    ConnectionClosed,
+
+    #[error("Too many cluster redirections")]
+    TooManyRedirections,
+
+    #[error("Transaction aborted because a watched key was modified")]
+    TransactionAborted,
+}
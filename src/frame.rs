@@ -1,12 +1,13 @@
-use anyhow::{bail, Context};
+use anyhow::bail;
 use atoi::atoi;
-use bytes::{Buf, Bytes};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
 use std::io::Cursor;
 
+use crate::constants::{MAX_PREALLOCATED_ELEMENTS, PROTO_MAX_BULK_LEN};
 use crate::err::RedisProtocolError;
-use crate::{bulk, error, integer, null, simple};
+use crate::{error, integer, null, simple};
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq)]
 pub enum Frame {
     Simple(String),    // `+{string data}\r\n`
     Error(String),     // `-{error message}\r\n`
@@ -14,61 +15,136 @@ pub enum Frame {
     Bulk(Bytes),       // `${number of bytes}\r\n{data}\r\n`
     Null,              // RESP2: `$-1\r\n (string of length -1)` OR RESP3: `_\r\n`
     Array(Vec<Frame>), // `*{number of elements}\r\n{frames}\r\n` (empty array `*0\r\n`)
+    // RESP3 frames, sent to connections that switched the protocol by `HELLO 3`.
+    // RESP2 connections receive them as the closest RESP2 frames, e.g., maps as flat arrays.
+    Double(f64),              // `,{value}\r\n` (also `inf`, `-inf` and `nan`)
+    Boolean(bool),            // `#t\r\n` or `#f\r\n`
+    BigNumber(String),        // `({digits}\r\n`
+    BlobError(Bytes),         // `!{number of bytes}\r\n{error}\r\n`
+    Verbatim(String, Bytes),  // `={number of bytes}\r\n{format}:{data}\r\n`
+    Map(Vec<(Frame, Frame)>), // `%{number of pairs}\r\n{key}{value}...`
+    Set(Vec<Frame>),          // `~{number of elements}\r\n{frames}`
+    Push(Vec<Frame>),         // `>{number of elements}\r\n{frames}`
+    Attribute(Vec<(Frame, Frame)>, Box<Frame>), // `|{number of pairs}\r\n{key}{value}...{frame}`
 }
 
-impl Frame {
-    /// Check if the buffer contains a parsable frame.
-    ///
-    /// Returns `Ok` if the buffer contains a parsable frame.
-    pub fn is_parsable(cursor: &mut Cursor<&[u8]>) -> anyhow::Result<(), RedisProtocolError> {
-        if !cursor.has_remaining() {
-            return Err(RedisProtocolError::NotEnoughData);
+/// Version of the protocol of a connection, switched by `HELLO`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Protocol {
+    #[default]
+    Resp2,
+    Resp3,
+}
+
+impl Protocol {
+    /// Get the protocol of a version number of `HELLO`.
+    pub fn from_version(version: u64) -> Option<Self> {
+        match version {
+            2 => Some(Protocol::Resp2),
+            3 => Some(Protocol::Resp3),
+            _ => None,
         }
-        match cursor.get_u8() {
-            b'+' | b'-' | b':' | b'_' => has_crlf_with_checks(cursor),
-            b'$' => {
-                let crlf_index = seek_newline(cursor)?;
-                let len_u8 = get_byte_slice(cursor, 1, crlf_index);
-                let len = atoi::<i64>(len_u8).ok_or_else(|| {
-                    RedisProtocolError::ConversionError(String::from_utf8_lossy(len_u8).to_string())
-                })?;
+    }
 
-                match len {
-                    -1 => {
-                        // Null bulk string
-                        Ok(())
-                    }
-                    len if len < -1 => Err(RedisProtocolError::NegativeBulkLength(len)),
-                    _ => {
-                        // Check that the buffer has enough data
-                        has_crlf(cursor)
-                    }
-                }
-            }
-            b'*' => {
-                // Array
-                let start = cursor.position() as usize;
-                let crlf_index = start + seek_newline(cursor)?;
-                let len_u8 = get_byte_slice(cursor, start, crlf_index);
-                let len = atoi::<usize>(len_u8).ok_or_else(|| {
-                    RedisProtocolError::ConversionError(String::from_utf8_lossy(len_u8).to_string())
-                })?;
-                for _ in 0..len {
-                    Frame::is_parsable(cursor)?;
-                }
-                Ok(())
-            }
-            byte => {
-                log::debug!("Parse check failed, buffer state: {:?}", cursor);
-                Err(RedisProtocolError::UnsupportedFrame(byte))
+    pub fn version(&self) -> i64 {
+        match self {
+            Protocol::Resp2 => 2,
+            Protocol::Resp3 => 3,
+        }
+    }
+}
+
+/// Limits of the frames received from a connection, so that a client cannot exhaust
+/// the memory or the stack of the server. Option names follow `redis.conf`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProtocolLimits {
+    /// Maximum length of a blob, e.g., a bulk string (`proto-max-bulk-len`).
+    pub max_bulk_len: usize,
+    /// Maximum number of elements of an aggregate frame, e.g., an array.
+    /// The decoded elements are also counted against `max_query_buffer`.
+    pub max_aggregate_len: usize,
+    /// Maximum depth of nested aggregate frames.
+    pub max_depth: usize,
+    /// Maximum length of the line of an inline command.
+    pub max_inline_len: usize,
+    /// Maximum size of the buffered data of a frame that was not received
+    /// as a whole yet (`client-query-buffer-limit`).
+    pub max_query_buffer: usize,
+}
+
+impl Default for ProtocolLimits {
+    fn default() -> Self {
+        Self {
+            max_bulk_len: PROTO_MAX_BULK_LEN,
+            max_aggregate_len: 16 * 1024 * 1024,
+            max_depth: 32,
+            max_inline_len: 64 * 1024,
+            max_query_buffer: 1024 * 1024 * 1024,
+        }
+    }
+}
+
+impl ProtocolLimits {
+    /// Check the length of a blob, e.g., of a bulk string.
+    pub(crate) fn check_bulk_len(&self, len: i64) -> anyhow::Result<(), RedisProtocolError> {
+        match len {
+            len if len < 0 => Err(RedisProtocolError::NegativeBulkLength(len)),
+            len if len as u64 > self.max_bulk_len as u64 => {
+                Err(RedisProtocolError::BulkTooLong(len as u64))
             }
+            _ => Ok(()),
         }
     }
 
-    /// Parse a frame from the buffer. Assumes that the frame was validated by `Frame::is_parsable`.
+    /// Check the length of the line of an inline command, received as a whole or not.
+    pub(crate) fn check_inline_len(&self, len: usize) -> anyhow::Result<(), RedisProtocolError> {
+        match len > self.max_inline_len {
+            true => Err(RedisProtocolError::InlineTooLong),
+            false => Ok(()),
+        }
+    }
+
+    /// Check the number of elements and the depth of an aggregate frame,
+    /// where the outermost aggregate has depth `1`.
+    pub(crate) fn check_aggregate(
+        &self,
+        len: usize,
+        depth: usize,
+    ) -> anyhow::Result<(), RedisProtocolError> {
+        if len > self.max_aggregate_len {
+            return Err(RedisProtocolError::ArrayTooLong(len));
+        }
+        if depth > self.max_depth {
+            return Err(RedisProtocolError::TooDeeplyNested(self.max_depth));
+        }
+        Ok(())
+    }
+}
+
+impl Frame {
+    /// Parse a frame from the buffer in a single pass.
+    ///
+    /// Returns `NotEnoughData` if the buffer does not contain the whole frame,
+    /// the position of the `cursor` is then unspecified.
+    /// Frames over the default `ProtocolLimits` are rejected.
     pub fn parse(cursor: &mut Cursor<&[u8]>) -> anyhow::Result<Frame, RedisProtocolError> {
+        Frame::parse_nested(cursor, &ProtocolLimits::default(), 0)
+    }
+
+    /// Parse a frame nested in `depth` aggregate frames.
+    fn parse_nested(
+        cursor: &mut Cursor<&[u8]>,
+        limits: &ProtocolLimits,
+        depth: usize,
+    ) -> anyhow::Result<Frame, RedisProtocolError> {
+        if !cursor.has_remaining() {
+            return Err(RedisProtocolError::NotEnoughData);
+        }
         match cursor.get_u8() {
-            b'_' => Ok(null!()),
+            b'_' => {
+                get_line(cursor)?;
+                Ok(null!())
+            }
             b'+' => {
                 let line = get_line(cursor)?;
                 Ok(simple!(String::from_utf8_lossy(line).to_string()))
@@ -85,55 +161,137 @@ impl Frame {
                 Ok(integer!(num))
             }
             b'$' => {
-                let start = cursor.position() as usize;
-                let crlf_index = start + seek_newline(cursor)?;
-                let len_u8 = get_byte_slice(cursor, start, crlf_index);
-                let len = atoi::<i64>(len_u8).ok_or_else(|| {
-                    RedisProtocolError::ConversionError(String::from_utf8_lossy(len_u8).to_string())
-                })?;
+                let len = get_length(cursor)?;
 
                 log::debug!("Parsing bulk string with length: {}", len);
 
                 if len == -1 {
                     return Ok(null!());
                 }
-
-                let data_start = cursor.position() as usize;
-                let data_end = data_start + len as usize - 1;
-
-                // Read the data and advance the cursor
-                let data = bulk!(get_byte_slice(cursor, data_start, data_end));
-                cursor.advance(len as usize + 2);
-
-                Ok(data)
+                limits.check_bulk_len(len)?;
+                Ok(Frame::Bulk(get_blob(cursor, len)?))
             }
             b'*' => {
                 // Example: `echo -e '*3\r\n:-78741\r\n+hello\r\n_\r\n' | nc 127.0.0.1 6379`
-                let start = cursor.position() as usize;
-                let crlf_index = start + seek_newline(cursor)?;
-                let len_u8 = get_byte_slice(cursor, start, crlf_index);
-                let len = atoi::<usize>(len_u8)
-                    .ok_or_else(|| {
-                        RedisProtocolError::ConversionError(
-                            String::from_utf8_lossy(len_u8).to_string(),
-                        )
-                    })
-                    .context("Error parsing array.")
-                    .map_err(|e| RedisProtocolError::ConversionError(e.to_string()))?;
+                let len = get_count(cursor)?;
 
                 log::debug!("Parsing array with length: {}", len);
 
-                let mut frames = Vec::with_capacity(len);
-                for _ in 0..len {
-                    let frame = Frame::parse(cursor)?;
-                    frames.push(frame);
+                Ok(Frame::Array(parse_frames(cursor, len, limits, depth + 1)?))
+            }
+            b',' => {
+                let line = get_line(cursor)?;
+                let value = std::str::from_utf8(line)
+                    .ok()
+                    .and_then(|line| line.parse::<f64>().ok())
+                    .ok_or_else(|| {
+                        RedisProtocolError::ConversionError(
+                            String::from_utf8_lossy(line).to_string(),
+                        )
+                    })?;
+                Ok(Frame::Double(value))
+            }
+            b'#' => match get_line(cursor)? {
+                b"t" => Ok(Frame::Boolean(true)),
+                b"f" => Ok(Frame::Boolean(false)),
+                line => Err(RedisProtocolError::ConversionError(
+                    String::from_utf8_lossy(line).to_string(),
+                )),
+            },
+            b'(' => {
+                let line = get_line(cursor)?;
+                let digits = line.strip_prefix(b"-").unwrap_or(line);
+                let digits = digits.strip_prefix(b"+").unwrap_or(digits);
+                if digits.is_empty() || !digits.iter().all(u8::is_ascii_digit) {
+                    return Err(RedisProtocolError::ConversionError(
+                        String::from_utf8_lossy(line).to_string(),
+                    ));
                 }
-                Ok(Frame::Array(frames))
+                Ok(Frame::BigNumber(String::from_utf8_lossy(line).to_string()))
+            }
+            b'!' => {
+                let len = get_length(cursor)?;
+                limits.check_bulk_len(len)?;
+                Ok(Frame::BlobError(get_blob(cursor, len)?))
+            }
+            b'=' => {
+                // The data is prefixed by a format of three bytes, e.g., `txt:`
+                let len = get_length(cursor)?;
+                limits.check_bulk_len(len)?;
+                let mut data = get_blob(cursor, len)?;
+                if data.len() < 4 || data[3] != b':' {
+                    return Err(RedisProtocolError::ConversionError(
+                        String::from_utf8_lossy(&data).to_string(),
+                    ));
+                }
+                let format = String::from_utf8_lossy(&data[..3]).to_string();
+                Ok(Frame::Verbatim(format, data.split_off(4)))
+            }
+            b'~' => {
+                let len = get_count(cursor)?;
+                Ok(Frame::Set(parse_frames(cursor, len, limits, depth + 1)?))
+            }
+            b'>' => {
+                let len = get_count(cursor)?;
+                Ok(Frame::Push(parse_frames(cursor, len, limits, depth + 1)?))
+            }
+            b'%' => {
+                let len = get_count(cursor)?;
+                Ok(Frame::Map(parse_pairs(cursor, len, limits, depth + 1)?))
+            }
+            b'|' => {
+                let len = get_count(cursor)?;
+                let attributes = parse_pairs(cursor, len, limits, depth + 1)?;
+                Ok(Frame::Attribute(
+                    attributes,
+                    Box::new(Frame::parse_nested(cursor, limits, depth + 1)?),
+                ))
             }
             byte => Err(RedisProtocolError::UnsupportedFrame(byte)),
         }
     }
 
+    /// Check if a frame starting with `byte` is an inline command, i.e., a line
+    /// of space-separated arguments without a RESP type prefix, e.g., `PING` typed into `nc`.
+    pub fn is_inline(byte: u8) -> bool {
+        !matches!(
+            byte,
+            b'+' | b'-'
+                | b':'
+                | b'$'
+                | b'*'
+                | b'_'
+                | b','
+                | b'#'
+                | b'('
+                | b'!'
+                | b'='
+                | b'%'
+                | b'~'
+                | b'>'
+                | b'|'
+        )
+    }
+
+    /// Parse an inline command to an array of bulk strings, the same frame as sent
+    /// by clients. The line ends with `\n` (`\r\n` or `\n`, as sent by `telnet` or `nc`),
+    /// `NotEnoughData` is returned without it.
+    ///
+    /// Arguments are split by whitespace and may be quoted as by `redis-cli`, i.e.,
+    /// `"double quotes"` with escapes like `\n` or `\x41` and `'single quotes'`.
+    /// An empty line is parsed to an empty array.
+    pub fn parse_inline(cursor: &mut Cursor<&[u8]>) -> anyhow::Result<Frame, RedisProtocolError> {
+        let start = cursor.position() as usize;
+        let line = &cursor.get_ref()[start..];
+        let end = line
+            .iter()
+            .position(|&b| b == b'\n')
+            .ok_or(RedisProtocolError::NotEnoughData)?;
+        cursor.set_position((start + end + 1) as u64);
+        let args = split_inline_args(&line[..end])?;
+        Ok(Frame::Array(args.into_iter().map(Frame::Bulk).collect()))
+    }
+
     /// Appends a new `Frame` to the `Array` variant.
     /// Returns a Result indicating success or error if called on a non-Array variant.
     pub fn append(&mut self, frame: Frame) -> anyhow::Result<()> {
@@ -144,6 +302,312 @@ impl Frame {
             bail!("Cannot append to a non-Array Frame")
         }
     }
+
+    /// Serialize the frame to `dst` in RESP2, e.g., to log commands to the append-only file.
+    ///
+    /// RESP3 frames are serialized as the closest RESP2 frames, e.g., maps as flat arrays.
+    pub fn encode(&self, dst: &mut BytesMut) {
+        match self {
+            Frame::Simple(value) => {
+                dst.put_u8(b'+');
+                dst.put_slice(value.as_bytes());
+                dst.put_slice(b"\r\n");
+            }
+            Frame::Error(value) => {
+                dst.put_u8(b'-');
+                dst.put_slice(value.as_bytes());
+                dst.put_slice(b"\r\n");
+            }
+            Frame::BlobError(value) => {
+                dst.put_u8(b'-');
+                dst.put_slice(value);
+                dst.put_slice(b"\r\n");
+            }
+            Frame::Integer(value) => {
+                dst.put_u8(b':');
+                dst.put_slice(value.to_string().as_bytes());
+                dst.put_slice(b"\r\n");
+            }
+            Frame::Boolean(value) => Frame::Integer(*value as i64).encode(dst),
+            Frame::Bulk(value) | Frame::Verbatim(_, value) => {
+                dst.put_u8(b'$');
+                dst.put_slice(value.len().to_string().as_bytes());
+                dst.put_slice(b"\r\n");
+                dst.put_slice(value);
+                dst.put_slice(b"\r\n");
+            }
+            Frame::Double(value) => Frame::Bulk(Bytes::from(format_double(*value))).encode(dst),
+            Frame::BigNumber(value) => Frame::Bulk(Bytes::from(value.clone())).encode(dst),
+            Frame::Null => dst.put_slice(b"$-1\r\n"),
+            Frame::Array(frames) | Frame::Set(frames) | Frame::Push(frames) => {
+                dst.put_u8(b'*');
+                dst.put_slice(frames.len().to_string().as_bytes());
+                dst.put_slice(b"\r\n");
+                for frame in frames {
+                    frame.encode(dst);
+                }
+            }
+            Frame::Map(pairs) => {
+                dst.put_u8(b'*');
+                dst.put_slice((pairs.len() * 2).to_string().as_bytes());
+                dst.put_slice(b"\r\n");
+                for (key, value) in pairs {
+                    key.encode(dst);
+                    value.encode(dst);
+                }
+            }
+            // Attributes are not sent to RESP2 connections
+            Frame::Attribute(_, frame) => frame.encode(dst),
+        }
+    }
+
+    /// Serialize the frame to `dst` in RESP3, i.e., for connections switched by `HELLO 3`.
+    pub fn encode_resp3(&self, dst: &mut BytesMut) {
+        match self {
+            Frame::Null => dst.put_slice(b"_\r\n"),
+            Frame::Double(value) => {
+                dst.put_u8(b',');
+                dst.put_slice(format_double(*value).as_bytes());
+                dst.put_slice(b"\r\n");
+            }
+            Frame::Boolean(value) => dst.put_slice(if *value { b"#t\r\n" } else { b"#f\r\n" }),
+            Frame::BigNumber(value) => {
+                dst.put_u8(b'(');
+                dst.put_slice(value.as_bytes());
+                dst.put_slice(b"\r\n");
+            }
+            Frame::BlobError(value) => put_blob(dst, b'!', &[value]),
+            Frame::Verbatim(format, value) => {
+                put_blob(dst, b'=', &[format.as_bytes(), b":", value]);
+            }
+            Frame::Array(frames) | Frame::Set(frames) | Frame::Push(frames) => {
+                let prefix = match self {
+                    Frame::Set(_) => b'~',
+                    Frame::Push(_) => b'>',
+                    _ => b'*',
+                };
+                put_header(dst, prefix, frames.len());
+                for frame in frames {
+                    frame.encode_resp3(dst);
+                }
+            }
+            Frame::Map(pairs) | Frame::Attribute(pairs, _) => {
+                let prefix = match self {
+                    Frame::Map(_) => b'%',
+                    _ => b'|',
+                };
+                put_header(dst, prefix, pairs.len());
+                for (key, value) in pairs {
+                    key.encode_resp3(dst);
+                    value.encode_resp3(dst);
+                }
+                // Attributes are followed by the frame they describe
+                if let Frame::Attribute(_, frame) = self {
+                    frame.encode_resp3(dst);
+                }
+            }
+            // The remaining frames are the same in RESP2
+            frame => frame.encode(dst),
+        }
+    }
+}
+
+/// Serialize the type and the length of a frame, e.g., `%2\r\n` of a map with two pairs.
+fn put_header(dst: &mut BytesMut, prefix: u8, len: usize) {
+    dst.put_u8(prefix);
+    dst.put_slice(len.to_string().as_bytes());
+    dst.put_slice(b"\r\n");
+}
+
+/// Serialize a frame with data of a known length, e.g., a blob error.
+/// The data is given in parts, e.g., the format and the text of a verbatim string.
+fn put_blob(dst: &mut BytesMut, prefix: u8, parts: &[&[u8]]) {
+    put_header(dst, prefix, parts.iter().map(|part| part.len()).sum());
+    for part in parts {
+        dst.put_slice(part);
+    }
+    dst.put_slice(b"\r\n");
+}
+
+/// Split a line of an inline command into arguments, following the rules
+/// of `sdssplitargs` of Redis.
+fn split_inline_args(line: &[u8]) -> anyhow::Result<Vec<Bytes>, RedisProtocolError> {
+    let mut args = Vec::new();
+    let mut bytes = line.iter().copied().peekable();
+    loop {
+        while bytes.next_if(u8::is_ascii_whitespace).is_some() {}
+        if bytes.peek().is_none() {
+            return Ok(args);
+        }
+        let mut arg = Vec::new();
+        // Quote of the argument, if inside of one
+        let mut quote = None;
+        loop {
+            match (quote, bytes.next()) {
+                (Some(_), None) => return Err(RedisProtocolError::UnbalancedQuotes),
+                (None, None) => break,
+                (None, Some(byte)) if byte.is_ascii_whitespace() => break,
+                (None, Some(byte @ (b'"' | b'\''))) => quote = Some(byte),
+                (None, Some(byte)) => arg.push(byte),
+                (Some(b'"'), Some(b'\\')) => match bytes.next() {
+                    Some(b'x') => {
+                        let hex = [bytes.peek().copied(), bytes.clone().nth(1)];
+                        match hex {
+                            [Some(high), Some(low)]
+                                if high.is_ascii_hexdigit() && low.is_ascii_hexdigit() =>
+                            {
+                                bytes.nth(1);
+                                let hex = [high, low];
+                                let hex = std::str::from_utf8(&hex).unwrap();
+                                arg.push(u8::from_str_radix(hex, 16).unwrap());
+                            }
+                            _ => arg.push(b'x'),
+                        }
+                    }
+                    Some(b'n') => arg.push(b'\n'),
+                    Some(b'r') => arg.push(b'\r'),
+                    Some(b't') => arg.push(b'\t'),
+                    Some(b'b') => arg.push(0x08),
+                    Some(b'a') => arg.push(0x07),
+                    Some(byte) => arg.push(byte),
+                    None => return Err(RedisProtocolError::UnbalancedQuotes),
+                },
+                (Some(b'\''), Some(b'\\')) if bytes.peek() == Some(&b'\'') => {
+                    arg.push(bytes.next().unwrap());
+                }
+                (Some(q), Some(byte)) if byte == q => {
+                    // The closing quote must be followed by a space or the end of the line
+                    if bytes.next_if(u8::is_ascii_whitespace).is_none() && bytes.peek().is_some() {
+                        return Err(RedisProtocolError::UnbalancedQuotes);
+                    }
+                    break;
+                }
+                (Some(_), Some(byte)) => arg.push(byte),
+            }
+        }
+        args.push(Bytes::from(arg));
+    }
+}
+
+/// Returns the length of a blob, e.g., of a bulk string, which may be `-1`.
+/// The `cursor` is advanced to the start of the data.
+pub(crate) fn get_length(cursor: &mut Cursor<&[u8]>) -> anyhow::Result<i64, RedisProtocolError> {
+    let start = cursor.position() as usize;
+    let crlf_index = start + seek_newline(cursor)?;
+    let len_u8 = get_byte_slice(cursor, start, crlf_index);
+    atoi::<i64>(len_u8).ok_or_else(|| {
+        RedisProtocolError::ConversionError(String::from_utf8_lossy(len_u8).to_string())
+    })
+}
+
+/// Returns the number of elements of an aggregate frame, e.g., of an array.
+/// The `cursor` is advanced to the first element.
+pub(crate) fn get_count(cursor: &mut Cursor<&[u8]>) -> anyhow::Result<usize, RedisProtocolError> {
+    let start = cursor.position() as usize;
+    let crlf_index = start + seek_newline(cursor)?;
+    let len_u8 = get_byte_slice(cursor, start, crlf_index);
+    atoi::<usize>(len_u8).ok_or_else(|| {
+        RedisProtocolError::ConversionError(String::from_utf8_lossy(len_u8).to_string())
+    })
+}
+
+/// Returns `len` bytes of data followed by CRLF and advances the cursor after the CRLF.
+fn get_blob(cursor: &mut Cursor<&[u8]>, len: i64) -> anyhow::Result<Bytes, RedisProtocolError> {
+    if len < 0 {
+        return Err(RedisProtocolError::NegativeBulkLength(len));
+    }
+    let start = cursor.position() as usize;
+    // The data may contain CRLF, so only the length is checked
+    let end = start + len as usize;
+    if cursor.get_ref().len() < end + 2 {
+        return Err(RedisProtocolError::NotEnoughData);
+    }
+    check_blob_end(cursor.get_ref(), end, len as usize)?;
+    let data = Bytes::copy_from_slice(&cursor.get_ref()[start..end]);
+    cursor.set_position((end + 2) as u64);
+    Ok(data)
+}
+
+/// Format a double the way Redis does, i.e., as `printf("%.17g")`,
+/// e.g., `3`, `1.5`, `0.10000000000000001`, `1e+300`, `inf`, `-inf` or `nan`.
+pub(crate) fn format_double(value: f64) -> String {
+    const PRECISION: i32 = 17;
+    if value.is_nan() {
+        return "nan".to_string();
+    }
+    if value.is_infinite() {
+        return if value > 0.0 { "inf" } else { "-inf" }.to_string();
+    }
+    // The exponent after rounding to the precision decides between the notations
+    let scientific = format!("{:.*e}", PRECISION as usize - 1, value);
+    let (mantissa, exponent) = scientific.split_once('e').unwrap();
+    let exponent: i32 = exponent.parse().unwrap();
+    if (-4..PRECISION).contains(&exponent) {
+        let fixed = format!("{:.*}", (PRECISION - 1 - exponent) as usize, value);
+        trim_fraction(&fixed).to_string()
+    } else {
+        let sign = if exponent < 0 { '-' } else { '+' };
+        format!(
+            "{}e{}{:02}",
+            trim_fraction(mantissa),
+            sign,
+            exponent.unsigned_abs()
+        )
+    }
+}
+
+/// Remove the trailing zeros of the fraction of a formatted number, and the point if nothing is left.
+fn trim_fraction(number: &str) -> &str {
+    match number.contains('.') {
+        true => number.trim_end_matches('0').trim_end_matches('.'),
+        false => number,
+    }
+}
+
+/// Check that the blob of `len` bytes ending at `end` of the buffer is followed by CRLF.
+pub(crate) fn check_blob_end(
+    buffer: &[u8],
+    end: usize,
+    len: usize,
+) -> anyhow::Result<(), RedisProtocolError> {
+    match buffer.get(end..end + 2) {
+        Some(b"\r\n") => Ok(()),
+        _ => Err(RedisProtocolError::UnterminatedBlob(len)),
+    }
+}
+
+/// Parse the elements of an aggregate frame of `depth`, which is checked against the `limits`.
+fn parse_frames(
+    cursor: &mut Cursor<&[u8]>,
+    len: usize,
+    limits: &ProtocolLimits,
+    depth: usize,
+) -> anyhow::Result<Vec<Frame>, RedisProtocolError> {
+    limits.check_aggregate(len, depth)?;
+    // The length is not trusted until the elements are received
+    let mut frames = Vec::with_capacity(len.min(MAX_PREALLOCATED_ELEMENTS));
+    for _ in 0..len {
+        frames.push(Frame::parse_nested(cursor, limits, depth)?);
+    }
+    Ok(frames)
+}
+
+/// Parse the pairs of a map or of an attribute of `depth`, which is checked against the `limits`.
+fn parse_pairs(
+    cursor: &mut Cursor<&[u8]>,
+    len: usize,
+    limits: &ProtocolLimits,
+    depth: usize,
+) -> anyhow::Result<Vec<(Frame, Frame)>, RedisProtocolError> {
+    limits.check_aggregate(len, depth)?;
+    let mut pairs = Vec::with_capacity(len.min(MAX_PREALLOCATED_ELEMENTS));
+    for _ in 0..len {
+        pairs.push((
+            Frame::parse_nested(cursor, limits, depth)?,
+            Frame::parse_nested(cursor, limits, depth)?,
+        ));
+    }
+    Ok(pairs)
 }
 
 /// Returns the index of the first newline character in the buffer
@@ -188,36 +652,17 @@ fn has_crlf_with_checks(cursor: &mut Cursor<&[u8]>) -> anyhow::Result<(), RedisP
     Err(RedisProtocolError::NotEnoughData)
 }
 
-/// Returns `Ok` if a closing CRLF character was found.
-/// The `cursor` is advanced to the next byte after the newline.
-fn has_crlf(cursor: &mut Cursor<&[u8]>) -> anyhow::Result<(), RedisProtocolError> {
-    while cursor.has_remaining() {
-        if is_crlf(cursor) {
-            return Ok(());
-        }
-    }
-    Err(RedisProtocolError::NotEnoughData)
-}
-
 /// Returns a slice of bytes from `start` to `end` (inclusive).
 fn get_byte_slice<'a>(cursor: &Cursor<&'a [u8]>, start: usize, end: usize) -> &'a [u8] {
     &cursor.get_ref()[start..=end]
 }
 
-/// Returns a slice of bytes from the current position to the next newline
-/// without checking for extra `\n` or `\r` bytes.
+/// Returns a slice of bytes from the current position to the next CRLF
+/// with the checks of `has_crlf_with_checks`.
 fn get_line<'a>(cursor: &mut Cursor<&'a [u8]>) -> anyhow::Result<&'a [u8], RedisProtocolError> {
     let start = cursor.position() as usize;
-    while cursor.has_remaining() {
-        if is_crlf(cursor) {
-            // -2 to exclude `\r\n`
-            let end = cursor.position() as usize - 2;
-            return Ok(&cursor.get_ref()[start..end]);
-        }
-    }
-    Err(RedisProtocolError::NotEnoughData)
-}
-
-fn is_crlf(cursor: &mut Cursor<&[u8]>) -> bool {
-    cursor.get_u8() == b'\r' && cursor.has_remaining() && cursor.get_u8() == b'\n'
+    has_crlf_with_checks(cursor)?;
+    // -2 to exclude `\r\n`
+    let end = cursor.position() as usize - 2;
+    Ok(&cursor.get_ref()[start..end])
 }
@@ -1,18 +1,34 @@
 mod client;
+mod cluster;
 mod cmd;
+mod codec;
+mod config;
 mod connection;
 mod db;
 mod frame;
 mod macros;
+mod persistence;
+mod pubsub;
+mod replication;
 mod server;
 
 pub mod common;
 pub mod constants;
 pub mod err;
 
-pub use client::RedisClient;
-pub use db::DB;
-pub use frame::Frame;
+pub use client::{ClusterClient, FromFrame, Pipeline, RedisClient, Responses, Subscriber};
+pub use cluster::{crc16, key_slot, ClusterNode, SlotRange, SlotState, SLOTS};
+pub use codec::RespCodec;
+pub use config::{AppendFsync, Config, SaveRule};
+pub use db::{
+    AutoClaim, BlockingPop, ClaimOptions, ConsumerInfo, GroupInfo, InsertPosition, LexBound,
+    ListEnd, ListWaiter, PendingEntry, PendingRange, PendingSummary, ScoreBound, SetOperation,
+    Snapshot, StreamEntry, StreamId, StreamInfo, StreamTrim, Watch, XAddId, ZAddComparison,
+    ZAddCondition, ZAddOptions, ZRangeBy, DB,
+};
+pub use frame::{Frame, Protocol, ProtocolLimits};
 #[allow(unused_imports)]
 pub use macros::*;
+pub use pubsub::{Message, PubSub};
+pub use replication::{LinkState, ReplicaInfo, Role};
 pub use server::RedisServer;
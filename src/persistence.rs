@@ -0,0 +1,564 @@
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex as StdMutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use anyhow::bail;
+use bytes::{Bytes, BytesMut};
+use tokio::sync::broadcast;
+use tokio_util::codec::Decoder;
+
+use crate::cmd::{parse_stream_id, Command};
+use crate::codec::RespCodec;
+use crate::config::{AppendFsync, Config, SaveRule};
+use crate::db::{now_ms, ClaimOptions, Snapshot, StreamId, XAddId, DB};
+use crate::frame::{Frame, ProtocolLimits};
+use crate::{array, bulk, integer, null};
+
+fn unix_time() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+/// Snapshot persistence shared by all connections, i.e., `SAVE`, `BGSAVE`, `LASTSAVE`
+/// and automatic snapshots by the save rules.
+#[derive(Debug, Clone)]
+pub(crate) struct Snapshots {
+    path: PathBuf,
+    rules: Vec<SaveRule>,
+    /// Number of writes since the last successful snapshot.
+    dirty: Arc<AtomicU64>,
+    /// Unix time of the last successful snapshot in seconds.
+    last_save: Arc<AtomicU64>,
+    in_progress: Arc<AtomicBool>,
+}
+
+impl Snapshots {
+    pub(crate) fn new(config: &Config) -> Self {
+        Self {
+            path: config.snapshot_path(),
+            rules: config.save.clone(),
+            dirty: Arc::new(AtomicU64::new(0)),
+            last_save: Arc::new(AtomicU64::new(unix_time())),
+            in_progress: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    /// Load the snapshot file into the database, if it exists.
+    pub(crate) async fn load(&self, db: &DB) -> anyhow::Result<()> {
+        let path = self.path.clone();
+        if let Some(snapshot) = tokio::task::spawn_blocking(move || Snapshot::load(&path)).await?? {
+            log::info!(
+                "Loaded {} keys from {}",
+                snapshot.len(),
+                self.path.display()
+            );
+            db.load_snapshot(snapshot).await;
+        }
+        Ok(())
+    }
+
+    pub(crate) fn record_write(&self) {
+        self.dirty.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn last_save(&self) -> u64 {
+        self.last_save.load(Ordering::Relaxed)
+    }
+
+    pub(crate) fn has_rules(&self) -> bool {
+        !self.rules.is_empty()
+    }
+
+    /// Take a snapshot and wait until it is written (`SAVE`).
+    pub(crate) async fn save(&self, db: &DB) -> anyhow::Result<()> {
+        if self.in_progress.swap(true, Ordering::AcqRel) {
+            bail!("Background save already in progress");
+        }
+        let result = self.write(db.snapshot().await).await;
+        self.in_progress.store(false, Ordering::Release);
+        result
+    }
+
+    /// Take a snapshot and write it in the background (`BGSAVE`).
+    ///
+    /// Only copying the data blocks other clients, not the encoding and writing.
+    pub(crate) async fn bgsave(&self, db: &DB) -> anyhow::Result<()> {
+        if self.in_progress.swap(true, Ordering::AcqRel) {
+            bail!("Background save already in progress");
+        }
+        let snapshot = db.snapshot().await;
+        let snapshots = self.clone();
+        tokio::spawn(async move {
+            if let Err(e) = snapshots.write(snapshot).await {
+                log::error!("Background save failed: {}", e);
+            }
+            snapshots.in_progress.store(false, Ordering::Release);
+        });
+        Ok(())
+    }
+
+    async fn write(&self, snapshot: Snapshot) -> anyhow::Result<()> {
+        let dirty = self.dirty.load(Ordering::Relaxed);
+        let path = self.path.clone();
+        let len = snapshot.len();
+        tokio::task::spawn_blocking(move || snapshot.save(&path)).await??;
+        // Writes during the save are counted towards the next snapshot
+        self.dirty.fetch_sub(dirty, Ordering::Relaxed);
+        self.last_save.store(unix_time(), Ordering::Relaxed);
+        log::info!("Saved {} keys to {}", len, self.path.display());
+        Ok(())
+    }
+
+    /// Check the save rules every second and start `BGSAVE` when any of them is met.
+    pub(crate) async fn run_save_rules(&self, db: DB, mut shutdown_rx: broadcast::Receiver<()>) {
+        let mut interval = tokio::time::interval(Duration::from_secs(1));
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown_rx.recv() => break,
+            }
+            let dirty = self.dirty.load(Ordering::Relaxed);
+            let elapsed = unix_time().saturating_sub(self.last_save());
+            let rule = self
+                .rules
+                .iter()
+                .find(|rule| dirty > 0 && dirty >= rule.changes && elapsed >= rule.seconds);
+            if let Some(rule) = rule {
+                log::info!(
+                    "{} changes in {} seconds. Saving...",
+                    rule.changes,
+                    rule.seconds
+                );
+                if let Err(e) = self.bgsave(&db).await {
+                    log::debug!("Automatic save skipped: {}", e);
+                }
+            }
+        }
+    }
+}
+
+/// Open append-only file with its size and the state of a rewrite.
+#[derive(Debug)]
+struct AofFile {
+    file: File,
+    size: u64,
+    /// Size after the last rewrite or on startup, automatic rewrites are relative to it.
+    base_size: u64,
+    /// Records appended while a rewrite is in progress, they are written after the rewritten data.
+    rewrite_buffer: Option<BytesMut>,
+}
+
+/// Append-only file shared by all connections. Every write command is appended
+/// as a RESP array, so that the database can be rebuilt by replaying the file.
+#[derive(Debug, Clone)]
+pub(crate) struct Aof {
+    path: PathBuf,
+    fsync: AppendFsync,
+    rewrite_percentage: u64,
+    rewrite_min_size: u64,
+    file: Arc<StdMutex<AofFile>>,
+}
+
+impl Aof {
+    /// Open the append-only file for appending, it is created if it does not exist.
+    pub(crate) fn open(config: &Config) -> anyhow::Result<Self> {
+        let path = config.aof_path();
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)?;
+        }
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let size = file.metadata()?.len();
+        Ok(Self {
+            path,
+            fsync: config.appendfsync,
+            rewrite_percentage: config.auto_aof_rewrite_percentage,
+            rewrite_min_size: config.auto_aof_rewrite_min_size,
+            file: Arc::new(StdMutex::new(AofFile {
+                file,
+                size,
+                base_size: size,
+                rewrite_buffer: None,
+            })),
+        })
+    }
+    /// Read the commands logged in the append-only file. Returns no commands if the file
+    /// does not exist.
+    ///
+    /// A command that was only partially written, e.g., because the server crashed,
+    /// is truncated from the end of the file.
+    pub(crate) fn load(path: &Path) -> anyhow::Result<Vec<Frame>> {
+        let mut data = match std::fs::read(path) {
+            Ok(data) => BytesMut::from(&data[..]),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+        let size = data.len();
+        // The whole file is buffered, only the limits of single frames apply
+        let mut codec = RespCodec::default().with_limits(ProtocolLimits {
+            max_query_buffer: usize::MAX,
+            ..ProtocolLimits::default()
+        });
+        let mut frames = Vec::new();
+        loop {
+            let start = size - data.len();
+            match codec.decode(&mut data) {
+                Ok(Some(frame)) => frames.push(frame),
+                Ok(None) if data.is_empty() => break,
+                Ok(None) => {
+                    log::warn!(
+                        "Truncating incomplete command at the end of {} (offset {})",
+                        path.display(),
+                        start
+                    );
+                    OpenOptions::new()
+                        .write(true)
+                        .open(path)?
+                        .set_len(start as u64)?;
+                    break;
+                }
+                Err(e) => bail!("invalid append-only file at offset {}: {}", start, e),
+            }
+        }
+        Ok(frames)
+    }
+
+    /// Append records to the file. The records are written at once,
+    /// so that a transaction is not interleaved with commands of other clients.
+    pub(crate) fn append(&self, records: &[Frame]) -> anyhow::Result<()> {
+        let mut buffer = BytesMut::new();
+        for record in records {
+            record.encode(&mut buffer);
+        }
+        let mut file = self.file.lock().unwrap();
+        file.file.write_all(&buffer)?;
+        file.size += buffer.len() as u64;
+        if let Some(rewrite_buffer) = &mut file.rewrite_buffer {
+            rewrite_buffer.extend_from_slice(&buffer);
+        }
+        if self.fsync == AppendFsync::Always {
+            file.file.sync_data()?;
+        }
+        Ok(())
+    }
+
+    /// Flush the written data to disk.
+    pub(crate) fn sync(&self) -> anyhow::Result<()> {
+        self.file.lock().unwrap().file.sync_data()?;
+        Ok(())
+    }
+
+    /// Rewrite the file in the background (`BGREWRITEAOF`).
+    ///
+    /// Only copying the data blocks other clients. Records appended in the meantime
+    /// are buffered and written after the rewritten data, before the new file
+    /// atomically replaces the current one.
+    pub(crate) async fn bgrewrite(&self, db: &DB) -> anyhow::Result<()> {
+        // Writes are logged under the lock, so none is missing from both the copy and the buffer
+        let db = db.lock().await;
+        {
+            let mut file = self.file.lock().unwrap();
+            if file.rewrite_buffer.is_some() {
+                bail!("Background append only file rewriting already in progress");
+            }
+            file.rewrite_buffer = Some(BytesMut::new());
+        }
+        let snapshot = db.snapshot().await;
+        drop(db);
+
+        let aof = self.clone();
+        tokio::spawn(async move {
+            let rewrite = aof.clone();
+            let result = tokio::task::spawn_blocking(move || rewrite.rewrite(snapshot)).await;
+            if let Err(e) = result
+                .map_err(anyhow::Error::from)
+                .and_then(|result| result)
+            {
+                log::error!("Background append only file rewriting failed: {}", e);
+                aof.file.lock().unwrap().rewrite_buffer = None;
+                let _ = std::fs::remove_file(aof.temp_path());
+            }
+        });
+        Ok(())
+    }
+
+    /// Write the commands that rebuild `snapshot` to a temporary file, followed by the
+    /// buffered records, and rename it to the append-only file.
+    fn rewrite(&self, snapshot: Snapshot) -> anyhow::Result<()> {
+        let mut buffer = BytesMut::new();
+        for command in snapshot.rewrite_commands() {
+            command.encode(&mut buffer);
+        }
+        let temp_path = self.temp_path();
+        let mut temp = File::create(&temp_path)?;
+        temp.write_all(&buffer)?;
+        temp.sync_data()?;
+
+        // Appends wait only until the records buffered in the meantime are written
+        let mut file = self.file.lock().unwrap();
+        let buffered = file.rewrite_buffer.take().unwrap_or_default();
+        temp.write_all(&buffered)?;
+        temp.sync_data()?;
+        std::fs::rename(&temp_path, &self.path)?;
+        let size = (buffer.len() + buffered.len()) as u64;
+        log::info!(
+            "Rewrote {} ({} -> {} bytes)",
+            self.path.display(),
+            file.size,
+            size
+        );
+        *file = AofFile {
+            file: temp,
+            size,
+            base_size: size,
+            rewrite_buffer: None,
+        };
+        Ok(())
+    }
+
+    /// Temporary file of a rewrite next to the append-only file, so that it can be renamed.
+    fn temp_path(&self) -> PathBuf {
+        self.path
+            .with_extension(format!("rewrite-{}", std::process::id()))
+    }
+
+    /// Check if the file grew by `auto-aof-rewrite-percentage` since the last rewrite
+    /// and is at least `auto-aof-rewrite-min-size` large.
+    fn should_rewrite(&self) -> bool {
+        if self.rewrite_percentage == 0 {
+            return false;
+        }
+        let file = self.file.lock().unwrap();
+        if file.rewrite_buffer.is_some() || file.size < self.rewrite_min_size {
+            return false;
+        }
+        let base_size = file.base_size.max(1);
+        file.size.saturating_sub(base_size) * 100 / base_size >= self.rewrite_percentage
+    }
+
+    /// Sync the file every second (`appendfsync everysec`) and start a rewrite
+    /// when the file grew enough.
+    pub(crate) async fn run_cron(&self, db: DB, mut shutdown_rx: broadcast::Receiver<()>) {
+        let mut interval = tokio::time::interval(Duration::from_secs(1));
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown_rx.recv() => break,
+            }
+            if self.fsync == AppendFsync::EverySec {
+                let aof = self.clone();
+                match tokio::task::spawn_blocking(move || aof.sync()).await {
+                    Ok(Ok(())) => {}
+                    Ok(Err(e)) => log::error!("Error syncing {}: {}", self.path.display(), e),
+                    Err(e) => log::error!("Error syncing {}: {}", self.path.display(), e),
+                }
+            }
+            if self.should_rewrite() {
+                log::info!("Append only file grew enough. Rewriting...");
+                if let Err(e) = self.bgrewrite(&db).await {
+                    log::debug!("Automatic rewrite skipped: {}", e);
+                }
+            }
+        }
+    }
+}
+
+/// Convert an executed write command to the records appended to the file, so that replaying
+/// them gives the same result.
+///
+/// Relative expirations are logged as absolute Unix times, so that a replay does not extend them.
+/// Commands with a random or time-dependent effect are logged by their effect,
+/// e.g., `SPOP` as `SREM` of the popped members or `XADD *` with the generated ID.
+/// Commands that failed or did not modify the database give no records.
+pub(crate) fn aof_records(command: Command, response: &Frame) -> anyhow::Result<Vec<Frame>> {
+    if matches!(response, Frame::Error(_)) {
+        return Ok(vec![]);
+    }
+    let record = match command {
+        Command::Set {
+            key,
+            val,
+            expiration: Some(expiration),
+        } => array!(
+            bulk!("SET"),
+            bulk!(key),
+            bulk!(val),
+            bulk!("PXAT"),
+            bulk!(expires_at(expiration).to_string())
+        ),
+        Command::Expire { key, duration } => {
+            if response != &integer!(1) {
+                return Ok(vec![]);
+            }
+            array!(
+                bulk!("PEXPIREAT"),
+                bulk!(key),
+                bulk!(expires_at(duration).to_string())
+            )
+        }
+        Command::Restore {
+            key,
+            ttl,
+            payload,
+            replace,
+            absttl: false,
+        } if ttl > 0 => Command::Restore {
+            key,
+            ttl: expires_at(Duration::from_millis(ttl)),
+            payload,
+            replace,
+            absttl: true,
+        }
+        .into_frame()?,
+        // Migrated keys are removed, keys that were copied stay
+        Command::Migrate { keys, copy, .. } => match (copy, response) {
+            (false, Frame::Simple(reply)) if reply == "OK" => Command::Del { keys }.into_frame()?,
+            _ => return Ok(vec![]),
+        },
+        // Replayed as a non-blocking pop from the list that served the client
+        Command::BPop { end, .. } => match response {
+            Frame::Array(frames) => match frames.first() {
+                Some(Frame::Bulk(key)) => Command::Pop {
+                    key: String::from_utf8(key.to_vec())?,
+                    end,
+                    count: None,
+                }
+                .into_frame()?,
+                _ => return Ok(vec![]),
+            },
+            _ => return Ok(vec![]),
+        },
+        Command::BLMove { .. } if response == &null!() => return Ok(vec![]),
+        Command::SPop { key, .. } => {
+            let members = match response {
+                Frame::Bulk(member) => vec![member.clone()],
+                Frame::Array(frames) => bulks(frames),
+                _ => vec![],
+            };
+            if members.is_empty() {
+                return Ok(vec![]);
+            }
+            Command::SRem { key, members }.into_frame()?
+        }
+        Command::XAdd {
+            key,
+            fields,
+            trim,
+            create,
+            ..
+        } => match response {
+            Frame::Bulk(id) => Command::XAdd {
+                key,
+                id: XAddId::Explicit(parse_stream_id(&String::from_utf8(id.to_vec())?, 0)?),
+                fields,
+                trim,
+                create,
+            }
+            .into_frame()?,
+            _ => return Ok(vec![]),
+        },
+        // Claims depend on the idle time, so only the claimed entries are logged
+        Command::XClaim {
+            key,
+            group,
+            consumer,
+            just_id,
+            ..
+        } => {
+            let Frame::Array(entries) = response else {
+                return Ok(vec![]);
+            };
+            return xclaim_records(key, group, consumer, entries, just_id, &[]);
+        }
+        Command::XAutoClaim {
+            key,
+            group,
+            consumer,
+            just_id,
+            ..
+        } => {
+            let Frame::Array(reply) = response else {
+                return Ok(vec![]);
+            };
+            let (Some(Frame::Array(entries)), Some(Frame::Array(deleted))) =
+                (reply.get(1), reply.get(2))
+            else {
+                return Ok(vec![]);
+            };
+            return xclaim_records(key, group, consumer, entries, just_id, deleted);
+        }
+        command => command.into_frame()?,
+    };
+    Ok(vec![record])
+}
+
+/// Log `XCLAIM` and `XAUTOCLAIM` as `XCLAIM` of the claimed entries with zero idle time.
+/// Entries that `XAUTOCLAIM` removed from the pending entries list are logged as `XACK`.
+fn xclaim_records(
+    key: String,
+    group: String,
+    consumer: String,
+    entries: &[Frame],
+    just_id: bool,
+    deleted: &[Frame],
+) -> anyhow::Result<Vec<Frame>> {
+    let ids = |frames: &[Frame]| -> anyhow::Result<Vec<StreamId>> {
+        frames
+            .iter()
+            .filter_map(|frame| match frame {
+                Frame::Array(entry) => entry.first(),
+                frame => Some(frame),
+            })
+            .map(|id| match id {
+                Frame::Bulk(id) => Ok(parse_stream_id(&String::from_utf8(id.to_vec())?, 0)?),
+                _ => bail!("unexpected stream ID: {:?}", id),
+            })
+            .collect()
+    };
+    let (claimed, deleted) = (ids(entries)?, ids(deleted)?);
+    let mut records = Vec::new();
+    if !claimed.is_empty() {
+        records.push(
+            Command::XClaim {
+                key: key.clone(),
+                group: group.clone(),
+                consumer,
+                min_idle: 0,
+                ids: claimed,
+                just_id,
+                options: ClaimOptions::default(),
+            }
+            .into_frame()?,
+        );
+    }
+    if !deleted.is_empty() {
+        records.push(
+            Command::XAck {
+                key,
+                group,
+                ids: deleted,
+            }
+            .into_frame()?,
+        );
+    }
+    Ok(records)
+}
+
+/// Absolute Unix time in milliseconds after `duration` from now.
+fn expires_at(duration: Duration) -> u64 {
+    now_ms().saturating_add(duration.as_millis() as u64)
+}
+
+fn bulks(frames: &[Frame]) -> Vec<Bytes> {
+    frames
+        .iter()
+        .filter_map(|frame| match frame {
+            Frame::Bulk(value) => Some(value.clone()),
+            _ => None,
+        })
+        .collect()
+}
@@ -0,0 +1,230 @@
+use std::collections::{BTreeSet, HashMap};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+
+use bytes::Bytes;
+use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver, UnboundedSender};
+
+/// Message published to a channel.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Message {
+    pub channel: String,
+    /// Pattern that matched the channel, if the message was received through `PSUBSCRIBE`.
+    pub pattern: Option<String>,
+    pub payload: Bytes,
+}
+
+/// Senders of the subscribed connections, keyed by the subscription ID.
+type Subscribers = HashMap<u64, UnboundedSender<Message>>;
+
+#[derive(Default)]
+struct Registry {
+    channels: HashMap<String, Subscribers>,
+    patterns: HashMap<String, (glob::Pattern, Subscribers)>,
+}
+
+/// Registry of channel and pattern subscriptions shared between connections.
+///
+/// ## Example
+/// ```
+/// let runtime = tokio::runtime::Runtime::new().unwrap();
+/// runtime.block_on(async {
+///     let pubsub = redis_clone::PubSub::new();
+///     let receivers = pubsub.publish("news", bytes::Bytes::from("hello"));
+///     assert_eq!(receivers, 0);
+/// });
+/// ```
+#[derive(Clone, Default)]
+pub struct PubSub {
+    registry: Arc<Mutex<Registry>>,
+    next_id: Arc<AtomicU64>,
+}
+
+impl PubSub {
+    /// Initialize an empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Publish `payload` to `channel`.
+    ///
+    /// Returns the number of subscriptions that received the message,
+    /// a connection subscribed to the channel and a matching pattern is counted twice.
+    pub fn publish(&self, channel: &str, payload: Bytes) -> usize {
+        let registry = self.registry.lock().unwrap();
+        let mut receivers = 0;
+        if let Some(subscribers) = registry.channels.get(channel) {
+            for sender in subscribers.values() {
+                let message = Message {
+                    channel: channel.to_string(),
+                    pattern: None,
+                    payload: payload.clone(),
+                };
+                receivers += sender.send(message).is_ok() as usize;
+            }
+        }
+        for (pattern, (glob_pattern, subscribers)) in &registry.patterns {
+            if !glob_pattern.matches(channel) {
+                continue;
+            }
+            for sender in subscribers.values() {
+                let message = Message {
+                    channel: channel.to_string(),
+                    pattern: Some(pattern.clone()),
+                    payload: payload.clone(),
+                };
+                receivers += sender.send(message).is_ok() as usize;
+            }
+        }
+        receivers
+    }
+
+    /// Get the channels with at least one subscriber, optionally matching a glob pattern.
+    pub fn channels(&self, pattern: Option<&str>) -> anyhow::Result<Vec<String>> {
+        let glob_pattern = pattern.map(glob::Pattern::new).transpose()?;
+        let registry = self.registry.lock().unwrap();
+        Ok(registry
+            .channels
+            .keys()
+            .filter(|channel| glob_pattern.as_ref().is_none_or(|p| p.matches(channel)))
+            .cloned()
+            .collect())
+    }
+
+    /// Get the number of subscribers of each channel. Pattern subscriptions are not counted.
+    pub fn numsub(&self, channels: &[String]) -> Vec<(String, usize)> {
+        let registry = self.registry.lock().unwrap();
+        channels
+            .iter()
+            .map(|channel| {
+                let count = registry.channels.get(channel).map_or(0, HashMap::len);
+                (channel.clone(), count)
+            })
+            .collect()
+    }
+
+    /// Get the number of distinct patterns subscribed to.
+    pub fn numpat(&self) -> usize {
+        self.registry.lock().unwrap().patterns.len()
+    }
+
+    /// Create the subscription state of a new connection.
+    pub(crate) fn subscription(&self) -> Subscription {
+        let (sender, receiver) = unbounded_channel();
+        Subscription {
+            id: self.next_id.fetch_add(1, Ordering::Relaxed),
+            pubsub: self.clone(),
+            sender,
+            receiver,
+            channels: BTreeSet::new(),
+            patterns: BTreeSet::new(),
+        }
+    }
+}
+
+/// Channels and patterns a single connection is subscribed to.
+///
+/// The connection is in the subscriber mode while it has at least one subscription.
+/// Dropping the subscription removes the connection from the registry.
+pub(crate) struct Subscription {
+    id: u64,
+    pubsub: PubSub,
+    sender: UnboundedSender<Message>,
+    receiver: UnboundedReceiver<Message>,
+    channels: BTreeSet<String>,
+    patterns: BTreeSet<String>,
+}
+
+impl Subscription {
+    /// Number of channels and patterns the connection is subscribed to.
+    pub(crate) fn count(&self) -> usize {
+        self.channels.len() + self.patterns.len()
+    }
+
+    pub(crate) fn is_active(&self) -> bool {
+        self.count() > 0
+    }
+
+    pub(crate) fn channels(&self) -> Vec<String> {
+        self.channels.iter().cloned().collect()
+    }
+
+    pub(crate) fn patterns(&self) -> Vec<String> {
+        self.patterns.iter().cloned().collect()
+    }
+
+    /// Subscribe to `channel`. Returns the number of subscriptions afterwards.
+    pub(crate) fn subscribe(&mut self, channel: String) -> usize {
+        if self.channels.insert(channel.clone()) {
+            let mut registry = self.pubsub.registry.lock().unwrap();
+            registry
+                .channels
+                .entry(channel)
+                .or_default()
+                .insert(self.id, self.sender.clone());
+        }
+        self.count()
+    }
+
+    /// Unsubscribe from `channel`. Returns the number of subscriptions afterwards.
+    pub(crate) fn unsubscribe(&mut self, channel: &str) -> usize {
+        if self.channels.remove(channel) {
+            let mut registry = self.pubsub.registry.lock().unwrap();
+            if let Some(subscribers) = registry.channels.get_mut(channel) {
+                subscribers.remove(&self.id);
+                if subscribers.is_empty() {
+                    registry.channels.remove(channel);
+                }
+            }
+        }
+        self.count()
+    }
+
+    /// Subscribe to channels matching a glob `pattern`.
+    /// Returns the number of subscriptions afterwards.
+    pub(crate) fn psubscribe(&mut self, pattern: String) -> anyhow::Result<usize> {
+        if !self.patterns.contains(&pattern) {
+            let glob_pattern = glob::Pattern::new(&pattern)?;
+            let mut registry = self.pubsub.registry.lock().unwrap();
+            registry
+                .patterns
+                .entry(pattern.clone())
+                .or_insert_with(|| (glob_pattern, HashMap::new()))
+                .1
+                .insert(self.id, self.sender.clone());
+            self.patterns.insert(pattern);
+        }
+        Ok(self.count())
+    }
+
+    /// Unsubscribe from `pattern`. Returns the number of subscriptions afterwards.
+    pub(crate) fn punsubscribe(&mut self, pattern: &str) -> usize {
+        if self.patterns.remove(pattern) {
+            let mut registry = self.pubsub.registry.lock().unwrap();
+            if let Some((_, subscribers)) = registry.patterns.get_mut(pattern) {
+                subscribers.remove(&self.id);
+                if subscribers.is_empty() {
+                    registry.patterns.remove(pattern);
+                }
+            }
+        }
+        self.count()
+    }
+
+    /// Receive the next published message. Pending forever if there are no subscriptions.
+    pub(crate) async fn recv(&mut self) -> Option<Message> {
+        // The subscription holds a sender, so the channel is never closed
+        self.receiver.recv().await
+    }
+}
+
+impl Drop for Subscription {
+    fn drop(&mut self) {
+        for channel in self.channels() {
+            self.unsubscribe(&channel);
+        }
+        for pattern in self.patterns() {
+            self.punsubscribe(&pattern);
+        }
+    }
+}
@@ -0,0 +1,480 @@
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex as StdMutex};
+
+use bytes::{Bytes, BytesMut};
+use tokio::sync::futures::Notified;
+use tokio::sync::{broadcast, Notify};
+use tokio::task::AbortHandle;
+
+use crate::common::random_id;
+use crate::config::Config;
+use crate::constants::REPL_PING_PERIOD;
+use crate::frame::Frame;
+use crate::{array, bulk};
+
+/// Capacity of the channel streaming the replication data to the connected replicas.
+/// A replica that falls behind by more messages is disconnected and resynchronized.
+const STREAM_CAPACITY: usize = 16 * 1024;
+
+/// State of the link of a replica to its primary, as reported by `ROLE`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkState {
+    /// Waiting to reconnect.
+    Connect,
+    /// Handshake with the primary.
+    Connecting,
+    /// Receiving the snapshot of the primary.
+    Sync,
+    /// Receiving the stream of write commands.
+    Connected,
+}
+
+impl LinkState {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            LinkState::Connect => "connect",
+            LinkState::Connecting => "connecting",
+            LinkState::Sync => "sync",
+            LinkState::Connected => "connected",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "connect" => Some(LinkState::Connect),
+            "connecting" => Some(LinkState::Connecting),
+            "sync" => Some(LinkState::Sync),
+            "connected" => Some(LinkState::Connected),
+            _ => None,
+        }
+    }
+}
+
+/// Replica connected to a primary, as reported by `ROLE`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReplicaInfo {
+    pub ip: String,
+    /// Listening port announced by the replica (`REPLCONF listening-port`).
+    pub port: u16,
+    /// Replication offset acknowledged by the replica.
+    pub offset: u64,
+}
+
+/// Replication role of a server, the reply of `ROLE`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Role {
+    Primary {
+        offset: u64,
+        replicas: Vec<ReplicaInfo>,
+    },
+    Replica {
+        host: String,
+        port: u16,
+        state: LinkState,
+        /// Offset of the replication stream processed by the replica.
+        offset: u64,
+    },
+}
+
+/// Circular buffer with the latest bytes of the replication stream,
+/// so that a replica can continue after a short disconnect (`PSYNC`).
+#[derive(Debug)]
+struct Backlog {
+    data: VecDeque<u8>,
+    capacity: usize,
+}
+
+impl Backlog {
+    fn new(capacity: usize) -> Self {
+        Self {
+            data: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    fn push(&mut self, bytes: &[u8]) {
+        let bytes = &bytes[bytes.len().saturating_sub(self.capacity)..];
+        let overflow = (self.data.len() + bytes.len()).saturating_sub(self.capacity);
+        self.data.drain(..overflow);
+        self.data.extend(bytes);
+    }
+}
+
+/// Link of a replica to its primary.
+#[derive(Debug)]
+struct PrimaryLink {
+    host: String,
+    port: u16,
+    state: LinkState,
+    /// The replica has synchronized with the primary at least once,
+    /// so it may ask for a partial resynchronization.
+    synced: bool,
+    task: Option<AbortHandle>,
+}
+
+#[derive(Debug)]
+struct State {
+    replication_id: String,
+    /// Length of the replication stream so far, i.e., the offset of its next byte.
+    offset: u64,
+    /// Created when the first replica connects.
+    backlog: Option<Backlog>,
+    /// Primary of a replica, `None` for a primary.
+    primary: Option<PrimaryLink>,
+    replicas: HashMap<SocketAddr, Replica>,
+}
+
+/// Replica served by this server.
+#[derive(Debug)]
+struct Replica {
+    info: ReplicaInfo,
+    /// Replication offset written to the append-only file of the replica and synced
+    /// to disk (`REPLCONF ACK offset FACK aof_offset`).
+    aof_offset: u64,
+}
+
+impl State {
+    /// Append data to the replication stream.
+    fn feed(&mut self, data: Bytes, sender: &broadcast::Sender<Bytes>) {
+        self.offset += data.len() as u64;
+        if let Some(backlog) = &mut self.backlog {
+            backlog.push(&data);
+        }
+        // Fails if no replica is connected
+        let _ = sender.send(data);
+    }
+}
+
+/// Replication state shared by all connections, i.e., the role of the server,
+/// the replication stream with its backlog and the connected replicas.
+#[derive(Debug, Clone)]
+pub(crate) struct Replication {
+    state: Arc<StdMutex<State>>,
+    sender: broadcast::Sender<Bytes>,
+    /// Notifies `WAIT` and `WAITAOF` of acknowledgements by the replicas.
+    acked: Arc<Notify>,
+    backlog_size: usize,
+    /// Port of this server, announced to the primary.
+    port: u16,
+}
+
+/// Removes a replica from the connected replicas when its connection is closed.
+pub(crate) struct ReplicaGuard {
+    replication: Replication,
+    addr: SocketAddr,
+}
+
+impl Drop for ReplicaGuard {
+    fn drop(&mut self) {
+        self.replication.lock().replicas.remove(&self.addr);
+    }
+}
+
+impl Replication {
+    /// Create the replication state. The server is a replica if `replicaof` is configured,
+    /// but the replication does not start until [`Replication::connect`].
+    pub(crate) fn new(config: &Config, port: u16) -> Self {
+        let primary = config.replicaof.clone().map(|(host, port)| PrimaryLink {
+            host,
+            port,
+            state: LinkState::Connect,
+            synced: false,
+            task: None,
+        });
+        Self {
+            state: Arc::new(StdMutex::new(State {
+                replication_id: random_id(),
+                offset: 0,
+                backlog: None,
+                primary,
+                replicas: HashMap::new(),
+            })),
+            sender: broadcast::channel(STREAM_CAPACITY).0,
+            acked: Arc::new(Notify::new()),
+            backlog_size: config.repl_backlog_size as usize,
+            port,
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, State> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub(crate) fn port(&self) -> u16 {
+        self.port
+    }
+
+    pub(crate) fn is_replica(&self) -> bool {
+        self.lock().primary.is_some()
+    }
+
+    /// Write commands are propagated only by a primary with connected (or recently
+    /// disconnected) replicas, a replica forwards the stream of its primary instead.
+    pub(crate) fn is_propagating(&self) -> bool {
+        let state = self.lock();
+        state.primary.is_none() && state.backlog.is_some()
+    }
+
+    /// Append the records of executed write commands to the replication stream.
+    /// Must be called with the database locked, so that the stream has the order
+    /// in which the commands were executed.
+    pub(crate) fn propagate(&self, records: &[Frame]) {
+        let mut state = self.lock();
+        if records.is_empty() || state.primary.is_some() || state.backlog.is_none() {
+            return;
+        }
+        let mut data = BytesMut::new();
+        for record in records {
+            record.encode(&mut data);
+        }
+        state.feed(data.freeze(), &self.sender);
+    }
+
+    /// Append data received from the primary to the stream of this replica,
+    /// so that it has the same offset and backlog, i.e., it may become a primary
+    /// that its own replicas continue to follow.
+    pub(crate) fn forward(&self, data: Bytes) {
+        self.lock().feed(data, &self.sender);
+    }
+
+    /// Start a full resynchronization of a replica. Must be called with the database locked
+    /// in the same critical section as the snapshot of the database is taken.
+    ///
+    /// Returns the replication ID, the offset of the snapshot and the receiver
+    /// of the data that follows.
+    pub(crate) fn full_resync(&self) -> (String, u64, broadcast::Receiver<Bytes>) {
+        let mut state = self.lock();
+        if state.backlog.is_none() {
+            state.backlog = Some(Backlog::new(self.backlog_size));
+        }
+        (
+            state.replication_id.clone(),
+            state.offset,
+            self.sender.subscribe(),
+        )
+    }
+
+    /// Continue the replication from `offset`, i.e., the offset of the next byte
+    /// the replica expects plus one (`PSYNC`), if the history matches and the backlog
+    /// still has the data.
+    ///
+    /// Returns the replication ID, the missing part of the stream and the receiver
+    /// of the data that follows.
+    pub(crate) fn partial_resync(
+        &self,
+        replication_id: &str,
+        offset: i64,
+    ) -> Option<(String, Bytes, broadcast::Receiver<Bytes>)> {
+        let state = self.lock();
+        let backlog = state.backlog.as_ref()?;
+        if replication_id != state.replication_id || offset < 1 {
+            return None;
+        }
+        let next = offset as u64 - 1;
+        let first = state.offset - backlog.data.len() as u64;
+        if next < first || next > state.offset {
+            return None;
+        }
+        let missing = backlog
+            .data
+            .range((next - first) as usize..)
+            .copied()
+            .collect::<Vec<u8>>();
+        Some((
+            state.replication_id.clone(),
+            Bytes::from(missing),
+            self.sender.subscribe(),
+        ))
+    }
+
+    /// Register a replica that is served the replication stream.
+    pub(crate) fn add_replica(&self, addr: SocketAddr, port: Option<u16>) -> ReplicaGuard {
+        self.lock().replicas.insert(
+            addr,
+            Replica {
+                info: ReplicaInfo {
+                    ip: addr.ip().to_string(),
+                    port: port.unwrap_or(addr.port()),
+                    offset: 0,
+                },
+                aof_offset: 0,
+            },
+        );
+        ReplicaGuard {
+            replication: self.clone(),
+            addr,
+        }
+    }
+
+    /// Offset of the replication stream, i.e., its length so far.
+    pub(crate) fn offset(&self) -> u64 {
+        self.lock().offset
+    }
+
+    /// Record the offset processed by a replica (`REPLCONF ACK`), and the offset
+    /// synced to its append-only file, if enabled (`FACK`).
+    pub(crate) fn ack(&self, addr: SocketAddr, offset: u64, aof_offset: Option<u64>) {
+        if let Some(replica) = self.lock().replicas.get_mut(&addr) {
+            replica.info.offset = replica.info.offset.max(offset);
+            if let Some(aof_offset) = aof_offset {
+                replica.aof_offset = replica.aof_offset.max(aof_offset);
+            }
+        }
+        self.acked.notify_waiters();
+    }
+
+    /// Number of replicas that acknowledged the offset, or synced it to their
+    /// append-only file if `aof` is set.
+    pub(crate) fn acked_replicas(&self, offset: u64, aof: bool) -> usize {
+        self.lock()
+            .replicas
+            .values()
+            .filter(|replica| match aof {
+                true => replica.aof_offset >= offset,
+                false => replica.info.offset >= offset,
+            })
+            .count()
+    }
+
+    /// Completes on the next acknowledgement by a replica.
+    pub(crate) fn acked(&self) -> Notified<'_> {
+        self.acked.notified()
+    }
+
+    /// Ask the replicas to acknowledge their offset right away (`REPLCONF GETACK *`).
+    pub(crate) fn request_ack(&self) {
+        self.propagate(&[array!(bulk!("REPLCONF"), bulk!("GETACK"), bulk!("*"))]);
+    }
+
+    /// Send `PING` to the replicas every `REPL_PING_PERIOD`, so that they detect
+    /// a broken link to the primary.
+    pub(crate) async fn run_cron(&self, mut shutdown_rx: broadcast::Receiver<()>) {
+        let mut interval = tokio::time::interval(REPL_PING_PERIOD);
+        interval.tick().await;
+        loop {
+            tokio::select! {
+                _ = interval.tick() => {}
+                _ = shutdown_rx.recv() => break,
+            }
+            if self.sender.receiver_count() > 0 {
+                self.propagate(&[array!(bulk!("PING"))]);
+            }
+        }
+    }
+
+    /// Make this server a replica of the primary, `start` spawns the task that keeps
+    /// the replica in sync. The link to the previous primary is aborted.
+    ///
+    /// Returns `false` if the server already is a replica of the primary.
+    pub(crate) fn connect<F>(&self, host: String, port: u16, start: F) -> bool
+    where
+        F: FnOnce() -> AbortHandle,
+    {
+        let mut state = self.lock();
+        if let Some(primary) = &state.primary {
+            if primary.host == host && primary.port == port && primary.task.is_some() {
+                return false;
+            }
+            if let Some(task) = &primary.task {
+                task.abort();
+            }
+        }
+        // The task waits for the lock, so it cannot see the previous primary
+        state.primary = Some(PrimaryLink {
+            host,
+            port,
+            state: LinkState::Connect,
+            synced: false,
+            task: Some(start()),
+        });
+        true
+    }
+
+    /// Primary configured on startup (`--replicaof`) that is not connected yet.
+    pub(crate) fn configured_primary(&self) -> Option<(String, u16)> {
+        match &self.lock().primary {
+            Some(primary) if primary.task.is_none() => Some((primary.host.clone(), primary.port)),
+            _ => None,
+        }
+    }
+
+    /// Stop the replication and become a primary (`REPLICAOF NO ONE`). The data is kept,
+    /// but it starts a new replication history.
+    pub(crate) fn promote(&self) {
+        let mut state = self.lock();
+        if let Some(primary) = state.primary.take() {
+            if let Some(task) = primary.task {
+                task.abort();
+            }
+            state.replication_id = random_id();
+        }
+    }
+
+    /// Abort the link to the primary on shutdown.
+    pub(crate) fn stop(&self) {
+        if let Some(task) = self.lock().primary.as_mut().and_then(|p| p.task.take()) {
+            task.abort();
+        }
+    }
+
+    pub(crate) fn set_link_state(&self, link_state: LinkState) {
+        if let Some(primary) = &mut self.lock().primary {
+            primary.state = link_state;
+        }
+    }
+
+    /// Replication ID and offset to continue the replication from (`PSYNC`),
+    /// or `?` and `-1` if the replica has never been synchronized.
+    pub(crate) fn psync_position(&self) -> (String, i64) {
+        let state = self.lock();
+        match &state.primary {
+            Some(primary) if primary.synced => {
+                (state.replication_id.clone(), state.offset as i64 + 1)
+            }
+            _ => ("?".to_string(), -1),
+        }
+    }
+
+    /// The replica was synchronized with its primary. A full resynchronization
+    /// (`offset` is given) replaces the data, so the backlog is dropped.
+    pub(crate) fn synced(&self, replication_id: Option<String>, offset: Option<u64>) {
+        let mut state = self.lock();
+        if let Some(replication_id) = replication_id {
+            state.replication_id = replication_id;
+        }
+        if let Some(offset) = offset {
+            state.offset = offset;
+            if state.backlog.is_some() {
+                state.backlog = Some(Backlog::new(self.backlog_size));
+            }
+        }
+        if let Some(primary) = &mut state.primary {
+            primary.synced = true;
+        }
+    }
+
+    pub(crate) fn role(&self) -> Role {
+        let state = self.lock();
+        match &state.primary {
+            Some(primary) => Role::Replica {
+                host: primary.host.clone(),
+                port: primary.port,
+                state: primary.state,
+                offset: state.offset,
+            },
+            None => {
+                let mut replicas: Vec<ReplicaInfo> = state
+                    .replicas
+                    .values()
+                    .map(|replica| replica.info.clone())
+                    .collect();
+                replicas.sort_by(|a, b| (&a.ip, a.port).cmp(&(&b.ip, b.port)));
+                Role::Primary {
+                    offset: state.offset,
+                    replicas,
+                }
+            }
+        }
+    }
+}
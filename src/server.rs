@@ -3,17 +3,28 @@ use std::net::SocketAddr;
 use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
 
-use tokio::net::TcpListener;
+use anyhow::{anyhow, bail};
+use bytes::{Bytes, BytesMut};
+use tokio::net::{TcpListener, TcpStream};
 use tokio::sync::broadcast;
+use tokio::sync::broadcast::error::RecvError;
 use tokio::time::{timeout, Duration};
 
+use crate::cluster::{key_slot, Cluster};
 use crate::cmd::Command;
+use crate::config::Config;
 use crate::connection::Connection;
-use crate::constants::{MAX_CLIENTS, SERVER_SHUTDOWN_CONNECTION_TIMEOUT, TIMEOUT_DURATION};
-use crate::db::DB;
-use crate::err::RedisCommandError;
-use crate::frame::Frame;
-use crate::{bulk, error, integer, null, simple};
+use crate::constants::{
+    CLIENT_CONNECTION_TIMEOUT, MAX_CLIENTS, REPL_ACK_PERIOD, REPL_RETRY_INTERVAL, REPL_TIMEOUT,
+    SERVER_SHUTDOWN_CONNECTION_TIMEOUT, TIMEOUT_DURATION,
+};
+use crate::db::{now_ms, BlockingPop, ListEnd, Snapshot, StreamEntry, Watch, DB};
+use crate::err::{RedisClusterError, RedisCommandError, RedisDatabaseError, RedisProtocolError};
+use crate::frame::{format_double, Frame, Protocol, ProtocolLimits};
+use crate::persistence::{aof_records, Aof, Snapshots};
+use crate::pubsub::{Message, PubSub, Subscription};
+use crate::replication::{LinkState, Replication, Role};
+use crate::{array, bulk, error, integer, null, simple};
 
 /// A guard to keep track of the number of active clients.
 struct ClientGuard {
@@ -33,29 +44,78 @@ impl Drop for ClientGuard {
     }
 }
 
+/// State shared by all connections, besides the database.
+#[derive(Clone)]
+struct Shared {
+    pubsub: PubSub,
+    snapshots: Snapshots,
+    /// Append-only file, if enabled.
+    aof: Option<Aof>,
+    replication: Replication,
+    /// Nodes of the cluster, if the cluster mode is enabled.
+    cluster: Option<Cluster>,
+}
+
+/// Commands queued after `MULTI`, executed atomically by `EXEC`.
+#[derive(Default)]
+struct Transaction {
+    commands: Vec<Command>,
+    /// A command failed to queue, so `EXEC` discards the transaction.
+    failed: bool,
+}
+
 pub struct RedisServer {
     listener: TcpListener,
     db: DB,
+    shared: Shared,
     shutdown: broadcast::Sender<()>,
     handles: Vec<tokio::task::JoinHandle<()>>,
     client_count: Arc<AtomicUsize>,
+    /// Limits of the frames received from clients.
+    limits: ProtocolLimits,
 
     address: String,
     port: u16, // default Redis port is 6379
 }
 
 impl RedisServer {
+    /// Create a server with the default configuration. The snapshot file `./dump.snap`
+    /// is loaded if it exists, but it is only written by `SAVE` and `BGSAVE`.
     pub async fn new(address: &str, port: u16) -> anyhow::Result<Self> {
+        Self::with_config(address, port, Config::default()).await
+    }
+
+    /// Create a server. The append-only file is replayed if it is enabled,
+    /// otherwise the snapshot file is loaded if it exists.
+    pub async fn with_config(address: &str, port: u16, config: Config) -> anyhow::Result<Self> {
         let listener = TcpListener::bind((address, port)).await?;
         let db = DB::new();
         let (shutdown, _) = broadcast::channel(1);
 
+        let mut shared = Shared {
+            pubsub: PubSub::new(),
+            snapshots: Snapshots::new(&config),
+            aof: None,
+            replication: Replication::new(&config, listener.local_addr()?.port()),
+            cluster: Cluster::open(&config, address, listener.local_addr()?.port())?,
+        };
+        if config.appendonly {
+            let path = config.aof_path();
+            let frames = tokio::task::spawn_blocking(move || Aof::load(&path)).await??;
+            Self::replay(frames, &db, &shared).await?;
+            shared.aof = Some(Aof::open(&config)?);
+        } else {
+            shared.snapshots.load(&db).await?;
+        }
+
         Ok(RedisServer {
             listener,
             db,
+            shared,
             shutdown,
             handles: Vec::new(),
             client_count: Arc::new(AtomicUsize::new(0)),
+            limits: config.protocol_limits(),
             address: address.to_string(),
             port,
         })
@@ -79,6 +139,37 @@ impl RedisServer {
 
         let mut shutdown_rx = self.shutdown.subscribe();
 
+        // Automatic snapshots by the save rules
+        if self.shared.snapshots.has_rules() {
+            let snapshots = self.shared.snapshots.clone();
+            let db = self.db.clone();
+            let shutdown_rx = self.shutdown.subscribe();
+            self.handles.push(tokio::spawn(async move {
+                snapshots.run_save_rules(db, shutdown_rx).await;
+            }));
+        }
+
+        // Periodic sync and automatic rewrites of the append-only file
+        if let Some(aof) = self.shared.aof.clone() {
+            let db = self.db.clone();
+            let shutdown_rx = self.shutdown.subscribe();
+            self.handles.push(tokio::spawn(async move {
+                aof.run_cron(db, shutdown_rx).await;
+            }));
+        }
+
+        // Pings of the replicas, and the replication of the configured primary
+        {
+            let replication = self.shared.replication.clone();
+            let shutdown_rx = self.shutdown.subscribe();
+            self.handles.push(tokio::spawn(async move {
+                replication.run_cron(shutdown_rx).await;
+            }));
+        }
+        if let Some((host, port)) = self.shared.replication.configured_primary() {
+            Self::start_replication(host, port, &self.db, &self.shared);
+        }
+
         // Setup Ctrl+C signal to shutdown the server.
         let shutdown_handle = self.get_shutdown_handle();
         self.handles.push(tokio::spawn(async move {
@@ -105,12 +196,13 @@ impl RedisServer {
                             }
 
                             let db = self.db.clone();
+                            let shared = self.shared.clone();
                             let shutdown_rx = self.shutdown.subscribe();
                             let client_count = Arc::clone(&self.client_count);
 
                             // Spawn a new task for each connection.
                             self.handles.push(tokio::spawn(async move {
-                                match Self::handle_client_connection(connection, db, addr, shutdown_rx, client_count).await {
+                                match Self::handle_client_connection(connection, db, shared, addr, shutdown_rx, client_count).await {
                                     Ok(_) => log::info!("Closed connection: {}", addr),
                                     Err(e) => log::error!("Connection error for {}: {}", addr, e),
                                 };
@@ -132,6 +224,9 @@ impl RedisServer {
     }
 
     async fn shutdown(&mut self) -> anyhow::Result<()> {
+        // Stop the replication from the primary
+        self.shared.replication.stop();
+
         // Stop database expiration task
         self.db.shutdown().await?;
 
@@ -142,9 +237,58 @@ impl RedisServer {
                 Err(e) => log::error!("Error shutting down connection: {}", e),
             }
         }
+
+        // Save the final state if snapshots are enabled
+        if self.shared.snapshots.has_rules() {
+            if let Err(e) = self.shared.snapshots.save(&self.db).await {
+                log::error!("Error saving snapshot on shutdown: {}", e);
+            }
+        }
+        if let Some(aof) = &self.shared.aof {
+            if let Err(e) = aof.sync() {
+                log::error!("Error syncing append-only file on shutdown: {}", e);
+            }
+        }
         Ok(())
     }
 
+    /// Execute the commands read from the append-only file. Commands of a transaction
+    /// that was not completed by `EXEC` at the end of the file are discarded.
+    async fn replay(frames: Vec<Frame>, db: &DB, shared: &Shared) -> anyhow::Result<()> {
+        let mut transaction: Option<Vec<Command>> = None;
+        let mut count = 0;
+        for frame in frames {
+            match Command::from_frame(frame)? {
+                Command::Multi => transaction = Some(Vec::new()),
+                Command::Exec => {
+                    for command in transaction.take().unwrap_or_default() {
+                        Self::replay_command(command, db, shared).await;
+                        count += 1;
+                    }
+                }
+                command => match transaction.as_mut() {
+                    Some(commands) => commands.push(command),
+                    None => {
+                        Self::replay_command(command, db, shared).await;
+                        count += 1;
+                    }
+                },
+            }
+        }
+        if transaction.is_some() {
+            log::warn!("Discarded an incomplete transaction at the end of the append-only file");
+        }
+        log::info!("Replayed {} commands from the append-only file", count);
+        Ok(())
+    }
+
+    async fn replay_command(command: Command, db: &DB, shared: &Shared) {
+        let command = Self::without_blocking(command);
+        if let Frame::Error(e) = Self::execute_command(command, db, shared).await {
+            log::warn!("Error replaying command from the append-only file: {}", e);
+        }
+    }
+
     /// Get a handle to the shutdown signal.
     pub fn get_shutdown_handle(&self) -> broadcast::Sender<()> {
         self.shutdown.clone()
@@ -154,72 +298,925 @@ impl RedisServer {
     async fn accept_connection(&self) -> anyhow::Result<(Connection, SocketAddr)> {
         let (socket, addr) = self.listener.accept().await?;
         log::info!("Accepted connection from: {}", addr);
-        Ok((Connection::new(socket), addr))
+        // Replies of pipelined commands are flushed in batches, which must not wait
+        // for the acknowledgement of the previous batch (Nagle's algorithm)
+        socket.set_nodelay(true)?;
+        Ok((Connection::with_limits(socket, self.limits), addr))
     }
 
     async fn handle_client_connection(
         mut conn: Connection,
         db: DB,
+        shared: Shared,
         addr: SocketAddr,
         mut shutdown_rx: broadcast::Receiver<()>,
         client_count: Arc<AtomicUsize>,
     ) -> anyhow::Result<()> {
         let _guard = ClientGuard::new(client_count);
+        let mut subscription = shared.pubsub.subscription();
+        let mut transaction: Option<Transaction> = None;
+        let mut watch = db.watcher();
+        // Listening port announced by a replica (`REPLCONF listening-port`)
+        let mut replica_port = None;
+        // Set by `ASKING` for the next command
+        let mut asking = false;
         loop {
-            let frame = tokio::select! {
-                result = timeout(TIMEOUT_DURATION, conn.read_frame()) => {
-                    match result {
-                        Ok(frame_result) => {
-                            match frame_result? {
-                                Some(frame) => frame,
-                                None => break Ok(()),
+            // Subscribers wait for messages, so they are not disconnected when idle
+            let read_timeout = if subscription.is_active() {
+                Duration::MAX
+            } else {
+                TIMEOUT_DURATION
+            };
+            // Pipelined commands that are already buffered are served before the replies
+            // are flushed, so a batch of commands costs a single write to the socket
+            let frame = match conn.parse_frame() {
+                Ok(Some(frame)) => frame,
+                Err(e) => break Self::close_with_protocol_error(&mut conn, addr, e).await,
+                Ok(None) => {
+                    if !Self::flush_responses(&mut conn, addr).await? {
+                        break Ok(());
+                    }
+                    tokio::select! {
+                        result = timeout(read_timeout, conn.read_frame()) => {
+                            match result {
+                                Ok(frame_result) => {
+                                    match frame_result {
+                                        Ok(Some(frame)) => frame,
+                                        Ok(None) => break Ok(()),
+                                        Err(e) => {
+                                            break Self::close_with_protocol_error(&mut conn, addr, e)
+                                                .await
+                                        }
+                                    }
+                                }
+                                Err(_) => {
+                                    log::warn!("Client {} connection timed out after {} seconds",
+                                        addr, TIMEOUT_DURATION.as_secs());
+                                    break Ok(());
+                                }
                             }
                         }
-                        Err(_) => {
-                            log::warn!("Client {} connection timed out after {} seconds",
-                                addr, TIMEOUT_DURATION.as_secs());
+                        Some(message) = subscription.recv() => {
+                            let message = Self::message_frame(message);
+                            if !Self::write_response(&mut conn, addr, &message).await?
+                                || !Self::flush_responses(&mut conn, addr).await?
+                            {
+                                break Ok(());
+                            }
+                            continue;
+                        }
+                        _ = shutdown_rx.recv() => {
+                            log::info!("Shutdown signal received, closing connection: {}", addr);
                             break Ok(());
                         }
                     }
                 }
-                _ = shutdown_rx.recv() => {
-                    log::info!("Shutdown signal received, closing connection: {}", addr);
-                    break Ok(());
-                }
             };
 
             log::debug!("Received from {}: {:?}", addr, frame);
 
-            let response = match Command::from_frame(frame) {
-                Ok(command) => Self::handle_command(command, &db).await,
-                Err(e) => error!(format!("ERR {}", e)),
+            let command = Command::from_frame(frame);
+            let asked = std::mem::take(&mut asking);
+            // Keys served by other nodes of the cluster are redirected
+            let redirection = match (&command, &shared.cluster) {
+                // `MIGRATE` moves the keys that exist here, missing keys are reported by `NOKEY`
+                (Ok(Command::Migrate { .. }), _) => None,
+                (Ok(command), Some(cluster)) => cluster
+                    .check_keys(&command.keys(), asked, &db)
+                    .await
+                    .err()
+                    .map(|e| error!(e.to_string())),
+                _ => None,
             };
-
-            match timeout(TIMEOUT_DURATION, conn.write_frame(&response)).await {
-                Ok(result) => match result {
-                    Ok(_) => log::debug!("Written to {}: {:?}", addr, response),
-                    Err(e) => {
-                        log::error!("Error writing to {}: {}", addr, e);
-                        break Err(e);
+            let resp3 = conn.protocol() == Protocol::Resp3;
+            let responses = match command {
+                // Commands that change the state of the connection are not queued
+                Ok(command)
+                    if transaction.is_some()
+                        && (command.is_subscription()
+                            || matches!(command, Command::Hello { .. })) =>
+                {
+                    transaction.as_mut().unwrap().failed = true;
+                    vec![error!("ERR Command not allowed inside a transaction")]
+                }
+                Ok(command) if command.is_subscription() => {
+                    Self::handle_subscription(command, &mut subscription)
+                }
+                // Only subscription commands and `PING` are allowed in the subscriber mode of RESP2,
+                // RESP3 separates the messages from the replies by pushes
+                Ok(Command::Ping { msg }) if subscription.is_active() && !resp3 => {
+                    vec![array!(bulk!("pong"), bulk!(msg.unwrap_or_default()))]
+                }
+                Ok(_) if subscription.is_active() && !resp3 => vec![error!(
+                    "ERR only (P)SUBSCRIBE / (P)UNSUBSCRIBE / PING are allowed in this context"
+                )],
+                Ok(_) if redirection.is_some() => {
+                    if let Some(transaction) = transaction.as_mut() {
+                        transaction.failed = true;
                     }
+                    vec![redirection.unwrap()]
+                }
+                Ok(Command::Asking) => match shared.cluster {
+                    Some(_) => {
+                        asking = true;
+                        vec![simple!("OK")]
+                    }
+                    None => vec![error!(RedisClusterError::Disabled.to_string())],
                 },
-                Err(_) => {
-                    log::warn!(
-                        "Client {} write timed out after {} seconds",
+                Ok(Command::Hello { protocol }) => {
+                    vec![Self::handle_hello(protocol, &mut conn, &shared)]
+                }
+                // Replicas only apply the writes of their primary
+                Ok(command) if command.is_write() && shared.replication.is_replica() => {
+                    if let Some(transaction) = transaction.as_mut() {
+                        transaction.failed = true;
+                    }
+                    vec![error!(
+                        "READONLY You can't write against a read only replica."
+                    )]
+                }
+                Ok(Command::ReplicaOf { primary }) if transaction.is_none() => {
+                    vec![Self::handle_replicaof(primary, &db, &shared)]
+                }
+                Ok(Command::ReplConf { options }) if transaction.is_none() => {
+                    vec![Self::handle_replconf(options, &mut replica_port)]
+                }
+                // The connection is handed over to the replication stream
+                Ok(command @ (Command::Sync | Command::PSync { .. })) if transaction.is_none() => {
+                    return Self::serve_replica(
+                        conn,
+                        command,
+                        db,
+                        shared,
                         addr,
-                        TIMEOUT_DURATION.as_secs()
-                    );
-                    break Ok(());
+                        replica_port,
+                        shutdown_rx,
+                    )
+                    .await;
+                }
+                Ok(command) if command.is_transaction() => vec![
+                    Self::handle_transaction(command, &db, &shared, &mut transaction, &mut watch)
+                        .await,
+                ],
+                // Commands after `MULTI` are queued until `EXEC`
+                Ok(Command::Unknown(cmd)) if transaction.is_some() => {
+                    transaction.as_mut().unwrap().failed = true;
+                    vec![error!(format!(
+                        "ERR {}",
+                        RedisCommandError::InvalidCommand(cmd)
+                    ))]
+                }
+                Ok(command) if transaction.is_some() => {
+                    transaction.as_mut().unwrap().commands.push(command);
+                    vec![simple!("QUEUED")]
+                }
+                Err(e) if transaction.is_some() => {
+                    transaction.as_mut().unwrap().failed = true;
+                    vec![error!(format!("ERR {}", e))]
+                }
+                // Blocking commands may wait indefinitely, so they are interrupted by the shutdown.
+                // Replies of the preceding pipelined commands are sent before blocking.
+                Ok(command) if command.is_blocking() => {
+                    if !Self::flush_responses(&mut conn, addr).await? {
+                        break Ok(());
+                    }
+                    tokio::select! {
+                        response = Self::handle_command(command, &db, &shared) => vec![response],
+                        _ = shutdown_rx.recv() => {
+                            log::info!("Shutdown signal received, closing blocked connection: {}", addr);
+                            break Ok(());
+                        }
+                    }
+                }
+                // The target is contacted without holding the database lock
+                Ok(command @ Command::Migrate { .. }) => {
+                    vec![Self::handle_migrate(command, &db, &shared).await]
+                }
+                Ok(command) if command.is_write() => {
+                    vec![Self::handle_write(command, &db, &shared).await]
+                }
+                Ok(command) => vec![Self::handle_command(command, &db, &shared).await],
+                Err(e) => vec![error!(format!("ERR {}", e))],
+            };
+
+            for response in &responses {
+                if !Self::write_response(&mut conn, addr, response).await? {
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Write a response to the buffer of the connection, sent by `flush_responses`.
+    ///
+    /// Returns `false` if the write timed out and the connection should be closed.
+    async fn write_response(
+        conn: &mut Connection,
+        addr: SocketAddr,
+        response: &Frame,
+    ) -> anyhow::Result<bool> {
+        match timeout(TIMEOUT_DURATION, conn.feed_frame(response)).await {
+            Ok(result) => match result {
+                Ok(_) => {
+                    log::debug!("Written to {}: {:?}", addr, response);
+                    Ok(true)
+                }
+                Err(e) => {
+                    log::error!("Error writing to {}: {}", addr, e);
+                    Err(e)
+                }
+            },
+            Err(_) => {
+                log::warn!(
+                    "Client {} write timed out after {} seconds",
+                    addr,
+                    TIMEOUT_DURATION.as_secs()
+                );
+                Ok(false)
+            }
+        }
+    }
+
+    /// Reply to a frame that violates the protocol, e.g., a bulk string over the limits,
+    /// and close the connection, as the rest of the buffered data cannot be parsed.
+    /// Other errors, e.g., of the socket, are returned.
+    async fn close_with_protocol_error(
+        conn: &mut Connection,
+        addr: SocketAddr,
+        e: anyhow::Error,
+    ) -> anyhow::Result<()> {
+        let Some(protocol_error) = e.downcast_ref::<RedisProtocolError>() else {
+            return Err(e);
+        };
+        log::warn!("Protocol error from client {}: {}", addr, protocol_error);
+        conn.write_frame(&error!(format!("ERR {}", protocol_error)))
+            .await?;
+        conn.shutdown().await
+    }
+
+    /// Flush the buffered responses to the client.
+    ///
+    /// Returns `false` if the write timed out and the connection should be closed.
+    async fn flush_responses(conn: &mut Connection, addr: SocketAddr) -> anyhow::Result<bool> {
+        match timeout(TIMEOUT_DURATION, conn.flush()).await {
+            Ok(result) => result.map(|_| true).inspect_err(|e| {
+                log::error!("Error writing to {}: {}", addr, e);
+            }),
+            Err(_) => {
+                log::warn!(
+                    "Client {} write timed out after {} seconds",
+                    addr,
+                    TIMEOUT_DURATION.as_secs()
+                );
+                Ok(false)
+            }
+        }
+    }
+
+    /// Handle `SUBSCRIBE`, `UNSUBSCRIBE`, `PSUBSCRIBE` and `PUNSUBSCRIBE`.
+    ///
+    /// Each channel or pattern is confirmed by a separate reply with the number of subscriptions.
+    fn handle_subscription(command: Command, subscription: &mut Subscription) -> Vec<Frame> {
+        let reply = |kind: &str, name: Option<String>, count: usize| {
+            Frame::Push(vec![
+                bulk!(kind.to_string()),
+                name.map_or(null!(), |name| bulk!(name)),
+                integer!(count as i64),
+            ])
+        };
+        match command {
+            Command::Subscribe { channels } => channels
+                .into_iter()
+                .map(|channel| {
+                    let count = subscription.subscribe(channel.clone());
+                    reply("subscribe", Some(channel), count)
+                })
+                .collect(),
+            Command::PSubscribe { patterns } => patterns
+                .into_iter()
+                .map(|pattern| match subscription.psubscribe(pattern.clone()) {
+                    Ok(count) => reply("psubscribe", Some(pattern), count),
+                    Err(e) => Self::error_frame(e),
+                })
+                .collect(),
+            Command::Unsubscribe { channels } => {
+                let channels = match channels.is_empty() {
+                    true => subscription.channels(),
+                    false => channels,
+                };
+                if channels.is_empty() {
+                    return vec![reply("unsubscribe", None, subscription.count())];
                 }
+                channels
+                    .into_iter()
+                    .map(|channel| {
+                        let count = subscription.unsubscribe(&channel);
+                        reply("unsubscribe", Some(channel), count)
+                    })
+                    .collect()
+            }
+            Command::PUnsubscribe { patterns } => {
+                let patterns = match patterns.is_empty() {
+                    true => subscription.patterns(),
+                    false => patterns,
+                };
+                if patterns.is_empty() {
+                    return vec![reply("punsubscribe", None, subscription.count())];
+                }
+                patterns
+                    .into_iter()
+                    .map(|pattern| {
+                        let count = subscription.punsubscribe(&pattern);
+                        reply("punsubscribe", Some(pattern), count)
+                    })
+                    .collect()
+            }
+            _ => vec![error!("ERR expected a subscription command")],
+        }
+    }
+
+    /// Convert a published message to a `message` or `pmessage` push.
+    fn message_frame(message: Message) -> Frame {
+        match message.pattern {
+            Some(pattern) => Frame::Push(vec![
+                bulk!("pmessage"),
+                bulk!(pattern),
+                bulk!(message.channel),
+                bulk!(message.payload),
+            ]),
+            None => Frame::Push(vec![
+                bulk!("message"),
+                bulk!(message.channel),
+                bulk!(message.payload),
+            ]),
+        }
+    }
+
+    /// Handle `HELLO`, switch the protocol of the connection and describe the server.
+    fn handle_hello(version: Option<u64>, conn: &mut Connection, shared: &Shared) -> Frame {
+        if let Some(version) = version {
+            match Protocol::from_version(version) {
+                Some(protocol) => conn.set_protocol(protocol),
+                None => return error!("NOPROTO sorry, this protocol version is not supported"),
+            }
+        }
+        let mode = match shared.cluster {
+            Some(_) => "cluster",
+            None => "standalone",
+        };
+        let role = match shared.replication.is_replica() {
+            true => "replica",
+            false => "master",
+        };
+        Frame::Map(vec![
+            (bulk!("server"), bulk!("redis")),
+            (bulk!("version"), bulk!(env!("CARGO_PKG_VERSION"))),
+            (bulk!("proto"), integer!(conn.protocol().version())),
+            (bulk!("mode"), bulk!(mode)),
+            (bulk!("role"), bulk!(role)),
+            (bulk!("modules"), Frame::Array(vec![])),
+        ])
+    }
+
+    /// Handle `REPLICAOF host port` and `REPLICAOF NO ONE`.
+    fn handle_replicaof(primary: Option<(String, u16)>, db: &DB, shared: &Shared) -> Frame {
+        match primary {
+            Some((host, port)) => match Self::start_replication(host, port, db, shared) {
+                true => simple!("OK"),
+                false => simple!("OK Already connected to specified master"),
+            },
+            None => {
+                shared.replication.promote();
+                log::info!("Replication stopped, the server is a primary now");
+                simple!("OK")
+            }
+        }
+    }
+
+    /// Handle `REPLCONF` sent by a replica before `PSYNC`.
+    fn handle_replconf(options: Vec<(String, String)>, replica_port: &mut Option<u16>) -> Frame {
+        for (option, value) in options {
+            match option.as_str() {
+                "listening-port" => match value.parse() {
+                    Ok(port) => *replica_port = Some(port),
+                    Err(_) => return error!(format!("ERR Invalid port: {}", value)),
+                },
+                // Capabilities are ignored, the server supports `PSYNC` only
+                "capa" => {}
+                _ => return error!(format!("ERR Unrecognized REPLCONF option: {}", option)),
+            }
+        }
+        simple!("OK")
+    }
+
+    /// Become a replica of the primary and start the task that keeps the replica in sync.
+    ///
+    /// Returns `false` if the server already is a replica of the primary.
+    fn start_replication(host: String, port: u16, db: &DB, shared: &Shared) -> bool {
+        let db = db.clone();
+        let shared_ = shared.clone();
+        let primary = (host.clone(), port);
+        shared.replication.connect(host, port, move || {
+            tokio::spawn(async move {
+                let (host, port) = primary;
+                Self::run_replication(&host, port, db, shared_).await;
+            })
+            .abort_handle()
+        })
+    }
+
+    /// Keep the replica in sync with its primary. The replica reconnects if the link
+    /// is broken, until the replication is stopped by `REPLICAOF NO ONE` or shutdown.
+    async fn run_replication(host: &str, port: u16, db: DB, shared: Shared) {
+        loop {
+            shared.replication.set_link_state(LinkState::Connecting);
+            match Self::sync_with_primary(host, port, &db, &shared).await {
+                Ok(()) => log::info!("Connection to primary {}:{} closed", host, port),
+                Err(e) => log::warn!("Replication from primary {}:{} failed: {}", host, port, e),
+            }
+            shared.replication.set_link_state(LinkState::Connect);
+            tokio::time::sleep(REPL_RETRY_INTERVAL).await;
+        }
+    }
+
+    /// Connect to the primary, synchronize the data by `PSYNC` and apply the stream of writes.
+    async fn sync_with_primary(
+        host: &str,
+        port: u16,
+        db: &DB,
+        shared: &Shared,
+    ) -> anyhow::Result<()> {
+        let stream = timeout(CLIENT_CONNECTION_TIMEOUT, TcpStream::connect((host, port))).await??;
+        let mut conn = Connection::new(stream);
+        let replication = &shared.replication;
+        log::info!("Connected to primary {}:{}", host, port);
+
+        Self::primary_request(&mut conn, Command::Ping { msg: None }).await?;
+        let options = vec![("listening-port".to_string(), replication.port().to_string())];
+        Self::primary_request(&mut conn, Command::ReplConf { options }).await?;
+        let options = vec![("capa".to_string(), "psync2".to_string())];
+        Self::primary_request(&mut conn, Command::ReplConf { options }).await?;
+
+        let (replication_id, offset) = replication.psync_position();
+        let reply = Self::primary_request(
+            &mut conn,
+            Command::PSync {
+                replication_id,
+                offset,
+            },
+        )
+        .await?;
+        let Frame::Simple(reply) = reply else {
+            bail!("unexpected reply to PSYNC: {:?}", reply);
+        };
+        match reply.split_whitespace().collect::<Vec<_>>()[..] {
+            ["FULLRESYNC", replication_id, offset] => {
+                let offset = offset
+                    .parse()
+                    .map_err(|_| anyhow!("invalid offset of FULLRESYNC: {}", offset))?;
+                replication.set_link_state(LinkState::Sync);
+                let payload = conn.read_payload().await?;
+                log::info!(
+                    "Received {} bytes of the snapshot of the primary",
+                    payload.len()
+                );
+                let snapshot =
+                    tokio::task::spawn_blocking(move || Snapshot::decode_rdb(&payload)).await??;
+                {
+                    let db = db.lock().await;
+                    db.load_snapshot(snapshot).await;
+                    replication.synced(Some(replication_id.to_string()), Some(offset));
+                }
+                // The loaded data replaces the logged writes
+                if let Some(aof) = &shared.aof {
+                    if let Err(e) = aof.bgrewrite(db).await {
+                        log::warn!("Append only file rewrite after the sync failed: {}", e);
+                    }
+                }
+            }
+            ["CONTINUE"] => replication.synced(None, None),
+            ["CONTINUE", replication_id] => {
+                replication.synced(Some(replication_id.to_string()), None)
+            }
+            _ => bail!("unexpected reply to PSYNC: {}", reply),
+        }
+        replication.set_link_state(LinkState::Connected);
+        log::info!("Replica is in sync with primary {}:{}", host, port);
+        Self::apply_replication_stream(conn, db, shared).await
+    }
+
+    /// Send a command of the handshake to the primary and read the reply.
+    async fn primary_request(conn: &mut Connection, command: Command) -> anyhow::Result<Frame> {
+        conn.write_frame(&command.into_frame()?).await?;
+        match timeout(REPL_TIMEOUT, conn.read_frame()).await? {
+            Ok(Some(Frame::Error(e))) => bail!("primary replied: {}", e),
+            Ok(Some(frame)) => Ok(frame),
+            Ok(None) => bail!("connection closed by the primary"),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Apply the write commands streamed by the primary. Each command is logged
+    /// and forwarded to the replication stream of this server, so that it has
+    /// the offset of the primary. The processed offset is acknowledged every
+    /// `REPL_ACK_PERIOD` and on request of the primary (`REPLCONF GETACK`).
+    async fn apply_replication_stream(
+        mut conn: Connection,
+        db: &DB,
+        shared: &Shared,
+    ) -> anyhow::Result<()> {
+        let mut transaction: Option<Vec<Command>> = None;
+        let mut ack_interval = tokio::time::interval(REPL_ACK_PERIOD);
+        let mut last_received = tokio::time::Instant::now();
+        loop {
+            let frame = tokio::select! {
+                frame = conn.read_frame() => match frame? {
+                    Some(frame) => frame,
+                    None => return Ok(()),
+                },
+                _ = ack_interval.tick() => {
+                    if last_received.elapsed() > REPL_TIMEOUT {
+                        bail!("no data from the primary in {} seconds", REPL_TIMEOUT.as_secs());
+                    }
+                    Self::send_ack(&mut conn, shared).await?;
+                    continue;
+                }
+            };
+            last_received = tokio::time::Instant::now();
+            let mut data = BytesMut::new();
+            frame.encode(&mut data);
+
+            // The database is only locked by writes, not while acknowledging to the primary
+            match Command::from_frame(frame) {
+                Ok(Command::Multi) => transaction = Some(Vec::new()),
+                Ok(Command::Exec) => {
+                    let commands = transaction.take().unwrap_or_default();
+                    let db = db.lock().await;
+                    Self::execute_transaction(commands, &db, shared).await;
+                }
+                Ok(command) if transaction.is_some() => transaction.as_mut().unwrap().push(command),
+                Ok(command) if command.is_write() => {
+                    let command = Self::without_blocking(command);
+                    let db = db.lock().await;
+                    if let Frame::Error(e) = Self::handle_write(command, &db, shared).await {
+                        log::warn!("Error applying a command of the primary: {}", e);
+                    }
+                }
+                // The acknowledged offset does not include the request itself
+                Ok(Command::ReplConf { options })
+                    if options
+                        .first()
+                        .is_some_and(|(option, _)| option == "getack") =>
+                {
+                    Self::send_ack(&mut conn, shared).await?
+                }
+                // E.g. `PING`
+                Ok(_) => {}
+                Err(e) => log::warn!("Invalid command from the primary: {}", e),
+            }
+            shared.replication.forward(data.freeze());
+        }
+    }
+
+    /// Acknowledge the processed offset to the primary (`REPLCONF ACK`). With the append-only
+    /// file enabled, it is synced first and the offset is acknowledged as synced (`FACK`).
+    async fn send_ack(conn: &mut Connection, shared: &Shared) -> anyhow::Result<()> {
+        let offset = shared.replication.offset().to_string();
+        let mut options = vec![("ack".to_string(), offset.clone())];
+        if let Some(aof) = shared.aof.clone() {
+            tokio::task::spawn_blocking(move || aof.sync()).await??;
+            options.push(("fack".to_string(), offset));
+        }
+        conn.write_frame(&Command::ReplConf { options }.into_frame()?)
+            .await
+    }
+
+    /// Handle `REPLCONF ACK offset [FACK aof_offset]` sent by a replica.
+    fn handle_replica_ack(
+        options: &[(String, String)],
+        addr: SocketAddr,
+        replication: &Replication,
+    ) {
+        let option = |name: &str| {
+            options
+                .iter()
+                .find(|(option, _)| option == name)
+                .and_then(|(_, value)| value.parse::<u64>().ok())
+        };
+        match option("ack") {
+            Some(offset) => replication.ack(addr, offset, option("fack")),
+            None => log::warn!("Invalid REPLCONF from replica {}: {:?}", addr, options),
+        }
+    }
+
+    /// Serve a replica after `SYNC` or `PSYNC`. A replica that continues with a known
+    /// replication ID and an offset still in the backlog gets the missing part of the stream
+    /// (`+CONTINUE`), otherwise it gets a snapshot of the database (`+FULLRESYNC`).
+    /// Then the writes are streamed to the replica until the connection is closed.
+    async fn serve_replica(
+        mut conn: Connection,
+        command: Command,
+        db: DB,
+        shared: Shared,
+        addr: SocketAddr,
+        replica_port: Option<u16>,
+        mut shutdown_rx: broadcast::Receiver<()>,
+    ) -> anyhow::Result<()> {
+        let replication = &shared.replication;
+        let resync = match &command {
+            Command::PSync {
+                replication_id,
+                offset,
+            } => replication.partial_resync(replication_id, *offset),
+            _ => None,
+        };
+        let mut receiver = match resync {
+            Some((replication_id, missing, receiver)) => {
+                log::info!(
+                    "Partial resynchronization of replica {}, sending {} bytes of the backlog",
+                    addr,
+                    missing.len()
+                );
+                conn.write_frame(&simple!(format!("CONTINUE {}", replication_id)))
+                    .await?;
+                conn.write_bytes(&missing).await?;
+                receiver
+            }
+            None => {
+                let (snapshot, replication_id, offset, receiver) = {
+                    let db = db.lock().await;
+                    let snapshot = db.snapshot().await;
+                    let (replication_id, offset, receiver) = replication.full_resync();
+                    (snapshot, replication_id, offset, receiver)
+                };
+                log::info!(
+                    "Full resynchronization of replica {} at offset {}",
+                    addr,
+                    offset
+                );
+                if let Command::PSync { .. } = command {
+                    conn.write_frame(&simple!(format!(
+                        "FULLRESYNC {} {}",
+                        replication_id, offset
+                    )))
+                    .await?;
+                }
+                let payload = tokio::task::spawn_blocking(move || snapshot.encode_rdb()).await?;
+                conn.write_payload(&payload).await?;
+                receiver
+            }
+        };
+        let _replica = replication.add_replica(addr, replica_port);
+        loop {
+            tokio::select! {
+                data = receiver.recv() => match data {
+                    Ok(data) => conn.write_bytes(&data).await?,
+                    Err(RecvError::Lagged(_)) => {
+                        log::warn!("Replica {} fell behind the replication stream, disconnecting", addr);
+                        return Ok(());
+                    }
+                    Err(RecvError::Closed) => return Ok(()),
+                },
+                frame = conn.read_frame() => match frame? {
+                    Some(frame) => match Command::from_frame(frame) {
+                        Ok(Command::ReplConf { options }) => {
+                            Self::handle_replica_ack(&options, addr, replication)
+                        }
+                        command => log::debug!("Received from replica {}: {:?}", addr, command),
+                    },
+                    None => return Ok(()),
+                },
+                _ = shutdown_rx.recv() => {
+                    log::info!("Shutdown signal received, closing replica connection: {}", addr);
+                    return Ok(());
+                }
+            }
+        }
+    }
+
+    /// Wait until `numreplicas` replicas acknowledged the current replication offset
+    /// (`WAIT`), or synced it to their append-only file if `aof` is set (`WAITAOF`).
+    ///
+    /// Returns the number of replicas that acknowledged the offset before the timeout.
+    async fn wait_for_replicas(
+        numreplicas: usize,
+        timeout: Option<Duration>,
+        aof: bool,
+        shared: &Shared,
+    ) -> usize {
+        let replication = &shared.replication;
+        let offset = replication.offset();
+        let acked = replication.acked_replicas(offset, aof);
+        if acked >= numreplicas || timeout == Some(Duration::ZERO) {
+            return acked;
+        }
+        replication.request_ack();
+        let deadline = timeout.map(|timeout| tokio::time::Instant::now() + timeout);
+        loop {
+            // Registered before the check, so that no acknowledgement is missed
+            let acked = replication.acked();
+            tokio::pin!(acked);
+            acked.as_mut().enable();
+            let count = replication.acked_replicas(offset, aof);
+            if count >= numreplicas {
+                return count;
+            }
+            match deadline {
+                Some(deadline) => {
+                    if tokio::time::timeout_at(deadline, acked).await.is_err() {
+                        return replication.acked_replicas(offset, aof);
+                    }
+                }
+                None => acked.await,
+            }
+        }
+    }
+
+    /// Handle `MULTI`, `EXEC`, `DISCARD`, `WATCH` and `UNWATCH`.
+    async fn handle_transaction(
+        command: Command,
+        db: &DB,
+        shared: &Shared,
+        transaction: &mut Option<Transaction>,
+        watch: &mut Watch,
+    ) -> Frame {
+        match command {
+            Command::Multi => {
+                if transaction.is_some() {
+                    return error!("ERR MULTI calls can not be nested");
+                }
+                *transaction = Some(Transaction::default());
+                simple!("OK")
+            }
+            Command::Exec => {
+                let Some(transaction) = transaction.take() else {
+                    return error!("ERR EXEC without MULTI");
+                };
+                if transaction.failed {
+                    watch.unwatch();
+                    return error!("EXECABORT Transaction discarded because of previous errors.");
+                }
+                // No other client can modify the store until the transaction is done,
+                // so the watched keys are checked in the same critical section
+                let db = db.lock().await;
+                db.remove_expired_watched(watch).await;
+                if watch.is_dirty() {
+                    watch.unwatch();
+                    return null!();
+                }
+                watch.unwatch();
+                Self::execute_transaction(transaction.commands, &db, shared).await
+            }
+            Command::Discard => {
+                if transaction.take().is_none() {
+                    return error!("ERR DISCARD without MULTI");
+                }
+                watch.unwatch();
+                simple!("OK")
+            }
+            Command::Watch { keys } => {
+                if transaction.is_some() {
+                    return error!("ERR WATCH inside MULTI is not allowed");
+                }
+                db.watch(watch, &keys).await;
+                simple!("OK")
+            }
+            Command::Unwatch => {
+                watch.unwatch();
+                simple!("OK")
+            }
+            _ => error!("ERR expected a transaction command"),
+        }
+    }
+
+    /// Execute the commands of a transaction. Must be called with the database locked.
+    async fn execute_transaction(commands: Vec<Command>, db: &DB, shared: &Shared) -> Frame {
+        let mut responses = Vec::with_capacity(commands.len());
+        let mut records = vec![array!(bulk!("MULTI"))];
+        for command in commands {
+            let keys: Vec<String> = command
+                .modified_keys()
+                .into_iter()
+                .map(String::from)
+                .collect();
+            let command = Self::without_blocking(command);
+            let logged = (Self::is_logged(shared) && command.is_write()).then(|| command.clone());
+            let response = Self::execute_command(command, db, shared).await;
+            if let Some(command) = logged {
+                records.extend(Self::aof_records(command, &response));
             }
+            responses.push(response);
+            db.touch(&keys);
+        }
+        // The writes are logged as a transaction, so that a partially written one is not replayed
+        if records.len() > 1 {
+            records.push(array!(bulk!("EXEC")));
+            Self::propagate(shared, &records);
+        }
+        Frame::Array(responses)
+    }
+
+    /// Handle a command that modifies the database. The keys are marked as modified
+    /// and the command is logged in the same critical section, so that no `EXEC`
+    /// or append-only file rewrite runs in between.
+    async fn handle_write(command: Command, db: &DB, shared: &Shared) -> Frame {
+        let keys: Vec<String> = command
+            .modified_keys()
+            .into_iter()
+            .map(String::from)
+            .collect();
+        let db = db.lock().await;
+        let response = Self::handle_command(command, &db, shared).await;
+        db.touch(&keys);
+        response
+    }
+
+    /// Blocking commands inside a transaction do not wait, they behave as if the timeout elapsed.
+    fn without_blocking(command: Command) -> Command {
+        let timeout = Some(Duration::ZERO);
+        match command {
+            Command::BPop { keys, end, .. } => Command::BPop { keys, end, timeout },
+            Command::BLMove {
+                source,
+                destination,
+                from,
+                to,
+                ..
+            } => Command::BLMove {
+                source,
+                destination,
+                from,
+                to,
+                timeout,
+            },
+            Command::Wait { numreplicas, .. } => Command::Wait {
+                numreplicas,
+                timeout,
+            },
+            Command::WaitAof {
+                numlocal,
+                numreplicas,
+                ..
+            } => Command::WaitAof {
+                numlocal,
+                numreplicas,
+                timeout,
+            },
+            command => command,
         }
     }
 
-    async fn handle_command(command: Command, db: &DB) -> Frame {
+    /// Execute a command, a write is logged to the append-only file and propagated to the replicas.
+    async fn handle_command(command: Command, db: &DB, shared: &Shared) -> Frame {
+        let logged = (Self::is_logged(shared) && command.is_write()).then(|| command.clone());
+        let is_blocking = command.is_blocking();
+        let response = Self::execute_command(command, db, shared).await;
+        if let Some(command) = logged {
+            // A push that served a blocked client is logged while the push holds the lock,
+            // so taking the lock orders the pop after the push
+            let _db = match is_blocking {
+                true => Some(db.lock().await),
+                false => None,
+            };
+            Self::propagate(shared, &Self::aof_records(command, &response));
+        }
+        response
+    }
+
+    fn aof_records(command: Command, response: &Frame) -> Vec<Frame> {
+        aof_records(command, response).unwrap_or_else(|e| {
+            log::error!("Error converting command for the append-only file: {}", e);
+            vec![]
+        })
+    }
+
+    /// Writes are converted to records for the append-only file or the replicas.
+    fn is_logged(shared: &Shared) -> bool {
+        shared.aof.is_some() || shared.replication.is_propagating()
+    }
+
+    /// Append the records to the append-only file and the replication stream.
+    fn propagate(shared: &Shared, records: &[Frame]) {
+        if records.is_empty() {
+            return;
+        }
+        if let Some(aof) = &shared.aof {
+            if let Err(e) = aof.append(records) {
+                log::error!("Error writing to the append-only file: {}", e);
+            }
+        }
+        shared.replication.propagate(records);
+    }
+
+    async fn execute_command(command: Command, db: &DB, shared: &Shared) -> Frame {
+        if command.is_write() {
+            shared.snapshots.record_write();
+        }
         match command {
             Command::Get { key } => match db.get(&key).await {
-                Some(value) => bulk!(value),
-                None => null!(),
+                Ok(Some(value)) => bulk!(value),
+                Ok(None) => null!(),
+                Err(e) => Self::error_frame(e),
             },
             Command::Set {
                 key,
@@ -245,10 +1242,26 @@ impl RedisServer {
                     },
                     Err(e) => {
                         log::debug!("Error incrementing key: {}", e);
-                        error!(format!("ERR {}", e))
+                        Self::error_frame(e)
                     }
                 }
             }
+            Command::Save => match shared.snapshots.save(db).await {
+                Ok(_) => simple!("OK"),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::BgSave => match shared.snapshots.bgsave(db).await {
+                Ok(_) => simple!("Background saving started"),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::LastSave => integer!(shared.snapshots.last_save() as i64),
+            Command::BgRewriteAof => match &shared.aof {
+                Some(aof) => match aof.bgrewrite(db).await {
+                    Ok(_) => simple!("Background append only file rewriting started"),
+                    Err(e) => Self::error_frame(e),
+                },
+                None => error!("ERR Append only file is disabled"),
+            },
             Command::FlushDB => {
                 db.flush().await;
                 simple!("OK")
@@ -256,7 +1269,7 @@ impl RedisServer {
             Command::Del { keys } => {
                 let mut count = 0;
                 for key in keys {
-                    if db.remove(&key).await.is_some() {
+                    if db.remove(&key).await {
                         count += 1;
                     }
                 }
@@ -272,6 +1285,119 @@ impl RedisServer {
                 integer!(count)
             }
             Command::DBSize => integer!(db.size().await as i64),
+            Command::Publish { channel, message } => {
+                integer!(shared.pubsub.publish(&channel, message) as i64)
+            }
+            Command::PubSubChannels { pattern } => match shared.pubsub.channels(pattern.as_deref())
+            {
+                Ok(channels) => Frame::Array(channels.into_iter().map(|c| bulk!(c)).collect()),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::PubSubNumSub { channels } => Frame::Array(
+                shared
+                    .pubsub
+                    .numsub(&channels)
+                    .into_iter()
+                    .flat_map(|(channel, count)| [bulk!(channel), integer!(count as i64)])
+                    .collect(),
+            ),
+            Command::PubSubNumPat => integer!(shared.pubsub.numpat() as i64),
+            Command::Subscribe { .. }
+            | Command::Unsubscribe { .. }
+            | Command::PSubscribe { .. }
+            | Command::PUnsubscribe { .. } => {
+                error!("ERR subscription commands are handled by the connection")
+            }
+            Command::Multi
+            | Command::Exec
+            | Command::Discard
+            | Command::Watch { .. }
+            | Command::Unwatch => error!("ERR transaction commands are handled by the connection"),
+            Command::ReplicaOf { .. }
+            | Command::ReplConf { .. }
+            | Command::Sync
+            | Command::PSync { .. } => {
+                error!("ERR replication commands are handled by the connection")
+            }
+            Command::Role => Self::role_frame(shared.replication.role()),
+            command @ (Command::ClusterSlots
+            | Command::ClusterShards
+            | Command::ClusterNodes
+            | Command::ClusterMyId
+            | Command::ClusterKeySlot { .. }
+            | Command::ClusterCountKeysInSlot { .. }
+            | Command::ClusterGetKeysInSlot { .. }
+            | Command::ClusterSetSlot { .. }) => match &shared.cluster {
+                Some(cluster) => Self::cluster_command(command, cluster, db).await,
+                None => Self::error_frame(RedisClusterError::Disabled.into()),
+            },
+            Command::Asking => error!("ERR ASKING is handled by the connection"),
+            Command::Hello { .. } => error!("ERR HELLO is handled by the connection"),
+            Command::Dump { key } => match db.dump(&key).await {
+                Some(payload) => bulk!(payload),
+                None => null!(),
+            },
+            Command::Restore {
+                key,
+                ttl,
+                payload,
+                replace,
+                absttl,
+            } => {
+                let expires_at = match (ttl, absttl) {
+                    (0, _) => None,
+                    (ttl, true) => Some(ttl),
+                    (ttl, false) => Some(now_ms().saturating_add(ttl)),
+                };
+                match db.restore(key, &payload, expires_at, replace).await {
+                    Ok(()) => simple!("OK"),
+                    Err(e) => Self::error_frame(e.into()),
+                }
+            }
+            Command::Migrate {
+                host,
+                port,
+                keys,
+                timeout,
+                copy,
+                replace,
+            } => Self::migrate(&host, port, &keys, timeout, copy, replace, db).await,
+            Command::Wait {
+                numreplicas,
+                timeout,
+            } => {
+                if shared.replication.is_replica() {
+                    return error!("ERR WAIT cannot be used with replica instances");
+                }
+                let acked =
+                    Self::wait_for_replicas(numreplicas as usize, timeout, false, shared).await;
+                integer!(acked as i64)
+            }
+            Command::WaitAof {
+                numlocal,
+                numreplicas,
+                timeout,
+            } => {
+                if shared.replication.is_replica() {
+                    return error!("ERR WAITAOF cannot be used with replica instances");
+                }
+                let local = match shared.aof.clone() {
+                    Some(aof) => match tokio::task::spawn_blocking(move || aof.sync()).await {
+                        Ok(Ok(())) => 1,
+                        Ok(Err(e)) => return Self::error_frame(e),
+                        Err(e) => return Self::error_frame(e.into()),
+                    },
+                    None if numlocal > 0 => {
+                        return error!(
+                            "ERR WAITAOF cannot be used when numlocal is set but appendonly is disabled."
+                        )
+                    }
+                    None => 0,
+                };
+                let acked =
+                    Self::wait_for_replicas(numreplicas as usize, timeout, true, shared).await;
+                array!(integer!(local), integer!(acked as i64))
+            }
             Command::Unknown(cmd) => error!(format!(
                 "ERR {}",
                 RedisCommandError::InvalidCommand(cmd.to_string())
@@ -290,8 +1416,8 @@ impl RedisServer {
                     Err(e) => error!(format!("ERR {}", e)),
                 }
             }
-            Command::Expire { key, seconds } => {
-                if db.expire(key.as_str(), Duration::from_secs(seconds)).await {
+            Command::Expire { key, duration } => {
+                if db.expire(key.as_str(), duration).await {
                     integer!(1)
                 } else {
                     integer!(0)
@@ -308,6 +1434,850 @@ impl RedisServer {
                     Err(_) => integer!(-2),
                 }
             }
+            Command::Push { key, values, end } => match db.push(&key, values, end).await {
+                Ok(len) => integer!(len as i64),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::Pop { key, end, count } => {
+                match db.pop(&key, end, count.unwrap_or(1) as usize).await {
+                    Ok(Some(values)) => match count {
+                        Some(_) => Self::bulk_array(values),
+                        None => values.into_iter().next().map_or(null!(), |v| bulk!(v)),
+                    },
+                    Ok(None) => null!(),
+                    Err(e) => Self::error_frame(e),
+                }
+            }
+            Command::BPop { keys, end, timeout } => {
+                match Self::blocking_pop(db, &keys, end, None, timeout).await {
+                    Ok(Some((key, value))) => array!(bulk!(key), bulk!(value)),
+                    Ok(None) => null!(),
+                    Err(e) => Self::error_frame(e),
+                }
+            }
+            Command::BLMove {
+                source,
+                destination,
+                from,
+                to,
+                timeout,
+            } => match Self::blocking_pop(db, &[source], from, Some((destination, to)), timeout)
+                .await
+            {
+                Ok(Some((_, value))) => bulk!(value),
+                Ok(None) => null!(),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::LRange { key, start, stop } => match db.lrange(&key, start, stop).await {
+                Ok(values) => Self::bulk_array(values),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::LLen { key } => match db.llen(&key).await {
+                Ok(len) => integer!(len as i64),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::LIndex { key, index } => match db.lindex(&key, index).await {
+                Ok(Some(value)) => bulk!(value),
+                Ok(None) => null!(),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::LSet { key, index, value } => match db.lset(&key, index, value).await {
+                Ok(_) => simple!("OK"),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::LTrim { key, start, stop } => match db.ltrim(&key, start, stop).await {
+                Ok(_) => simple!("OK"),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::LRem { key, count, value } => match db.lrem(&key, count, &value).await {
+                Ok(removed) => integer!(removed as i64),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::LInsert {
+                key,
+                position,
+                pivot,
+                value,
+            } => match db.linsert(&key, position, &pivot, value).await {
+                Ok(len) => integer!(len),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::HSet { key, fields } => match db.hset(&key, fields).await {
+                Ok(added) => integer!(added as i64),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::HSetNx { key, field, value } => match db.hsetnx(&key, field, value).await {
+                Ok(set) => integer!(set as i64),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::HGet { key, field } => match db.hget(&key, &field).await {
+                Ok(Some(value)) => bulk!(value),
+                Ok(None) => null!(),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::HMGet { key, fields } => match db.hmget(&key, &fields).await {
+                Ok(values) => Frame::Array(
+                    values
+                        .into_iter()
+                        .map(|value| value.map_or(null!(), |v| bulk!(v)))
+                        .collect(),
+                ),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::HDel { key, fields } => match db.hdel(&key, &fields).await {
+                Ok(removed) => integer!(removed as i64),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::HGetAll { key } => match db.hgetall(&key).await {
+                Ok(pairs) => Frame::Map(
+                    pairs
+                        .into_iter()
+                        .map(|(field, value)| (bulk!(field), bulk!(value)))
+                        .collect(),
+                ),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::HIncrBy {
+                key,
+                field,
+                increment,
+            } => match db.hincrby(&key, field, increment).await {
+                Ok(value) => integer!(value),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::HKeys { key } => match db.hkeys(&key).await {
+                Ok(fields) => Self::bulk_array(fields),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::HVals { key } => match db.hvals(&key).await {
+                Ok(values) => Self::bulk_array(values),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::HLen { key } => match db.hlen(&key).await {
+                Ok(len) => integer!(len as i64),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::HExists { key, field } => match db.hexists(&key, &field).await {
+                Ok(exists) => integer!(exists as i64),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::SAdd { key, members } => match db.sadd(&key, members).await {
+                Ok(added) => integer!(added as i64),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::SRem { key, members } => match db.srem(&key, &members).await {
+                Ok(removed) => integer!(removed as i64),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::SMembers { key } => match db.smembers(&key).await {
+                Ok(members) => Self::bulk_set(members),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::SIsMember { key, member } => match db.sismember(&key, &member).await {
+                Ok(is_member) => integer!(is_member as i64),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::SCard { key } => match db.scard(&key).await {
+                Ok(len) => integer!(len as i64),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::SPop { key, count } => {
+                match db.spop(&key, count.unwrap_or(1) as usize).await {
+                    Ok(members) => match count {
+                        Some(_) => Self::bulk_array(members),
+                        None => members.into_iter().next().map_or(null!(), |m| bulk!(m)),
+                    },
+                    Err(e) => Self::error_frame(e),
+                }
+            }
+            Command::SRandMember { key, count } => {
+                match db.srandmember(&key, count.unwrap_or(1)).await {
+                    Ok(members) => match count {
+                        Some(_) => Self::bulk_array(members),
+                        None => members.into_iter().next().map_or(null!(), |m| bulk!(m)),
+                    },
+                    Err(e) => Self::error_frame(e),
+                }
+            }
+            Command::SetOp { operation, keys } => match db.set_operation(operation, &keys).await {
+                Ok(members) => Self::bulk_set(members),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::SetOpStore {
+                operation,
+                destination,
+                keys,
+            } => match db.set_operation_store(operation, &destination, &keys).await {
+                Ok(len) => integer!(len as i64),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::ZAdd {
+                key,
+                options,
+                mut members,
+            } => {
+                if options.increment {
+                    let (increment, member) = members.remove(0);
+                    match db.zadd_incr(&key, increment, member, options).await {
+                        Ok(Some(score)) => Frame::Double(score),
+                        Ok(None) => null!(),
+                        Err(e) => Self::error_frame(e),
+                    }
+                } else {
+                    match db.zadd(&key, members, options).await {
+                        Ok(count) => integer!(count as i64),
+                        Err(e) => Self::error_frame(e),
+                    }
+                }
+            }
+            Command::ZRange {
+                key,
+                by,
+                rev,
+                limit,
+                with_scores,
+            } => match db.zrange(&key, &by, rev, limit).await {
+                Ok(entries) if with_scores => Self::score_pairs(entries),
+                Ok(entries) => Self::bulk_array(entries.into_iter().map(|(member, _)| member)),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::ZRank { key, member } => match db.zrank(&key, &member).await {
+                Ok(Some(rank)) => integer!(rank as i64),
+                Ok(None) => null!(),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::ZScore { key, member } => match db.zscore(&key, &member).await {
+                Ok(Some(score)) => Frame::Double(score),
+                Ok(None) => null!(),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::ZRem { key, members } => match db.zrem(&key, &members).await {
+                Ok(removed) => integer!(removed as i64),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::ZIncrBy {
+                key,
+                increment,
+                member,
+            } => match db.zincrby(&key, increment, member).await {
+                Ok(score) => Frame::Double(score),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::ZCount { key, min, max } => match db.zcount(&key, min, max).await {
+                Ok(count) => integer!(count as i64),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::ZCard { key } => match db.zcard(&key).await {
+                Ok(len) => integer!(len as i64),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::ZPop {
+                key,
+                count,
+                highest,
+            } => match db.zpop(&key, count.unwrap_or(1) as usize, highest).await {
+                Ok(entries) => Self::score_pairs(entries),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::XAdd {
+                key,
+                id,
+                fields,
+                trim,
+                create,
+            } => match db.xadd(&key, id, fields, trim, create).await {
+                Ok(Some(id)) => bulk!(id.to_string()),
+                Ok(None) => null!(),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::XRange {
+                key,
+                start,
+                end,
+                rev,
+                count,
+            } => match db
+                .xrange(&key, start, end, rev, count.map(|count| count as usize))
+                .await
+            {
+                Ok(entries) => Self::stream_entries(entries),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::XLen { key } => match db.xlen(&key).await {
+                Ok(len) => integer!(len as i64),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::XTrim { key, trim } => match db.xtrim(&key, trim).await {
+                Ok(removed) => integer!(removed as i64),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::XSetId { key, last_id } => match db.xsetid(&key, last_id).await {
+                Ok(()) => simple!("OK"),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::XDel { key, ids } => match db.xdel(&key, &ids).await {
+                Ok(deleted) => integer!(deleted as i64),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::XRead { streams, count } => {
+                match db.xread(&streams, count.map(|count| count as usize)).await {
+                    Ok(streams) => Self::streams(streams),
+                    Err(e) => Self::error_frame(e),
+                }
+            }
+            Command::XGroupCreate {
+                key,
+                group,
+                id,
+                create,
+            } => match db.xgroup_create(&key, &group, id, create).await {
+                Ok(()) => simple!("OK"),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::XGroupDestroy { key, group } => match db.xgroup_destroy(&key, &group).await {
+                Ok(destroyed) => integer!(destroyed as i64),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::XGroupCreateConsumer {
+                key,
+                group,
+                consumer,
+            } => match db.xgroup_create_consumer(&key, &group, &consumer).await {
+                Ok(created) => integer!(created as i64),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::XGroupDelConsumer {
+                key,
+                group,
+                consumer,
+            } => match db.xgroup_del_consumer(&key, &group, &consumer).await {
+                Ok(pending) => integer!(pending as i64),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::XGroupSetId { key, group, id } => {
+                match db.xgroup_set_id(&key, &group, id).await {
+                    Ok(()) => simple!("OK"),
+                    Err(e) => Self::error_frame(e),
+                }
+            }
+            Command::XReadGroup {
+                group,
+                consumer,
+                streams,
+                count,
+                noack,
+            } => match db
+                .xreadgroup(
+                    &group,
+                    &consumer,
+                    &streams,
+                    count.map(|count| count as usize),
+                    noack,
+                )
+                .await
+            {
+                Ok(streams) => Self::streams(streams),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::XAck { key, group, ids } => match db.xack(&key, &group, &ids).await {
+                Ok(acknowledged) => integer!(acknowledged as i64),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::XPending {
+                key,
+                group,
+                range: None,
+            } => match db.xpending(&key, &group).await {
+                Ok(summary) if summary.count == 0 => array![integer!(0), null!(), null!(), null!()],
+                Ok(summary) => array![
+                    integer!(summary.count as i64),
+                    summary.min_id.map_or(null!(), |id| bulk!(id.to_string())),
+                    summary.max_id.map_or(null!(), |id| bulk!(id.to_string())),
+                    Frame::Array(
+                        summary
+                            .consumers
+                            .into_iter()
+                            .map(|(name, count)| array![bulk!(name), bulk!(count.to_string())])
+                            .collect()
+                    )
+                ],
+                Err(e) => Self::error_frame(e),
+            },
+            Command::XPending {
+                key,
+                group,
+                range: Some(range),
+            } => match db.xpending_range(&key, &group, &range).await {
+                Ok(entries) => Frame::Array(
+                    entries
+                        .into_iter()
+                        .map(|entry| {
+                            array![
+                                bulk!(entry.id.to_string()),
+                                bulk!(entry.consumer),
+                                integer!(entry.idle as i64),
+                                integer!(entry.delivery_count as i64)
+                            ]
+                        })
+                        .collect(),
+                ),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::XClaim {
+                key,
+                group,
+                consumer,
+                min_idle,
+                ids,
+                just_id,
+                options,
+            } => match db
+                .xclaim(&key, &group, &consumer, min_idle, &ids, just_id, options)
+                .await
+            {
+                Ok(entries) => Self::claimed_entries(entries, just_id),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::XAutoClaim {
+                key,
+                group,
+                consumer,
+                min_idle,
+                start,
+                count,
+                just_id,
+            } => match db
+                .xautoclaim(
+                    &key,
+                    &group,
+                    &consumer,
+                    min_idle,
+                    start,
+                    count.unwrap_or(100) as usize,
+                    just_id,
+                )
+                .await
+            {
+                Ok(claim) => array![
+                    bulk!(claim.next_id.to_string()),
+                    Self::claimed_entries(claim.entries, just_id),
+                    Self::bulk_array(claim.deleted.iter().map(|id| Bytes::from(id.to_string())))
+                ],
+                Err(e) => Self::error_frame(e),
+            },
+            Command::XInfoStream { key } => match db.xinfo_stream(&key).await {
+                Ok(info) => {
+                    let entry = |entry: Option<StreamEntry>| match entry {
+                        Some(entry) => Self::stream_entry(entry),
+                        None => null!(),
+                    };
+                    Frame::Map(vec![
+                        (bulk!("length"), integer!(info.length as i64)),
+                        (
+                            bulk!("last-generated-id"),
+                            bulk!(info.last_generated_id.to_string()),
+                        ),
+                        (bulk!("groups"), integer!(info.groups as i64)),
+                        (bulk!("first-entry"), entry(info.first_entry)),
+                        (bulk!("last-entry"), entry(info.last_entry)),
+                    ])
+                }
+                Err(e) => Self::error_frame(e),
+            },
+            Command::XInfoGroups { key } => match db.xinfo_groups(&key).await {
+                Ok(groups) => Frame::Array(
+                    groups
+                        .into_iter()
+                        .map(|group| {
+                            Frame::Map(vec![
+                                (bulk!("name"), bulk!(group.name)),
+                                (bulk!("consumers"), integer!(group.consumers as i64)),
+                                (bulk!("pending"), integer!(group.pending as i64)),
+                                (
+                                    bulk!("last-delivered-id"),
+                                    bulk!(group.last_delivered_id.to_string()),
+                                ),
+                            ])
+                        })
+                        .collect(),
+                ),
+                Err(e) => Self::error_frame(e),
+            },
+            Command::XInfoConsumers { key, group } => {
+                match db.xinfo_consumers(&key, &group).await {
+                    Ok(consumers) => Frame::Array(
+                        consumers
+                            .into_iter()
+                            .map(|consumer| {
+                                Frame::Map(vec![
+                                    (bulk!("name"), bulk!(consumer.name)),
+                                    (bulk!("pending"), integer!(consumer.pending as i64)),
+                                    (bulk!("idle"), integer!(consumer.idle as i64)),
+                                ])
+                            })
+                            .collect(),
+                    ),
+                    Err(e) => Self::error_frame(e),
+                }
+            }
+        }
+    }
+
+    /// Convert `(member, score)` pairs to a flat array of bulk strings.
+    fn score_pairs(entries: Vec<(Bytes, f64)>) -> Frame {
+        Frame::Array(
+            entries
+                .into_iter()
+                .flat_map(|(member, score)| [bulk!(member), bulk!(format_double(score))])
+                .collect(),
+        )
+    }
+
+    /// Convert a stream entry to an `[id, [field, value, ...]]` array.
+    fn stream_entry(entry: StreamEntry) -> Frame {
+        let fields = entry
+            .fields
+            .into_iter()
+            .flat_map(|(field, value)| [bulk!(field), bulk!(value)])
+            .collect();
+        array![bulk!(entry.id.to_string()), Frame::Array(fields)]
+    }
+
+    /// Convert stream entries to an array of `[id, [field, value, ...]]` arrays.
+    fn stream_entries(entries: Vec<StreamEntry>) -> Frame {
+        Frame::Array(entries.into_iter().map(Self::stream_entry).collect())
+    }
+
+    /// Convert entries of multiple streams to an array of `[key, entries]` arrays,
+    /// or null if there are none.
+    fn streams(streams: Vec<(String, Vec<StreamEntry>)>) -> Frame {
+        if streams.is_empty() {
+            return null!();
+        }
+        Frame::Array(
+            streams
+                .into_iter()
+                .map(|(key, entries)| array![bulk!(key), Self::stream_entries(entries)])
+                .collect(),
+        )
+    }
+
+    /// Convert claimed stream entries to an array of entries, or only their IDs with `JUSTID`.
+    fn claimed_entries(entries: Vec<StreamEntry>, just_id: bool) -> Frame {
+        if just_id {
+            Self::bulk_array(
+                entries
+                    .iter()
+                    .map(|entry| Bytes::from(entry.id.to_string())),
+            )
+        } else {
+            Self::stream_entries(entries)
+        }
+    }
+
+    /// Pop an element, waiting for a push up to `timeout` if all lists are empty.
+    ///
+    /// Returns `None` if the timeout elapsed.
+    async fn blocking_pop(
+        db: &DB,
+        keys: &[String],
+        end: ListEnd,
+        destination: Option<(String, ListEnd)>,
+        timeout: Option<Duration>,
+    ) -> anyhow::Result<Option<(String, Bytes)>> {
+        let mut waiter = match db.blocking_pop(keys, end, destination).await? {
+            BlockingPop::Ready(key, value) => return Ok(Some((key, value))),
+            BlockingPop::Blocked(waiter) => waiter,
+        };
+        match timeout {
+            Some(duration) => match tokio::time::timeout(duration, waiter.wait()).await {
+                Ok(result) => result.map(Some),
+                Err(_) => waiter.cancel().await,
+            },
+            None => waiter.wait().await.map(Some),
+        }
+    }
+
+    /// Convert values to an array of bulk strings.
+    fn bulk_array(values: impl IntoIterator<Item = Bytes>) -> Frame {
+        Frame::Array(values.into_iter().map(|value| bulk!(value)).collect())
+    }
+
+    /// Convert unordered members, e.g., of `SMEMBERS`, to a set of bulk strings.
+    fn bulk_set(values: impl IntoIterator<Item = Bytes>) -> Frame {
+        Frame::Set(values.into_iter().map(|value| bulk!(value)).collect())
+    }
+
+    /// Move keys to another server (`MIGRATE`) without holding the database lock while
+    /// communicating with the target, so that two nodes migrating keys to each other do not
+    /// block each other. The keys are removed unless they were modified in the meantime.
+    async fn handle_migrate(command: Command, db: &DB, shared: &Shared) -> Frame {
+        let Command::Migrate {
+            host,
+            port,
+            keys,
+            timeout,
+            copy,
+            replace,
+        } = command
+        else {
+            return error!("ERR expected MIGRATE");
+        };
+        let restores = Self::dump_keys(&keys, replace, &db.lock().await).await;
+        if restores.is_empty() {
+            return simple!("NOKEY");
+        }
+        let payloads: Vec<(String, Bytes)> = restores
+            .iter()
+            .filter_map(|restore| match restore {
+                Command::Restore { key, payload, .. } => Some((key.clone(), payload.clone())),
+                _ => None,
+            })
+            .collect();
+        if let Some(e) = Self::send_restores_with_timeout(&host, port, restores, timeout).await {
+            return e;
+        }
+        if copy {
+            return simple!("OK");
+        }
+
+        // Only the keys that still hold the migrated values are removed
+        let db = db.lock().await;
+        let mut migrated = Vec::with_capacity(payloads.len());
+        for (key, payload) in payloads {
+            if db.dump(&key).await == Some(payload) {
+                migrated.push(key);
+            }
+        }
+        if !migrated.is_empty() {
+            Self::handle_write(Command::Del { keys: migrated }, &db, shared).await;
+        }
+        simple!("OK")
+    }
+
+    /// Move keys to another server (`MIGRATE`) inside a transaction, i.e., with the database
+    /// locked for the whole transaction. The keys are removed once the target restored
+    /// all of them.
+    async fn migrate(
+        host: &str,
+        port: u16,
+        keys: &[String],
+        timeout: Duration,
+        copy: bool,
+        replace: bool,
+        db: &DB,
+    ) -> Frame {
+        let restores = Self::dump_keys(keys, replace, db).await;
+        if restores.is_empty() {
+            return simple!("NOKEY");
+        }
+        if let Some(e) = Self::send_restores_with_timeout(host, port, restores, timeout).await {
+            return e;
+        }
+        if !copy {
+            for key in keys {
+                db.remove(key).await;
+            }
+        }
+        simple!("OK")
+    }
+
+    /// Serialize the existing keys as `RESTORE` commands for `MIGRATE`.
+    /// Must be called with the database locked, so that the keys are dumped at once.
+    async fn dump_keys(keys: &[String], replace: bool, db: &DB) -> Vec<Command> {
+        let mut restores = Vec::with_capacity(keys.len());
+        for key in keys {
+            let Some(payload) = db.dump(key).await else {
+                continue;
+            };
+            // Zero means no expiration, so an expiring key has at least 1 ms left
+            let ttl = match db.ttl(key).await {
+                Ok(Some(ttl)) => (ttl.as_millis() as u64).max(1),
+                _ => 0,
+            };
+            restores.push(Command::Restore {
+                key: key.clone(),
+                ttl,
+                payload,
+                replace,
+                absttl: false,
+            });
+        }
+        restores
+    }
+
+    /// Send the `RESTORE` commands of `MIGRATE` within the `timeout`.
+    /// Returns the error reply of `MIGRATE` if the target did not restore all keys.
+    async fn send_restores_with_timeout(
+        host: &str,
+        port: u16,
+        restores: Vec<Command>,
+        timeout: Duration,
+    ) -> Option<Frame> {
+        match tokio::time::timeout(timeout, Self::send_restores(host, port, restores)).await {
+            Ok(Ok(None)) => None,
+            Ok(Ok(Some(e))) => Some(error!(format!(
+                "ERR Target instance replied with error: {}",
+                e
+            ))),
+            result => {
+                if let Ok(Err(e)) = result {
+                    log::warn!("Error migrating keys to {}:{}: {}", host, port, e);
+                }
+                Some(error!(
+                    "IOERR error or timeout communicating with the target instance"
+                ))
+            }
+        }
+    }
+
+    /// Send the `RESTORE` commands of `MIGRATE` to the target and return its first error.
+    /// Replies to `ASKING` are ignored, a server without cluster support rejects it.
+    async fn send_restores(
+        host: &str,
+        port: u16,
+        restores: Vec<Command>,
+    ) -> anyhow::Result<Option<String>> {
+        let mut conn = Connection::new(TcpStream::connect((host, port)).await?);
+        let count = restores.len();
+        for restore in restores {
+            conn.feed_frame(&array!(bulk!("ASKING"))).await?;
+            conn.feed_frame(&restore.into_frame()?).await?;
+        }
+        conn.flush().await?;
+        let mut error = None;
+        for _ in 0..count {
+            let (Some(_), Some(reply)) = (conn.read_frame().await?, conn.read_frame().await?)
+            else {
+                bail!("connection closed by the target instance");
+            };
+            if let Frame::Error(e) = reply {
+                error.get_or_insert(e);
+            }
+        }
+        Ok(error)
+    }
+
+    /// Execute a `CLUSTER` subcommand.
+    async fn cluster_command(command: Command, cluster: &Cluster, db: &DB) -> Frame {
+        match command {
+            Command::ClusterSlots => Frame::Array(
+                cluster
+                    .slots()
+                    .into_iter()
+                    .map(|range| {
+                        array!(
+                            integer!(range.start as i64),
+                            integer!(range.end as i64),
+                            array!(
+                                bulk!(range.host),
+                                integer!(range.port as i64),
+                                bulk!(range.id)
+                            )
+                        )
+                    })
+                    .collect(),
+            ),
+            Command::ClusterShards => Frame::Array(
+                cluster
+                    .nodes()
+                    .into_iter()
+                    .map(|node| {
+                        let slots = node
+                            .slots
+                            .iter()
+                            .flat_map(|&(start, end)| {
+                                [integer!(start as i64), integer!(end as i64)]
+                            })
+                            .collect();
+                        array!(
+                            bulk!("slots"),
+                            Frame::Array(slots),
+                            bulk!("nodes"),
+                            array!(array!(
+                                bulk!("id"),
+                                bulk!(node.id),
+                                bulk!("port"),
+                                integer!(node.port as i64),
+                                bulk!("ip"),
+                                bulk!(node.host.clone()),
+                                bulk!("endpoint"),
+                                bulk!(node.host),
+                                bulk!("role"),
+                                bulk!("master"),
+                                bulk!("health"),
+                                bulk!("online")
+                            ))
+                        )
+                    })
+                    .collect(),
+            ),
+            Command::ClusterNodes => bulk!(cluster.nodes_info()),
+            Command::ClusterMyId => bulk!(cluster.myid()),
+            Command::ClusterKeySlot { key } => integer!(key_slot(key.as_bytes()) as i64),
+            Command::ClusterCountKeysInSlot { slot } => {
+                integer!(db.count_keys_in_slot(slot).await as i64)
+            }
+            Command::ClusterGetKeysInSlot { slot, count } => Frame::Array(
+                db.keys_in_slot(slot, count as usize)
+                    .await
+                    .into_iter()
+                    .map(|key| bulk!(key))
+                    .collect(),
+            ),
+            Command::ClusterSetSlot { slot, state } => {
+                let keys = db.count_keys_in_slot(slot).await;
+                match cluster.set_slot(slot, state, keys) {
+                    Ok(()) => simple!("OK"),
+                    Err(e) => Self::error_frame(e),
+                }
+            }
+            _ => error!("ERR expected a cluster command"),
+        }
+    }
+
+    /// Reply of `ROLE`, e.g., `["master", offset, [[ip, port, offset], ...]]`.
+    fn role_frame(role: Role) -> Frame {
+        match role {
+            Role::Primary { offset, replicas } => array!(
+                bulk!("master"),
+                integer!(offset as i64),
+                Frame::Array(
+                    replicas
+                        .into_iter()
+                        .map(|replica| array!(
+                            bulk!(replica.ip),
+                            bulk!(replica.port.to_string()),
+                            bulk!(replica.offset.to_string())
+                        ))
+                        .collect()
+                )
+            ),
+            Role::Replica {
+                host,
+                port,
+                state,
+                offset,
+            } => array!(
+                bulk!("slave"),
+                bulk!(host),
+                integer!(port as i64),
+                bulk!(state.as_str()),
+                integer!(offset as i64)
+            ),
+        }
+    }
+
+    /// Convert an error returned by the database to an error frame.
+    ///
+    /// Database errors carry their own prefix (e.g. `WRONGTYPE`), other errors are prefixed with `ERR`.
+    fn error_frame(e: anyhow::Error) -> Frame {
+        if let Some(e) = e.downcast_ref::<RedisDatabaseError>() {
+            return error!(e.to_string());
+        }
+        match e.downcast_ref::<RedisClusterError>() {
+            Some(e) => error!(e.to_string()),
+            None => error!(format!("ERR {}", e)),
         }
     }
 }
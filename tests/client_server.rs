@@ -1,12 +1,17 @@
 use std::sync::Arc;
 
 use assert_matches::assert_matches;
+use bytes::Bytes;
 
 use redis_clone::common::bytes_to_i64;
 use redis_clone::constants::MAX_CLIENTS;
+use redis_clone::err::{RedisClientError, RedisDatabaseError};
 use redis_clone::Frame;
-use redis_clone::RedisClient;
 use redis_clone::{array, bulk, integer, null, simple};
+use redis_clone::{
+    InsertPosition, LexBound, ListEnd, Message, PendingRange, RedisClient, ScoreBound, StreamId,
+    StreamTrim, XAddId, ZAddComparison, ZAddCondition, ZAddOptions, ZRangeBy,
+};
 
 mod common;
 
@@ -31,6 +36,8 @@ impl TestClient for RedisClient {
 mod tests {
     use std::time::Duration;
 
+    use tokio_stream::StreamExt;
+
     use super::*;
 
     #[tokio::test]
@@ -280,6 +287,81 @@ mod tests {
         }
     }
 
+    #[tokio::test]
+    async fn pipelined() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        common::get_or_init_logger();
+
+        let test_server = common::TestServer::new().await;
+        let num_commands = 10_000;
+
+        // All commands are sent at once and the replies are read as a batch
+        let mut stream = tokio::net::TcpStream::connect((common::SERVER_ADDR, test_server.port()))
+            .await
+            .unwrap();
+        let (mut reader, mut writer) = stream.split();
+        let commands = "*2\r\n$4\r\nINCR\r\n$9\r\npipelined\r\n".repeat(num_commands);
+        let expected: String = (1..=num_commands).map(|i| format!(":{}\r\n", i)).collect();
+        let mut replies = vec![0; expected.len()];
+        let (written, read) = tokio::join!(
+            writer.write_all(commands.as_bytes()),
+            reader.read_exact(&mut replies)
+        );
+        written.unwrap();
+        read.unwrap();
+        // The replies arrive in the order of the commands
+        assert_eq!(String::from_utf8_lossy(&replies), expected);
+
+        // Tasks sharing a client as in `stress_test_single_client`,
+        // each sending its commands in one batch
+        let client = Arc::new(tokio::sync::Mutex::new(
+            test_server.create_client().await.unwrap(),
+        ));
+        let num_tasks = 256;
+        let commands_per_task = 40;
+        let barrier = common::create_barrier(num_tasks);
+        let mut handles = Vec::new();
+        for task_id in 0..num_tasks {
+            let client_clone = Arc::clone(&client);
+            let barrier_clone = Arc::clone(&barrier);
+            handles.push(tokio::task::spawn(async move {
+                barrier_clone.wait().await;
+
+                let key = format!("test_key_{}", task_id);
+                let mut client_guard = client_clone.lock().await;
+                let mut pipeline = client_guard.pipeline().set(
+                    key.clone(),
+                    Bytes::from(task_id.to_string()),
+                    None,
+                );
+                for _ in 0..commands_per_task {
+                    pipeline = pipeline.incr("counter".to_string());
+                }
+                let mut responses = pipeline.get(key).exec().await.unwrap();
+                assert_eq!(responses.len(), commands_per_task + 2);
+                responses.next_as::<()>().unwrap();
+                // Other tasks do not interleave their commands with the batch
+                let first: i64 = responses.next_as().unwrap();
+                for i in 1..commands_per_task {
+                    assert_eq!(responses.next_as::<i64>().unwrap(), first + i as i64);
+                }
+                let value: Option<Bytes> = responses.next_as().unwrap();
+                assert_eq!(value, Some(Bytes::from(task_id.to_string())));
+            }));
+        }
+        for handle in handles {
+            handle.await.expect("Task failed");
+        }
+
+        let mut client_guard = client.lock().await;
+        let counter = client_guard.get("counter".to_string()).await.unwrap();
+        assert_eq!(
+            counter,
+            Some(bulk!((num_tasks * commands_per_task).to_string()))
+        );
+    }
+
     #[tokio::test]
     async fn keys() {
         common::get_or_init_logger();
@@ -495,4 +577,1890 @@ mod tests {
         let response = client.ttl(key).await.unwrap().unwrap();
         assert_eq!(response, integer!(-2));
     }
+
+    #[tokio::test]
+    async fn list_push_pop() {
+        common::get_or_init_logger();
+
+        let test_server = common::TestServer::new().await;
+        let mut client = test_server.create_client().await.unwrap();
+
+        let key = "list".to_string();
+        let response = client
+            .rpush(key.clone(), vec!["b".into(), "c".into()])
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(response, integer!(2));
+
+        let response = client
+            .lpush(key.clone(), vec!["a".into(), "z".into()])
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(response, integer!(4));
+
+        let response = client.lrange(key.clone(), 0, -1).await.unwrap().unwrap();
+        assert_eq!(
+            response,
+            array!(bulk!("z"), bulk!("a"), bulk!("b"), bulk!("c"))
+        );
+
+        let response = client.lpop(key.clone(), None).await.unwrap().unwrap();
+        assert_eq!(response, bulk!("z"));
+
+        let response = client.rpop(key.clone(), Some(2)).await.unwrap().unwrap();
+        assert_eq!(response, array!(bulk!("c"), bulk!("b")));
+
+        let response = client.llen(key.clone()).await.unwrap().unwrap();
+        assert_eq!(response, integer!(1));
+
+        // Popping the last element removes the key
+        let response = client.lpop(key.clone(), None).await.unwrap().unwrap();
+        assert_eq!(response, bulk!("a"));
+        let response = client.exists(vec![key.clone()]).await.unwrap().unwrap();
+        assert_eq!(response, integer!(0));
+        let response = client.lpop(key, None).await.unwrap().unwrap();
+        assert_eq!(response, null!());
+    }
+
+    #[tokio::test]
+    async fn list_index_set_trim() {
+        common::get_or_init_logger();
+
+        let test_server = common::TestServer::new().await;
+        let mut client = test_server.create_client().await.unwrap();
+
+        let key = "list".to_string();
+        let values = ["one", "two", "three", "four", "five"];
+        client
+            .rpush(
+                key.clone(),
+                values.iter().map(|v| v.to_string().into()).collect(),
+            )
+            .await
+            .unwrap();
+
+        let response = client.lindex(key.clone(), -1).await.unwrap().unwrap();
+        assert_eq!(response, bulk!("five"));
+        let response = client.lindex(key.clone(), 42).await.unwrap().unwrap();
+        assert_eq!(response, null!());
+
+        let response = client
+            .lset(key.clone(), 1, "TWO".into())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(response, simple!("OK"));
+        let response = client
+            .lset(key.clone(), 42, "nope".into())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(response, Frame::Error("ERR index out of range".to_string()));
+
+        let response = client.ltrim(key.clone(), 1, -2).await.unwrap().unwrap();
+        assert_eq!(response, simple!("OK"));
+        let response = client.lrange(key.clone(), 0, -1).await.unwrap().unwrap();
+        assert_eq!(
+            response,
+            array!(bulk!("TWO"), bulk!("three"), bulk!("four"))
+        );
+
+        // Out of range trim removes the list
+        client.ltrim(key.clone(), 5, 10).await.unwrap();
+        let response = client.llen(key).await.unwrap().unwrap();
+        assert_eq!(response, integer!(0));
+    }
+
+    #[tokio::test]
+    async fn list_rem_insert() {
+        common::get_or_init_logger();
+
+        let test_server = common::TestServer::new().await;
+        let mut client = test_server.create_client().await.unwrap();
+
+        let key = "list".to_string();
+        let values = ["a", "x", "b", "x", "c", "x"];
+        client
+            .rpush(
+                key.clone(),
+                values.iter().map(|v| v.to_string().into()).collect(),
+            )
+            .await
+            .unwrap();
+
+        // Remove the last occurrence
+        let response = client
+            .lrem(key.clone(), -1, "x".into())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(response, integer!(1));
+
+        // Remove all remaining occurrences
+        let response = client
+            .lrem(key.clone(), 0, "x".into())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(response, integer!(2));
+
+        let response = client
+            .linsert(key.clone(), InsertPosition::Before, "b".into(), "a2".into())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(response, integer!(4));
+        let response = client
+            .linsert(key.clone(), InsertPosition::After, "c".into(), "d".into())
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(response, integer!(5));
+        let response = client
+            .linsert(
+                key.clone(),
+                InsertPosition::After,
+                "missing".into(),
+                "e".into(),
+            )
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(response, integer!(-1));
+
+        let response = client.lrange(key, 0, -1).await.unwrap().unwrap();
+        assert_eq!(
+            response,
+            array!(bulk!("a"), bulk!("a2"), bulk!("b"), bulk!("c"), bulk!("d"))
+        );
+    }
+
+    #[tokio::test]
+    async fn list_wrong_type() {
+        common::get_or_init_logger();
+
+        let test_server = common::TestServer::new().await;
+        let mut client = test_server.create_client().await.unwrap();
+
+        let wrong_type = Frame::Error(RedisDatabaseError::WrongType.to_string());
+
+        client.set_key_value("string", "value").await;
+        let response = client
+            .lpush("string".to_string(), vec!["a".into()])
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(response, wrong_type);
+
+        client
+            .rpush("list".to_string(), vec!["a".into()])
+            .await
+            .unwrap();
+        let response = client.get("list".to_string()).await.unwrap().unwrap();
+        assert_eq!(response, wrong_type);
+        let response = client.incr("list".to_string()).await.unwrap().unwrap();
+        assert_eq!(response, wrong_type);
+
+        // SET overwrites a key of any type
+        client.set_key_value("list", "value").await;
+        let response = client.get("list".to_string()).await.unwrap().unwrap();
+        assert_eq!(response, bulk!("value"));
+    }
+
+    #[tokio::test]
+    async fn list_blocking_pop() {
+        common::get_or_init_logger();
+
+        let test_server = common::TestServer::new().await;
+        let mut client = test_server.create_client().await.unwrap();
+        let timeout = Some(Duration::from_millis(100));
+
+        // Elements are popped immediately from the first non-empty list
+        client
+            .rpush("second".to_string(), vec!["a".into(), "b".into()])
+            .await
+            .unwrap();
+        let keys = vec!["first".to_string(), "second".to_string()];
+        let response = client.blpop(keys.clone(), timeout).await.unwrap();
+        assert_eq!(response, Some(("second".to_string(), "a".into())));
+        let response = client.brpop(keys.clone(), timeout).await.unwrap();
+        assert_eq!(response, Some(("second".to_string(), "b".into())));
+
+        // Timeout elapses
+        let response = client.blpop(keys.clone(), timeout).await.unwrap();
+        assert_eq!(response, None);
+
+        // Blocked clients are served in the order they blocked
+        let mut handles = Vec::new();
+        for _ in 0..2 {
+            let mut blocked = test_server.create_client().await.unwrap();
+            let keys = keys.clone();
+            handles.push(tokio::spawn(async move {
+                blocked.blpop(keys, None).await.unwrap()
+            }));
+            tokio::time::sleep(Duration::from_millis(50)).await;
+        }
+        let response = client
+            .rpush("first".to_string(), vec!["x".into(), "y".into()])
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(response, integer!(2));
+        let mut responses = Vec::new();
+        for handle in handles {
+            responses.push(handle.await.unwrap());
+        }
+        assert_eq!(
+            responses,
+            vec![
+                Some(("first".to_string(), "x".into())),
+                Some(("first".to_string(), "y".into()))
+            ]
+        );
+        let response = client.exists(vec!["first".to_string()]).await.unwrap();
+        assert_eq!(response, Some(integer!(0)));
+
+        // A client that timed out is not served
+        let response = client.blpop(keys, timeout).await.unwrap();
+        assert_eq!(response, None);
+        client
+            .rpush("first".to_string(), vec!["z".into()])
+            .await
+            .unwrap();
+        let response = client.llen("first".to_string()).await.unwrap();
+        assert_eq!(response, Some(integer!(1)));
+    }
+
+    #[tokio::test]
+    async fn list_blocking_move() {
+        common::get_or_init_logger();
+
+        let test_server = common::TestServer::new().await;
+        let mut client = test_server.create_client().await.unwrap();
+        let timeout = Some(Duration::from_millis(100));
+
+        client
+            .rpush("source".to_string(), vec!["a".into(), "b".into()])
+            .await
+            .unwrap();
+        let response = client
+            .blmove(
+                "source".to_string(),
+                "destination".to_string(),
+                ListEnd::Left,
+                ListEnd::Right,
+                timeout,
+            )
+            .await
+            .unwrap();
+        assert_eq!(response, Some("a".into()));
+        let response = client
+            .brpoplpush("source".to_string(), "destination".to_string(), timeout)
+            .await
+            .unwrap();
+        assert_eq!(response, Some("b".into()));
+        let response = client
+            .lrange("destination".to_string(), 0, -1)
+            .await
+            .unwrap();
+        assert_eq!(response, Some(array!(bulk!("b"), bulk!("a"))));
+        let response = client
+            .brpoplpush("source".to_string(), "destination".to_string(), timeout)
+            .await
+            .unwrap();
+        assert_eq!(response, None);
+
+        // A moved element serves the clients blocked on the destination
+        let mut blocked_move = test_server.create_client().await.unwrap();
+        let move_handle = tokio::spawn(async move {
+            blocked_move
+                .brpoplpush("source".to_string(), "target".to_string(), None)
+                .await
+                .unwrap()
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let mut blocked_pop = test_server.create_client().await.unwrap();
+        let pop_handle = tokio::spawn(async move {
+            blocked_pop
+                .blpop(vec!["target".to_string()], None)
+                .await
+                .unwrap()
+        });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        client
+            .lpush("source".to_string(), vec!["c".into()])
+            .await
+            .unwrap();
+        assert_eq!(move_handle.await.unwrap(), Some("c".into()));
+        assert_eq!(
+            pop_handle.await.unwrap(),
+            Some(("target".to_string(), "c".into()))
+        );
+
+        // Wrong type of the destination
+        client.set_key_value("string", "value").await;
+        client
+            .rpush("source".to_string(), vec!["d".into()])
+            .await
+            .unwrap();
+        let response = client
+            .brpoplpush("source".to_string(), "string".to_string(), timeout)
+            .await;
+        assert_matches!(
+            response.unwrap_err().downcast_ref::<RedisClientError>(),
+            Some(RedisClientError::ServerError(msg)) if *msg == RedisDatabaseError::WrongType.to_string()
+        );
+        let response = client.llen("source".to_string()).await.unwrap();
+        assert_eq!(response, Some(integer!(1)));
+    }
+
+    #[tokio::test]
+    async fn list_blocking_shutdown() {
+        common::get_or_init_logger();
+
+        let test_server = common::TestServer::new().await;
+        let mut client = test_server.create_client().await.unwrap();
+
+        let handle =
+            tokio::spawn(async move { client.blpop(vec!["list".to_string()], None).await });
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        test_server.shutdown();
+
+        // The blocked connection is closed by the server
+        let response = tokio::time::timeout(Duration::from_secs(1), handle)
+            .await
+            .expect("blocked client was not released on shutdown")
+            .unwrap();
+        assert_matches!(
+            response.unwrap_err().downcast_ref::<RedisClientError>(),
+            Some(RedisClientError::ConnectionClosed)
+        );
+    }
+
+    #[tokio::test]
+    async fn pubsub_subscribe_publish() {
+        common::get_or_init_logger();
+
+        let test_server = common::TestServer::new().await;
+        let mut publisher = test_server.create_client().await.unwrap();
+
+        let client = test_server.create_client().await.unwrap();
+        let mut subscriber = client
+            .subscribe(vec!["news".to_string(), "sport".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(subscriber.channels(), ["news", "sport"]);
+
+        let response = publisher
+            .publish("news".to_string(), "hello".into())
+            .await
+            .unwrap();
+        assert_eq!(response, 1);
+        let message = subscriber.next_message().await.unwrap().unwrap();
+        assert_eq!(
+            message,
+            Message {
+                channel: "news".to_string(),
+                pattern: None,
+                payload: "hello".into(),
+            }
+        );
+
+        subscriber
+            .psubscribe(vec!["news.*".to_string()])
+            .await
+            .unwrap();
+        let response = publisher.pubsub_numpat().await.unwrap();
+        assert_eq!(response, 1);
+        let mut channels = publisher.pubsub_channels(None).await.unwrap();
+        channels.sort();
+        assert_eq!(channels, ["news", "sport"]);
+        let response = publisher
+            .pubsub_channels(Some("s*".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(response, ["sport"]);
+        let response = publisher
+            .pubsub_numsub(vec!["news".to_string(), "weather".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(
+            response,
+            vec![("news".to_string(), 1), ("weather".to_string(), 0)]
+        );
+
+        subscriber.unsubscribe(Vec::new()).await.unwrap();
+        assert!(subscriber.channels().is_empty());
+        assert_eq!(subscriber.patterns(), ["news.*"]);
+        let response = publisher
+            .publish("news".to_string(), "ignored".into())
+            .await
+            .unwrap();
+        assert_eq!(response, 0);
+
+        // Messages are received as a stream
+        {
+            let stream = subscriber.into_stream();
+            tokio::pin!(stream);
+            for payload in ["one", "two"] {
+                let response = publisher
+                    .publish("news.local".to_string(), payload.into())
+                    .await
+                    .unwrap();
+                assert_eq!(response, 1);
+            }
+            for payload in ["one", "two"] {
+                let message = stream.next().await.unwrap().unwrap();
+                assert_eq!(
+                    message,
+                    Message {
+                        channel: "news.local".to_string(),
+                        pattern: Some("news.*".to_string()),
+                        payload: payload.into(),
+                    }
+                );
+            }
+        }
+
+        // The subscriptions are removed when the subscriber disconnects
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        let response = publisher.pubsub_numpat().await.unwrap();
+        assert_eq!(response, 0);
+    }
+
+    #[tokio::test]
+    async fn transaction_exec() {
+        common::get_or_init_logger();
+
+        let test_server = common::TestServer::new().await;
+        let mut client = test_server.create_client().await.unwrap();
+
+        let responses = client
+            .transaction()
+            .incr("counter".to_string())
+            .incr("counter".to_string())
+            .rpush("list".to_string(), vec!["a".into(), "b".into()])
+            .incr("list".to_string())
+            .get("counter".to_string())
+            .exec()
+            .await
+            .unwrap();
+        assert_eq!(
+            responses.into_frames(),
+            vec![
+                integer!(1),
+                integer!(2),
+                integer!(2),
+                // Errors during `EXEC` do not abort the transaction
+                Frame::Error(RedisDatabaseError::WrongType.to_string()),
+                bulk!("2"),
+            ]
+        );
+        let response = client.llen("list".to_string()).await.unwrap().unwrap();
+        assert_eq!(response, integer!(2));
+    }
+
+    #[tokio::test]
+    async fn transaction_watch() {
+        common::get_or_init_logger();
+
+        let test_server = common::TestServer::new().await;
+        let mut client = test_server.create_client().await.unwrap();
+        let mut other_client = test_server.create_client().await.unwrap();
+        client.set_key_value("balance", "10").await;
+
+        // The watched key is modified by another client, so the transaction is aborted
+        client.watch(vec!["balance".to_string()]).await.unwrap();
+        other_client.set_key_value("balance", "20").await;
+        let err = client
+            .transaction()
+            .set("balance".to_string(), "0".into(), None)
+            .exec()
+            .await
+            .unwrap_err();
+        assert_matches!(
+            err.downcast_ref::<RedisClientError>(),
+            Some(RedisClientError::TransactionAborted)
+        );
+        let response = client.get("balance".to_string()).await.unwrap().unwrap();
+        assert_eq!(response, bulk!("20"));
+
+        // `EXEC` unwatches all keys, so the next transaction succeeds
+        let response = client
+            .transaction()
+            .set("balance".to_string(), "0".into(), None)
+            .exec()
+            .await
+            .unwrap();
+        assert_eq!(response.into_frames(), vec![simple!("OK")]);
+
+        // Keys modified by the watching client itself abort the transaction as well
+        client.watch(vec!["balance".to_string()]).await.unwrap();
+        client.set_key_value("balance", "5").await;
+        let response = client
+            .transaction()
+            .incr("balance".to_string())
+            .exec()
+            .await;
+        assert_matches!(
+            response.unwrap_err().downcast_ref::<RedisClientError>(),
+            Some(RedisClientError::TransactionAborted)
+        );
+
+        // Unwatched keys do not abort the transaction
+        client.watch(vec!["balance".to_string()]).await.unwrap();
+        client.unwatch().await.unwrap();
+        other_client.set_key_value("balance", "7").await;
+        let response = client
+            .transaction()
+            .incr("balance".to_string())
+            .exec()
+            .await;
+        assert_eq!(response.unwrap().into_frames(), vec![integer!(8)]);
+
+        // Flushing the database modifies all existing watched keys
+        client.watch(vec!["balance".to_string()]).await.unwrap();
+        other_client.flushdb().await.unwrap();
+        let response = client
+            .transaction()
+            .incr("balance".to_string())
+            .exec()
+            .await;
+        assert_matches!(
+            response.unwrap_err().downcast_ref::<RedisClientError>(),
+            Some(RedisClientError::TransactionAborted)
+        );
+
+        // A watched key that expires before `EXEC` aborts the transaction,
+        // even before it is removed by the expiration task
+        client
+            .set(
+                "ttl".to_string(),
+                "1".into(),
+                Some(Duration::from_millis(20)),
+            )
+            .await
+            .unwrap();
+        client.watch(vec!["ttl".to_string()]).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(30)).await;
+        let response = client.transaction().incr("ttl".to_string()).exec().await;
+        assert_matches!(
+            response.unwrap_err().downcast_ref::<RedisClientError>(),
+            Some(RedisClientError::TransactionAborted)
+        );
+    }
+
+    #[tokio::test]
+    async fn pipeline() {
+        common::get_or_init_logger();
+
+        let test_server = common::TestServer::new().await;
+        let mut client = test_server.create_client().await.unwrap();
+        let mut other_client = test_server.create_client().await.unwrap();
+
+        let mut responses = client
+            .pipeline()
+            .set("key".to_string(), "value".into(), None)
+            .incr("counter".to_string())
+            .incr("key".to_string())
+            .get("key".to_string())
+            .rpush("list".to_string(), vec!["a".into(), "b".into()])
+            .lrange("list".to_string(), 0, -1)
+            .exec()
+            .await
+            .unwrap();
+        assert_eq!(responses.len(), 6);
+        responses.next_as::<()>().unwrap();
+        assert_eq!(responses.next_as::<i64>().unwrap(), 1);
+        // Failed commands do not stop the pipeline
+        let err = responses.next_as::<i64>().unwrap_err();
+        assert_matches!(
+            err.downcast_ref::<RedisClientError>(),
+            Some(RedisClientError::ServerError(_))
+        );
+        let value: Option<Bytes> = responses.next_as().unwrap();
+        assert_eq!(value, Some(Bytes::from("value")));
+        assert_eq!(
+            responses.into_frames(),
+            vec![integer!(2), array!(bulk!("a"), bulk!("b"))]
+        );
+
+        let responses = client.pipeline().exec().await.unwrap();
+        assert!(responses.is_empty());
+
+        let mut responses = client
+            .pipeline()
+            .atomic()
+            .incr("counter".to_string())
+            .incr("counter".to_string())
+            .exec()
+            .await
+            .unwrap();
+        assert_eq!(responses.next_as::<i64>().unwrap(), 2);
+        assert_eq!(responses.next_as::<i64>().unwrap(), 3);
+        assert!(responses.next_as::<i64>().is_err());
+
+        // The watched key is modified by another client, so the atomic pipeline is aborted
+        client.watch(vec!["counter".to_string()]).await.unwrap();
+        other_client.set_key_value("counter", "10").await;
+        let err = client
+            .pipeline()
+            .atomic()
+            .incr("counter".to_string())
+            .exec()
+            .await
+            .unwrap_err();
+        assert_matches!(
+            err.downcast_ref::<RedisClientError>(),
+            Some(RedisClientError::TransactionAborted)
+        );
+        let response = client.get("counter".to_string()).await.unwrap().unwrap();
+        assert_eq!(response, bulk!("10"));
+    }
+
+    #[tokio::test]
+    async fn hash_set_get() {
+        common::get_or_init_logger();
+
+        let test_server = common::TestServer::new().await;
+        let mut client = test_server.create_client().await.unwrap();
+
+        let key = "user:1".to_string();
+        let added = client
+            .hset(
+                key.clone(),
+                vec![("name".into(), "Alice".into()), ("age".into(), "30".into())],
+            )
+            .await
+            .unwrap();
+        assert_eq!(added, 2);
+
+        // Updating an existing field does not count as added
+        let added = client
+            .hset(key.clone(), vec![("age".into(), "31".into())])
+            .await
+            .unwrap();
+        assert_eq!(added, 0);
+
+        let name = client.hget(key.clone(), "name".into()).await.unwrap();
+        assert_eq!(name, Some(Bytes::from("Alice")));
+
+        let values = client
+            .hmget(key.clone(), vec!["age".into(), "missing".into()])
+            .await
+            .unwrap();
+        assert_eq!(values, vec![Some(Bytes::from("31")), None]);
+
+        assert!(!client
+            .hsetnx(key.clone(), "name".into(), "Bob".into())
+            .await
+            .unwrap());
+        assert!(client
+            .hsetnx(key.clone(), "email".into(), "alice@example.com".into())
+            .await
+            .unwrap());
+
+        let mut pairs = client.hgetall(key.clone()).await.unwrap();
+        pairs.sort();
+        assert_eq!(
+            pairs,
+            vec![
+                (Bytes::from("age"), Bytes::from("31")),
+                (Bytes::from("email"), Bytes::from("alice@example.com")),
+                (Bytes::from("name"), Bytes::from("Alice")),
+            ]
+        );
+
+        let mut fields = client.hkeys(key.clone()).await.unwrap();
+        fields.sort();
+        assert_eq!(fields, vec!["age", "email", "name"]);
+        assert_eq!(client.hvals(key.clone()).await.unwrap().len(), 3);
+        assert_eq!(client.hlen(key.clone()).await.unwrap(), 3);
+        assert!(client.hexists(key.clone(), "email".into()).await.unwrap());
+
+        // Removing all fields removes the key
+        let removed = client
+            .hdel(
+                key.clone(),
+                vec!["age".into(), "email".into(), "name".into()],
+            )
+            .await
+            .unwrap();
+        assert_eq!(removed, 3);
+        let response = client.exists(vec![key]).await.unwrap().unwrap();
+        assert_eq!(response, integer!(0));
+    }
+
+    #[tokio::test]
+    async fn hash_incrby() {
+        common::get_or_init_logger();
+
+        let test_server = common::TestServer::new().await;
+        let mut client = test_server.create_client().await.unwrap();
+
+        let key = "counters".to_string();
+        assert_eq!(
+            client
+                .hincrby(key.clone(), "visits".into(), 5)
+                .await
+                .unwrap(),
+            5
+        );
+        assert_eq!(
+            client
+                .hincrby(key.clone(), "visits".into(), -7)
+                .await
+                .unwrap(),
+            -2
+        );
+
+        client
+            .hset(key.clone(), vec![("name".into(), "abc".into())])
+            .await
+            .unwrap();
+        let err = client
+            .hincrby(key.clone(), "name".into(), 1)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            RedisDatabaseError::HashValueNotInteger.to_string()
+        );
+
+        client
+            .hset(
+                key.clone(),
+                vec![("max".into(), i64::MAX.to_string().into())],
+            )
+            .await
+            .unwrap();
+        let err = client.hincrby(key, "max".into(), 1).await.unwrap_err();
+        assert_eq!(err.to_string(), RedisDatabaseError::Overflow.to_string());
+    }
+
+    #[tokio::test]
+    async fn hash_wrong_type() {
+        common::get_or_init_logger();
+
+        let test_server = common::TestServer::new().await;
+        let mut client = test_server.create_client().await.unwrap();
+
+        client.set_key_value("string", "value").await;
+        let err = client
+            .hget("string".to_string(), "field".into())
+            .await
+            .unwrap_err();
+        assert_matches!(
+            err.downcast_ref::<RedisClientError>(),
+            Some(RedisClientError::ServerError(msg)) if msg.starts_with("WRONGTYPE")
+        );
+
+        client
+            .hset("hash".to_string(), vec![("field".into(), "value".into())])
+            .await
+            .unwrap();
+        let response = client.llen("hash".to_string()).await.unwrap().unwrap();
+        assert_eq!(
+            response,
+            Frame::Error(RedisDatabaseError::WrongType.to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn set_members() {
+        common::get_or_init_logger();
+
+        let test_server = common::TestServer::new().await;
+        let mut client = test_server.create_client().await.unwrap();
+
+        let key = "tags".to_string();
+        let added = client
+            .sadd(key.clone(), vec!["a".into(), "b".into(), "a".into()])
+            .await
+            .unwrap();
+        assert_eq!(added, 2);
+        assert_eq!(client.scard(key.clone()).await.unwrap(), 2);
+        assert!(client.sismember(key.clone(), "a".into()).await.unwrap());
+        assert!(!client.sismember(key.clone(), "c".into()).await.unwrap());
+
+        let mut members = client.smembers(key.clone()).await.unwrap();
+        members.sort();
+        assert_eq!(members, vec!["a", "b"]);
+
+        let removed = client
+            .srem(key.clone(), vec!["a".into(), "c".into()])
+            .await
+            .unwrap();
+        assert_eq!(removed, 1);
+
+        let member = client.srandmember(key.clone()).await.unwrap();
+        assert_eq!(member, Some(Bytes::from("b")));
+        let members = client.srandmember_count(key.clone(), -3).await.unwrap();
+        assert_eq!(members, vec!["b", "b", "b"]);
+
+        // Popping the last member removes the key
+        let member = client.spop(key.clone()).await.unwrap();
+        assert_eq!(member, Some(Bytes::from("b")));
+        assert_eq!(client.spop(key.clone()).await.unwrap(), None);
+        let response = client.exists(vec![key]).await.unwrap().unwrap();
+        assert_eq!(response, integer!(0));
+    }
+
+    #[tokio::test]
+    async fn set_pop_count() {
+        common::get_or_init_logger();
+
+        let test_server = common::TestServer::new().await;
+        let mut client = test_server.create_client().await.unwrap();
+
+        let key = "numbers".to_string();
+        let members: Vec<Bytes> = (0..10).map(|i| i.to_string().into()).collect();
+        client.sadd(key.clone(), members.clone()).await.unwrap();
+
+        let popped = client.spop_count(key.clone(), 4).await.unwrap();
+        assert_eq!(popped.len(), 4);
+        assert_eq!(client.scard(key.clone()).await.unwrap(), 6);
+        for member in popped {
+            assert!(members.contains(&member));
+            assert!(!client.sismember(key.clone(), member).await.unwrap());
+        }
+
+        let sample = client.srandmember_count(key.clone(), 100).await.unwrap();
+        assert_eq!(sample.len(), 6);
+        let sample = client
+            .srandmember_count(key.clone(), 1 << 40)
+            .await
+            .unwrap();
+        assert_eq!(sample.len(), 6);
+        let sample = client.srandmember_count(key.clone(), -100).await.unwrap();
+        assert_eq!(sample.len(), 100);
+        let err = client
+            .srandmember_count(key.clone(), i64::MIN)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("value is out of range"));
+
+        // Popping more members than the set has removes the key
+        let popped = client.spop_count(key.clone(), 10).await.unwrap();
+        assert_eq!(popped.len(), 6);
+        assert_eq!(client.scard(key).await.unwrap(), 0);
+    }
+
+    #[tokio::test]
+    async fn set_algebra() {
+        common::get_or_init_logger();
+
+        let test_server = common::TestServer::new().await;
+        let mut client = test_server.create_client().await.unwrap();
+
+        client
+            .sadd("s1".to_string(), vec!["a".into(), "b".into(), "c".into()])
+            .await
+            .unwrap();
+        client
+            .sadd("s2".to_string(), vec!["b".into(), "c".into(), "d".into()])
+            .await
+            .unwrap();
+
+        let keys = vec!["s1".to_string(), "s2".to_string()];
+
+        let mut inter = client.sinter(keys.clone()).await.unwrap();
+        inter.sort();
+        assert_eq!(inter, vec!["b", "c"]);
+
+        let mut union = client.sunion(keys.clone()).await.unwrap();
+        union.sort();
+        assert_eq!(union, vec!["a", "b", "c", "d"]);
+
+        let diff = client.sdiff(keys.clone()).await.unwrap();
+        assert_eq!(diff, vec!["a"]);
+
+        // Missing keys are empty sets
+        let inter = client
+            .sinter(vec!["s1".to_string(), "missing".to_string()])
+            .await
+            .unwrap();
+        assert!(inter.is_empty());
+
+        // The destination is overwritten regardless of its type
+        client.set_key_value("dest", "string").await;
+        let len = client
+            .sunionstore("dest".to_string(), keys.clone())
+            .await
+            .unwrap();
+        assert_eq!(len, 4);
+        assert_eq!(client.scard("dest".to_string()).await.unwrap(), 4);
+
+        let len = client
+            .sinterstore("dest".to_string(), keys.clone())
+            .await
+            .unwrap();
+        assert_eq!(len, 2);
+
+        // Empty result removes the destination
+        let len = client
+            .sdiffstore("dest".to_string(), vec!["s1".to_string(), "s1".to_string()])
+            .await
+            .unwrap();
+        assert_eq!(len, 0);
+        let response = client
+            .exists(vec!["dest".to_string()])
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(response, integer!(0));
+
+        // Wrong type in any of the source keys
+        client.set_key_value("string", "value").await;
+        let err = client
+            .sunion(vec!["s1".to_string(), "string".to_string()])
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), RedisDatabaseError::WrongType.to_string());
+    }
+
+    #[tokio::test]
+    async fn sorted_set_add_score_rank() {
+        common::get_or_init_logger();
+
+        let test_server = common::TestServer::new().await;
+        let mut client = test_server.create_client().await.unwrap();
+        let key = "zset".to_string();
+
+        let added = client
+            .zadd(
+                key.clone(),
+                vec![
+                    (1.0, "one".into()),
+                    (2.0, "two".into()),
+                    (3.0, "three".into()),
+                ],
+            )
+            .await
+            .unwrap();
+        assert_eq!(added, 3);
+        assert_eq!(client.zcard(key.clone()).await.unwrap(), 3);
+        assert_eq!(
+            client.zscore(key.clone(), "two".into()).await.unwrap(),
+            Some(2.0)
+        );
+        assert_eq!(
+            client.zscore(key.clone(), "none".into()).await.unwrap(),
+            None
+        );
+        assert_eq!(
+            client.zrank(key.clone(), "three".into()).await.unwrap(),
+            Some(2)
+        );
+        assert_eq!(
+            client.zrank(key.clone(), "none".into()).await.unwrap(),
+            None
+        );
+
+        // NX only adds new members
+        let options = ZAddOptions {
+            condition: Some(ZAddCondition::Nx),
+            ..Default::default()
+        };
+        let added = client
+            .zadd_with_options(
+                key.clone(),
+                vec![(10.0, "one".into()), (4.0, "four".into())],
+                options,
+            )
+            .await
+            .unwrap();
+        assert_eq!(added, 1);
+        assert_eq!(
+            client.zscore(key.clone(), "one".into()).await.unwrap(),
+            Some(1.0)
+        );
+
+        // XX with CH counts updated members and never adds new ones
+        let options = ZAddOptions {
+            condition: Some(ZAddCondition::Xx),
+            changed: true,
+            ..Default::default()
+        };
+        let changed = client
+            .zadd_with_options(
+                key.clone(),
+                vec![
+                    (5.0, "one".into()),
+                    (2.0, "two".into()),
+                    (6.0, "six".into()),
+                ],
+                options,
+            )
+            .await
+            .unwrap();
+        assert_eq!(changed, 1);
+        assert_eq!(
+            client.zscore(key.clone(), "six".into()).await.unwrap(),
+            None
+        );
+
+        // GT only updates when the new score is greater
+        let options = ZAddOptions {
+            comparison: Some(ZAddComparison::Gt),
+            changed: true,
+            ..Default::default()
+        };
+        let changed = client
+            .zadd_with_options(
+                key.clone(),
+                vec![(1.0, "one".into()), (7.0, "two".into())],
+                options,
+            )
+            .await
+            .unwrap();
+        assert_eq!(changed, 1);
+        assert_eq!(
+            client.zscore(key.clone(), "one".into()).await.unwrap(),
+            Some(5.0)
+        );
+
+        // INCR returns the new score, or nil if aborted by the options
+        let score = client
+            .zadd_incr(key.clone(), 0.5, "two".into(), ZAddOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(score, Some(7.5));
+        let options = ZAddOptions {
+            condition: Some(ZAddCondition::Nx),
+            ..Default::default()
+        };
+        let score = client
+            .zadd_incr(key.clone(), 1.0, "two".into(), options)
+            .await
+            .unwrap();
+        assert_eq!(score, None);
+
+        let score = client
+            .zincrby(key.clone(), -2.5, "two".into())
+            .await
+            .unwrap();
+        assert_eq!(score, 5.0);
+        let score = client
+            .zincrby(key.clone(), f64::INFINITY, "new".into())
+            .await
+            .unwrap();
+        assert_eq!(score, f64::INFINITY);
+
+        let removed = client
+            .zrem(key.clone(), vec!["one".into(), "none".into()])
+            .await
+            .unwrap();
+        assert_eq!(removed, 1);
+    }
+
+    #[tokio::test]
+    async fn sorted_set_range_pop() {
+        common::get_or_init_logger();
+
+        let test_server = common::TestServer::new().await;
+        let mut client = test_server.create_client().await.unwrap();
+        let key = "zset".to_string();
+
+        client
+            .zadd(
+                key.clone(),
+                vec![
+                    (1.0, "a".into()),
+                    (2.0, "b".into()),
+                    (2.0, "c".into()),
+                    (3.0, "d".into()),
+                    (4.0, "e".into()),
+                ],
+            )
+            .await
+            .unwrap();
+
+        let members = client
+            .zrange(
+                key.clone(),
+                ZRangeBy::Index { start: 1, stop: -2 },
+                false,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(members, vec!["b", "c", "d"]);
+
+        let members = client
+            .zrange(
+                key.clone(),
+                ZRangeBy::Index { start: 0, stop: 1 },
+                true,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(members, vec!["e", "d"]);
+
+        let by_score = ZRangeBy::Score {
+            min: ScoreBound::Exclusive(1.0),
+            max: ScoreBound::Inclusive(f64::INFINITY),
+        };
+        let entries = client
+            .zrange_withscores(key.clone(), by_score.clone(), false, Some((1, 2)))
+            .await
+            .unwrap();
+        assert_eq!(entries, vec![("c".into(), 2.0), ("d".into(), 3.0)]);
+
+        let members = client
+            .zrange(key.clone(), by_score, true, Some((0, 2)))
+            .await
+            .unwrap();
+        assert_eq!(members, vec!["e", "d"]);
+
+        // Lex ranges expect members of the same score
+        let lex_key = "lex".to_string();
+        client
+            .zadd(
+                lex_key.clone(),
+                ["a", "b", "c", "d"].map(|m| (0.0, m.into())).to_vec(),
+            )
+            .await
+            .unwrap();
+        let by_lex = ZRangeBy::Lex {
+            min: LexBound::Inclusive("b".into()),
+            max: LexBound::Exclusive("d".into()),
+        };
+        let members = client
+            .zrange(lex_key.clone(), by_lex, false, None)
+            .await
+            .unwrap();
+        assert_eq!(members, vec!["b", "c"]);
+        let by_lex = ZRangeBy::Lex {
+            min: LexBound::Exclusive("a".into()),
+            max: LexBound::Max,
+        };
+        let members = client
+            .zrange(lex_key, by_lex, true, Some((0, 2)))
+            .await
+            .unwrap();
+        assert_eq!(members, vec!["d", "c"]);
+
+        let count = client
+            .zcount(
+                key.clone(),
+                ScoreBound::Inclusive(f64::NEG_INFINITY),
+                ScoreBound::Exclusive(3.0),
+            )
+            .await
+            .unwrap();
+        assert_eq!(count, 3);
+        // Empty and single score ranges
+        for (min, max, expected) in [
+            (ScoreBound::Inclusive(2.0), ScoreBound::Inclusive(2.0), 2),
+            (ScoreBound::Exclusive(2.0), ScoreBound::Exclusive(2.0), 0),
+            (ScoreBound::Inclusive(3.0), ScoreBound::Inclusive(2.0), 0),
+            (
+                ScoreBound::Exclusive(f64::INFINITY),
+                ScoreBound::Inclusive(f64::INFINITY),
+                0,
+            ),
+        ] {
+            let count = client.zcount(key.clone(), min, max).await.unwrap();
+            assert_eq!(count, expected);
+        }
+
+        let popped = client.zpopmin(key.clone(), None).await.unwrap();
+        assert_eq!(popped, vec![(Bytes::from("a"), 1.0)]);
+        let popped = client.zpopmax(key.clone(), Some(2)).await.unwrap();
+        assert_eq!(
+            popped,
+            vec![(Bytes::from("e"), 4.0), (Bytes::from("d"), 3.0)]
+        );
+
+        // Popping the remaining members removes the key
+        let popped = client.zpopmin(key.clone(), Some(10)).await.unwrap();
+        assert_eq!(popped.len(), 2);
+        let response = client.exists(vec![key.clone()]).await.unwrap().unwrap();
+        assert_eq!(response, integer!(0));
+
+        // Wrong type
+        client.set_key_value("string", "value").await;
+        let err = client
+            .zadd("string".to_string(), vec![(1.0, "a".into())])
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), RedisDatabaseError::WrongType.to_string());
+    }
+
+    #[tokio::test]
+    async fn stream_add_range() {
+        common::get_or_init_logger();
+
+        let test_server = common::TestServer::new().await;
+        let mut client = test_server.create_client().await.unwrap();
+        let key = "stream".to_string();
+
+        let id = client
+            .xadd(
+                key.clone(),
+                XAddId::Explicit(StreamId::new(1, 1)),
+                vec![("temp".into(), "20".into())],
+            )
+            .await
+            .unwrap();
+        assert_eq!(id, StreamId::new(1, 1));
+
+        // Partial IDs get the next sequence number
+        let id = client
+            .xadd(
+                key.clone(),
+                XAddId::Partial(1),
+                vec![("temp".into(), "21".into())],
+            )
+            .await
+            .unwrap();
+        assert_eq!(id, StreamId::new(1, 2));
+
+        // Auto-generated IDs use the current time
+        let id = client
+            .xadd(
+                key.clone(),
+                XAddId::Auto,
+                vec![("temp".into(), "22".into())],
+            )
+            .await
+            .unwrap();
+        assert!(id.ms > 1);
+
+        // IDs must be increasing
+        let err = client
+            .xadd(
+                key.clone(),
+                XAddId::Explicit(StreamId::new(1, 2)),
+                vec![("temp".into(), "23".into())],
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            RedisDatabaseError::StreamIdTooSmall.to_string()
+        );
+        let err = client
+            .xadd(
+                "other".to_string(),
+                XAddId::Explicit(StreamId::new(0, 0)),
+                vec![("temp".into(), "23".into())],
+            )
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            RedisDatabaseError::StreamIdZero.to_string()
+        );
+
+        assert_eq!(client.xlen(key.clone()).await.unwrap(), 3);
+
+        let entries = client
+            .xrange(key.clone(), StreamId::MIN, StreamId::MAX, None)
+            .await
+            .unwrap();
+        assert_eq!(entries.len(), 3);
+        assert_eq!(entries[0].id, StreamId::new(1, 1));
+        assert_eq!(
+            entries[0].fields,
+            vec![(Bytes::from("temp"), Bytes::from("20"))]
+        );
+
+        let entries = client
+            .xrevrange(key.clone(), StreamId::MAX, StreamId::MIN, Some(2))
+            .await
+            .unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].id, id);
+        assert_eq!(entries[1].id, StreamId::new(1, 2));
+
+        let deleted = client
+            .xdel(key.clone(), vec![StreamId::new(1, 2), StreamId::new(9, 9)])
+            .await
+            .unwrap();
+        assert_eq!(deleted, 1);
+        assert_eq!(client.xlen(key.clone()).await.unwrap(), 2);
+
+        // NOMKSTREAM does not create the stream
+        let id = client
+            .xadd_with_options(
+                "missing".to_string(),
+                XAddId::Auto,
+                vec![("a".into(), "b".into())],
+                None,
+                false,
+            )
+            .await
+            .unwrap();
+        assert_eq!(id, None);
+    }
+
+    #[tokio::test]
+    async fn stream_trim_read() {
+        common::get_or_init_logger();
+
+        let test_server = common::TestServer::new().await;
+        let mut client = test_server.create_client().await.unwrap();
+
+        for seq in 1..=5 {
+            client
+                .xadd(
+                    "s1".to_string(),
+                    XAddId::Explicit(StreamId::new(1, seq)),
+                    vec![("n".into(), seq.to_string().into())],
+                )
+                .await
+                .unwrap();
+        }
+
+        // Trimming while adding
+        client
+            .xadd_with_options(
+                "s1".to_string(),
+                XAddId::Explicit(StreamId::new(1, 6)),
+                vec![("n".into(), "6".into())],
+                Some(StreamTrim::MaxLen(4)),
+                true,
+            )
+            .await
+            .unwrap();
+        assert_eq!(client.xlen("s1".to_string()).await.unwrap(), 4);
+
+        let removed = client
+            .xtrim("s1".to_string(), StreamTrim::MinId(StreamId::new(1, 5)))
+            .await
+            .unwrap();
+        assert_eq!(removed, 2);
+        let removed = client
+            .xtrim("s1".to_string(), StreamTrim::MaxLen(0))
+            .await
+            .unwrap();
+        assert_eq!(removed, 2);
+
+        // Empty streams are kept
+        let response = client
+            .exists(vec!["s1".to_string()])
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(response, integer!(1));
+
+        for (key, seq) in [("s1", 7), ("s2", 1), ("s2", 2)] {
+            client
+                .xadd(
+                    key.to_string(),
+                    XAddId::Explicit(StreamId::new(2, seq)),
+                    vec![("n".into(), seq.to_string().into())],
+                )
+                .await
+                .unwrap();
+        }
+
+        let streams = client
+            .xread(
+                vec![
+                    ("s1".to_string(), Some(StreamId::MIN)),
+                    ("s2".to_string(), Some(StreamId::new(2, 1))),
+                    ("missing".to_string(), Some(StreamId::MIN)),
+                ],
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(streams.len(), 2);
+        assert_eq!(streams[0].0, "s1");
+        assert_eq!(streams[0].1[0].id, StreamId::new(2, 7));
+        assert_eq!(streams[1].0, "s2");
+        assert_eq!(streams[1].1.len(), 1);
+        assert_eq!(streams[1].1[0].id, StreamId::new(2, 2));
+
+        let streams = client
+            .xread(vec![("s2".to_string(), Some(StreamId::MIN))], Some(1))
+            .await
+            .unwrap();
+        assert_eq!(streams[0].1.len(), 1);
+
+        // `$` only returns entries added after the call
+        let streams = client
+            .xread(vec![("s2".to_string(), None)], None)
+            .await
+            .unwrap();
+        assert!(streams.is_empty());
+
+        // The last ID may only move forward
+        client
+            .xsetid("s2".to_string(), StreamId::new(3, 0))
+            .await
+            .unwrap();
+        let err = client
+            .xsetid("s2".to_string(), StreamId::new(2, 1))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            RedisDatabaseError::StreamIdSmallerThanTop.to_string()
+        );
+        let err = client
+            .xadd(
+                "s2".to_string(),
+                XAddId::Explicit(StreamId::new(2, 3)),
+                vec![("n".into(), "3".into())],
+            )
+            .await;
+        assert!(err.is_err());
+
+        client.set_key_value("string", "value").await;
+        let err = client.xlen("string".to_string()).await.unwrap_err();
+        assert_eq!(err.to_string(), RedisDatabaseError::WrongType.to_string());
+    }
+
+    #[tokio::test]
+    async fn stream_consumer_group_read_ack() {
+        common::get_or_init_logger();
+
+        let test_server = common::TestServer::new().await;
+        let mut client = test_server.create_client().await.unwrap();
+        let (key, group) = ("jobs".to_string(), "workers".to_string());
+
+        // The stream must exist unless MKSTREAM is used
+        let err = client
+            .xgroup_create(key.clone(), group.clone(), Some(StreamId::MIN), false)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            RedisDatabaseError::StreamKeyRequired.to_string()
+        );
+        client
+            .xgroup_create(key.clone(), group.clone(), None, true)
+            .await
+            .unwrap();
+        let err = client
+            .xgroup_create(key.clone(), group.clone(), None, false)
+            .await
+            .unwrap_err();
+        assert_eq!(err.to_string(), RedisDatabaseError::BusyGroup.to_string());
+
+        for seq in 1..=3 {
+            client
+                .xadd(
+                    key.clone(),
+                    XAddId::Explicit(StreamId::new(1, seq)),
+                    vec![("job".into(), seq.to_string().into())],
+                )
+                .await
+                .unwrap();
+        }
+
+        // New entries are delivered to one consumer only
+        let streams = client
+            .xreadgroup(
+                group.clone(),
+                "alice".to_string(),
+                vec![(key.clone(), None)],
+                Some(2),
+                false,
+            )
+            .await
+            .unwrap();
+        assert_eq!(streams.len(), 1);
+        let ids: Vec<_> = streams[0].1.iter().map(|entry| entry.id).collect();
+        assert_eq!(ids, vec![StreamId::new(1, 1), StreamId::new(1, 2)]);
+
+        let streams = client
+            .xreadgroup(
+                group.clone(),
+                "bob".to_string(),
+                vec![(key.clone(), None)],
+                None,
+                false,
+            )
+            .await
+            .unwrap();
+        assert_eq!(streams[0].1.len(), 1);
+        assert_eq!(streams[0].1[0].id, StreamId::new(1, 3));
+
+        // Nothing new to deliver
+        let streams = client
+            .xreadgroup(
+                group.clone(),
+                "bob".to_string(),
+                vec![(key.clone(), None)],
+                None,
+                false,
+            )
+            .await
+            .unwrap();
+        assert!(streams.is_empty());
+
+        let summary = client.xpending(key.clone(), group.clone()).await.unwrap();
+        assert_eq!(summary.count, 3);
+        assert_eq!(summary.min_id, Some(StreamId::new(1, 1)));
+        assert_eq!(summary.max_id, Some(StreamId::new(1, 3)));
+        assert_eq!(
+            summary.consumers,
+            vec![("alice".to_string(), 2), ("bob".to_string(), 1)]
+        );
+
+        // History of the consumer's pending entries
+        let streams = client
+            .xreadgroup(
+                group.clone(),
+                "alice".to_string(),
+                vec![(key.clone(), Some(StreamId::MIN))],
+                None,
+                false,
+            )
+            .await
+            .unwrap();
+        assert_eq!(streams[0].1.len(), 2);
+
+        let acknowledged = client
+            .xack(
+                key.clone(),
+                group.clone(),
+                vec![
+                    StreamId::new(1, 1),
+                    StreamId::new(1, 3),
+                    StreamId::new(9, 9),
+                ],
+            )
+            .await
+            .unwrap();
+        assert_eq!(acknowledged, 2);
+
+        let pending = client
+            .xpending_range(
+                key.clone(),
+                group.clone(),
+                PendingRange {
+                    min_idle: None,
+                    start: StreamId::MIN,
+                    end: StreamId::MAX,
+                    count: 10,
+                    consumer: None,
+                },
+            )
+            .await
+            .unwrap();
+        assert_eq!(pending.len(), 1);
+        assert_eq!(pending[0].id, StreamId::new(1, 2));
+        assert_eq!(pending[0].consumer, "alice");
+        // Delivered once as a new entry and once as history
+        assert_eq!(pending[0].delivery_count, 2);
+
+        // Unknown group
+        let err = client
+            .xreadgroup(
+                "unknown".to_string(),
+                "alice".to_string(),
+                vec![(key.clone(), None)],
+                None,
+                false,
+            )
+            .await
+            .unwrap_err();
+        assert!(err.to_string().starts_with("NOGROUP"));
+    }
+
+    #[tokio::test]
+    async fn stream_consumer_group_claim_info() {
+        common::get_or_init_logger();
+
+        let test_server = common::TestServer::new().await;
+        let mut client = test_server.create_client().await.unwrap();
+        let (key, group) = ("jobs".to_string(), "workers".to_string());
+
+        for seq in 1..=3 {
+            client
+                .xadd(
+                    key.clone(),
+                    XAddId::Explicit(StreamId::new(1, seq)),
+                    vec![("job".into(), seq.to_string().into())],
+                )
+                .await
+                .unwrap();
+        }
+        client
+            .xgroup_create(key.clone(), group.clone(), Some(StreamId::MIN), false)
+            .await
+            .unwrap();
+        client
+            .xreadgroup(
+                group.clone(),
+                "alice".to_string(),
+                vec![(key.clone(), None)],
+                None,
+                false,
+            )
+            .await
+            .unwrap();
+
+        // Entries are not idle long enough
+        let claimed = client
+            .xclaim(
+                key.clone(),
+                group.clone(),
+                "bob".to_string(),
+                60_000,
+                vec![StreamId::new(1, 1)],
+            )
+            .await
+            .unwrap();
+        assert!(claimed.is_empty());
+
+        let claimed = client
+            .xclaim(
+                key.clone(),
+                group.clone(),
+                "bob".to_string(),
+                0,
+                vec![StreamId::new(1, 1)],
+            )
+            .await
+            .unwrap();
+        assert_eq!(claimed.len(), 1);
+        assert_eq!(
+            claimed[0].fields,
+            vec![(Bytes::from("job"), Bytes::from("1"))]
+        );
+
+        let claimed = client
+            .xclaim_justid(
+                key.clone(),
+                group.clone(),
+                "bob".to_string(),
+                0,
+                vec![StreamId::new(1, 2)],
+            )
+            .await
+            .unwrap();
+        assert_eq!(claimed, vec![StreamId::new(1, 2)]);
+
+        // Deleted entries are removed from the PEL
+        client
+            .xdel(key.clone(), vec![StreamId::new(1, 3)])
+            .await
+            .unwrap();
+        let claim = client
+            .xautoclaim(
+                key.clone(),
+                group.clone(),
+                "carol".to_string(),
+                0,
+                StreamId::MIN,
+                Some(2),
+            )
+            .await
+            .unwrap();
+        assert_eq!(claim.next_id, StreamId::new(1, 3));
+        assert_eq!(claim.entries.len(), 2);
+        let claim = client
+            .xautoclaim(
+                key.clone(),
+                group.clone(),
+                "carol".to_string(),
+                0,
+                claim.next_id,
+                None,
+            )
+            .await
+            .unwrap();
+        assert_eq!(claim.next_id, StreamId::MIN);
+        assert!(claim.entries.is_empty());
+        assert_eq!(claim.deleted, vec![StreamId::new(1, 3)]);
+
+        let info = client.xinfo_stream(key.clone()).await.unwrap();
+        assert_eq!(info.length, 2);
+        assert_eq!(info.last_generated_id, StreamId::new(1, 3));
+        assert_eq!(info.groups, 1);
+        assert_eq!(info.first_entry.unwrap().id, StreamId::new(1, 1));
+
+        let groups = client.xinfo_groups(key.clone()).await.unwrap();
+        assert_eq!(groups.len(), 1);
+        assert_eq!(groups[0].name, group);
+        assert_eq!(groups[0].consumers, 3);
+        assert_eq!(groups[0].pending, 2);
+        assert_eq!(groups[0].last_delivered_id, StreamId::new(1, 3));
+
+        let consumers = client
+            .xinfo_consumers(key.clone(), group.clone())
+            .await
+            .unwrap();
+        let pending: Vec<_> = consumers
+            .iter()
+            .map(|consumer| (consumer.name.as_str(), consumer.pending))
+            .collect();
+        assert_eq!(pending, vec![("alice", 0), ("bob", 0), ("carol", 2)]);
+
+        let deleted = client
+            .xgroup_delconsumer(key.clone(), group.clone(), "carol".to_string())
+            .await
+            .unwrap();
+        assert_eq!(deleted, 2);
+        assert_eq!(
+            client
+                .xpending(key.clone(), group.clone())
+                .await
+                .unwrap()
+                .count,
+            0
+        );
+        assert!(client
+            .xgroup_destroy(key.clone(), group.clone())
+            .await
+            .unwrap());
+    }
+
+    #[tokio::test]
+    async fn hello() {
+        common::get_or_init_logger();
+
+        let test_server = common::TestServer::new().await;
+        let mut client = test_server.create_client().await.unwrap();
+        let mut publisher = test_server.create_client().await.unwrap();
+
+        let properties = client.hello(None).await.unwrap();
+        assert_eq!(properties["proto"], integer!(2));
+        let properties = client.hello(Some(3)).await.unwrap();
+        assert_eq!(properties["proto"], integer!(3));
+        assert_eq!(properties["role"], bulk!("master"));
+        let err = client.hello(Some(1)).await.unwrap_err();
+        assert!(err.to_string().starts_with("NOPROTO"), "{}", err);
+
+        // Typed replies are the same with both protocols
+        client
+            .hset("hash".into(), vec![(Bytes::from("f"), Bytes::from("v"))])
+            .await
+            .unwrap();
+        assert_eq!(
+            client.hgetall("hash".into()).await.unwrap(),
+            vec![(Bytes::from("f"), Bytes::from("v"))]
+        );
+        client
+            .zadd("zset".into(), vec![(1.5, Bytes::from("m"))])
+            .await
+            .unwrap();
+        assert_eq!(
+            client
+                .zscore("zset".into(), Bytes::from("m"))
+                .await
+                .unwrap(),
+            Some(1.5)
+        );
+        client
+            .sadd("set".into(), vec![Bytes::from("a")])
+            .await
+            .unwrap();
+        assert_eq!(
+            client.smembers("set".into()).await.unwrap(),
+            vec![Bytes::from("a")]
+        );
+        client
+            .xadd(
+                "stream".into(),
+                XAddId::Auto,
+                vec![(Bytes::from("f"), Bytes::from("v"))],
+            )
+            .await
+            .unwrap();
+        assert_eq!(
+            client.xinfo_stream("stream".into()).await.unwrap().length,
+            1
+        );
+        assert_eq!(client.get("missing".into()).await.unwrap(), Some(null!()));
+
+        let mut subscriber = client.subscribe(vec!["news".into()]).await.unwrap();
+        publisher
+            .publish("news".into(), Bytes::from("hello"))
+            .await
+            .unwrap();
+        let message = subscriber.next_message().await.unwrap().unwrap();
+        assert_eq!(message.payload, Bytes::from("hello"));
+    }
+
+    #[tokio::test]
+    async fn dump_restore() {
+        common::get_or_init_logger();
+
+        let test_server = common::TestServer::new().await;
+        let mut client = test_server.create_client().await.unwrap();
+
+        client.set_key_value("string", "bar").await;
+        let payload = client.dump("string".into()).await.unwrap().unwrap();
+        // RDB type and value, RDB version 9 and the CRC64 checksum
+        assert!(payload.starts_with(b"\x00\x03bar\x09\x00"));
+        assert_eq!(payload.len(), 5 + 2 + 8);
+        assert_eq!(client.dump("missing".into()).await.unwrap(), None);
+
+        client
+            .restore(
+                "copy".into(),
+                Some(std::time::Duration::from_secs(100)),
+                payload.clone(),
+                false,
+            )
+            .await
+            .unwrap();
+        assert_eq!(client.get("copy".into()).await.unwrap(), Some(bulk!("bar")));
+        assert_matches!(client.ttl("copy".into()).await.unwrap(), Some(Frame::Integer(ttl)) if ttl > 90);
+
+        // Existing keys are only replaced with REPLACE
+        let err = client
+            .restore("string".into(), None, payload.clone(), false)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().starts_with("BUSYKEY"), "{}", err);
+        client
+            .rpush("list".into(), vec![Bytes::from("a"), Bytes::from("b")])
+            .await
+            .unwrap();
+        let list = client.dump("list".into()).await.unwrap().unwrap();
+        client
+            .restore("string".into(), None, list, true)
+            .await
+            .unwrap();
+        assert_eq!(
+            client.lrange("string".into(), 0, -1).await.unwrap(),
+            Some(array!(bulk!("a"), bulk!("b")))
+        );
+
+        client
+            .hset("hash".into(), vec![(Bytes::from("f"), Bytes::from("v"))])
+            .await
+            .unwrap();
+        let hash = client.dump("hash".into()).await.unwrap().unwrap();
+        client
+            .restore("hash2".into(), None, hash, false)
+            .await
+            .unwrap();
+        assert_eq!(
+            client.hgetall("hash2".into()).await.unwrap(),
+            vec![(Bytes::from("f"), Bytes::from("v"))]
+        );
+
+        // Corrupted payloads are rejected
+        let mut corrupted = payload.to_vec();
+        corrupted[2] = b'c';
+        let err = client
+            .restore("corrupted".into(), None, corrupted.into(), false)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("checksum"), "{}", err);
+
+        // LZF string claiming 2^40 decompressed bytes, with a disabled checksum
+        let mut corrupted = b"\x00\xc3\x02\x81".to_vec();
+        corrupted.extend_from_slice(&(1u64 << 40).to_be_bytes());
+        corrupted.extend_from_slice(b"\x00a\x09\x00");
+        corrupted.extend_from_slice(&[0; 8]);
+        let err = client
+            .restore("corrupted".into(), None, corrupted.into(), false)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("Bad data format"), "{}", err);
+    }
 }
@@ -0,0 +1,537 @@
+use bytes::Bytes;
+use redis_clone::{
+    bulk, crc16, integer, key_slot, ClusterClient, ClusterNode, Config, Frame, SlotRange, SlotState,
+};
+use tempfile::TempDir;
+
+mod common;
+
+/// Start a cluster of three nodes sharing a nodes file, each serving a third of the slots.
+async fn start_cluster() -> (TempDir, Vec<common::TestServer>, Vec<String>) {
+    let dir = common::temp_dir();
+    let config = Config {
+        cluster_enabled: true,
+        ..common::test_config(&dir)
+    };
+    let ports: Vec<u16> = (0..3).map(|_| common::next_port()).collect();
+    let ids: Vec<String> = (0..3).map(|i| format!("{:040x}", i + 1)).collect();
+    let ranges = ["0-5460", "5461-10922", "10923-16383"];
+    let mut nodes = String::new();
+    for i in 0..3 {
+        nodes.push_str(&format!(
+            "{} {}:{}@{} master - 0 0 0 connected {}\n",
+            ids[i],
+            common::SERVER_ADDR,
+            ports[i],
+            ports[i] as u32 + 10000,
+            ranges[i]
+        ));
+    }
+    nodes.push_str("vars currentEpoch 0 lastVoteEpoch 0\n");
+    std::fs::write(config.cluster_config_path(), nodes).unwrap();
+
+    let mut servers = Vec::new();
+    for port in ports {
+        servers.push(common::TestServer::with_port(port, config.clone()).await);
+    }
+    (dir, servers, ids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn slots() {
+        assert_eq!(crc16(b"123456789"), 0x31c3);
+        assert_eq!(key_slot(b"foo"), 12182);
+        assert_eq!(key_slot(b"bar"), 5061);
+        assert_eq!(
+            key_slot(b"{user1000}.following"),
+            key_slot(b"{user1000}.followers")
+        );
+        assert_eq!(key_slot(b"{user1000}.following"), key_slot(b"user1000"));
+        // Only the first pair of braces counts, empty tags hash the whole key
+        assert_eq!(key_slot(b"foo{}{bar}"), crc16(b"foo{}{bar}") % 16384);
+        assert_eq!(key_slot(b"foo{{bar}}zap"), key_slot(b"{bar"));
+        assert_eq!(key_slot(b"foo{bar}{zap}"), key_slot(b"bar"));
+    }
+
+    #[test]
+    fn config_from_args() {
+        let args = [
+            "--cluster-enabled",
+            "yes",
+            "--cluster-config-file",
+            "nodes-6380.conf",
+        ];
+        let config = Config::from_args(args.map(str::to_string)).unwrap();
+        assert!(config.cluster_enabled);
+        assert!(config.cluster_config_path().ends_with("nodes-6380.conf"));
+        assert!(Config::from_args(["--cluster-enabled", "maybe"].map(str::to_string)).is_err());
+    }
+
+    #[tokio::test]
+    async fn cluster_commands() {
+        common::get_or_init_logger();
+        let (_dir, servers, ids) = start_cluster().await;
+        let mut first = servers[0].create_client().await.unwrap();
+        let mut third = servers[2].create_client().await.unwrap();
+
+        assert_eq!(first.cluster_myid().await.unwrap(), ids[0]);
+        assert_eq!(third.cluster_myid().await.unwrap(), ids[2]);
+        assert_eq!(first.cluster_keyslot("foo".into()).await.unwrap(), 12182);
+
+        let slots = first.cluster_slots().await.unwrap();
+        assert_eq!(slots.len(), 3);
+        assert_eq!(
+            slots[1],
+            SlotRange {
+                start: 5461,
+                end: 10922,
+                host: common::SERVER_ADDR.to_string(),
+                port: servers[1].port(),
+                id: ids[1].clone(),
+            }
+        );
+        let shards = third.cluster_shards().await.unwrap();
+        assert_eq!(shards.len(), 3);
+        assert_eq!(
+            shards[2],
+            ClusterNode {
+                id: ids[2].clone(),
+                host: common::SERVER_ADDR.to_string(),
+                port: servers[2].port(),
+                slots: vec![(10923, 16383)],
+            }
+        );
+        let nodes = first.cluster_nodes().await.unwrap();
+        assert_eq!(nodes.lines().count(), 3);
+        assert!(nodes
+            .lines()
+            .any(|line| line.starts_with(&ids[0]) && line.contains("myself,master")));
+        assert!(nodes.contains(&format!("{}:{}", common::SERVER_ADDR, servers[1].port())));
+
+        // Keys of other nodes are redirected
+        let response = first
+            .set("foo".into(), Bytes::from("bar"), None)
+            .await
+            .unwrap();
+        assert_eq!(
+            response,
+            Some(Frame::Error(format!(
+                "MOVED 12182 {}:{}",
+                common::SERVER_ADDR,
+                servers[2].port()
+            )))
+        );
+        third
+            .set("foo".into(), Bytes::from("bar"), None)
+            .await
+            .unwrap();
+        third
+            .set("{foo}.other".into(), Bytes::from("baz"), None)
+            .await
+            .unwrap();
+        assert_eq!(third.cluster_countkeysinslot(12182).await.unwrap(), 2);
+        let mut keys = third.cluster_getkeysinslot(12182, 10).await.unwrap();
+        keys.sort();
+        assert_eq!(keys, vec!["foo".to_string(), "{foo}.other".to_string()]);
+        assert_eq!(
+            third.cluster_getkeysinslot(12182, 1).await.unwrap().len(),
+            1
+        );
+        assert_eq!(
+            third
+                .exists(vec!["foo".into(), "{foo}.other".into()])
+                .await
+                .unwrap(),
+            Some(integer!(2))
+        );
+
+        // Keys of one command must be in one slot
+        assert_eq!(
+            third
+                .exists(vec!["foo".into(), "bar".into()])
+                .await
+                .unwrap(),
+            Some(Frame::Error(
+                "CROSSSLOT Keys in request don't hash to the same slot".to_string()
+            ))
+        );
+        // A redirection while queuing discards the transaction
+        let result = first
+            .transaction()
+            .set("foo".into(), Bytes::from("baz"), None)
+            .exec()
+            .await;
+        assert!(result.is_err());
+
+        for server in servers {
+            server.shutdown();
+        }
+    }
+
+    #[tokio::test]
+    async fn migration() {
+        common::get_or_init_logger();
+        let (_dir, servers, ids) = start_cluster().await;
+        let mut clients = Vec::new();
+        for server in &servers {
+            clients.push(server.create_client().await.unwrap());
+        }
+        let redirect = |kind: &str, i: usize| {
+            Some(Frame::Error(format!(
+                "{} 12182 {}:{}",
+                kind,
+                common::SERVER_ADDR,
+                servers[i].port()
+            )))
+        };
+        // Slot 12182 of `foo` is migrated from the third node to the first one
+        let slot = key_slot(b"foo");
+        for key in ["foo", "{foo}2"] {
+            clients[2]
+                .set(key.into(), Bytes::from("bar"), None)
+                .await
+                .unwrap();
+        }
+        clients[0]
+            .cluster_setslot(slot, SlotState::Importing(ids[2].clone()))
+            .await
+            .unwrap();
+        clients[2]
+            .cluster_setslot(slot, SlotState::Migrating(ids[0].clone()))
+            .await
+            .unwrap();
+        let err = clients[2]
+            .cluster_setslot(slot, SlotState::Importing(ids[1].clone()))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("I'm already the owner"), "{}", err);
+        assert!(clients[2]
+            .cluster_nodes()
+            .await
+            .unwrap()
+            .contains(&format!("[{}->-{}]", slot, ids[0])));
+
+        // Keys that were not migrated yet are served by the source, others are asked for
+        assert_eq!(
+            clients[2].get("foo".into()).await.unwrap(),
+            Some(bulk!("bar"))
+        );
+        assert_eq!(
+            clients[2].get("{foo}3".into()).await.unwrap(),
+            redirect("ASK", 0)
+        );
+        assert_eq!(
+            clients[0].get("foo".into()).await.unwrap(),
+            redirect("MOVED", 2)
+        );
+
+        let timeout = std::time::Duration::from_secs(1);
+        let port = servers[0].port();
+        assert!(clients[2]
+            .migrate(
+                common::SERVER_ADDR,
+                port,
+                vec!["foo".into()],
+                timeout,
+                false,
+                false
+            )
+            .await
+            .unwrap());
+        assert_eq!(
+            clients[2].get("foo".into()).await.unwrap(),
+            redirect("ASK", 0)
+        );
+        // Only some keys of the command were migrated
+        assert_eq!(
+            clients[2]
+                .exists(vec!["foo".into(), "{foo}2".into()])
+                .await
+                .unwrap(),
+            Some(Frame::Error(
+                "TRYAGAIN Multiple keys request during rehashing of slot".to_string()
+            ))
+        );
+        clients[0].asking().await.unwrap();
+        assert_eq!(
+            clients[0].get("foo".into()).await.unwrap(),
+            Some(bulk!("bar"))
+        );
+        // `ASKING` only applies to the next command
+        assert_eq!(
+            clients[0].get("foo".into()).await.unwrap(),
+            redirect("MOVED", 2)
+        );
+
+        assert!(clients[2]
+            .migrate(
+                common::SERVER_ADDR,
+                port,
+                vec!["{foo}2".into()],
+                timeout,
+                false,
+                false
+            )
+            .await
+            .unwrap());
+        assert!(!clients[2]
+            .migrate(
+                common::SERVER_ADDR,
+                port,
+                vec!["foo".into()],
+                timeout,
+                false,
+                false
+            )
+            .await
+            .unwrap());
+        assert_eq!(clients[2].cluster_countkeysinslot(slot).await.unwrap(), 0);
+
+        // The slot is assigned to the first node on all nodes
+        for client in &mut clients {
+            client
+                .cluster_setslot(slot, SlotState::Node(ids[0].clone()))
+                .await
+                .unwrap();
+        }
+        assert_eq!(
+            clients[0].get("{foo}2".into()).await.unwrap(),
+            Some(bulk!("bar"))
+        );
+        assert_eq!(
+            clients[2].get("foo".into()).await.unwrap(),
+            redirect("MOVED", 0)
+        );
+        let slots = clients[2].cluster_slots().await.unwrap();
+        let ranges: Vec<(u16, u16, u16)> = slots
+            .iter()
+            .map(|range| (range.start, range.end, range.port))
+            .collect();
+        assert_eq!(
+            &ranges[2..],
+            &[
+                (10923, slot - 1, servers[2].port()),
+                (slot, slot, servers[0].port()),
+                (slot + 1, 16383, servers[2].port())
+            ]
+        );
+
+        // A slot is only assigned to another node without keys
+        let err = clients[0]
+            .cluster_setslot(slot, SlotState::Node(ids[1].clone()))
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("still hold keys"), "{}", err);
+
+        for server in &servers {
+            server.shutdown();
+        }
+    }
+
+    #[tokio::test]
+    async fn migration_to_unresponsive_target() {
+        common::get_or_init_logger();
+        let server = common::TestServer::new().await;
+        let mut client = server.create_client().await.unwrap();
+        let mut other_client = server.create_client().await.unwrap();
+        client
+            .set("foo".into(), Bytes::from("bar"), None)
+            .await
+            .unwrap();
+
+        // The target accepts the connection, but never replies
+        let target = tokio::net::TcpListener::bind((common::SERVER_ADDR, 0))
+            .await
+            .unwrap();
+        let port = target.local_addr().unwrap().port();
+        let accepted = tokio::spawn(async move { target.accept().await });
+        let timeout = std::time::Duration::from_millis(500);
+        let migration = tokio::spawn(async move {
+            client
+                .migrate(
+                    common::SERVER_ADDR,
+                    port,
+                    vec!["foo".into()],
+                    timeout,
+                    false,
+                    false,
+                )
+                .await
+        });
+
+        // Other clients are served while the source waits for the target
+        let _connection = accepted.await.unwrap().unwrap();
+        let response = tokio::time::timeout(timeout / 2, other_client.get("foo".into()))
+            .await
+            .expect("the database is locked during the migration")
+            .unwrap();
+        assert_eq!(response, Some(bulk!("bar")));
+
+        let err = migration.await.unwrap().unwrap_err();
+        assert!(err.to_string().contains("IOERR"), "{}", err);
+        assert_eq!(
+            other_client.get("foo".into()).await.unwrap(),
+            Some(bulk!("bar"))
+        );
+        server.shutdown();
+    }
+
+    #[tokio::test]
+    async fn cluster_client() {
+        common::get_or_init_logger();
+        let (_dir, servers, ids) = start_cluster().await;
+        let mut client = ClusterClient::new(common::SERVER_ADDR, servers[0].port())
+            .await
+            .unwrap();
+        let slot = key_slot(b"foo");
+        assert_eq!(
+            client.node_for_slot(slot),
+            Some((common::SERVER_ADDR, servers[2].port()))
+        );
+
+        // Commands are sent to the nodes serving their keys
+        for key in ["foo", "bar", "{foo}2"] {
+            client
+                .set(key.into(), Bytes::from(key), None)
+                .await
+                .unwrap();
+        }
+        let mut third = servers[2].create_client().await.unwrap();
+        assert_eq!(third.get("foo".into()).await.unwrap(), Some(bulk!("foo")));
+        assert_eq!(
+            client
+                .exists(vec!["foo".into(), "{foo}2".into()])
+                .await
+                .unwrap(),
+            Some(integer!(2))
+        );
+        let err = client
+            .exists(vec!["foo".into(), "bar".into()])
+            .await
+            .unwrap_err();
+        assert!(err.to_string().starts_with("CROSSSLOT"), "{}", err);
+
+        // Migrated keys are followed by ASK
+        let mut first = servers[0].create_client().await.unwrap();
+        first
+            .cluster_setslot(slot, SlotState::Importing(ids[2].clone()))
+            .await
+            .unwrap();
+        third
+            .cluster_setslot(slot, SlotState::Migrating(ids[0].clone()))
+            .await
+            .unwrap();
+        assert!(third
+            .migrate(
+                common::SERVER_ADDR,
+                servers[0].port(),
+                vec!["foo".into()],
+                std::time::Duration::from_secs(1),
+                false,
+                false
+            )
+            .await
+            .unwrap());
+        assert_eq!(client.get("foo".into()).await.unwrap(), Some(bulk!("foo")));
+        assert_eq!(
+            client.node_for_slot(slot),
+            Some((common::SERVER_ADDR, servers[2].port()))
+        );
+
+        // The slots are reloaded after MOVED
+        third
+            .migrate(
+                common::SERVER_ADDR,
+                servers[0].port(),
+                vec!["{foo}2".into()],
+                std::time::Duration::from_secs(1),
+                false,
+                false,
+            )
+            .await
+            .unwrap();
+        for server in &servers {
+            let mut node = server.create_client().await.unwrap();
+            node.cluster_setslot(slot, SlotState::Node(ids[0].clone()))
+                .await
+                .unwrap();
+        }
+        assert_eq!(
+            client.get("{foo}2".into()).await.unwrap(),
+            Some(bulk!("{foo}2"))
+        );
+        assert_eq!(
+            client.node_for_slot(slot),
+            Some((common::SERVER_ADDR, servers[0].port()))
+        );
+
+        for server in &servers {
+            server.shutdown();
+        }
+    }
+
+    #[tokio::test]
+    async fn nodes_file() {
+        common::get_or_init_logger();
+        // A missing nodes file is created with one node serving all slots
+        let dir = common::temp_dir();
+        let config = Config {
+            cluster_enabled: true,
+            ..common::test_config(&dir)
+        };
+        let server = common::TestServer::with_config(config.clone()).await;
+        let mut client = server.create_client().await.unwrap();
+        let id = client.cluster_myid().await.unwrap();
+        assert_eq!(id.len(), 40);
+        let nodes = std::fs::read_to_string(config.cluster_config_path()).unwrap();
+        assert!(nodes.starts_with(&format!(
+            "{} {}:{}@{} myself,master",
+            id,
+            common::SERVER_ADDR,
+            server.port(),
+            server.port() as u32 + 10000
+        )));
+        assert!(nodes.trim_end().ends_with("0-16383"));
+        client
+            .set("foo".into(), Bytes::from("bar"), None)
+            .await
+            .unwrap();
+        server.shutdown();
+
+        // Slots served by two nodes are rejected
+        let dir = common::temp_dir();
+        let config = Config {
+            cluster_enabled: true,
+            ..common::test_config(&dir)
+        };
+        let port = common::next_port();
+        std::fs::write(
+            config.cluster_config_path(),
+            format!(
+                "a 127.0.0.1:{}@0 master - 0 0 0 connected 0-100\nb 127.0.0.1:1@0 master - 0 0 0 connected 100\n",
+                port
+            ),
+        )
+        .unwrap();
+        assert!(
+            redis_clone::RedisServer::with_config(common::SERVER_ADDR, port, config)
+                .await
+                .is_err()
+        );
+
+        // CLUSTER needs the cluster mode
+        let server = common::TestServer::new().await;
+        let mut client = server.create_client().await.unwrap();
+        let err = client.cluster_myid().await.unwrap_err();
+        assert!(
+            err.to_string().contains("cluster support disabled"),
+            "{}",
+            err
+        );
+        server.shutdown();
+    }
+}
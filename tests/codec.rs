@@ -0,0 +1,258 @@
+use bytes::{Bytes, BytesMut};
+use futures_util::SinkExt;
+use tokio_stream::StreamExt;
+use tokio_util::codec::{Decoder, Encoder, Framed};
+
+use redis_clone::err::RedisProtocolError;
+use redis_clone::{array, bulk, integer, null, simple};
+use redis_clone::{Frame, Protocol, ProtocolLimits, RedisClient, RespCodec};
+
+mod common;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decode_incrementally() {
+        common::get_or_init_logger();
+
+        let data = b"*3\r\n$3\r\nSET\r\n$3\r\nkey\r\n$5\r\nva\r\nl\r\n+OK\r\n\r\nPING\r\n_\r\n";
+        let expected = [
+            array!(bulk!("SET"), bulk!("key"), bulk!("va\r\nl")),
+            simple!("OK"),
+            // The empty inline command is skipped
+            array!(bulk!("PING")),
+            null!(),
+        ];
+        let mut codec = RespCodec::default();
+        let mut buffer = BytesMut::new();
+        let mut frames = vec![];
+        // Frames are decoded once their last byte is buffered
+        for &byte in data {
+            buffer.extend_from_slice(&[byte]);
+            while let Some(frame) = codec.decode(&mut buffer).unwrap() {
+                frames.push(frame);
+            }
+        }
+        assert_eq!(frames, expected);
+        assert!(buffer.is_empty());
+
+        // Frames that are already buffered are decoded one by one
+        let mut buffer = BytesMut::from(&data[..]);
+        for frame in expected {
+            assert_eq!(codec.decode(&mut buffer).unwrap(), Some(frame));
+        }
+        assert_eq!(codec.decode(&mut buffer).unwrap(), None);
+    }
+
+    #[test]
+    fn decode_resp3_aggregates() {
+        let data = b"|1\r\n+ttl\r\n:3\r\n%2\r\n$1\r\na\r\n~0\r\n=7\r\ntxt:abc\r\n>1\r\n*0\r\n";
+        let expected = Frame::Attribute(
+            vec![(simple!("ttl"), integer!(3))],
+            Box::new(Frame::Map(vec![
+                (bulk!("a"), Frame::Set(vec![])),
+                (
+                    Frame::Verbatim("txt".to_string(), Bytes::from("abc")),
+                    Frame::Push(vec![array!()]),
+                ),
+            ])),
+        );
+        let mut codec = RespCodec::default();
+        let mut buffer = BytesMut::new();
+        for (i, chunk) in data.chunks(5).enumerate() {
+            buffer.extend_from_slice(chunk);
+            let frame = codec.decode(&mut buffer).unwrap();
+            if (i + 1) * 5 < data.len() {
+                assert_eq!(frame, None);
+            } else {
+                assert_eq!(frame, Some(expected.clone()));
+            }
+        }
+    }
+
+    #[test]
+    fn decode_without_copying() {
+        let mut buffer = BytesMut::from(&b"*2\r\n$5\r\nhello\r\n$"[..]);
+        let start = buffer.as_ptr() as usize;
+        let mut codec = RespCodec::default();
+        assert_eq!(codec.decode(&mut buffer).unwrap(), None);
+        // The decoded elements are discarded from the buffer
+        assert_eq!(buffer, &b"$"[..]);
+
+        buffer.extend_from_slice(b"5\r\nworld\r\n");
+        let Some(Frame::Array(frames)) = codec.decode(&mut buffer).unwrap() else {
+            panic!("expected an array");
+        };
+        let Frame::Bulk(hello) = &frames[0] else {
+            panic!("expected a bulk string");
+        };
+        assert_eq!(hello, "hello");
+        // The bulk string points to the data of the buffer
+        assert_eq!(hello.as_ptr() as usize, start + b"*2\r\n$5\r\n".len());
+        assert_eq!(frames[1], bulk!("world"));
+        assert!(buffer.is_empty());
+    }
+
+    #[test]
+    fn decode_errors() {
+        let mut codec = RespCodec::default();
+        for (data, expected) in [
+            (&b"+OK\n0\r\n"[..], RedisProtocolError::ExcessiveNewline),
+            (b"*1\r\n$-2\r\n", RedisProtocolError::NegativeBulkLength(-2)),
+            (b"*1\r\n^1\r\n", RedisProtocolError::UnsupportedFrame(b'^')),
+            (b"$3\r\nfooXY", RedisProtocolError::UnterminatedBlob(3)),
+            (
+                b"*1\r\n!3\r\nfoo\rX",
+                RedisProtocolError::UnterminatedBlob(3),
+            ),
+        ] {
+            // Decoding is not resumed after an error
+            let mut codec = RespCodec::default();
+            let err = codec.decode(&mut BytesMut::from(data)).unwrap_err();
+            let err = err.downcast::<RedisProtocolError>().unwrap();
+            assert_eq!(err.to_string(), expected.to_string());
+        }
+
+        // A frame cut by the end of the stream
+        let mut buffer = BytesMut::from(&b"$5\r\nhel"[..]);
+        assert_eq!(codec.decode(&mut buffer).unwrap(), None);
+        assert!(codec.decode_eof(&mut buffer).is_err());
+    }
+
+    #[test]
+    fn decode_limits() {
+        let limits = ProtocolLimits {
+            max_bulk_len: 5,
+            max_aggregate_len: 2,
+            max_depth: 2,
+            max_inline_len: 32,
+            max_query_buffer: 16,
+        };
+        for (data, expected) in [
+            (&b"$6\r\n"[..], RedisProtocolError::BulkTooLong(6)),
+            (b"*1\r\n!6\r\n", RedisProtocolError::BulkTooLong(6)),
+            (b"*3\r\n", RedisProtocolError::ArrayTooLong(3)),
+            (b"%1\r\n~3\r\n", RedisProtocolError::ArrayTooLong(3)),
+            (
+                b"*1\r\n*1\r\n*1\r\n",
+                RedisProtocolError::TooDeeplyNested(2),
+            ),
+            (
+                b"GET aaaaaaaaaaaaa",
+                RedisProtocolError::QueryBufferExceeded(17),
+            ),
+        ] {
+            let mut codec = RespCodec::default().with_limits(limits);
+            let err = codec.decode(&mut BytesMut::from(data)).unwrap_err();
+            let err = err.downcast::<RedisProtocolError>().unwrap();
+            assert_eq!(err.to_string(), expected.to_string());
+        }
+
+        // A frame sent in parts counts the decoded elements against the query buffer
+        let mut codec = RespCodec::default().with_limits(ProtocolLimits {
+            max_aggregate_len: 1000,
+            max_query_buffer: 1024,
+            ..limits
+        });
+        let mut buffer = BytesMut::from(&b"*1000\r\n"[..]);
+        let err = (0..1000)
+            .find_map(|_| {
+                buffer.extend_from_slice(b":1\r\n");
+                codec.decode(&mut buffer).err()
+            })
+            .expect("the query buffer limit was not reached");
+        let err = err.downcast::<RedisProtocolError>().unwrap();
+        assert!(matches!(err, RedisProtocolError::QueryBufferExceeded(_)));
+
+        // Inline commands are limited with or without the end of the line
+        let limits = ProtocolLimits {
+            max_inline_len: 8,
+            ..limits
+        };
+        for data in [&b"GET aaaaaaa\r\n"[..], b"GET aaaaa"] {
+            let mut codec = RespCodec::default().with_limits(limits);
+            let err = codec.decode(&mut BytesMut::from(data)).unwrap_err();
+            let err = err.downcast::<RedisProtocolError>().unwrap();
+            assert!(matches!(err, RedisProtocolError::InlineTooLong));
+        }
+        // The search for the end of a line received in parts is resumed
+        let mut codec = RespCodec::default().with_limits(limits);
+        let mut buffer = BytesMut::from(&b"GET "[..]);
+        assert_eq!(codec.decode(&mut buffer).unwrap(), None);
+        buffer.extend_from_slice(b"key\r\n");
+        assert_eq!(
+            codec.decode(&mut buffer).unwrap(),
+            Some(array!(bulk!("GET"), bulk!("key")))
+        );
+
+        // Frames within the limits
+        let mut codec = RespCodec::default().with_limits(limits);
+        let mut buffer = BytesMut::from(&b"*2\r\n*2\r\n$5\r\nhello\r\n_\r\n:1\r\n"[..]);
+        assert_eq!(
+            codec.decode(&mut buffer).unwrap(),
+            Some(array!(array!(bulk!("hello"), null!()), integer!(1)))
+        );
+    }
+
+    #[test]
+    fn encode_protocol() {
+        let frame = Frame::Map(vec![
+            (bulk!("proto"), integer!(3)),
+            (bulk!("score"), Frame::Double(1.5)),
+        ]);
+        let mut codec = RespCodec::default();
+        let mut buffer = BytesMut::new();
+        codec.encode(&frame, &mut buffer).unwrap();
+        assert_eq!(
+            buffer,
+            &b"*4\r\n$5\r\nproto\r\n:3\r\n$5\r\nscore\r\n$3\r\n1.5\r\n"[..]
+        );
+
+        codec.set_protocol(Protocol::Resp3);
+        let mut buffer = BytesMut::new();
+        codec.encode(&frame, &mut buffer).unwrap();
+        assert_eq!(
+            buffer,
+            &b"%2\r\n$5\r\nproto\r\n:3\r\n$5\r\nscore\r\n,1.5\r\n"[..]
+        );
+        assert_eq!(codec.decode(&mut buffer).unwrap(), Some(frame));
+    }
+
+    #[tokio::test]
+    async fn client_over_duplex() {
+        common::get_or_init_logger();
+
+        let (client_stream, server_stream) = tokio::io::duplex(64);
+        let server = tokio::spawn(async move {
+            let mut framed = Framed::new(server_stream, RespCodec::default());
+            while let Some(frame) = framed.next().await {
+                let reply = match frame.unwrap() {
+                    Frame::Array(parts) if parts == [bulk!("PING")] => simple!("PONG"),
+                    Frame::Array(parts) => Frame::Bulk(Bytes::from(format!("{:?}", parts))),
+                    frame => panic!("unexpected frame {:?}", frame),
+                };
+                framed.send(&reply).await.unwrap();
+            }
+        });
+
+        let mut client = RedisClient::from_stream(client_stream);
+        assert_eq!(client.ping(None).await.unwrap(), Some(simple!("PONG")));
+        // Commands larger than the duplex buffer are written in parts
+        let value = "x".repeat(1000);
+        let mut responses = client
+            .pipeline()
+            .ping(None)
+            .set("key".to_string(), Bytes::from(value.clone()), None)
+            .exec()
+            .await
+            .unwrap();
+        responses.next_as::<()>().unwrap();
+        let echo: Bytes = responses.next_as().unwrap();
+        assert!(echo.ends_with(format!("{}\")]", value).as_bytes()));
+
+        drop(client);
+        server.await.unwrap();
+    }
+}
@@ -1,10 +1,11 @@
 #![allow(unused)]
 
-use redis_clone::{RedisClient, RedisServer};
+use redis_clone::{Config, RedisClient, RedisServer};
 use std::sync::{
     atomic::{AtomicU16, Ordering},
     Arc,
 };
+use tempfile::TempDir;
 use tokio::sync::Barrier;
 
 pub const SERVER_ADDR: &str = "127.0.0.1";
@@ -18,13 +19,27 @@ pub struct TestServer {
     port: u16,
     handle: Arc<tokio::task::JoinHandle<()>>,
     shutdown: Arc<tokio::sync::broadcast::Sender<()>>,
+    /// Directory of the persistence files of `TestServer::new`
+    dir: Option<Arc<TempDir>>,
 }
 
 impl TestServer {
     pub async fn new() -> Self {
-        let server_port = SERVER_PORT_COUNTER.fetch_add(1, Ordering::SeqCst);
+        let dir = temp_dir();
+        let server = Self::with_config(test_config(&dir)).await;
+        TestServer {
+            dir: Some(Arc::new(dir)),
+            ..server
+        }
+    }
 
-        let mut server = RedisServer::new(SERVER_ADDR, server_port)
+    pub async fn with_config(config: Config) -> Self {
+        Self::with_port(next_port(), config).await
+    }
+
+    /// Start a server on a port reserved by `next_port`, e.g., one listed in a nodes file.
+    pub async fn with_port(server_port: u16, config: Config) -> Self {
+        let mut server = RedisServer::with_config(SERVER_ADDR, server_port, config)
             .await
             .expect("Failed to create Redis server");
 
@@ -38,6 +53,7 @@ impl TestServer {
             port: server_port,
             handle: Arc::new(handle),
             shutdown: Arc::new(shutdown),
+            dir: None,
         }
     }
 
@@ -54,6 +70,32 @@ impl TestServer {
     pub fn addr(&self) -> String {
         format!("{}:{}", SERVER_ADDR, self.port)
     }
+
+    /// Send the shutdown signal to the server
+    pub fn shutdown(&self) {
+        let _ = self.shutdown.send(());
+    }
+}
+
+/// Reserve a unique port for a test server.
+pub fn next_port() -> u16 {
+    SERVER_PORT_COUNTER.fetch_add(1, Ordering::SeqCst)
+}
+
+/// Create a temporary directory for persistence files, removed when dropped.
+pub fn temp_dir() -> TempDir {
+    tempfile::Builder::new()
+        .prefix("redis-clone-test-")
+        .tempdir()
+        .expect("Failed to create a temporary directory")
+}
+
+/// Configuration with persistence files stored in `dir`.
+pub fn test_config(dir: &TempDir) -> Config {
+    Config {
+        dir: dir.path().to_path_buf(),
+        ..Config::default()
+    }
 }
 
 /// Initializes logger for a test (call at the start of test functions)
@@ -5,7 +5,7 @@ mod tests {
     use super::common::get_or_init_logger;
 
     use assert_matches::assert_matches;
-    use bytes::Bytes;
+    use bytes::{Bytes, BytesMut};
     use std::io::Cursor;
 
     use redis_clone::err::RedisProtocolError;
@@ -16,9 +16,6 @@ mod tests {
         let data = b"+OK\r\n";
         let mut cursor = Cursor::new(&data[..]);
 
-        assert!(Frame::is_parsable(&mut cursor).is_ok());
-
-        cursor.set_position(0);
         match Frame::parse(&mut cursor).unwrap() {
             Frame::Simple(s) => assert_eq!(s, "OK"),
             _ => panic!("Expected Simple frame"),
@@ -31,9 +28,6 @@ mod tests {
         let data = "+汉语 ϩⲉⲛⲥϩⲁⲓ̈ ⲛ̄ⲥⲁϩ 🚀\r\n".as_bytes();
         let mut cursor = Cursor::new(data);
 
-        assert!(Frame::is_parsable(&mut cursor).is_ok());
-
-        cursor.set_position(0);
         match Frame::parse(&mut cursor).unwrap() {
             Frame::Simple(s) => assert_eq!(s, "汉语 ϩⲉⲛⲥϩⲁⲓ̈ ⲛ̄ⲥⲁϩ 🚀"),
             _ => panic!("Expected Simple frame"),
@@ -48,9 +42,6 @@ mod tests {
             let data = data.as_bytes();
             let mut cursor = Cursor::new(data);
 
-            assert!(Frame::is_parsable(&mut cursor).is_ok());
-
-            cursor.set_position(0);
             match Frame::parse(&mut cursor).unwrap() {
                 Frame::Integer(parsed_n) => {
                     assert_eq!(parsed_n, n, "Failed to parse integer {}", n)
@@ -65,9 +56,6 @@ mod tests {
         let data = b"$5\r\nhello\r\n";
         let mut cursor = Cursor::new(&data[..]);
 
-        assert!(Frame::is_parsable(&mut cursor).is_ok());
-
-        cursor.set_position(0);
         match Frame::parse(&mut cursor).unwrap() {
             Frame::Bulk(bytes) => assert_eq!(bytes, Bytes::from("hello")),
             _ => panic!("Expected Bulk frame"),
@@ -75,15 +63,13 @@ mod tests {
     }
 
     #[test]
+    #[allow(clippy::needless_as_bytes)]
     fn test_bulk_string_gibberish() {
         let my_string = "汉语 ϩⲉⲛⲥϩⲁⲓ̈ ⲛ̄ⲥⲁϩ 🚀";
         let data = format!("${}\r\n{}\r\n", my_string.bytes().len(), my_string);
         let data = data.as_bytes();
         let mut cursor = Cursor::new(data);
 
-        assert!(Frame::is_parsable(&mut cursor).is_ok());
-
-        cursor.set_position(0);
         match Frame::parse(&mut cursor).unwrap() {
             Frame::Bulk(bytes) => assert_eq!(bytes, Bytes::from("汉语 ϩⲉⲛⲥϩⲁⲓ̈ ⲛ̄ⲥⲁϩ 🚀")),
             _ => panic!("Expected Bulk frame"),
@@ -96,7 +82,7 @@ mod tests {
         let mut cursor = Cursor::new(&data[..]);
 
         assert_matches!(
-            Frame::is_parsable(&mut cursor),
+            Frame::parse(&mut cursor),
             Err(RedisProtocolError::NegativeBulkLength(x)) if x == -25
         );
     }
@@ -105,7 +91,7 @@ mod tests {
     fn test_bulk_empty() {
         let data = b"$0\r\n";
         let mut cursor = Cursor::new(&data[..]);
-        let result = Frame::is_parsable(&mut cursor);
+        let result = Frame::parse(&mut cursor);
         assert!(result.is_err());
         assert_matches!(result.unwrap_err(), RedisProtocolError::NotEnoughData);
     }
@@ -115,9 +101,6 @@ mod tests {
         let data = b"$-1\r\n";
         let mut cursor = Cursor::new(&data[..]);
 
-        assert!(Frame::is_parsable(&mut cursor).is_ok());
-
-        cursor.set_position(0);
         match Frame::parse(&mut cursor).unwrap() {
             Frame::Null => (),
             _ => panic!("Expected Null frame"),
@@ -129,9 +112,6 @@ mod tests {
         let data = b"_\r\n";
         let mut cursor = Cursor::new(&data[..]);
 
-        assert!(Frame::is_parsable(&mut cursor).is_ok());
-
-        cursor.set_position(0);
         match Frame::parse(&mut cursor).unwrap() {
             Frame::Null => (),
             _ => panic!("Expected Null frame"),
@@ -143,9 +123,6 @@ mod tests {
         let data = b"*3\r\n:-78741\r\n+hello\r\n_\r\n";
         let mut cursor = Cursor::new(&data[..]);
 
-        assert!(Frame::is_parsable(&mut cursor).is_ok());
-
-        cursor.set_position(0);
         match Frame::parse(&mut cursor).unwrap() {
             Frame::Array(frames) => {
                 assert_eq!(frames.len(), 3);
@@ -171,20 +148,35 @@ mod tests {
         let data = b"*0\r\n";
         let mut cursor = Cursor::new(&data[..]);
 
-        assert!(Frame::is_parsable(&mut cursor).is_ok());
-
-        cursor.set_position(0);
         match Frame::parse(&mut cursor).unwrap() {
             Frame::Array(frames) => assert_eq!(frames.len(), 0),
             _ => panic!("Expected Array frame"),
         }
     }
 
+    #[test]
+    fn nesting_limit() {
+        // Deeply nested arrays are rejected instead of overflowing the stack
+        let data = "*1\r\n".repeat(100_000);
+        let mut cursor = Cursor::new(data.as_bytes());
+        assert_matches!(
+            Frame::parse(&mut cursor),
+            Err(RedisProtocolError::TooDeeplyNested(_))
+        );
+
+        let data = b"*1\r\n$536870913\r\n";
+        let mut cursor = Cursor::new(&data[..]);
+        assert_matches!(
+            Frame::parse(&mut cursor),
+            Err(RedisProtocolError::BulkTooLong(536870913))
+        );
+    }
+
     #[test]
     fn test_array_with_negative_length() {
         let data = b"*-1\r\n";
         let mut cursor = Cursor::new(&data[..]);
-        assert!(Frame::is_parsable(&mut cursor).is_err());
+        assert!(Frame::parse(&mut cursor).is_err());
     }
 
     #[test]
@@ -194,7 +186,7 @@ mod tests {
         let mut cursor = Cursor::new(&data[..]);
         assert!(
             matches!(
-                Frame::is_parsable(&mut cursor),
+                Frame::parse(&mut cursor),
                 Err(RedisProtocolError::NotEnoughData)
             ),
             "Expected NotEnoughData"
@@ -225,7 +217,7 @@ mod tests {
         let mut cursor = Cursor::new(&data[..]);
         assert!(
             matches!(
-                Frame::is_parsable(&mut cursor),
+                Frame::parse(&mut cursor),
                 Err(RedisProtocolError::ExcessiveNewline)
             ),
             "Expected ExcessiveNewline error"
@@ -234,7 +226,7 @@ mod tests {
         // Test excessive newline in bulk string
         let data = b"$3\r\na\nb\r\n";
         let mut cursor = Cursor::new(&data[..]);
-        assert!(Frame::is_parsable(&mut cursor).is_ok(), "Expected Ok");
+        assert!(Frame::parse(&mut cursor).is_ok(), "Expected Ok");
     }
 
     #[test]
@@ -244,7 +236,7 @@ mod tests {
 
         assert!(
             matches!(
-                Frame::is_parsable(&mut cursor),
+                Frame::parse(&mut cursor),
                 Err(RedisProtocolError::ExcessiveNewline)
             ),
             "Expected ExcessiveNewline"
@@ -258,13 +250,23 @@ mod tests {
 
         assert!(
             matches!(
-                Frame::is_parsable(&mut cursor),
+                Frame::parse(&mut cursor),
                 Err(RedisProtocolError::NotEnoughData)
             ),
             "Expected NotEnoughData"
         );
     }
 
+    #[test]
+    fn unterminated_bulk_string() {
+        let data = b"$3\r\nfooXY";
+        let mut cursor = Cursor::new(&data[..]);
+        assert_matches!(
+            Frame::parse(&mut cursor),
+            Err(RedisProtocolError::UnterminatedBlob(3))
+        );
+    }
+
     #[test]
     fn test_null_bulk_string() {
         get_or_init_logger();
@@ -272,9 +274,6 @@ mod tests {
         let data = b"$-1\r\n";
         let mut cursor = Cursor::new(&data[..]);
 
-        assert!(Frame::is_parsable(&mut cursor).is_ok());
-
-        cursor.set_position(0);
         match Frame::parse(&mut cursor).unwrap() {
             Frame::Null => (),
             _ => panic!("Expected Null frame"),
@@ -288,14 +287,6 @@ mod tests {
         let data = b"*2\r\n*2\r\n:1\r\n:2\r\n*1\r\n+hello\r\n";
         let mut cursor = Cursor::new(&data[..]);
 
-        let result = Frame::is_parsable(&mut cursor);
-        assert!(
-            result.is_ok(),
-            "Failed to parse frame: {:?}",
-            result.err().unwrap()
-        );
-
-        cursor.set_position(0);
         match Frame::parse(&mut cursor).unwrap() {
             Frame::Array(frames) => {
                 assert_eq!(frames.len(), 2);
@@ -327,4 +318,181 @@ mod tests {
             _ => panic!("Expected Array frame"),
         }
     }
+
+    #[test]
+    fn array_with_null_and_binary_bulk() {
+        get_or_init_logger();
+
+        let data = b"*3\r\n$2\r\n31\r\n$-1\r\n$4\r\na\r\nb\r\n";
+        let mut cursor = Cursor::new(&data[..]);
+
+        assert_eq!(
+            Frame::parse(&mut cursor).unwrap(),
+            Frame::Array(vec![
+                Frame::Bulk(Bytes::from("31")),
+                Frame::Null,
+                Frame::Bulk(Bytes::from("a\r\nb")),
+            ])
+        );
+        assert_eq!(cursor.position() as usize, data.len());
+    }
+
+    #[test]
+    fn resp3_frames() {
+        get_or_init_logger();
+
+        let cases: [(&[u8], Frame); 10] = [
+            (b",1.5\r\n", Frame::Double(1.5)),
+            (b",-inf\r\n", Frame::Double(f64::NEG_INFINITY)),
+            (b"#t\r\n", Frame::Boolean(true)),
+            (b"#f\r\n", Frame::Boolean(false)),
+            (
+                b"(3492890328409238509324850943850943825024385\r\n",
+                Frame::BigNumber("3492890328409238509324850943850943825024385".to_string()),
+            ),
+            (
+                b"!22\r\nSYNTAX invalid\r\nsyntax\r\n",
+                Frame::BlobError(Bytes::from("SYNTAX invalid\r\nsyntax")),
+            ),
+            (
+                b"=15\r\ntxt:Some string\r\n",
+                Frame::Verbatim("txt".to_string(), Bytes::from("Some string")),
+            ),
+            (
+                b"%2\r\n+first\r\n:1\r\n$6\r\nsecond\r\n_\r\n",
+                Frame::Map(vec![
+                    (Frame::Simple("first".to_string()), Frame::Integer(1)),
+                    (Frame::Bulk(Bytes::from("second")), Frame::Null),
+                ]),
+            ),
+            (
+                b"~2\r\n:1\r\n#t\r\n",
+                Frame::Set(vec![Frame::Integer(1), Frame::Boolean(true)]),
+            ),
+            (
+                b">2\r\n+message\r\n,0.25\r\n",
+                Frame::Push(vec![
+                    Frame::Simple("message".to_string()),
+                    Frame::Double(0.25),
+                ]),
+            ),
+        ];
+        for (data, expected) in cases {
+            let mut cursor = Cursor::new(data);
+            assert_eq!(Frame::parse(&mut cursor).unwrap(), expected);
+            assert_eq!(cursor.position() as usize, data.len());
+        }
+
+        for data in [&b"#x\r\n"[..], b",one\r\n", b"(12a\r\n", b"=3\r\ntxt\r\n"] {
+            let mut cursor = Cursor::new(data);
+            assert_matches!(
+                Frame::parse(&mut cursor).unwrap_err(),
+                RedisProtocolError::ConversionError(_)
+            );
+        }
+    }
+
+    #[test]
+    fn double_formatting() {
+        for (value, expected) in [
+            (3.0, "3"),
+            (1.5, "1.5"),
+            (0.1, "0.10000000000000001"),
+            (0.0001, "0.0001"),
+            (1e-10, "1e-10"),
+            (1e300, "1.0000000000000001e+300"),
+            (123456789012345680.0, "1.2345678901234568e+17"),
+            (f64::INFINITY, "inf"),
+            (f64::NEG_INFINITY, "-inf"),
+            (f64::NAN, "nan"),
+        ] {
+            let mut resp2 = BytesMut::new();
+            Frame::Double(value).encode(&mut resp2);
+            let bulk = format!("${}\r\n{}\r\n", expected.len(), expected);
+            assert_eq!(resp2, bulk.as_bytes());
+
+            let mut resp3 = BytesMut::new();
+            Frame::Double(value).encode_resp3(&mut resp3);
+            assert_eq!(resp3, format!(",{}\r\n", expected).as_bytes());
+        }
+    }
+
+    #[test]
+    fn resp3_attribute() {
+        get_or_init_logger();
+
+        let data = b"|1\r\n+key-popularity\r\n%1\r\n$1\r\na\r\n,0.1923\r\n*1\r\n:2039123\r\n";
+        // The attribute is not parsable without the frame it describes
+        for end in [data.len() - 13, data.len() - 1] {
+            let mut cursor = Cursor::new(&data[..end]);
+            assert_matches!(
+                Frame::parse(&mut cursor),
+                Err(RedisProtocolError::NotEnoughData)
+            );
+        }
+
+        let mut cursor = Cursor::new(&data[..]);
+        assert_eq!(
+            Frame::parse(&mut cursor).unwrap(),
+            Frame::Attribute(
+                vec![(
+                    Frame::Simple("key-popularity".to_string()),
+                    Frame::Map(vec![(Frame::Bulk(Bytes::from("a")), Frame::Double(0.1923))]),
+                )],
+                Box::new(Frame::Array(vec![Frame::Integer(2039123)])),
+            )
+        );
+    }
+
+    #[test]
+    fn inline_commands() {
+        get_or_init_logger();
+
+        let cases: [(&[u8], Vec<&[u8]>); 7] = [
+            (b"PING\r\n", vec![b"PING"]),
+            (b"  set key  value\n", vec![b"set", b"key", b"value"]),
+            (
+                b"SET key \"hello world\"\r\n",
+                vec![b"SET", b"key", b"hello world"],
+            ),
+            (
+                b"ECHO \"a\\\"b\\n\\x41\\xZZ\"\r\n",
+                vec![b"ECHO", b"a\"b\nAxZZ"],
+            ),
+            (
+                b"ECHO 'it\\'s \"raw\"\\n'\r\n",
+                vec![b"ECHO", b"it's \"raw\"\\n"],
+            ),
+            (b"ECHO \"\" ''\r\n", vec![b"ECHO", b"", b""]),
+            (b"\r\n", vec![]),
+        ];
+        for (data, expected) in cases {
+            let mut cursor = Cursor::new(data);
+            assert!(Frame::is_inline(data[0]));
+            assert_eq!(
+                Frame::parse_inline(&mut cursor).unwrap(),
+                Frame::Array(
+                    expected
+                        .into_iter()
+                        .map(|arg| Frame::Bulk(Bytes::copy_from_slice(arg)))
+                        .collect()
+                )
+            );
+            assert_eq!(cursor.position() as usize, data.len());
+        }
+
+        assert!(!Frame::is_inline(b'*'));
+        let mut cursor = Cursor::new(&b"PING"[..]);
+        assert_matches!(
+            Frame::parse_inline(&mut cursor),
+            Err(RedisProtocolError::NotEnoughData)
+        );
+        for data in [&b"GET \"key\r\n"[..], b"GET 'key\r\n", b"GET \"a\"b\r\n"] {
+            let mut cursor = Cursor::new(data);
+            assert_matches!(
+                Frame::parse_inline(&mut cursor),
+                Err(RedisProtocolError::UnbalancedQuotes)
+            );
+        }
+    }
 }
@@ -0,0 +1,679 @@
+use std::time::Duration;
+
+use redis_clone::constants::SERVER_SHUTDOWN_CONNECTION_TIMEOUT;
+use redis_clone::{
+    array, bulk, integer, AppendFsync, Config, Frame, RedisClient, SaveRule, Snapshot, StreamId,
+    XAddId,
+};
+use tempfile::TempDir;
+
+mod common;
+
+/// Start a server, waiting until the previous server on the same directory has stopped.
+async fn restart(server: common::TestServer, config: &Config) -> common::TestServer {
+    server.shutdown();
+    tokio::time::sleep(SERVER_SHUTDOWN_CONNECTION_TIMEOUT * 2).await;
+    common::TestServer::with_config(config.clone()).await
+}
+
+async fn wait_for_snapshot(config: &Config, len: usize) -> Snapshot {
+    for _ in 0..50 {
+        if let Some(snapshot) = Snapshot::load(&config.snapshot_path()).unwrap() {
+            if snapshot.len() == len {
+                return snapshot;
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("snapshot with {} keys was not written", len);
+}
+
+fn aof_config(dir: &TempDir) -> Config {
+    Config {
+        appendonly: true,
+        appendfsync: AppendFsync::Always,
+        ..common::test_config(dir)
+    }
+}
+
+async fn wait_for_aof_smaller(config: &Config, len: u64) -> u64 {
+    for _ in 0..50 {
+        let current = std::fs::metadata(config.aof_path()).unwrap().len();
+        if current < len {
+            return current;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("append-only file was not rewritten");
+}
+
+/// RDB file of Redis 7.2 with the compact encodings, without the checksum.
+fn redis_rdb() -> Vec<u8> {
+    [
+        &b"REDIS0011"[..],
+        b"\xfa\x09redis-ver\x057.2.4",
+        b"\xfa\x0aredis-bits\xc0@",
+        b"\xfe\x00\xfb\x0b\x01",
+        // Strings encoded as integers and compressed by LZF
+        b"\x00\x03int\xc1\xd2\x04",
+        b"\x00\x03neg\xc0\xfb",
+        b"\x00\x05int32\xc2\xa0\x86\x01\x00",
+        b"\x00\x03lzf\xc3\x07\x0c\x02abc\xe0\x00\x02",
+        // Expiration in milliseconds (year 2100) and an expired key in seconds
+        b"\xfc\x00\xd8\xc3,\xbb\x03\x00\x00\x00\x03exp\x01v",
+        b"\xfd\xe8\x03\x00\x00\x00\x03old\x01v",
+        b"\xf9\x05\xf8\x0a",
+        // Hash listpack, intset, sorted set listpack, quicklist and sorted set ziplist
+        b"\x10\x04hash\x1a\x1a\x00\x00\x00\x04\x00\x85field\x06\x85value\x06\x81n\x02\x07\x01\xff",
+        b"\x0b\x03set\x0e\x02\x00\x00\x00\x03\x00\x00\x00\xfe\xff\x01\x00,\x01",
+        b"\x11\x04zset\x14\x14\x00\x00\x00\x04\x00\x81a\x02\x831.5\x04\x81b\x02\x02\x01\xff",
+        b"\x12\x04list\x02\x02\x12\x12\x00\x00\x00\x03\x00\x81x\x02\xdf\x9c\x02\xf2@B\x0f\x04\xff\x01\x05plain",
+        b"\x0c\x02zl\x10\x10\x00\x00\x00\x00\x00\x00\x00\x02\x00\x00\x01m\x03\xf6\xff",
+        b"\xff",
+    ]
+    .concat()
+}
+
+/// CRC-64/Jones checksum of `redis_rdb`.
+const REDIS_RDB_CHECKSUM: u64 = 0xe401_28ee_619e_bb93;
+
+async fn populate(client: &mut RedisClient) {
+    client
+        .set("string".to_string(), "value".into(), None)
+        .await
+        .unwrap();
+    client
+        .set(
+            "expiring".to_string(),
+            "value".into(),
+            Some(Duration::from_secs(100)),
+        )
+        .await
+        .unwrap();
+    client
+        .rpush("list".to_string(), vec!["a".into(), "b".into()])
+        .await
+        .unwrap();
+    client
+        .hset("hash".to_string(), vec![("field".into(), "value".into())])
+        .await
+        .unwrap();
+    client
+        .sadd("set".to_string(), vec!["member".into()])
+        .await
+        .unwrap();
+    client
+        .zadd("zset".to_string(), vec![(1.5, "member".into())])
+        .await
+        .unwrap();
+    client
+        .xadd(
+            "stream".to_string(),
+            XAddId::Explicit(StreamId::new(1, 1)),
+            vec![("field".into(), "value".into())],
+        )
+        .await
+        .unwrap();
+    client
+        .xgroup_create("stream".to_string(), "group".to_string(), None, false)
+        .await
+        .unwrap();
+    client
+        .xadd(
+            "stream".to_string(),
+            XAddId::Explicit(StreamId::new(2, 0)),
+            vec![("field".into(), "value".into())],
+        )
+        .await
+        .unwrap();
+    client
+        .xreadgroup(
+            "group".to_string(),
+            "consumer".to_string(),
+            vec![("stream".to_string(), None)],
+            None,
+            false,
+        )
+        .await
+        .unwrap();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn save_rules() {
+        let rules = SaveRule::parse_rules("900 1 300 10").unwrap();
+        assert_eq!(
+            rules,
+            vec![
+                SaveRule {
+                    seconds: 900,
+                    changes: 1
+                },
+                SaveRule {
+                    seconds: 300,
+                    changes: 10
+                },
+            ]
+        );
+        assert!(SaveRule::parse_rules("").unwrap().is_empty());
+        assert!(SaveRule::parse_rules("900").is_err());
+        assert!(SaveRule::parse_rules("900 x").is_err());
+
+        let args = ["--dir", "/tmp", "--save", "60 1"].map(str::to_string);
+        let config = Config::from_args(args).unwrap();
+        assert_eq!(
+            config.snapshot_path(),
+            std::path::Path::new("/tmp/dump.snap")
+        );
+        assert_eq!(config.save.len(), 1);
+        assert!(Config::from_args(["--unknown".to_string(), "1".to_string()]).is_err());
+        assert!(Config::from_args(["--dir".to_string()]).is_err());
+
+        let args = ["--appendonly", "yes", "--appendfsync", "always"].map(str::to_string);
+        let config = Config::from_args(args).unwrap();
+        assert!(config.appendonly);
+        assert_eq!(config.appendfsync, AppendFsync::Always);
+        assert_eq!(config.aof_path(), std::path::Path::new("./appendonly.aof"));
+        let args = ["--appendfsync", "sometimes"].map(str::to_string);
+        assert!(Config::from_args(args).is_err());
+
+        let args = [
+            "--auto-aof-rewrite-percentage",
+            "50",
+            "--auto-aof-rewrite-min-size",
+            "1kb",
+        ]
+        .map(str::to_string);
+        let config = Config::from_args(args).unwrap();
+        assert_eq!(config.auto_aof_rewrite_percentage, 50);
+        assert_eq!(config.auto_aof_rewrite_min_size, 1024);
+        let args = ["--auto-aof-rewrite-min-size", "1xb"].map(str::to_string);
+        assert!(Config::from_args(args).is_err());
+    }
+
+    #[test]
+    fn snapshot_decode_errors() {
+        assert!(Snapshot::decode(b"").is_err());
+        assert!(Snapshot::decode(b"REDIS0011").is_err());
+        // Valid header, but the end marker is missing
+        assert!(Snapshot::decode(b"RCSNAP\x01").is_err());
+        let empty = Snapshot::decode(b"RCSNAP\x01\xFF").unwrap();
+        assert!(empty.is_empty());
+        assert_eq!(&empty.encode()[..], b"RCSNAP\x01\xFF");
+    }
+
+    #[tokio::test]
+    async fn snapshot_save_load() {
+        common::get_or_init_logger();
+
+        let dir = common::temp_dir();
+        let config = common::test_config(&dir);
+
+        let server = common::TestServer::with_config(config.clone()).await;
+        let mut client = server.create_client().await.unwrap();
+        populate(&mut client).await;
+        let lastsave = client.lastsave().await.unwrap();
+        client.save().await.unwrap();
+        assert!(client.lastsave().await.unwrap() >= lastsave);
+        let snapshot = Snapshot::load(&config.snapshot_path()).unwrap().unwrap();
+        assert_eq!(snapshot.len(), 7);
+
+        // All data types survive a restart
+        let server = restart(server, &config).await;
+        let mut client = server.create_client().await.unwrap();
+        let response = client.dbsize().await.unwrap().unwrap();
+        assert_eq!(response, integer!(7));
+        let response = client.get("string".to_string()).await.unwrap().unwrap();
+        assert_eq!(response, bulk!("value"));
+        let response = client.lrange("list".to_string(), 0, -1).await.unwrap();
+        assert_eq!(response.unwrap(), array!(bulk!("a"), bulk!("b")));
+        let response = client.hgetall("hash".to_string()).await.unwrap();
+        assert_eq!(response, vec![("field".into(), "value".into())]);
+        let response = client.smembers("set".to_string()).await.unwrap();
+        assert_eq!(response, vec![bytes::Bytes::from("member")]);
+        let response = client
+            .zscore("zset".to_string(), "member".into())
+            .await
+            .unwrap();
+        assert_eq!(response, Some(1.5));
+        let response = client
+            .xpending("stream".to_string(), "group".to_string())
+            .await
+            .unwrap();
+        assert_eq!(response.count, 1);
+        assert_eq!(response.consumers, vec![("consumer".to_string(), 1)]);
+        let response = client
+            .xadd(
+                "stream".to_string(),
+                XAddId::Explicit(StreamId::new(2, 0)),
+                vec![("field".into(), "value".into())],
+            )
+            .await;
+        assert!(response.is_err(), "the last ID of the stream is restored");
+
+        // The remaining time to live is kept
+        let response = client.ttl("expiring".to_string()).await.unwrap().unwrap();
+        let Frame::Integer(ttl) = response else {
+            panic!("expected an integer, got {:?}", response);
+        };
+        assert!((1..=100).contains(&ttl), "unexpected TTL {}", ttl);
+
+        // BGSAVE writes the snapshot in the background
+        client
+            .set("another".to_string(), "value".into(), None)
+            .await
+            .unwrap();
+        client.bgsave().await.unwrap();
+        wait_for_snapshot(&config, 8).await;
+
+        server.shutdown();
+    }
+
+    #[tokio::test]
+    async fn snapshot_save_rules() {
+        common::get_or_init_logger();
+
+        let dir = common::temp_dir();
+        let config = Config {
+            save: vec![SaveRule {
+                seconds: 0,
+                changes: 2,
+            }],
+            ..common::test_config(&dir)
+        };
+
+        let server = common::TestServer::with_config(config.clone()).await;
+        let mut client = server.create_client().await.unwrap();
+        client.set("a".to_string(), "1".into(), None).await.unwrap();
+        tokio::time::sleep(Duration::from_millis(1500)).await;
+        assert!(
+            Snapshot::load(&config.snapshot_path()).unwrap().is_none(),
+            "a single change does not meet the rule"
+        );
+        client.set("b".to_string(), "2".into(), None).await.unwrap();
+        wait_for_snapshot(&config, 2).await;
+
+        // A final snapshot is saved on shutdown
+        client.set("c".to_string(), "3".into(), None).await.unwrap();
+        let server = restart(server, &config).await;
+        let mut client = server.create_client().await.unwrap();
+        let response = client.dbsize().await.unwrap().unwrap();
+        assert_eq!(response, integer!(3));
+
+        server.shutdown();
+        tokio::time::sleep(SERVER_SHUTDOWN_CONNECTION_TIMEOUT * 2).await;
+    }
+
+    #[tokio::test]
+    async fn aof_replay() {
+        common::get_or_init_logger();
+
+        let dir = common::temp_dir();
+        let config = aof_config(&dir);
+        let server = common::TestServer::with_config(config.clone()).await;
+        let mut client = server.create_client().await.unwrap();
+        populate(&mut client).await;
+
+        // Random and time-dependent commands are replayed with the same effect
+        client
+            .sadd(
+                "random".to_string(),
+                vec!["a".into(), "b".into(), "c".into()],
+            )
+            .await
+            .unwrap();
+        let popped = client.spop("random".to_string()).await.unwrap().unwrap();
+        let id = client
+            .xadd(
+                "stream".to_string(),
+                XAddId::Auto,
+                vec![("field".into(), "value".into())],
+            )
+            .await
+            .unwrap();
+        let response = client
+            .blpop(vec!["list".to_string()], Some(Duration::from_secs(1)))
+            .await
+            .unwrap();
+        assert_eq!(response, Some(("list".to_string(), "a".into())));
+        client
+            .transaction()
+            .incr("counter".to_string())
+            .incr("counter".to_string())
+            .exec()
+            .await
+            .unwrap();
+        client.del(vec!["hash".to_string()]).await.unwrap();
+
+        let server = restart(server, &config).await;
+        let mut client = server.create_client().await.unwrap();
+        let response = client.dbsize().await.unwrap().unwrap();
+        assert_eq!(response, integer!(8));
+        let response = client.get("string".to_string()).await.unwrap().unwrap();
+        assert_eq!(response, bulk!("value"));
+        let response = client.lrange("list".to_string(), 0, -1).await.unwrap();
+        assert_eq!(response.unwrap(), array!(bulk!("b")));
+        let response = client.get("counter".to_string()).await.unwrap().unwrap();
+        assert_eq!(response, bulk!("2"));
+        let response = client.smembers("random".to_string()).await.unwrap();
+        assert_eq!(response.len(), 2);
+        assert!(!response.contains(&popped));
+        let response = client
+            .xrange("stream".to_string(), id, id, None)
+            .await
+            .unwrap();
+        assert_eq!(response.len(), 1);
+        let response = client
+            .xpending("stream".to_string(), "group".to_string())
+            .await
+            .unwrap();
+        assert_eq!(response.count, 1);
+
+        server.shutdown();
+        tokio::time::sleep(SERVER_SHUTDOWN_CONNECTION_TIMEOUT * 2).await;
+    }
+
+    #[tokio::test]
+    async fn aof_expiration_not_extended() {
+        common::get_or_init_logger();
+
+        let dir = common::temp_dir();
+        let config = aof_config(&dir);
+        let server = common::TestServer::with_config(config.clone()).await;
+        let mut client = server.create_client().await.unwrap();
+        client
+            .set(
+                "set".to_string(),
+                "value".into(),
+                Some(Duration::from_secs(2)),
+            )
+            .await
+            .unwrap();
+        client
+            .set("expire".to_string(), "value".into(), None)
+            .await
+            .unwrap();
+        client.expire("expire".to_string(), 2).await.unwrap();
+        let started = std::time::Instant::now();
+
+        // The restart takes longer than a second, relative expirations would restart
+        // from 2 seconds after the replay
+        let server = restart(server, &config).await;
+        let mut client = server.create_client().await.unwrap();
+        let response = client.dbsize().await.unwrap().unwrap();
+        assert_eq!(response, integer!(2));
+        tokio::time::sleep(Duration::from_millis(2200).saturating_sub(started.elapsed())).await;
+        let response = client.get("set".to_string()).await.unwrap().unwrap();
+        assert_eq!(response, redis_clone::Frame::Null);
+        let response = client.get("expire".to_string()).await.unwrap().unwrap();
+        assert_eq!(response, redis_clone::Frame::Null);
+
+        server.shutdown();
+        tokio::time::sleep(SERVER_SHUTDOWN_CONNECTION_TIMEOUT * 2).await;
+    }
+
+    #[tokio::test]
+    async fn aof_truncated() {
+        common::get_or_init_logger();
+
+        let dir = common::temp_dir();
+        let config = aof_config(&dir);
+        let complete = b"*3\r\n$3\r\nSET\r\n$1\r\na\r\n$1\r\n1\r\n";
+        // Transaction without `EXEC` and a command cut off by a crash
+        let incomplete = b"*1\r\n$5\r\nMULTI\r\n*2\r\n$4\r\nINCR\r\n$1\r\nb\r\n*2\r\n$4\r\nINCR";
+        std::fs::write(config.aof_path(), [&complete[..], &incomplete[..]].concat()).unwrap();
+
+        let server = common::TestServer::with_config(config.clone()).await;
+        let mut client = server.create_client().await.unwrap();
+        let response = client.dbsize().await.unwrap().unwrap();
+        assert_eq!(response, integer!(1));
+        let response = client.get("a".to_string()).await.unwrap().unwrap();
+        assert_eq!(response, bulk!("1"));
+        let len = std::fs::metadata(config.aof_path()).unwrap().len();
+        assert_eq!(len as usize, complete.len() + incomplete.len() - 12);
+
+        server.shutdown();
+        tokio::time::sleep(SERVER_SHUTDOWN_CONNECTION_TIMEOUT * 2).await;
+    }
+
+    #[tokio::test]
+    async fn aof_rewrite() {
+        common::get_or_init_logger();
+
+        let dir = common::temp_dir();
+        let config = aof_config(&dir);
+        let server = common::TestServer::with_config(config.clone()).await;
+        let mut client = server.create_client().await.unwrap();
+        populate(&mut client).await;
+        for _ in 0..100 {
+            client.incr("counter".to_string()).await.unwrap();
+        }
+        // The last ID of the stream is kept after the last entry is deleted
+        client
+            .xadd(
+                "stream".to_string(),
+                XAddId::Explicit(StreamId::new(3, 0)),
+                vec![("field".into(), "value".into())],
+            )
+            .await
+            .unwrap();
+        client
+            .xdel("stream".to_string(), vec![StreamId::new(3, 0)])
+            .await
+            .unwrap();
+
+        let len = std::fs::metadata(config.aof_path()).unwrap().len();
+        client.bgrewriteaof().await.unwrap();
+        wait_for_aof_smaller(&config, len).await;
+        // Writes after the rewrite are appended to the new file
+        client.incr("counter".to_string()).await.unwrap();
+
+        let server = restart(server, &config).await;
+        let mut client = server.create_client().await.unwrap();
+        let response = client.dbsize().await.unwrap().unwrap();
+        assert_eq!(response, integer!(8));
+        let response = client.get("counter".to_string()).await.unwrap().unwrap();
+        assert_eq!(response, bulk!("101"));
+        let response = client.lrange("list".to_string(), 0, -1).await.unwrap();
+        assert_eq!(response.unwrap(), array!(bulk!("a"), bulk!("b")));
+        let response = client.ttl("expiring".to_string()).await.unwrap().unwrap();
+        assert!(matches!(response, Frame::Integer(ttl) if ttl > 0 && ttl <= 100));
+        let info = client.xinfo_stream("stream".to_string()).await.unwrap();
+        assert_eq!(info.length, 2);
+        assert_eq!(info.last_generated_id, StreamId::new(3, 0));
+        let response = client
+            .xpending("stream".to_string(), "group".to_string())
+            .await
+            .unwrap();
+        assert_eq!(response.count, 1);
+        assert_eq!(response.consumers, vec![("consumer".to_string(), 1)]);
+
+        server.shutdown();
+        tokio::time::sleep(SERVER_SHUTDOWN_CONNECTION_TIMEOUT * 2).await;
+    }
+
+    #[tokio::test]
+    async fn aof_auto_rewrite() {
+        common::get_or_init_logger();
+
+        let dir = common::temp_dir();
+        let config = Config {
+            auto_aof_rewrite_percentage: 100,
+            auto_aof_rewrite_min_size: 1024,
+            ..aof_config(&dir)
+        };
+        let server = common::TestServer::with_config(config.clone()).await;
+        let mut client = server.create_client().await.unwrap();
+        // Each `INCR` appends about 30 bytes
+        for _ in 0..100 {
+            client.incr("counter".to_string()).await.unwrap();
+        }
+        let len = std::fs::metadata(config.aof_path()).unwrap().len();
+        assert!(len > 1024);
+        wait_for_aof_smaller(&config, len).await;
+
+        let server = restart(server, &config).await;
+        let mut client = server.create_client().await.unwrap();
+        let response = client.get("counter".to_string()).await.unwrap().unwrap();
+        assert_eq!(response, bulk!("100"));
+
+        server.shutdown();
+        tokio::time::sleep(SERVER_SHUTDOWN_CONNECTION_TIMEOUT * 2).await;
+    }
+
+    #[test]
+    fn rdb_decode_errors() {
+        let rdb = redis_rdb();
+        let with_checksum = |checksum: u64| [&rdb[..], &checksum.to_le_bytes()].concat();
+        let snapshot = Snapshot::decode_rdb(&with_checksum(REDIS_RDB_CHECKSUM)).unwrap();
+        assert_eq!(snapshot.len(), 11);
+        // Zero if the checksum is disabled
+        assert!(Snapshot::decode_rdb(&with_checksum(0)).is_ok());
+        let err = Snapshot::decode_rdb(&with_checksum(REDIS_RDB_CHECKSUM + 1)).unwrap_err();
+        assert_eq!(err.to_string(), "wrong RDB checksum");
+        assert!(Snapshot::decode_rdb(&rdb).is_err());
+
+        assert!(Snapshot::decode_rdb(b"RCSNAP\x01\xFF").is_err());
+        let err = Snapshot::decode_rdb(b"REDIS0013\xff").unwrap_err();
+        assert_eq!(err.to_string(), "unsupported RDB version 13");
+        let err = Snapshot::decode_rdb(b"REDIS0011\xfe\x01\xff").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "keys of database 1 are not supported, only database 0"
+        );
+        let err = Snapshot::decode_rdb(b"REDIS0011\x07\x01m\x00").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "value of key 'm': module values are not supported"
+        );
+        let err = Snapshot::decode_rdb(b"REDIS0012\x19\x01h\x00").unwrap_err();
+        assert_eq!(
+            err.to_string(),
+            "value of key 'h': hashes with field expiration are not supported"
+        );
+        // Version 4 has no checksum
+        let snapshot = Snapshot::decode_rdb(b"REDIS0004\x00\x01a\x01b\xff").unwrap();
+        assert_eq!(snapshot.len(), 1);
+        let encoded = snapshot.encode_rdb();
+        assert!(encoded.starts_with(b"REDIS0009"));
+        assert_eq!(Snapshot::decode_rdb(&encoded).unwrap().len(), 1);
+    }
+
+    #[tokio::test]
+    async fn rdb_load() {
+        common::get_or_init_logger();
+
+        let dir = common::temp_dir();
+        let config = Config {
+            dbfilename: "dump.rdb".to_string(),
+            ..common::test_config(&dir)
+        };
+        let rdb = [&redis_rdb()[..], &REDIS_RDB_CHECKSUM.to_le_bytes()].concat();
+        std::fs::write(config.snapshot_path(), rdb).unwrap();
+
+        let server = common::TestServer::with_config(config.clone()).await;
+        let mut client = server.create_client().await.unwrap();
+        // The expired key is skipped
+        let response = client.dbsize().await.unwrap().unwrap();
+        assert_eq!(response, integer!(10));
+        for (key, value) in [
+            ("int", "1234"),
+            ("neg", "-5"),
+            ("int32", "100000"),
+            ("lzf", "abcabcabcabc"),
+            ("exp", "v"),
+        ] {
+            let response = client.get(key.to_string()).await.unwrap().unwrap();
+            assert_eq!(response, bulk!(value));
+        }
+        let response = client.ttl("exp".to_string()).await.unwrap().unwrap();
+        assert!(matches!(response, Frame::Integer(ttl) if ttl > 0));
+        let mut response = client.hgetall("hash".to_string()).await.unwrap();
+        response.sort();
+        assert_eq!(
+            response,
+            vec![("field".into(), "value".into()), ("n".into(), "7".into())]
+        );
+        let mut response = client.smembers("set".to_string()).await.unwrap();
+        response.sort();
+        assert_eq!(response, vec!["-2", "1", "300"]);
+        let response = client.zscore("zset".to_string(), "a".into()).await.unwrap();
+        assert_eq!(response, Some(1.5));
+        let response = client.zscore("zset".to_string(), "b".into()).await.unwrap();
+        assert_eq!(response, Some(2.0));
+        let response = client.zscore("zl".to_string(), "m".into()).await.unwrap();
+        assert_eq!(response, Some(5.0));
+        let response = client.lrange("list".to_string(), 0, -1).await.unwrap();
+        assert_eq!(
+            response.unwrap(),
+            array!(bulk!("x"), bulk!("-100"), bulk!("1000000"), bulk!("plain"))
+        );
+
+        server.shutdown();
+        tokio::time::sleep(SERVER_SHUTDOWN_CONNECTION_TIMEOUT * 2).await;
+    }
+
+    #[tokio::test]
+    async fn rdb_round_trip() {
+        common::get_or_init_logger();
+
+        let dir = common::temp_dir();
+        let config = common::test_config(&dir);
+        let server = common::TestServer::with_config(config.clone()).await;
+        let mut client = server.create_client().await.unwrap();
+        populate(&mut client).await;
+        // Entries with other fields than the first entry of the listpack
+        client
+            .xadd(
+                "stream".to_string(),
+                XAddId::Explicit(StreamId::new(3, 0)),
+                vec![("other".into(), "value".into())],
+            )
+            .await
+            .unwrap();
+        client
+            .xdel("stream".to_string(), vec![StreamId::new(1, 1)])
+            .await
+            .unwrap();
+        client.save().await.unwrap();
+        let snapshot = Snapshot::load(&config.snapshot_path()).unwrap().unwrap();
+
+        let config = Config {
+            dbfilename: "dump.rdb".to_string(),
+            ..config
+        };
+        std::fs::write(config.snapshot_path(), snapshot.encode_rdb()).unwrap();
+        let server = restart(server, &config).await;
+        let mut client = server.create_client().await.unwrap();
+        let response = client.dbsize().await.unwrap().unwrap();
+        assert_eq!(response, integer!(7));
+        let response = client.lrange("list".to_string(), 0, -1).await.unwrap();
+        assert_eq!(response.unwrap(), array!(bulk!("a"), bulk!("b")));
+        let response = client
+            .zscore("zset".to_string(), "member".into())
+            .await
+            .unwrap();
+        assert_eq!(response, Some(1.5));
+        let response = client.ttl("expiring".to_string()).await.unwrap().unwrap();
+        assert!(matches!(response, Frame::Integer(ttl) if ttl > 0 && ttl <= 100));
+        let entries = client
+            .xrange("stream".to_string(), StreamId::MIN, StreamId::MAX, None)
+            .await
+            .unwrap();
+        let ids: Vec<StreamId> = entries.iter().map(|entry| entry.id).collect();
+        assert_eq!(ids, vec![StreamId::new(2, 0), StreamId::new(3, 0)]);
+        assert_eq!(entries[1].fields, vec![("other".into(), "value".into())]);
+        let response = client
+            .xpending("stream".to_string(), "group".to_string())
+            .await
+            .unwrap();
+        assert_eq!(response.count, 1);
+        assert_eq!(response.consumers, vec![("consumer".to_string(), 1)]);
+
+        server.shutdown();
+        tokio::time::sleep(SERVER_SHUTDOWN_CONNECTION_TIMEOUT * 2).await;
+    }
+}
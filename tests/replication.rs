@@ -0,0 +1,321 @@
+use std::time::Duration;
+
+use bytes::Bytes;
+use redis_clone::{array, bulk, AppendFsync, Config, Frame, LinkState, RedisClient, Role};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+
+mod common;
+
+/// Wait until the replica is connected to its primary.
+async fn wait_for_link(replica: &mut RedisClient) {
+    for _ in 0..50 {
+        if let Role::Replica {
+            state: LinkState::Connected,
+            ..
+        } = replica.role().await.unwrap()
+        {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(100)).await;
+    }
+    panic!("replica did not connect to the primary");
+}
+
+/// Wait until the key has the value on the replica.
+async fn wait_for_value(replica: &mut RedisClient, key: &str, value: Frame) {
+    let mut last = None;
+    for _ in 0..50 {
+        last = replica.get(key.to_string()).await.unwrap();
+        if last.as_ref() == Some(&value) {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(50)).await;
+    }
+    panic!("key '{}' has value {:?}, expected {:?}", key, last, value);
+}
+
+/// Send a command as an array of bulk strings.
+async fn send(stream: &mut BufReader<TcpStream>, parts: &[&str]) {
+    let mut command = format!("*{}\r\n", parts.len());
+    for part in parts {
+        command.push_str(&format!("${}\r\n{}\r\n", part.len(), part));
+    }
+    stream.write_all(command.as_bytes()).await.unwrap();
+}
+
+async fn read_line(stream: &mut BufReader<TcpStream>) -> String {
+    let mut line = String::new();
+    stream.read_line(&mut line).await.unwrap();
+    line.trim_end().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn config_from_args() {
+        let args = [
+            "--replicaof",
+            "127.0.0.1 6380",
+            "--repl-backlog-size",
+            "1kb",
+        ];
+        let config = Config::from_args(args.map(str::to_string)).unwrap();
+        assert_eq!(config.replicaof, Some(("127.0.0.1".to_string(), 6380)));
+        assert_eq!(config.repl_backlog_size, 1024);
+        for value in ["127.0.0.1", "127.0.0.1 port", "127.0.0.1 6380 1"] {
+            assert!(Config::from_args(["--replicaof", value].map(str::to_string)).is_err());
+        }
+        assert!(Config::from_args(["--repl-backlog-size", "0"].map(str::to_string)).is_err());
+    }
+
+    #[tokio::test]
+    async fn replicaof() {
+        common::get_or_init_logger();
+        let primary = common::TestServer::new().await;
+        let replica = common::TestServer::new().await;
+        let mut primary_client = primary.create_client().await.unwrap();
+        let mut replica_client = replica.create_client().await.unwrap();
+
+        primary_client
+            .set("before".into(), Bytes::from("sync"), None)
+            .await
+            .unwrap();
+        primary_client
+            .rpush("list".into(), vec![Bytes::from("a"), Bytes::from("b")])
+            .await
+            .unwrap();
+
+        replica_client
+            .replicaof(common::SERVER_ADDR, primary.port())
+            .await
+            .unwrap();
+        wait_for_link(&mut replica_client).await;
+        wait_for_value(&mut replica_client, "before", bulk!("sync")).await;
+        assert_eq!(
+            replica_client.lrange("list".into(), 0, -1).await.unwrap(),
+            Some(array!(bulk!("a"), bulk!("b")))
+        );
+
+        // Writes after the sync are streamed
+        primary_client
+            .set("after".into(), Bytes::from("stream"), None)
+            .await
+            .unwrap();
+        wait_for_value(&mut replica_client, "after", bulk!("stream")).await;
+        primary_client
+            .transaction()
+            .incr("counter".into())
+            .incr("counter".into())
+            .exec()
+            .await
+            .unwrap();
+        wait_for_value(&mut replica_client, "counter", bulk!("2")).await;
+
+        // Replicas are read-only
+        let err = replica_client
+            .hset("hash".into(), vec![(Bytes::from("f"), Bytes::from("v"))])
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("READONLY"), "{}", err);
+        let mut transaction = replica_client.transaction();
+        transaction = transaction.set("key".into(), Bytes::from("value"), None);
+        assert!(transaction.exec().await.is_err());
+
+        let Role::Primary { offset, replicas } = primary_client.role().await.unwrap() else {
+            panic!("expected a primary");
+        };
+        assert!(offset > 0);
+        assert_eq!(replicas.len(), 1);
+        assert_eq!(replicas[0].port, replica.port());
+        let Role::Replica {
+            host,
+            port,
+            offset: replica_offset,
+            ..
+        } = replica_client.role().await.unwrap()
+        else {
+            panic!("expected a replica");
+        };
+        assert_eq!((host.as_str(), port), (common::SERVER_ADDR, primary.port()));
+        assert_eq!(replica_offset, offset);
+
+        // The promoted replica keeps its data and accepts writes
+        replica_client.replicaof_no_one().await.unwrap();
+        assert!(matches!(
+            replica_client.role().await.unwrap(),
+            Role::Primary { .. }
+        ));
+        replica_client
+            .set("after".into(), Bytes::from("promoted"), None)
+            .await
+            .unwrap();
+        primary_client
+            .set("after".into(), Bytes::from("ignored"), None)
+            .await
+            .unwrap();
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        assert_eq!(
+            replica_client.get("after".into()).await.unwrap(),
+            Some(bulk!("promoted"))
+        );
+
+        replica.shutdown();
+        primary.shutdown();
+    }
+
+    #[tokio::test]
+    async fn replicaof_config() {
+        common::get_or_init_logger();
+        let primary = common::TestServer::new().await;
+        let mut primary_client = primary.create_client().await.unwrap();
+        primary_client
+            .set("key".into(), Bytes::from("value"), None)
+            .await
+            .unwrap();
+
+        let dir = common::temp_dir();
+        let replica = common::TestServer::with_config(Config {
+            replicaof: Some((common::SERVER_ADDR.to_string(), primary.port())),
+            ..common::test_config(&dir)
+        })
+        .await;
+        let mut replica_client = replica.create_client().await.unwrap();
+        wait_for_link(&mut replica_client).await;
+        wait_for_value(&mut replica_client, "key", bulk!("value")).await;
+
+        replica.shutdown();
+        primary.shutdown();
+    }
+
+    #[tokio::test]
+    async fn wait() {
+        common::get_or_init_logger();
+        let aof_config = |dir| Config {
+            appendonly: true,
+            appendfsync: AppendFsync::No,
+            ..common::test_config(dir)
+        };
+        let (primary_dir, replica_dir) = (common::temp_dir(), common::temp_dir());
+        let primary = common::TestServer::with_config(aof_config(&primary_dir)).await;
+        let replica = common::TestServer::with_config(aof_config(&replica_dir)).await;
+        let mut primary_client = primary.create_client().await.unwrap();
+        let mut replica_client = replica.create_client().await.unwrap();
+
+        // Without replicas, WAIT returns right away with no timeout
+        assert_eq!(primary_client.wait(0, None).await.unwrap(), 0);
+        assert_eq!(primary_client.waitaof(1, 0, None).await.unwrap(), (1, 0));
+
+        replica_client
+            .replicaof(common::SERVER_ADDR, primary.port())
+            .await
+            .unwrap();
+        wait_for_link(&mut replica_client).await;
+
+        primary_client
+            .set("key".into(), Bytes::from("value"), None)
+            .await
+            .unwrap();
+        assert_eq!(primary_client.wait(1, None).await.unwrap(), 1);
+        assert_eq!(
+            replica_client.get("key".into()).await.unwrap(),
+            Some(bulk!("value"))
+        );
+        primary_client
+            .set("key".into(), Bytes::from("synced"), None)
+            .await
+            .unwrap();
+        assert_eq!(
+            primary_client
+                .waitaof(1, 1, Some(Duration::from_secs(5)))
+                .await
+                .unwrap(),
+            (1, 1)
+        );
+
+        // Not enough replicas, the timeout elapses
+        let start = std::time::Instant::now();
+        let acked = primary_client
+            .wait(2, Some(Duration::from_millis(200)))
+            .await
+            .unwrap();
+        assert_eq!(acked, 1);
+        assert!(start.elapsed() >= Duration::from_millis(200));
+
+        let err = replica_client.wait(1, None).await.unwrap_err();
+        assert!(err.to_string().contains("replica"), "{}", err);
+
+        replica.shutdown();
+        primary.shutdown();
+
+        // WAITAOF needs the local append-only file
+        let server = common::TestServer::new().await;
+        let mut client = server.create_client().await.unwrap();
+        assert!(client.waitaof(1, 0, None).await.is_err());
+        assert_eq!(client.waitaof(0, 0, None).await.unwrap(), (0, 0));
+        server.shutdown();
+    }
+
+    #[tokio::test]
+    async fn psync() {
+        common::get_or_init_logger();
+        let primary = common::TestServer::new().await;
+        let mut client = primary.create_client().await.unwrap();
+        client
+            .set("key".into(), Bytes::from("value"), None)
+            .await
+            .unwrap();
+
+        let connect = || TcpStream::connect((common::SERVER_ADDR, primary.port()));
+        let mut stream = BufReader::new(connect().await.unwrap());
+        send(&mut stream, &["PSYNC", "?", "-1"]).await;
+        let reply = read_line(&mut stream).await;
+        let parts: Vec<&str> = reply.split(' ').collect();
+        assert_eq!(parts[0], "+FULLRESYNC", "{}", reply);
+        let replication_id = parts[1].to_string();
+        assert_eq!(replication_id.len(), 40);
+        let offset: u64 = parts[2].parse().unwrap();
+
+        let len: usize = read_line(&mut stream).await[1..].parse().unwrap();
+        let mut payload = vec![0; len];
+        stream.read_exact(&mut payload).await.unwrap();
+        assert!(payload.starts_with(b"REDIS"));
+
+        // Writes are streamed as commands
+        client
+            .set("streamed".into(), Bytes::from("1"), None)
+            .await
+            .unwrap();
+        let command = "*3\r\n$3\r\nSET\r\n$8\r\nstreamed\r\n$1\r\n1\r\n";
+        let mut streamed = vec![0; command.len()];
+        stream.read_exact(&mut streamed).await.unwrap();
+        assert_eq!(String::from_utf8_lossy(&streamed), command);
+        drop(stream);
+
+        // After a disconnect, the replica continues from the backlog
+        client
+            .set("missed".into(), Bytes::from("2"), None)
+            .await
+            .unwrap();
+        let mut stream = BufReader::new(connect().await.unwrap());
+        let next = (offset + command.len() as u64 + 1).to_string();
+        send(&mut stream, &["PSYNC", &replication_id, &next]).await;
+        assert_eq!(
+            read_line(&mut stream).await,
+            format!("+CONTINUE {}", replication_id)
+        );
+        let command = "*3\r\n$3\r\nSET\r\n$6\r\nmissed\r\n$1\r\n2\r\n";
+        let mut missed = vec![0; command.len()];
+        stream.read_exact(&mut missed).await.unwrap();
+        assert_eq!(String::from_utf8_lossy(&missed), command);
+
+        // An unknown history or an offset outside of the backlog needs a full resync
+        let mut stream = BufReader::new(connect().await.unwrap());
+        send(&mut stream, &["PSYNC", &replication_id, "1000000"]).await;
+        assert!(read_line(&mut stream).await.starts_with("+FULLRESYNC"));
+
+        primary.shutdown();
+    }
+}
@@ -4,6 +4,7 @@ use tokio::net::TcpStream;
 use tokio::time::timeout;
 
 use redis_clone::constants::CLIENT_CONNECTION_TIMEOUT;
+use redis_clone::{Config, ProtocolLimits};
 
 mod common;
 
@@ -181,7 +182,7 @@ mod tests {
 
         // Send PING with message
         client
-            .send(format!("*2\r\n$4\r\nPING\r\n${}\r\n{}\r\n", msg.bytes().len(), msg).as_str())
+            .send(format!("*2\r\n$4\r\nPING\r\n${}\r\n{}\r\n", msg.len(), msg).as_str())
             .await;
 
         // Read PONG response
@@ -446,6 +447,59 @@ mod tests {
         client.assert_response(b"$-1\r\n").await;
     }
 
+    #[tokio::test]
+    async fn absolute_expiration() {
+        common::get_or_init_logger();
+
+        let port = common::TestServer::new().await.port();
+        let mut client = TestClient::new(port).await;
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap();
+        let future_ms = (now.as_millis() + 100_000).to_string();
+        let future_s = (now.as_secs() + 100).to_string();
+
+        // Unix times in the past expire the key immediately
+        client
+            .send("*5\r\n$3\r\nSET\r\n$4\r\nkey1\r\n$5\r\nvalue\r\n$4\r\nPXAT\r\n$1\r\n1\r\n")
+            .await;
+        client.assert_response(b"+OK\r\n").await;
+        client.send_get("key1").await;
+        client.assert_response(b"$-1\r\n").await;
+
+        client
+            .send(&format!(
+                "*5\r\n$3\r\nSET\r\n$4\r\nkey1\r\n$5\r\nvalue\r\n$4\r\nEXAT\r\n${}\r\n{}\r\n",
+                future_s.len(),
+                future_s
+            ))
+            .await;
+        client.assert_response(b"+OK\r\n").await;
+        client.send_get("key1").await;
+        client.assert_response(b"$5\r\nvalue\r\n").await;
+
+        client
+            .send(&format!(
+                "*3\r\n$9\r\nPEXPIREAT\r\n$4\r\nkey1\r\n${}\r\n{}\r\n",
+                future_ms.len(),
+                future_ms
+            ))
+            .await;
+        client.assert_response(b":1\r\n").await;
+        client
+            .send("*3\r\n$7\r\nPEXPIRE\r\n$4\r\nkey1\r\n$4\r\n1000\r\n")
+            .await;
+        client.assert_response(b":1\r\n").await;
+        client.send("*2\r\n$3\r\nTTL\r\n$4\r\nkey1\r\n").await;
+        client.assert_response(b":0\r\n").await;
+        client
+            .send("*3\r\n$8\r\nEXPIREAT\r\n$4\r\nkey1\r\n$1\r\n1\r\n")
+            .await;
+        client.assert_response(b":1\r\n").await;
+        client.send_get("key1").await;
+        client.assert_response(b"$-1\r\n").await;
+    }
+
     #[tokio::test]
     async fn wrong_number_of_arguments() {
         let port = common::TestServer::new().await.port();
@@ -461,4 +515,497 @@ mod tests {
             .assert_response(format!("-ERR {}\r\n", expected_err).as_bytes())
             .await;
     }
+
+    #[tokio::test]
+    async fn list_commands() {
+        let port = common::TestServer::new().await.port();
+        let mut client = TestClient::new(port).await;
+
+        client
+            .send("*4\r\n$5\r\nRPUSH\r\n$4\r\nlist\r\n$1\r\na\r\n$1\r\nb\r\n")
+            .await;
+        client.assert_response(b":2\r\n").await;
+
+        client
+            .send("*4\r\n$6\r\nLRANGE\r\n$4\r\nlist\r\n$1\r\n0\r\n$2\r\n-1\r\n")
+            .await;
+        client
+            .assert_response(b"*2\r\n$1\r\na\r\n$1\r\nb\r\n")
+            .await;
+
+        // String command on a list
+        client.send_get("list").await;
+        client
+            .assert_response(
+                b"-WRONGTYPE Operation against a key holding the wrong kind of value\r\n",
+            )
+            .await;
+    }
+
+    #[tokio::test]
+    async fn sorted_set_commands() {
+        let port = common::TestServer::new().await.port();
+        let mut client = TestClient::new(port).await;
+
+        client
+            .send("*8\r\n$4\r\nZADD\r\n$4\r\nzset\r\n$1\r\n1\r\n$1\r\na\r\n$3\r\n2.5\r\n$1\r\nb\r\n$1\r\n3\r\n$1\r\nc\r\n")
+            .await;
+        client.assert_response(b":3\r\n").await;
+
+        // Reversed score range with an exclusive bound and infinity
+        client
+            .send("*7\r\n$6\r\nZRANGE\r\n$4\r\nzset\r\n$4\r\n+inf\r\n$2\r\n(1\r\n$7\r\nBYSCORE\r\n$3\r\nREV\r\n$10\r\nWITHSCORES\r\n")
+            .await;
+        client
+            .assert_response(b"*4\r\n$1\r\nc\r\n$1\r\n3\r\n$1\r\nb\r\n$3\r\n2.5\r\n")
+            .await;
+
+        // Lex ranges expect members of the same score
+        client
+            .send("*8\r\n$4\r\nZADD\r\n$3\r\nlex\r\n$1\r\n0\r\n$1\r\na\r\n$1\r\n0\r\n$1\r\nb\r\n$1\r\n0\r\n$1\r\nc\r\n")
+            .await;
+        client.assert_response(b":3\r\n").await;
+        client
+            .send("*8\r\n$6\r\nZRANGE\r\n$3\r\nlex\r\n$2\r\n(a\r\n$1\r\n+\r\n$5\r\nBYLEX\r\n$5\r\nLIMIT\r\n$1\r\n0\r\n$1\r\n2\r\n")
+            .await;
+        client
+            .assert_response(b"*2\r\n$1\r\nb\r\n$1\r\nc\r\n")
+            .await;
+
+        client
+            .send(
+                "*6\r\n$4\r\nZADD\r\n$4\r\nzset\r\n$2\r\nNX\r\n$2\r\nGT\r\n$1\r\n1\r\n$1\r\na\r\n",
+            )
+            .await;
+        client
+            .assert_response(
+                b"-ERR Invalid or unimplemented command: GT, LT, and/or NX options at the same time are not compatible\r\n",
+            )
+            .await;
+
+        // Scores are not trimmed
+        client
+            .send("*4\r\n$4\r\nZADD\r\n$4\r\nzset\r\n$3\r\n 1 \r\n$1\r\na\r\n")
+            .await;
+        client
+            .assert_response(b"-ERR Failed to parse decimal: value is not a valid float\r\n")
+            .await;
+    }
+
+    #[tokio::test]
+    async fn stream_commands() {
+        let port = common::TestServer::new().await.port();
+        let mut client = TestClient::new(port).await;
+
+        client
+            .send("*5\r\n$4\r\nXADD\r\n$6\r\nstream\r\n$3\r\n1-1\r\n$1\r\na\r\n$1\r\n1\r\n")
+            .await;
+        client.assert_response(b"$3\r\n1-1\r\n").await;
+        client.send("*8\r\n$4\r\nXADD\r\n$6\r\nstream\r\n$6\r\nMAXLEN\r\n$1\r\n~\r\n$2\r\n10\r\n$3\r\n2-*\r\n$1\r\nb\r\n$1\r\n2\r\n").await;
+        client.assert_response(b"$3\r\n2-0\r\n").await;
+
+        // Exclusive start and incomplete end ID
+        client
+            .send("*4\r\n$6\r\nXRANGE\r\n$6\r\nstream\r\n$4\r\n(1-1\r\n$1\r\n2\r\n")
+            .await;
+        client
+            .assert_response(b"*1\r\n*2\r\n$3\r\n2-0\r\n*2\r\n$1\r\nb\r\n$1\r\n2\r\n")
+            .await;
+
+        client.send("*6\r\n$5\r\nXREAD\r\n$5\r\nCOUNT\r\n$1\r\n5\r\n$7\r\nSTREAMS\r\n$6\r\nstream\r\n$3\r\n1-1\r\n").await;
+        client
+            .assert_response(b"*1\r\n*2\r\n$6\r\nstream\r\n*1\r\n*2\r\n$3\r\n2-0\r\n*2\r\n$1\r\nb\r\n$1\r\n2\r\n")
+            .await;
+
+        client
+            .send("*4\r\n$5\r\nXREAD\r\n$7\r\nSTREAMS\r\n$6\r\nstream\r\n$1\r\n$\r\n")
+            .await;
+        client.assert_response(b"$-1\r\n").await;
+    }
+
+    #[tokio::test]
+    async fn blocking_list_commands() {
+        let port = common::TestServer::new().await.port();
+        let mut client = TestClient::new(port).await;
+
+        client
+            .send("*3\r\n$5\r\nRPUSH\r\n$4\r\nlist\r\n$1\r\na\r\n")
+            .await;
+        client.assert_response(b":1\r\n").await;
+        client
+            .send("*3\r\n$5\r\nBLPOP\r\n$4\r\nlist\r\n$3\r\n0.1\r\n")
+            .await;
+        client
+            .assert_response(b"*2\r\n$4\r\nlist\r\n$1\r\na\r\n")
+            .await;
+        client
+            .send("*3\r\n$5\r\nBRPOP\r\n$4\r\nlist\r\n$3\r\n0.1\r\n")
+            .await;
+        client.assert_response(b"$-1\r\n").await;
+
+        client
+            .send("*3\r\n$5\r\nBLPOP\r\n$4\r\nlist\r\n$2\r\n-1\r\n")
+            .await;
+        client
+            .assert_response(b"-ERR Invalid or unimplemented command: timeout is negative\r\n")
+            .await;
+        client
+            .send(
+                "*6\r\n$6\r\nBLMOVE\r\n$1\r\na\r\n$1\r\nb\r\n$2\r\nUP\r\n$4\r\nLEFT\r\n$1\r\n0\r\n",
+            )
+            .await;
+        client
+            .assert_response(b"-ERR Invalid or unimplemented command: syntax error\r\n")
+            .await;
+    }
+
+    #[tokio::test]
+    async fn pubsub_commands() {
+        let port = common::TestServer::new().await.port();
+        let mut subscriber = TestClient::new(port).await;
+        let mut publisher = TestClient::new(port).await;
+
+        subscriber
+            .send("*3\r\n$9\r\nSUBSCRIBE\r\n$1\r\na\r\n$1\r\nb\r\n")
+            .await;
+        subscriber
+            .assert_response(b"*3\r\n$9\r\nsubscribe\r\n$1\r\na\r\n:1\r\n")
+            .await;
+        subscriber
+            .assert_response(b"*3\r\n$9\r\nsubscribe\r\n$1\r\nb\r\n:2\r\n")
+            .await;
+
+        // Only subscription commands and PING are allowed in the subscriber mode
+        subscriber.send_get("key").await;
+        subscriber
+            .assert_response(
+                b"-ERR only (P)SUBSCRIBE / (P)UNSUBSCRIBE / PING are allowed in this context\r\n",
+            )
+            .await;
+        subscriber.send("*1\r\n$4\r\nPING\r\n").await;
+        subscriber
+            .assert_response(b"*2\r\n$4\r\npong\r\n$0\r\n\r\n")
+            .await;
+
+        publisher
+            .send("*3\r\n$7\r\nPUBLISH\r\n$1\r\nb\r\n$5\r\nhello\r\n")
+            .await;
+        publisher.assert_response(b":1\r\n").await;
+        subscriber
+            .assert_response(b"*3\r\n$7\r\nmessage\r\n$1\r\nb\r\n$5\r\nhello\r\n")
+            .await;
+
+        subscriber
+            .send("*2\r\n$10\r\nPSUBSCRIBE\r\n$2\r\nh*\r\n")
+            .await;
+        subscriber
+            .assert_response(b"*3\r\n$10\r\npsubscribe\r\n$2\r\nh*\r\n:3\r\n")
+            .await;
+        publisher
+            .send("*3\r\n$7\r\nPUBLISH\r\n$2\r\nhi\r\n$1\r\nx\r\n")
+            .await;
+        publisher.assert_response(b":1\r\n").await;
+        subscriber
+            .assert_response(b"*4\r\n$8\r\npmessage\r\n$2\r\nh*\r\n$2\r\nhi\r\n$1\r\nx\r\n")
+            .await;
+
+        publisher
+            .send("*2\r\n$6\r\nPUBSUB\r\n$6\r\nNUMPAT\r\n")
+            .await;
+        publisher.assert_response(b":1\r\n").await;
+
+        // Unsubscribe from all channels, then from all patterns
+        subscriber.send("*1\r\n$11\r\nUNSUBSCRIBE\r\n").await;
+        subscriber
+            .assert_response(b"*3\r\n$11\r\nunsubscribe\r\n$1\r\na\r\n:2\r\n")
+            .await;
+        subscriber
+            .assert_response(b"*3\r\n$11\r\nunsubscribe\r\n$1\r\nb\r\n:1\r\n")
+            .await;
+        subscriber.send("*1\r\n$12\r\nPUNSUBSCRIBE\r\n").await;
+        subscriber
+            .assert_response(b"*3\r\n$12\r\npunsubscribe\r\n$2\r\nh*\r\n:0\r\n")
+            .await;
+        subscriber.send("*1\r\n$12\r\nPUNSUBSCRIBE\r\n").await;
+        subscriber
+            .assert_response(b"*3\r\n$12\r\npunsubscribe\r\n$-1\r\n:0\r\n")
+            .await;
+
+        // Back in the normal mode
+        subscriber.send_get("key").await;
+        subscriber.assert_response(b"$-1\r\n").await;
+        publisher
+            .send("*3\r\n$7\r\nPUBLISH\r\n$1\r\nb\r\n$5\r\nhello\r\n")
+            .await;
+        publisher.assert_response(b":0\r\n").await;
+    }
+
+    #[tokio::test]
+    async fn transaction_commands() {
+        let port = common::TestServer::new().await.port();
+        let mut client = TestClient::new(port).await;
+        let mut other_client = TestClient::new(port).await;
+
+        client.send("*1\r\n$4\r\nEXEC\r\n").await;
+        client.assert_response(b"-ERR EXEC without MULTI\r\n").await;
+        client.send("*1\r\n$7\r\nDISCARD\r\n").await;
+        client
+            .assert_response(b"-ERR DISCARD without MULTI\r\n")
+            .await;
+
+        // Commands are queued until `EXEC`
+        client.send("*1\r\n$5\r\nMULTI\r\n").await;
+        client.assert_response(b"+OK\r\n").await;
+        client.send("*1\r\n$5\r\nMULTI\r\n").await;
+        client
+            .assert_response(b"-ERR MULTI calls can not be nested\r\n")
+            .await;
+        client.send("*2\r\n$5\r\nWATCH\r\n$1\r\na\r\n").await;
+        client
+            .assert_response(b"-ERR WATCH inside MULTI is not allowed\r\n")
+            .await;
+        client.send_incr("a").await;
+        client.assert_response(b"+QUEUED\r\n").await;
+        // Blocking commands do not wait inside a transaction
+        client
+            .send("*3\r\n$5\r\nBLPOP\r\n$4\r\nlist\r\n$1\r\n0\r\n")
+            .await;
+        client.assert_response(b"+QUEUED\r\n").await;
+        other_client.send_get("a").await;
+        other_client.assert_response(b"$-1\r\n").await;
+        client.send("*1\r\n$4\r\nEXEC\r\n").await;
+        client.assert_response(b"*2\r\n:1\r\n$-1\r\n").await;
+
+        // `DISCARD` drops the queued commands
+        client.send("*1\r\n$5\r\nMULTI\r\n").await;
+        client.assert_response(b"+OK\r\n").await;
+        client.send_incr("a").await;
+        client.assert_response(b"+QUEUED\r\n").await;
+        client.send("*1\r\n$7\r\nDISCARD\r\n").await;
+        client.assert_response(b"+OK\r\n").await;
+        client.send_get("a").await;
+        client.assert_response(b"$1\r\n1\r\n").await;
+
+        // An invalid command discards the transaction on `EXEC`
+        client.send("*1\r\n$5\r\nMULTI\r\n").await;
+        client.assert_response(b"+OK\r\n").await;
+        client.send_incr("a").await;
+        client.assert_response(b"+QUEUED\r\n").await;
+        client.send("*1\r\n$7\r\nUNKNOWN\r\n").await;
+        client
+            .assert_response(b"-ERR Invalid or unimplemented command: UNKNOWN\r\n")
+            .await;
+        client.send("*1\r\n$4\r\nEXEC\r\n").await;
+        client
+            .assert_response(b"-EXECABORT Transaction discarded because of previous errors.\r\n")
+            .await;
+        client.send_get("a").await;
+        client.assert_response(b"$1\r\n1\r\n").await;
+
+        // Commands that change the state of the connection are not allowed in a transaction
+        client.send("*1\r\n$5\r\nMULTI\r\n").await;
+        client.assert_response(b"+OK\r\n").await;
+        for command in [
+            "*2\r\n$9\r\nSUBSCRIBE\r\n$4\r\nnews\r\n",
+            "*2\r\n$5\r\nHELLO\r\n$1\r\n3\r\n",
+        ] {
+            client.send(command).await;
+            client
+                .assert_response(b"-ERR Command not allowed inside a transaction\r\n")
+                .await;
+        }
+        client.send("*1\r\n$4\r\nEXEC\r\n").await;
+        client
+            .assert_response(b"-EXECABORT Transaction discarded because of previous errors.\r\n")
+            .await;
+
+        // A watched key modified by another client aborts the transaction
+        client.send("*2\r\n$5\r\nWATCH\r\n$1\r\na\r\n").await;
+        client.assert_response(b"+OK\r\n").await;
+        other_client.send_incr("a").await;
+        other_client.assert_response(b":2\r\n").await;
+        client.send("*1\r\n$5\r\nMULTI\r\n").await;
+        client.assert_response(b"+OK\r\n").await;
+        client.send_incr("a").await;
+        client.assert_response(b"+QUEUED\r\n").await;
+        client.send("*1\r\n$4\r\nEXEC\r\n").await;
+        client.assert_response(b"$-1\r\n").await;
+        client.send_get("a").await;
+        client.assert_response(b"$1\r\n2\r\n").await;
+    }
+
+    #[tokio::test]
+    async fn resp3_commands() {
+        common::get_or_init_logger();
+        let port = common::TestServer::new().await.port();
+        let mut client = TestClient::new(port).await;
+        let hello = |proto: u8| {
+            let version = env!("CARGO_PKG_VERSION");
+            format!(
+                "$6\r\nserver\r\n$5\r\nredis\r\n$7\r\nversion\r\n${}\r\n{}\r\n\
+                 $5\r\nproto\r\n:{}\r\n$4\r\nmode\r\n$10\r\nstandalone\r\n\
+                 $4\r\nrole\r\n$6\r\nmaster\r\n$7\r\nmodules\r\n*0\r\n",
+                version.len(),
+                version,
+                proto
+            )
+        };
+
+        client.send("*2\r\n$5\r\nHELLO\r\n$1\r\n4\r\n").await;
+        client
+            .assert_response(b"-NOPROTO sorry, this protocol version is not supported\r\n")
+            .await;
+        client.send("*2\r\n$5\r\nHELLO\r\n$1\r\n3\r\n").await;
+        client
+            .assert_response(format!("%6\r\n{}", hello(3)).as_bytes())
+            .await;
+
+        client
+            .send("*4\r\n$4\r\nHSET\r\n$1\r\nh\r\n$1\r\nf\r\n$1\r\nv\r\n")
+            .await;
+        client.assert_response(b":1\r\n").await;
+        client.send("*2\r\n$7\r\nHGETALL\r\n$1\r\nh\r\n").await;
+        client
+            .assert_response(b"%1\r\n$1\r\nf\r\n$1\r\nv\r\n")
+            .await;
+        client
+            .send("*4\r\n$4\r\nZADD\r\n$1\r\nz\r\n$3\r\n1.5\r\n$1\r\nm\r\n")
+            .await;
+        client.assert_response(b":1\r\n").await;
+        client
+            .send("*3\r\n$6\r\nZSCORE\r\n$1\r\nz\r\n$1\r\nm\r\n")
+            .await;
+        client.assert_response(b",1.5\r\n").await;
+        client
+            .send("*3\r\n$4\r\nSADD\r\n$1\r\ns\r\n$1\r\na\r\n")
+            .await;
+        client.assert_response(b":1\r\n").await;
+        client.send("*2\r\n$8\r\nSMEMBERS\r\n$1\r\ns\r\n").await;
+        client.assert_response(b"~1\r\n$1\r\na\r\n").await;
+        client.send_get("missing").await;
+        client.assert_response(b"_\r\n").await;
+
+        // Subscribers receive pushes and may send any command
+        client.send("*2\r\n$9\r\nSUBSCRIBE\r\n$2\r\nch\r\n").await;
+        client
+            .assert_response(b">3\r\n$9\r\nsubscribe\r\n$2\r\nch\r\n:1\r\n")
+            .await;
+        client.send_get("missing").await;
+        client.assert_response(b"_\r\n").await;
+        client
+            .send("*2\r\n$11\r\nUNSUBSCRIBE\r\n$2\r\nch\r\n")
+            .await;
+        client
+            .assert_response(b">3\r\n$11\r\nunsubscribe\r\n$2\r\nch\r\n:0\r\n")
+            .await;
+
+        // RESP2 replies again after `HELLO 2`
+        client.send("*2\r\n$5\r\nHELLO\r\n$1\r\n2\r\n").await;
+        client
+            .assert_response(format!("*12\r\n{}", hello(2)).as_bytes())
+            .await;
+        client.send("*2\r\n$7\r\nHGETALL\r\n$1\r\nh\r\n").await;
+        client
+            .assert_response(b"*2\r\n$1\r\nf\r\n$1\r\nv\r\n")
+            .await;
+        client
+            .send("*3\r\n$6\r\nZSCORE\r\n$1\r\nz\r\n$1\r\nm\r\n")
+            .await;
+        client.assert_response(b"$3\r\n1.5\r\n").await;
+        client.send_get("missing").await;
+        client.assert_response(b"$-1\r\n").await;
+    }
+
+    #[tokio::test]
+    async fn inline_commands() {
+        common::get_or_init_logger();
+        let port = common::TestServer::new().await.port();
+        let mut client = TestClient::new(port).await;
+
+        client.send("PING\r\n").await;
+        client.assert_response(b"+PONG\r\n").await;
+        client.send("set greeting \"hello world\"\n").await;
+        client.assert_response(b"+OK\r\n").await;
+        // Empty lines are ignored
+        client.send("\r\n\nGET greeting\r\n").await;
+        client.assert_response(b"$11\r\nhello world\r\n").await;
+
+        // Inline and RESP commands can be mixed
+        client
+            .send("EXISTS greeting missing\r\n*2\r\n$3\r\nGET\r\n$8\r\ngreeting\r\n")
+            .await;
+        client
+            .assert_response(b":1\r\n$11\r\nhello world\r\n")
+            .await;
+        client.send("PI").await;
+        client.send("NG\r\n").await;
+        client.assert_response(b"+PONG\r\n").await;
+    }
+
+    #[test]
+    fn config_protocol_limits() {
+        let args = [
+            "--proto-max-bulk-len",
+            "2mb",
+            "--client-query-buffer-limit",
+            "4mb",
+        ];
+        let limits = Config::from_args(args.map(str::to_string))
+            .unwrap()
+            .protocol_limits();
+        assert_eq!(limits.max_bulk_len, 2 * 1024 * 1024);
+        assert_eq!(limits.max_query_buffer, 4 * 1024 * 1024);
+        assert_eq!(limits.max_depth, ProtocolLimits::default().max_depth);
+        for option in ["--proto-max-bulk-len", "--client-query-buffer-limit"] {
+            assert!(Config::from_args([option, "1kb"].map(str::to_string)).is_err());
+        }
+    }
+
+    #[tokio::test]
+    async fn protocol_limits() {
+        common::get_or_init_logger();
+        let dir = common::temp_dir();
+        let server = common::TestServer::with_config(Config {
+            proto_max_bulk_len: 1024 * 1024,
+            client_query_buffer_limit: 2 * 1024 * 1024,
+            ..common::test_config(&dir)
+        })
+        .await;
+        let port = server.port();
+
+        // The connection is closed after the error reply
+        let cases = [
+            (
+                "*3\r\n$3\r\nSET\r\n$3\r\nkey\r\n$1048577\r\n".to_string(),
+                "-ERR Protocol error: invalid bulk length `1048577`\r\n",
+            ),
+            (
+                "*1\r\n".repeat(ProtocolLimits::default().max_depth + 1),
+                "-ERR Protocol error: aggregate frames nested deeper than 32\r\n",
+            ),
+            (
+                // An inline command without the end of the line
+                "x".repeat(ProtocolLimits::default().max_inline_len + 1),
+                "-ERR Protocol error: too big inline request\r\n",
+            ),
+            (
+                "GET \"key\r\n".to_string(),
+                "-ERR Protocol error: unbalanced quotes in inline command\r\n",
+            ),
+        ];
+        for (command, reply) in cases {
+            let mut client = TestClient::new(port).await;
+            client.set("key", "value").await;
+            client.send(&command).await;
+            client.assert_response(reply.as_bytes()).await;
+            let mut rest = vec![];
+            client.stream.read_to_end(&mut rest).await.unwrap();
+            assert!(rest.is_empty(), "{:?}", String::from_utf8_lossy(&rest));
+        }
+
+        // Values up to the limit are accepted
+        let mut client = TestClient::new(port).await;
+        client.set("key", &"x".repeat(1024 * 1024)).await;
+        server.shutdown();
+    }
 }